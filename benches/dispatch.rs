@@ -0,0 +1,581 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use interpreter_dispatch_research::{runner, switch::Inst, Register};
+
+/// The default number of loop iterations of the `counter_loop` workload.
+///
+/// Can be overwritten via the `DISPATCH_REPETITIONS` environment variable.
+const DEFAULT_REPETITIONS: u64 = 1_000_000;
+
+fn repetitions() -> u64 {
+    std::env::var("DISPATCH_REPETITIONS")
+        .ok()
+        .and_then(|repetitions| repetitions.parse().ok())
+        .unwrap_or(DEFAULT_REPETITIONS)
+}
+
+fn bench_counter_loop(c: &mut Criterion) {
+    let repetitions = repetitions();
+    let mut group = c.benchmark_group("counter_loop");
+    group.throughput(Throughput::Elements(repetitions));
+    for runner in runner::counter_loop(repetitions) {
+        group.bench_function(runner.name(), |b| b.iter(|| black_box(runner.run())));
+    }
+    group.finish();
+}
+
+fn bench_counter_loop_ordering(c: &mut Criterion) {
+    let repetitions = repetitions();
+    let mut group = c.benchmark_group("counter_loop_ordering");
+    group.throughput(Throughput::Elements(repetitions));
+    for runner in runner::counter_loop_ordering(repetitions) {
+        group.bench_function(runner.name(), |b| b.iter(|| black_box(runner.run())));
+    }
+    group.finish();
+}
+
+fn bench_more_comps(c: &mut Criterion) {
+    let repetitions = repetitions();
+    let mut group = c.benchmark_group("more_comps");
+    group.throughput(Throughput::Elements(repetitions));
+    for runner in runner::more_comps(repetitions) {
+        group.bench_function(runner.name(), |b| b.iter(|| black_box(runner.run())));
+    }
+    group.finish();
+}
+
+fn bench_random_branch(c: &mut Criterion) {
+    let repetitions = repetitions();
+    let mut group = c.benchmark_group("random_branch");
+    group.throughput(Throughput::Elements(repetitions));
+    for runner in runner::random_branch(repetitions) {
+        group.bench_function(runner.name(), |b| b.iter(|| black_box(runner.run())));
+    }
+    group.finish();
+}
+
+/// The number of repeated instructions of the `single_opcode` workloads.
+const SINGLE_OPCODE_LEN: usize = 10_000;
+
+/// Returns a representative instruction for every opcode that does not branch.
+///
+/// All registers are zero so `div` and `rem` are only measured with a non-zero immediate.
+fn single_opcode_insts() -> Vec<(&'static str, Inst)> {
+    vec![
+        (
+            "add",
+            Inst::Add {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "sub",
+            Inst::Sub {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "mul",
+            Inst::Mul {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "shl",
+            Inst::Shl {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "shr_u",
+            Inst::ShrU {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "shr_s",
+            Inst::ShrS {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "rotl",
+            Inst::Rotl {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "rotr",
+            Inst::Rotr {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "eq",
+            Inst::Eq {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "ne",
+            Inst::Ne {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "lt_u",
+            Inst::LtU {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "lt_s",
+            Inst::LtS {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "add32",
+            Inst::Add32 {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "sub32",
+            Inst::Sub32 {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "mul32",
+            Inst::Mul32 {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "lt_s32",
+            Inst::LtS32 {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "shl32",
+            Inst::Shl32 {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "shr_u32",
+            Inst::ShrU32 {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "shr_s32",
+            Inst::ShrS32 {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "add_checked",
+            Inst::AddChecked {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "sub_checked",
+            Inst::SubChecked {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "mul_checked",
+            Inst::MulChecked {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "mul_wide",
+            Inst::MulWide {
+                hi: Register(2),
+                lo: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "min_u",
+            Inst::MinU {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "max_u",
+            Inst::MaxU {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "min_s",
+            Inst::MinS {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "max_s",
+            Inst::MaxS {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "add_imm",
+            Inst::AddImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "sub_imm",
+            Inst::SubImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "mul_imm",
+            Inst::MulImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "div_imm",
+            Inst::DivImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "rem_imm",
+            Inst::RemImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "shl_imm",
+            Inst::ShlImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "shr_u_imm",
+            Inst::ShrUImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "shr_s_imm",
+            Inst::ShrSImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "rotl_imm",
+            Inst::RotlImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "rotr_imm",
+            Inst::RotrImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "eq_imm",
+            Inst::EqImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "ne_imm",
+            Inst::NeImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "lt_u_imm",
+            Inst::LtUImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "lt_s_imm",
+            Inst::LtSImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "min_u_imm",
+            Inst::MinUImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "max_u_imm",
+            Inst::MaxUImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "min_s_imm",
+            Inst::MinSImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "max_s_imm",
+            Inst::MaxSImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+        ),
+        (
+            "move",
+            Inst::Move {
+                result: Register(0),
+                src: Register(1),
+            },
+        ),
+        (
+            "popcnt",
+            Inst::Popcnt {
+                result: Register(0),
+                src: Register(1),
+            },
+        ),
+        (
+            "clz",
+            Inst::Clz {
+                result: Register(0),
+                src: Register(1),
+            },
+        ),
+        (
+            "ctz",
+            Inst::Ctz {
+                result: Register(0),
+                src: Register(1),
+            },
+        ),
+        (
+            "select",
+            Inst::Select {
+                result: Register(0),
+                cond: Register(1),
+                if_nonzero: Register(2),
+                if_zero: Register(3),
+            },
+        ),
+        (
+            "fadd",
+            Inst::FAdd {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "fsub",
+            Inst::FSub {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "fmul",
+            Inst::FMul {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "fdiv",
+            Inst::FDiv {
+                result: Register(0),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+        ),
+        (
+            "load8",
+            Inst::Load8 {
+                result: Register(0),
+                base: Register(1),
+                offset: 8,
+            },
+        ),
+        (
+            "load16",
+            Inst::Load16 {
+                result: Register(0),
+                base: Register(1),
+                offset: 8,
+            },
+        ),
+        (
+            "load32",
+            Inst::Load32 {
+                result: Register(0),
+                base: Register(1),
+                offset: 8,
+            },
+        ),
+        (
+            "load64",
+            Inst::Load64 {
+                result: Register(0),
+                base: Register(1),
+                offset: 8,
+            },
+        ),
+        (
+            "store8",
+            Inst::Store8 {
+                base: Register(1),
+                offset: 8,
+                value: Register(0),
+            },
+        ),
+        (
+            "store16",
+            Inst::Store16 {
+                base: Register(1),
+                offset: 8,
+                value: Register(0),
+            },
+        ),
+        (
+            "store32",
+            Inst::Store32 {
+                base: Register(1),
+                offset: 8,
+                value: Register(0),
+            },
+        ),
+        (
+            "store64",
+            Inst::Store64 {
+                base: Register(1),
+                offset: 8,
+                value: Register(0),
+            },
+        ),
+        ("nop", Inst::Nop),
+    ]
+}
+
+fn bench_single_opcode(c: &mut Criterion) {
+    for (name, inst) in single_opcode_insts() {
+        let mut group = c.benchmark_group(format!("single_opcode/{name}"));
+        group.throughput(Throughput::Elements(SINGLE_OPCODE_LEN as u64));
+        for runner in runner::single_opcode(inst, SINGLE_OPCODE_LEN) {
+            group.bench_function(runner.name(), |b| b.iter(|| black_box(runner.run())));
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(
+    benches,
+    bench_counter_loop,
+    bench_counter_loop_ordering,
+    bench_more_comps,
+    bench_random_branch,
+    bench_single_opcode
+);
+criterion_main!(benches);
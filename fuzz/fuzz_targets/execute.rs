@@ -0,0 +1,18 @@
+#![no_main]
+
+use interpreter_dispatch_research::{bytecode, switch, validate, Context};
+use libfuzzer_sys::fuzz_target;
+
+/// The maximum number of instructions dispatched per program to bound endless loops.
+const FUEL: u64 = 10_000;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(insts) = bytecode::decode(data) else {
+        return;
+    };
+    if validate::validate(&insts).is_err() {
+        return;
+    }
+    let mut context = Context::default();
+    let _ = switch::execute_with_fuel(&insts, &mut context, FUEL);
+});
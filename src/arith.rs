@@ -0,0 +1,26 @@
+use core::cmp::Ordering;
+
+use super::Bits;
+
+/// Compares `lhs` and `rhs` as unsigned integers.
+#[inline(always)]
+pub fn cmp_u(lhs: Bits, rhs: Bits) -> Ordering {
+    lhs.cmp(&rhs)
+}
+
+/// Compares `lhs` and `rhs` as two's complement signed integers.
+#[inline(always)]
+pub fn cmp_s(lhs: Bits, rhs: Bits) -> Ordering {
+    (lhs as i64).cmp(&(rhs as i64))
+}
+
+#[test]
+fn signedness() {
+    assert_eq!(cmp_u(Bits::MAX, 0), Ordering::Greater);
+    assert_eq!(cmp_s(Bits::MAX, 0), Ordering::Less);
+    assert_eq!(cmp_u(1 << 63, 1), Ordering::Greater);
+    assert_eq!(cmp_s(1 << 63, 1), Ordering::Less);
+    assert_eq!(cmp_u(7, 7), Ordering::Equal);
+    assert_eq!(cmp_s(Bits::MAX, Bits::MAX), Ordering::Equal);
+    assert_eq!(cmp_s(Bits::MAX, (-2_i64) as Bits), Ordering::Greater);
+}
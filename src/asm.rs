@@ -0,0 +1,919 @@
+#![allow(dead_code)]
+
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use core::fmt::{self, Write as _};
+
+use super::{switch::Inst, Bits, Register, Target};
+
+/// An error that occurred while parsing assembly text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The 1-based line number of the offending line.
+    line: usize,
+    /// A description of what went wrong.
+    message: String,
+}
+
+impl ParseError {
+    /// Returns the 1-based line number at which the error occurred.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the description of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Maps label names to the index of the instruction they precede.
+type Labels<'a> = BTreeMap<&'a str, Target>;
+
+/// Parses the assembly text `src` into a list of instructions.
+///
+/// Every non-empty line holds a single instruction such as `add_imm r0, r0, 100`,
+/// `branch_eqz @4, r0` or `ret r0` where `rN` denotes a register and `@N`
+/// denotes the absolute index of an instruction. Comments start with `;`.
+///
+/// A line may start with a label definition such as `loop:` which can then be
+/// used in place of `@N` as branch target, e.g. `branch loop`.
+///
+/// # Errors
+///
+/// - If a line contains an unknown mnemonic or malformed operands.
+/// - If a label is defined twice or referenced but never defined.
+pub fn parse(src: &str) -> Result<Vec<Inst>, ParseError> {
+    // First pass: resolve all label definitions to instruction indices.
+    let mut labels = Labels::new();
+    let mut label_lines = BTreeMap::new();
+    let mut len = 0;
+    for (line, label, code) in lines(src) {
+        if let Some(label) = label {
+            if let Some(first) = label_lines.insert(label, line) {
+                return Err(ParseError {
+                    line,
+                    message: format!("duplicate label `{label}` (first defined on line {first})"),
+                });
+            }
+            labels.insert(label, Target(len));
+        }
+        if !code.is_empty() {
+            len += 1;
+        }
+    }
+    // Second pass: parse all instructions with known labels.
+    let mut insts = Vec::with_capacity(len);
+    for (line, _label, code) in lines(src) {
+        if code.is_empty() {
+            continue;
+        }
+        let inst = parse_inst(code, &labels).map_err(|message| ParseError { line, message })?;
+        insts.push(inst);
+    }
+    Ok(insts)
+}
+
+/// Yields the 1-based line number, optional label and comment free code of every line in `src`.
+fn lines(src: &str) -> impl Iterator<Item = (usize, Option<&str>, &str)> {
+    src.lines().enumerate().map(|(index, line)| {
+        let (label, code) = split_label(strip_comment(line).trim());
+        (index + 1, label, code)
+    })
+}
+
+/// Returns `line` without its trailing `;` comment.
+fn strip_comment(line: &str) -> &str {
+    match line.split_once(';') {
+        Some((code, _comment)) => code,
+        None => line,
+    }
+}
+
+/// Splits off a leading label definition such as `loop:` from `code`.
+fn split_label(code: &str) -> (Option<&str>, &str) {
+    match code.split_once(':') {
+        Some((label, rest)) if is_label(label) => (Some(label), rest.trim()),
+        _ => (None, code),
+    }
+}
+
+/// Returns `true` if `name` is a valid label name.
+fn is_label(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parses a single instruction from its comment free `code`.
+fn parse_inst(code: &str, labels: &Labels) -> Result<Inst, String> {
+    let (mnemonic, operands) = match code.split_once(char::is_whitespace) {
+        Some((mnemonic, operands)) => (mnemonic, operands.trim()),
+        None => (code, ""),
+    };
+    let operands = match operands.is_empty() {
+        true => Vec::new(),
+        false => operands.split(',').map(str::trim).collect::<Vec<_>>(),
+    };
+    let inst = match mnemonic {
+        "add" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::Add { result, lhs, rhs }
+        }
+        "add_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::AddImm { result, src, imm }
+        }
+        "sub" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::Sub { result, lhs, rhs }
+        }
+        "sub_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::SubImm { result, src, imm }
+        }
+        "mul" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::Mul { result, lhs, rhs }
+        }
+        "mul_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::MulImm { result, src, imm }
+        }
+        "div" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::Div { result, lhs, rhs }
+        }
+        "div_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::DivImm { result, src, imm }
+        }
+        "rem" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::Rem { result, lhs, rhs }
+        }
+        "rem_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::RemImm { result, src, imm }
+        }
+        "shl" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::Shl { result, lhs, rhs }
+        }
+        "shl_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::ShlImm { result, src, imm }
+        }
+        "shr_u" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::ShrU { result, lhs, rhs }
+        }
+        "shr_u_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::ShrUImm { result, src, imm }
+        }
+        "shr_s" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::ShrS { result, lhs, rhs }
+        }
+        "shr_s_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::ShrSImm { result, src, imm }
+        }
+        "rotl" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::Rotl { result, lhs, rhs }
+        }
+        "rotl_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::RotlImm { result, src, imm }
+        }
+        "rotr" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::Rotr { result, lhs, rhs }
+        }
+        "rotr_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::RotrImm { result, src, imm }
+        }
+        "eq" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::Eq { result, lhs, rhs }
+        }
+        "eq_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::EqImm { result, src, imm }
+        }
+        "ne" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::Ne { result, lhs, rhs }
+        }
+        "ne_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::NeImm { result, src, imm }
+        }
+        "lt_u" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::LtU { result, lhs, rhs }
+        }
+        "lt_u_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::LtUImm { result, src, imm }
+        }
+        "lt_s" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::LtS { result, lhs, rhs }
+        }
+        "lt_s_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::LtSImm { result, src, imm }
+        }
+        "mov" => {
+            let [result, src] = expect_operands(&operands)?;
+            Inst::Move {
+                result: parse_register(result)?,
+                src: parse_register(src)?,
+            }
+        }
+        "add32" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::Add32 { result, lhs, rhs }
+        }
+        "sub32" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::Sub32 { result, lhs, rhs }
+        }
+        "mul32" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::Mul32 { result, lhs, rhs }
+        }
+        "lt_s32" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::LtS32 { result, lhs, rhs }
+        }
+        "shl32" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::Shl32 { result, lhs, rhs }
+        }
+        "shr_u32" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::ShrU32 { result, lhs, rhs }
+        }
+        "shr_s32" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::ShrS32 { result, lhs, rhs }
+        }
+        "add_checked" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::AddChecked { result, lhs, rhs }
+        }
+        "sub_checked" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::SubChecked { result, lhs, rhs }
+        }
+        "mul_checked" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::MulChecked { result, lhs, rhs }
+        }
+        "mul_wide" => {
+            let [hi, lo, lhs, rhs] = expect_operands(&operands)?;
+            Inst::MulWide {
+                hi: parse_register(hi)?,
+                lo: parse_register(lo)?,
+                lhs: parse_register(lhs)?,
+                rhs: parse_register(rhs)?,
+            }
+        }
+        "popcnt" => {
+            let [result, src] = expect_operands(&operands)?;
+            Inst::Popcnt {
+                result: parse_register(result)?,
+                src: parse_register(src)?,
+            }
+        }
+        "clz" => {
+            let [result, src] = expect_operands(&operands)?;
+            Inst::Clz {
+                result: parse_register(result)?,
+                src: parse_register(src)?,
+            }
+        }
+        "ctz" => {
+            let [result, src] = expect_operands(&operands)?;
+            Inst::Ctz {
+                result: parse_register(result)?,
+                src: parse_register(src)?,
+            }
+        }
+        "rand_next" => {
+            let [result] = expect_operands(&operands)?;
+            Inst::RandNext {
+                result: parse_register(result)?,
+            }
+        }
+        "min_u" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::MinU { result, lhs, rhs }
+        }
+        "min_u_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::MinUImm { result, src, imm }
+        }
+        "max_u" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::MaxU { result, lhs, rhs }
+        }
+        "max_u_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::MaxUImm { result, src, imm }
+        }
+        "min_s" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::MinS { result, lhs, rhs }
+        }
+        "min_s_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::MinSImm { result, src, imm }
+        }
+        "max_s" => {
+            let (result, lhs, rhs) = register_operands(&operands)?;
+            Inst::MaxS { result, lhs, rhs }
+        }
+        "max_s_imm" => {
+            let (result, src, imm) = immediate_operands(&operands)?;
+            Inst::MaxSImm { result, src, imm }
+        }
+        "select" => {
+            let [result, cond, if_nonzero, if_zero] = expect_operands(&operands)?;
+            Inst::Select {
+                result: parse_register(result)?,
+                cond: parse_register(cond)?,
+                if_nonzero: parse_register(if_nonzero)?,
+                if_zero: parse_register(if_zero)?,
+            }
+        }
+        "fadd" => {
+            let (result, lhs, rhs) = float_operands(&operands)?;
+            Inst::FAdd { result, lhs, rhs }
+        }
+        "fsub" => {
+            let (result, lhs, rhs) = float_operands(&operands)?;
+            Inst::FSub { result, lhs, rhs }
+        }
+        "fmul" => {
+            let (result, lhs, rhs) = float_operands(&operands)?;
+            Inst::FMul { result, lhs, rhs }
+        }
+        "fdiv" => {
+            let (result, lhs, rhs) = float_operands(&operands)?;
+            Inst::FDiv { result, lhs, rhs }
+        }
+        "load8" => {
+            let (result, base, offset) = immediate_operands(&operands)?;
+            Inst::Load8 {
+                result,
+                base,
+                offset,
+            }
+        }
+        "load16" => {
+            let (result, base, offset) = immediate_operands(&operands)?;
+            Inst::Load16 {
+                result,
+                base,
+                offset,
+            }
+        }
+        "load32" => {
+            let (result, base, offset) = immediate_operands(&operands)?;
+            Inst::Load32 {
+                result,
+                base,
+                offset,
+            }
+        }
+        "load64" => {
+            let (result, base, offset) = immediate_operands(&operands)?;
+            Inst::Load64 {
+                result,
+                base,
+                offset,
+            }
+        }
+        "store8" => {
+            let (base, offset, value) = store_operands(&operands)?;
+            Inst::Store8 {
+                base,
+                offset,
+                value,
+            }
+        }
+        "store16" => {
+            let (base, offset, value) = store_operands(&operands)?;
+            Inst::Store16 {
+                base,
+                offset,
+                value,
+            }
+        }
+        "store32" => {
+            let (base, offset, value) = store_operands(&operands)?;
+            Inst::Store32 {
+                base,
+                offset,
+                value,
+            }
+        }
+        "store64" => {
+            let (base, offset, value) = store_operands(&operands)?;
+            Inst::Store64 {
+                base,
+                offset,
+                value,
+            }
+        }
+        "branch" => {
+            let [target] = expect_operands(&operands)?;
+            Inst::Branch {
+                target: parse_target(target, labels)?,
+            }
+        }
+        "branch_eqz" => {
+            let [target, condition] = expect_operands(&operands)?;
+            Inst::BranchEqz {
+                target: parse_target(target, labels)?,
+                condition: parse_register(condition)?,
+            }
+        }
+        "branch_table" => {
+            let [index, targets @ .., default] = &operands[..] else {
+                return Err(format!(
+                    "expected at least 2 operands but found {}",
+                    operands.len()
+                ));
+            };
+            Inst::BranchTable {
+                index: parse_register(index)?,
+                targets: targets
+                    .iter()
+                    .map(|target| parse_target(target, labels))
+                    .collect::<Result<_, _>>()?,
+                default: parse_target(default, labels)?,
+            }
+        }
+        "branch_nez" => {
+            let [target, condition] = expect_operands(&operands)?;
+            Inst::BranchNez {
+                target: parse_target(target, labels)?,
+                condition: parse_register(condition)?,
+            }
+        }
+        "branch_eq" => {
+            let [target, lhs, rhs] = expect_operands(&operands)?;
+            Inst::BranchEq {
+                target: parse_target(target, labels)?,
+                lhs: parse_register(lhs)?,
+                rhs: parse_register(rhs)?,
+            }
+        }
+        "branch_ne" => {
+            let [target, lhs, rhs] = expect_operands(&operands)?;
+            Inst::BranchNe {
+                target: parse_target(target, labels)?,
+                lhs: parse_register(lhs)?,
+                rhs: parse_register(rhs)?,
+            }
+        }
+        "branch_lt_u" => {
+            let [target, lhs, rhs] = expect_operands(&operands)?;
+            Inst::BranchLtU {
+                target: parse_target(target, labels)?,
+                lhs: parse_register(lhs)?,
+                rhs: parse_register(rhs)?,
+            }
+        }
+        "branch_lt_s" => {
+            let [target, lhs, rhs] = expect_operands(&operands)?;
+            Inst::BranchLtS {
+                target: parse_target(target, labels)?,
+                lhs: parse_register(lhs)?,
+                rhs: parse_register(rhs)?,
+            }
+        }
+        "call" => {
+            let [target, num_args] = expect_operands(&operands)?;
+            Inst::Call {
+                target: parse_target(target, labels)?,
+                num_args: num_args
+                    .parse()
+                    .map_err(|_| format!("malformed argument count `{num_args}`"))?,
+            }
+        }
+        "nop" => {
+            let [] = expect_operands(&operands)?;
+            Inst::Nop
+        }
+        "yield" => {
+            let [] = expect_operands(&operands)?;
+            Inst::Yield
+        }
+        "unreachable" => {
+            let [] = expect_operands(&operands)?;
+            Inst::Unreachable
+        }
+        "ret" => {
+            let [result] = expect_operands(&operands)?;
+            Inst::Return {
+                result: parse_register(result)?,
+            }
+        }
+        unknown => return Err(format!("unknown mnemonic `{unknown}`")),
+    };
+    Ok(inst)
+}
+
+/// Returns the `operands` if there are exactly `N` of them.
+fn expect_operands<'a, const N: usize>(operands: &[&'a str]) -> Result<[&'a str; N], String> {
+    <[&str; N]>::try_from(operands)
+        .map_err(|_| format!("expected {N} operands but found {}", operands.len()))
+}
+
+/// Parses the `result, lhs, rhs` register operands of a binary instruction.
+fn register_operands(operands: &[&str]) -> Result<(Register, Register, Register), String> {
+    let [result, lhs, rhs] = expect_operands(operands)?;
+    Ok((
+        parse_register(result)?,
+        parse_register(lhs)?,
+        parse_register(rhs)?,
+    ))
+}
+
+/// Parses the `result, lhs, rhs` float register operands of a binary float instruction.
+fn float_operands(operands: &[&str]) -> Result<(Register, Register, Register), String> {
+    let [result, lhs, rhs] = expect_operands(operands)?;
+    Ok((
+        parse_float_register(result)?,
+        parse_float_register(lhs)?,
+        parse_float_register(rhs)?,
+    ))
+}
+
+/// Parses the `result, src, imm` operands of a binary instruction with an immediate.
+///
+/// Also used for the `result, base, offset` operands of load instructions.
+fn immediate_operands(operands: &[&str]) -> Result<(Register, Register, Bits), String> {
+    let [result, src, imm] = expect_operands(operands)?;
+    Ok((
+        parse_register(result)?,
+        parse_register(src)?,
+        parse_immediate(imm)?,
+    ))
+}
+
+/// Parses the `base, offset, value` operands of a store instruction.
+fn store_operands(operands: &[&str]) -> Result<(Register, Bits, Register), String> {
+    let [base, offset, value] = expect_operands(operands)?;
+    Ok((
+        parse_register(base)?,
+        parse_immediate(offset)?,
+        parse_register(value)?,
+    ))
+}
+
+/// Parses a register operand such as `r0`.
+fn parse_register(operand: &str) -> Result<Register, String> {
+    operand
+        .strip_prefix('r')
+        .and_then(|index| index.parse().ok())
+        .map(Register)
+        .ok_or_else(|| format!("malformed register `{operand}`"))
+}
+
+/// Parses a float register operand such as `f0`.
+fn parse_float_register(operand: &str) -> Result<Register, String> {
+    operand
+        .strip_prefix('f')
+        .and_then(|index| index.parse().ok())
+        .map(Register)
+        .ok_or_else(|| format!("malformed float register `{operand}`"))
+}
+
+/// Parses a branch target operand such as `@4` or a label reference such as `loop`.
+fn parse_target(operand: &str, labels: &Labels) -> Result<Target, String> {
+    if is_label(operand) {
+        return labels
+            .get(operand)
+            .copied()
+            .ok_or_else(|| format!("undefined label `{operand}`"));
+    }
+    operand
+        .strip_prefix('@')
+        .and_then(|index| index.parse().ok())
+        .map(Target)
+        .ok_or_else(|| format!("malformed target `{operand}`"))
+}
+
+/// Parses a decimal or `0x` prefixed hexadecimal immediate operand.
+fn parse_immediate(operand: &str) -> Result<Bits, String> {
+    let parsed = match operand.strip_prefix("0x") {
+        Some(hex) => Bits::from_str_radix(hex, 16),
+        None => operand.parse(),
+    };
+    parsed.map_err(|_| format!("malformed immediate `{operand}`"))
+}
+
+/// Disassembles `insts` into assembly text that [`parse`] accepts.
+///
+/// Every instruction is preceded by a comment holding its index so that
+/// `@N` branch targets are easy to follow.
+pub fn disassemble(insts: &[Inst]) -> String {
+    let mut out = String::new();
+    for (index, inst) in insts.iter().enumerate() {
+        writeln!(out, "; {index}").expect("writing to a String cannot fail");
+        write_inst(&mut out, inst).expect("writing to a String cannot fail");
+        out.push('\n');
+    }
+    out
+}
+
+/// Writes the assembly text of a single instruction into `out`.
+fn write_inst(out: &mut String, inst: &Inst) -> fmt::Result {
+    match *inst {
+        Inst::Add { result, lhs, rhs } => write!(out, "add r{result}, r{lhs}, r{rhs}"),
+        Inst::AddImm { result, src, imm } => write!(out, "add_imm r{result}, r{src}, {imm}"),
+        Inst::Sub { result, lhs, rhs } => write!(out, "sub r{result}, r{lhs}, r{rhs}"),
+        Inst::SubImm { result, src, imm } => write!(out, "sub_imm r{result}, r{src}, {imm}"),
+        Inst::Mul { result, lhs, rhs } => write!(out, "mul r{result}, r{lhs}, r{rhs}"),
+        Inst::MulImm { result, src, imm } => write!(out, "mul_imm r{result}, r{src}, {imm}"),
+        Inst::Div { result, lhs, rhs } => write!(out, "div r{result}, r{lhs}, r{rhs}"),
+        Inst::DivImm { result, src, imm } => write!(out, "div_imm r{result}, r{src}, {imm}"),
+        Inst::Rem { result, lhs, rhs } => write!(out, "rem r{result}, r{lhs}, r{rhs}"),
+        Inst::RemImm { result, src, imm } => write!(out, "rem_imm r{result}, r{src}, {imm}"),
+        Inst::Shl { result, lhs, rhs } => write!(out, "shl r{result}, r{lhs}, r{rhs}"),
+        Inst::ShlImm { result, src, imm } => write!(out, "shl_imm r{result}, r{src}, {imm}"),
+        Inst::ShrU { result, lhs, rhs } => write!(out, "shr_u r{result}, r{lhs}, r{rhs}"),
+        Inst::ShrUImm { result, src, imm } => write!(out, "shr_u_imm r{result}, r{src}, {imm}"),
+        Inst::ShrS { result, lhs, rhs } => write!(out, "shr_s r{result}, r{lhs}, r{rhs}"),
+        Inst::ShrSImm { result, src, imm } => write!(out, "shr_s_imm r{result}, r{src}, {imm}"),
+        Inst::Rotl { result, lhs, rhs } => write!(out, "rotl r{result}, r{lhs}, r{rhs}"),
+        Inst::RotlImm { result, src, imm } => write!(out, "rotl_imm r{result}, r{src}, {imm}"),
+        Inst::Rotr { result, lhs, rhs } => write!(out, "rotr r{result}, r{lhs}, r{rhs}"),
+        Inst::RotrImm { result, src, imm } => write!(out, "rotr_imm r{result}, r{src}, {imm}"),
+        Inst::Eq { result, lhs, rhs } => write!(out, "eq r{result}, r{lhs}, r{rhs}"),
+        Inst::EqImm { result, src, imm } => write!(out, "eq_imm r{result}, r{src}, {imm}"),
+        Inst::Ne { result, lhs, rhs } => write!(out, "ne r{result}, r{lhs}, r{rhs}"),
+        Inst::NeImm { result, src, imm } => write!(out, "ne_imm r{result}, r{src}, {imm}"),
+        Inst::LtU { result, lhs, rhs } => write!(out, "lt_u r{result}, r{lhs}, r{rhs}"),
+        Inst::LtUImm { result, src, imm } => write!(out, "lt_u_imm r{result}, r{src}, {imm}"),
+        Inst::LtS { result, lhs, rhs } => write!(out, "lt_s r{result}, r{lhs}, r{rhs}"),
+        Inst::LtSImm { result, src, imm } => write!(out, "lt_s_imm r{result}, r{src}, {imm}"),
+        Inst::Move { result, src } => write!(out, "mov r{result}, r{src}"),
+        Inst::Add32 { result, lhs, rhs } => write!(out, "add32 r{result}, r{lhs}, r{rhs}"),
+        Inst::Sub32 { result, lhs, rhs } => write!(out, "sub32 r{result}, r{lhs}, r{rhs}"),
+        Inst::Mul32 { result, lhs, rhs } => write!(out, "mul32 r{result}, r{lhs}, r{rhs}"),
+        Inst::LtS32 { result, lhs, rhs } => write!(out, "lt_s32 r{result}, r{lhs}, r{rhs}"),
+        Inst::Shl32 { result, lhs, rhs } => write!(out, "shl32 r{result}, r{lhs}, r{rhs}"),
+        Inst::ShrU32 { result, lhs, rhs } => write!(out, "shr_u32 r{result}, r{lhs}, r{rhs}"),
+        Inst::ShrS32 { result, lhs, rhs } => write!(out, "shr_s32 r{result}, r{lhs}, r{rhs}"),
+        Inst::AddChecked { result, lhs, rhs } => {
+            write!(out, "add_checked r{result}, r{lhs}, r{rhs}")
+        }
+        Inst::SubChecked { result, lhs, rhs } => {
+            write!(out, "sub_checked r{result}, r{lhs}, r{rhs}")
+        }
+        Inst::MulChecked { result, lhs, rhs } => {
+            write!(out, "mul_checked r{result}, r{lhs}, r{rhs}")
+        }
+        Inst::MulWide { hi, lo, lhs, rhs } => {
+            write!(out, "mul_wide r{hi}, r{lo}, r{lhs}, r{rhs}")
+        }
+        Inst::Popcnt { result, src } => write!(out, "popcnt r{result}, r{src}"),
+        Inst::Clz { result, src } => write!(out, "clz r{result}, r{src}"),
+        Inst::Ctz { result, src } => write!(out, "ctz r{result}, r{src}"),
+        Inst::RandNext { result } => write!(out, "rand_next r{result}"),
+        Inst::MinU { result, lhs, rhs } => write!(out, "min_u r{result}, r{lhs}, r{rhs}"),
+        Inst::MinUImm { result, src, imm } => write!(out, "min_u_imm r{result}, r{src}, {imm}"),
+        Inst::MaxU { result, lhs, rhs } => write!(out, "max_u r{result}, r{lhs}, r{rhs}"),
+        Inst::MaxUImm { result, src, imm } => write!(out, "max_u_imm r{result}, r{src}, {imm}"),
+        Inst::MinS { result, lhs, rhs } => write!(out, "min_s r{result}, r{lhs}, r{rhs}"),
+        Inst::MinSImm { result, src, imm } => write!(out, "min_s_imm r{result}, r{src}, {imm}"),
+        Inst::MaxS { result, lhs, rhs } => write!(out, "max_s r{result}, r{lhs}, r{rhs}"),
+        Inst::MaxSImm { result, src, imm } => write!(out, "max_s_imm r{result}, r{src}, {imm}"),
+        Inst::Select {
+            result,
+            cond,
+            if_nonzero,
+            if_zero,
+        } => write!(out, "select r{result}, r{cond}, r{if_nonzero}, r{if_zero}"),
+        Inst::FAdd { result, lhs, rhs } => write!(out, "fadd f{result}, f{lhs}, f{rhs}"),
+        Inst::FSub { result, lhs, rhs } => write!(out, "fsub f{result}, f{lhs}, f{rhs}"),
+        Inst::FMul { result, lhs, rhs } => write!(out, "fmul f{result}, f{lhs}, f{rhs}"),
+        Inst::FDiv { result, lhs, rhs } => write!(out, "fdiv f{result}, f{lhs}, f{rhs}"),
+        Inst::Load8 {
+            result,
+            base,
+            offset,
+        } => write!(out, "load8 r{result}, r{base}, {offset}"),
+        Inst::Load16 {
+            result,
+            base,
+            offset,
+        } => write!(out, "load16 r{result}, r{base}, {offset}"),
+        Inst::Load32 {
+            result,
+            base,
+            offset,
+        } => write!(out, "load32 r{result}, r{base}, {offset}"),
+        Inst::Load64 {
+            result,
+            base,
+            offset,
+        } => write!(out, "load64 r{result}, r{base}, {offset}"),
+        Inst::Store8 {
+            base,
+            offset,
+            value,
+        } => write!(out, "store8 r{base}, {offset}, r{value}"),
+        Inst::Store16 {
+            base,
+            offset,
+            value,
+        } => write!(out, "store16 r{base}, {offset}, r{value}"),
+        Inst::Store32 {
+            base,
+            offset,
+            value,
+        } => write!(out, "store32 r{base}, {offset}, r{value}"),
+        Inst::Store64 {
+            base,
+            offset,
+            value,
+        } => write!(out, "store64 r{base}, {offset}, r{value}"),
+        Inst::Branch { target } => write!(out, "branch @{target}"),
+        Inst::BranchEqz { target, condition } => write!(out, "branch_eqz @{target}, r{condition}"),
+        Inst::BranchNez { target, condition } => write!(out, "branch_nez @{target}, r{condition}"),
+        Inst::BranchEq { target, lhs, rhs } => write!(out, "branch_eq @{target}, r{lhs}, r{rhs}"),
+        Inst::BranchNe { target, lhs, rhs } => write!(out, "branch_ne @{target}, r{lhs}, r{rhs}"),
+        Inst::BranchLtU { target, lhs, rhs } => {
+            write!(out, "branch_lt_u @{target}, r{lhs}, r{rhs}")
+        }
+        Inst::BranchLtS { target, lhs, rhs } => {
+            write!(out, "branch_lt_s @{target}, r{lhs}, r{rhs}")
+        }
+        Inst::BranchTable {
+            index,
+            ref targets,
+            default,
+        } => {
+            write!(out, "branch_table r{index}")?;
+            for target in targets.iter() {
+                write!(out, ", @{target}")?;
+            }
+            write!(out, ", @{default}")
+        }
+        Inst::Call { target, num_args } => write!(out, "call @{target}, {num_args}"),
+        Inst::Nop => write!(out, "nop"),
+        Inst::Yield => write!(out, "yield"),
+        Inst::Unreachable => write!(out, "unreachable"),
+        Inst::Return { result } => write!(out, "ret r{result}"),
+    }
+}
+
+#[test]
+fn parse_counter_loop() {
+    let src = "
+        ; Store `repetitions` into r0.
+        ; Note: r0 is our loop counter register.
+        add_imm r0, r0, 100000000
+        ; Branch to the end if r0 is zero.
+        branch_eqz @4, r0
+        ; Decrease r0 by 1.
+        sub_imm r0, r0, 1
+        ; Jump back to the loop header.
+        branch @1
+        ; Return value and end function execution.
+        ret r0 ; r0 is zero at this point
+    ";
+    let expected = vec![
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 100_000_000,
+        },
+        Inst::BranchEqz {
+            target: Target(4),
+            condition: Register(0),
+        },
+        Inst::SubImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        Inst::Branch { target: Target(1) },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_eq!(parse(src), Ok(expected));
+}
+
+#[test]
+fn parse_errors() {
+    let error = parse("ret r0\n\nfoo r0").unwrap_err();
+    assert_eq!(error.line(), 3);
+    assert_eq!(error.message(), "unknown mnemonic `foo`");
+    let error = parse("add r0, r1").unwrap_err();
+    assert_eq!(error.line(), 1);
+    assert_eq!(error.message(), "expected 3 operands but found 2");
+    let error = parse("add_imm r0, x1, 5").unwrap_err();
+    assert_eq!(error.message(), "malformed register `x1`");
+    let error = parse("add_imm r0, r1, five").unwrap_err();
+    assert_eq!(error.message(), "malformed immediate `five`");
+    let error = parse("branch 4").unwrap_err();
+    assert_eq!(error.message(), "malformed target `4`");
+}
+
+#[test]
+fn parse_labels() {
+    let labeled = "
+        add_imm r0, r0, 100000000
+    loop:
+        branch_eqz end, r0
+        sub_imm r0, r0, 1
+        branch loop
+    end: ret r0
+    ";
+    let indexed = "
+        add_imm r0, r0, 100000000
+        branch_eqz @4, r0
+        sub_imm r0, r0, 1
+        branch @1
+        ret r0
+    ";
+    assert_eq!(parse(labeled), parse(indexed));
+    assert!(parse(indexed).is_ok());
+}
+
+#[test]
+fn parse_label_errors() {
+    let error = parse("branch end\nret r0").unwrap_err();
+    assert_eq!(error.line(), 1);
+    assert_eq!(error.message(), "undefined label `end`");
+    let error = parse("loop:\nbranch loop\nloop: ret r0").unwrap_err();
+    assert_eq!(error.line(), 3);
+    assert_eq!(
+        error.message(),
+        "duplicate label `loop` (first defined on line 1)"
+    );
+}
+
+#[test]
+fn disassemble_round_trip() {
+    let more_comps = vec![
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 100_000_000,
+        },
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 1,
+        },
+        Inst::BranchEqz {
+            target: Target(7),
+            condition: Register(0),
+        },
+        Inst::Mul {
+            result: Register(1),
+            lhs: Register(1),
+            rhs: Register(0),
+        },
+        Inst::Sub {
+            result: Register(1),
+            lhs: Register(1),
+            rhs: Register(0),
+        },
+        Inst::SubImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        Inst::Branch { target: Target(2) },
+        Inst::Return {
+            result: Register(1),
+        },
+    ];
+    assert_eq!(parse(&disassemble(&more_comps)), Ok(more_comps));
+    assert_eq!(parse(&disassemble(&[])), Ok(vec![]));
+    let mut rng = crate::bytecode::Rng(0x9E37_79B9_7F4A_7C15);
+    for len in 0..100 {
+        let insts = (0..len).map(|_| rng.inst()).collect::<Vec<_>>();
+        assert_eq!(parse(&disassemble(&insts)), Ok(insts));
+    }
+}
@@ -0,0 +1,69 @@
+#![allow(dead_code)]
+
+//! Tail call dispatch with guaranteed tail calls via the experimental `become` keyword.
+//!
+//! Every executed instruction tail calls [`dispatch`] for the next instruction.
+//! With the `nightly` feature `become` replaces the caller's stack frame by the callee's
+//! so that the stack usage stays constant no matter how many instructions are executed.
+//! Without it the tail calls fall back to ordinary calls which only run in constant stack
+//! space if the optimizer happens to eliminate them, otherwise every executed instruction
+//! grows the stack by one frame until it overflows.
+
+#[cfg(all(test, feature = "nightly"))]
+use crate::benchmark;
+
+#[cfg(test)]
+use super::switch;
+use super::{fetch, switch::Inst, Bits, Context, Outcome};
+
+/// Calls `$call` as a guaranteed tail call if the `nightly` feature is enabled.
+#[cfg(feature = "nightly")]
+macro_rules! tail_call {
+    ($call:expr) => {
+        become $call
+    };
+}
+
+/// Calls `$call` and returns its result, relying on the optimizer to eliminate the call.
+#[cfg(not(feature = "nightly"))]
+macro_rules! tail_call {
+    ($call:expr) => {
+        return $call
+    };
+}
+
+/// Executes the instruction at the `pc` and tail calls itself for the next instruction.
+fn dispatch(insts: &[Inst], context: &mut Context) -> Outcome {
+    let inst = match fetch(insts, context.pc) {
+        Ok(inst) => inst,
+        Err(trap) => return Outcome::Trap(trap),
+    };
+    match inst.execute(context) {
+        Outcome::Continue => tail_call!(dispatch(insts, context)),
+        outcome => outcome,
+    }
+}
+
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Bits {
+    dispatch(insts, context);
+    context.get_reg_abs(0)
+}
+
+#[test]
+#[cfg(feature = "nightly")]
+fn counter_loop() {
+    let insts = switch::counter_loop_insts(100_000_000);
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn more_comps() {
+    let insts = switch::more_comps_insts(1_000);
+    assert_eq!(
+        Ok(execute(&insts, &mut Context::default())),
+        switch::execute(&insts, &mut Context::default())
+    );
+}
@@ -0,0 +1,275 @@
+#![allow(dead_code)]
+
+#[cfg(test)]
+use crate::{benchmark, switch, Register, Target};
+
+use alloc::{boxed::Box, vec::Vec};
+use core::ops::Range;
+
+use super::{cfg, handler, subroutine_threaded, switch::Inst, Bits, Context, Outcome, TrapCode};
+
+/// The continuation of an executed [`Block`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Next {
+    /// Continue with the block at the given index.
+    Block(usize),
+    /// Return function execution.
+    Return,
+    /// Abort function execution due to a runtime fault.
+    Trap(TrapCode),
+}
+
+/// A compiled basic block.
+///
+/// Runs all straight-line instructions of the block without returning to the
+/// driver loop in between and yields the index of the block to continue with.
+pub type Block = Box<dyn Fn(&mut Context) -> Next>;
+
+/// Returns the indices of the basic blocks of `insts` that contain an instruction
+/// estimated to be hot by [`cfg::estimate_hot_blocks`].
+pub fn hot_blocks(insts: &[Inst]) -> Vec<usize> {
+    hot_ranges(&cfg::basic_blocks(insts), insts)
+}
+
+/// Returns the indices of the `ranges` that contain a hot instruction of `insts`.
+fn hot_ranges(ranges: &[Range<usize>], insts: &[Inst]) -> Vec<usize> {
+    let hot = cfg::estimate_hot_blocks(insts);
+    ranges
+        .iter()
+        .enumerate()
+        .filter(|(_, range)| hot.iter().any(|index| range.contains(index)))
+        .map(|(block, _)| block)
+        .collect()
+}
+
+/// Compiles the list of instructions into one [`Block`] per basic block.
+///
+/// Blocks estimated to be hot are compiled before all other blocks.
+///
+/// # Errors
+///
+/// Returns the first [`Inst::Call`] since a call leaves its block in the middle.
+pub fn compile(insts: &[Inst]) -> Result<Vec<Block>, Inst> {
+    if let Some(call) = insts.iter().find(|inst| matches!(inst, Inst::Call { .. })) {
+        return Err(call.clone());
+    }
+    let ranges = cfg::basic_blocks(insts);
+    // Maps every instruction index to its block index.
+    // Indices past the end map to `ranges.len()` which no block has.
+    let mut block_of = vec![ranges.len(); insts.len()];
+    for (block, range) in ranges.iter().enumerate() {
+        block_of[range.clone()].fill(block);
+    }
+    let block_of = |pc: usize| block_of.get(pc).copied().unwrap_or(ranges.len());
+    let compile_block = |block: usize| {
+        let (last, body) = insts[ranges[block].clone()]
+            .split_last()
+            .expect("basic blocks are never empty");
+        let body = subroutine_threaded::compile(body);
+        let exit = compile_exit(last.clone(), block + 1, block_of);
+        Box::new(move |context: &mut Context| {
+            for subroutine in &body {
+                if let Outcome::Trap(trap) = subroutine(context) {
+                    return Next::Trap(trap);
+                }
+            }
+            exit(context)
+        }) as Block
+    };
+    let hot = hot_ranges(&ranges, insts);
+    let cold = (0..ranges.len()).filter(|block| !hot.contains(block));
+    let mut blocks = (0..ranges.len()).map(|_| None).collect::<Vec<_>>();
+    for block in hot.iter().copied().chain(cold) {
+        blocks[block] = Some(compile_block(block));
+    }
+    Ok(blocks
+        .into_iter()
+        .map(|block| block.expect("every block has been compiled"))
+        .collect())
+}
+
+/// Compiles the last instruction `inst` of a block that is followed by the block `next`.
+///
+/// Branch targets are resolved to their blocks via `block_of` during compilation.
+fn compile_exit(inst: Inst, next: usize, block_of: impl Fn(usize) -> usize) -> Block {
+    match inst {
+        Inst::Branch { target } => {
+            let target_block = block_of(target.0);
+            Box::new(move |context| {
+                handler::branch(context, target);
+                Next::Block(target_block)
+            })
+        }
+        Inst::BranchEqz { target, condition } => {
+            let target_block = block_of(target.0);
+            Box::new(move |context| {
+                handler::branch_eqz(context, target, condition);
+                if context.pc == target.0 {
+                    Next::Block(target_block)
+                } else {
+                    Next::Block(next)
+                }
+            })
+        }
+        Inst::BranchNez { target, condition } => {
+            let target_block = block_of(target.0);
+            Box::new(move |context| {
+                handler::branch_nez(context, target, condition);
+                if context.pc == target.0 {
+                    Next::Block(target_block)
+                } else {
+                    Next::Block(next)
+                }
+            })
+        }
+        Inst::BranchEq { target, .. }
+        | Inst::BranchNe { target, .. }
+        | Inst::BranchLtU { target, .. }
+        | Inst::BranchLtS { target, .. } => {
+            let target_block = block_of(target.0);
+            let branch = subroutine_threaded::compile_inst(inst);
+            Box::new(move |context| {
+                branch(context);
+                if context.pc == target.0 {
+                    Next::Block(target_block)
+                } else {
+                    Next::Block(next)
+                }
+            })
+        }
+        Inst::BranchTable {
+            index,
+            targets,
+            default,
+        } => {
+            let target_blocks = targets
+                .iter()
+                .map(|&target| block_of(target.0))
+                .collect::<Vec<_>>();
+            let default_block = block_of(default.0);
+            Box::new(move |context| {
+                let selected = usize::try_from(context.get_reg(index)).ok();
+                handler::branch_table(context, index, &targets, default);
+                let block = selected.and_then(|selected| target_blocks.get(selected));
+                Next::Block(block.copied().unwrap_or(default_block))
+            })
+        }
+        inst => {
+            let subroutine = subroutine_threaded::compile_inst(inst);
+            Box::new(move |context| match subroutine(context) {
+                Outcome::Continue | Outcome::Yield => Next::Block(next),
+                Outcome::Return => Next::Return,
+                Outcome::Trap(trap) => Next::Trap(trap),
+            })
+        }
+    }
+}
+
+/// Executes the list of blocks using the given [`Context`] and returns the result in register 0.
+pub fn execute(blocks: &[Block], context: &mut Context) -> Bits {
+    let mut block = 0;
+    loop {
+        match blocks[block](context) {
+            Next::Block(next) => block = next,
+            Next::Return | Next::Trap(_) => return context.get_reg_abs(0),
+        }
+    }
+}
+
+/// Runs the list of blocks using the given [`Context`] and returns the result
+/// together with the number of dispatched blocks.
+///
+/// # Errors
+///
+/// If the execution traps or continues past the last block.
+pub fn run_counted(blocks: &[Block], context: &mut Context) -> (Result<Bits, TrapCode>, usize) {
+    let mut block = 0;
+    let mut dispatches = 0;
+    loop {
+        let Some(current) = blocks.get(block) else {
+            return (Err(TrapCode::OutOfBoundsPc), dispatches);
+        };
+        dispatches += 1;
+        match current(context) {
+            Next::Block(next) => block = next,
+            Next::Return => return (Ok(context.get_reg_abs(0)), dispatches),
+            Next::Trap(trap) => return (Err(trap), dispatches),
+        }
+    }
+}
+
+#[test]
+fn counter_loop() {
+    let insts = switch::counter_loop_insts(100_000_000);
+    let blocks = compile(&insts).ok().unwrap();
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&blocks, &mut context));
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn counter_loop_dispatches() {
+    use super::closure_loop;
+
+    let repetitions = 1_000;
+    let insts = switch::counter_loop_insts(repetitions);
+    let blocks = compile(&insts).ok().unwrap();
+    let (result, block_dispatches) = run_counted(&blocks, &mut Context::default());
+    // `closure_loop` dispatches once per executed instruction.
+    let (_, trace) = switch::run_trace(&insts, &mut Context::default());
+    let closure_insts = closure_loop::counter_loop_insts(repetitions);
+    assert_eq!(
+        result,
+        closure_loop::run(&closure_insts, &mut Context::default())
+    );
+    // The decrement and the back edge of the loop form a single block.
+    assert_eq!(trace.len(), 3 * repetitions as usize + 3);
+    assert_eq!(block_dispatches, 2 * repetitions as usize + 3);
+}
+
+#[test]
+fn counter_loop_hot_blocks() {
+    let insts = switch::counter_loop_insts(1_000);
+    // The loop header and the block of the decrement and the back edge.
+    assert_eq!(hot_blocks(&insts), [1, 2]);
+}
+
+#[test]
+fn more_comps() {
+    let insts = switch::more_comps_insts(1_000);
+    let blocks = compile(&insts).ok().unwrap();
+    assert_eq!(
+        run_counted(&blocks, &mut Context::default()).0,
+        switch::run(&insts, &mut Context::default())
+    );
+}
+
+#[test]
+fn traps() {
+    let insts = [
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 1,
+        },
+        Inst::Div {
+            result: Register(0),
+            lhs: Register(1),
+            rhs: Register(0),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let blocks = compile(&insts).ok().unwrap();
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(
+        run_counted(&blocks, &mut Context::default()),
+        (Err(TrapCode::DivisionByZero), 1)
+    );
+    let insts = [Inst::Call {
+        target: Target(0),
+        num_args: 0,
+    }];
+    assert!(matches!(compile(&insts), Err(Inst::Call { .. })));
+}
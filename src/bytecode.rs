@@ -0,0 +1,1573 @@
+#![allow(dead_code)]
+
+use alloc::vec::Vec;
+
+use super::{switch::Inst, Bits, Context, Outcome, Register, Target};
+
+/// The opcodes of the encoded instructions.
+///
+/// Every encoded instruction starts with its opcode byte followed by its
+/// operands encoded as unsigned LEB128 variable length integers.
+pub(crate) mod opcode {
+    pub const ADD: u8 = 0x00;
+    pub const ADD_IMM: u8 = 0x01;
+    pub const SUB: u8 = 0x02;
+    pub const SUB_IMM: u8 = 0x03;
+    pub const MUL: u8 = 0x04;
+    pub const MUL_IMM: u8 = 0x05;
+    pub const DIV: u8 = 0x06;
+    pub const DIV_IMM: u8 = 0x07;
+    pub const REM: u8 = 0x08;
+    pub const REM_IMM: u8 = 0x09;
+    pub const SHL: u8 = 0x0A;
+    pub const SHL_IMM: u8 = 0x0B;
+    pub const SHR_U: u8 = 0x0C;
+    pub const SHR_U_IMM: u8 = 0x0D;
+    pub const SHR_S: u8 = 0x0E;
+    pub const SHR_S_IMM: u8 = 0x0F;
+    pub const ROTL: u8 = 0x10;
+    pub const ROTL_IMM: u8 = 0x11;
+    pub const ROTR: u8 = 0x12;
+    pub const ROTR_IMM: u8 = 0x13;
+    pub const BRANCH: u8 = 0x14;
+    pub const BRANCH_EQZ: u8 = 0x15;
+    pub const BRANCH_NEZ: u8 = 0x16;
+    pub const UNREACHABLE: u8 = 0x17;
+    pub const RETURN: u8 = 0x18;
+    pub const LOAD8: u8 = 0x19;
+    pub const LOAD16: u8 = 0x1A;
+    pub const LOAD32: u8 = 0x1B;
+    pub const LOAD64: u8 = 0x1C;
+    pub const STORE8: u8 = 0x1D;
+    pub const STORE16: u8 = 0x1E;
+    pub const STORE32: u8 = 0x1F;
+    pub const STORE64: u8 = 0x20;
+    pub const CALL: u8 = 0x21;
+    pub const FADD: u8 = 0x22;
+    pub const FSUB: u8 = 0x23;
+    pub const FMUL: u8 = 0x24;
+    pub const FDIV: u8 = 0x25;
+    pub const SELECT: u8 = 0x26;
+    pub const EQ: u8 = 0x27;
+    pub const EQ_IMM: u8 = 0x28;
+    pub const NE: u8 = 0x29;
+    pub const NE_IMM: u8 = 0x2A;
+    pub const LT_U: u8 = 0x2B;
+    pub const LT_U_IMM: u8 = 0x2C;
+    pub const LT_S: u8 = 0x2D;
+    pub const LT_S_IMM: u8 = 0x2E;
+    pub const MOVE: u8 = 0x2F;
+    pub const ADD32: u8 = 0x30;
+    pub const SUB32: u8 = 0x31;
+    pub const MUL32: u8 = 0x32;
+    pub const LT_S32: u8 = 0x33;
+    pub const SHL32: u8 = 0x34;
+    pub const SHR_U32: u8 = 0x35;
+    pub const SHR_S32: u8 = 0x36;
+    pub const ADD_CHECKED: u8 = 0x37;
+    pub const SUB_CHECKED: u8 = 0x38;
+    pub const MUL_CHECKED: u8 = 0x39;
+    pub const POPCNT: u8 = 0x3A;
+    pub const CLZ: u8 = 0x3B;
+    pub const CTZ: u8 = 0x3C;
+    pub const NOP: u8 = 0x3D;
+    pub const BRANCH_TABLE: u8 = 0x3E;
+    pub const MIN_U: u8 = 0x3F;
+    pub const MIN_U_IMM: u8 = 0x40;
+    pub const MAX_U: u8 = 0x41;
+    pub const MAX_U_IMM: u8 = 0x42;
+    pub const MIN_S: u8 = 0x43;
+    pub const MIN_S_IMM: u8 = 0x44;
+    pub const MAX_S: u8 = 0x45;
+    pub const MAX_S_IMM: u8 = 0x46;
+    pub const RAND_NEXT: u8 = 0x47;
+    pub const YIELD: u8 = 0x48;
+    pub const BRANCH_EQ: u8 = 0x49;
+    pub const BRANCH_NE: u8 = 0x4A;
+    pub const BRANCH_LT_U: u8 = 0x4B;
+    pub const BRANCH_LT_S: u8 = 0x4C;
+    pub const MUL_WIDE: u8 = 0x4D;
+}
+
+/// Errors that can occur when decoding byte-code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The byte-code ended in the middle of an instruction.
+    UnexpectedEnd,
+    /// The byte-code contained an unknown opcode.
+    UnknownOpcode(u8),
+    /// A variable length integer operand did not fit its operand type.
+    OperandOverflow,
+}
+
+/// Encodes the list of instructions into a compact byte-code.
+pub fn encode(insts: &[Inst]) -> Vec<u8> {
+    let mut encoder = Encoder::default();
+    for inst in insts {
+        encoder.inst(inst);
+    }
+    encoder.bytes
+}
+
+/// Decodes the byte-code back into its list of instructions.
+///
+/// # Errors
+///
+/// If the byte-code is truncated or malformed.
+pub fn decode(bytes: &[u8]) -> Result<Vec<Inst>, DecodeError> {
+    let mut decoder = Decoder { bytes };
+    let mut insts = Vec::new();
+    while !decoder.is_empty() {
+        insts.push(decoder.inst()?);
+    }
+    Ok(insts)
+}
+
+/// Executes the byte-code by decoding every instruction right before it is executed.
+///
+/// Only an index of the byte offsets of all instructions is built up front since branches
+/// require random access. The decoded instructions themselves are never stored.
+///
+/// # Errors
+///
+/// If the byte-code is truncated or malformed.
+pub fn execute_bytecode(code: &[u8], context: &mut Context) -> Result<Bits, DecodeError> {
+    let offsets = offsets(code)?;
+    loop {
+        let offset = offsets.get(context.pc).copied().unwrap_or(code.len());
+        let inst = Decoder {
+            bytes: &code[offset..],
+        }
+        .inst()?;
+        match inst.execute(context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return Ok(context.get_reg_abs(0)),
+        }
+    }
+}
+
+/// Returns the byte offset of every instruction in the byte-code indexed by its [`Target`].
+fn offsets(code: &[u8]) -> Result<Vec<usize>, DecodeError> {
+    let mut decoder = Decoder { bytes: code };
+    let mut offsets = Vec::new();
+    while !decoder.is_empty() {
+        offsets.push(code.len() - decoder.bytes.len());
+        decoder.inst()?;
+    }
+    Ok(offsets)
+}
+
+/// Encodes instructions into byte-code.
+#[derive(Default)]
+struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    fn opcode(&mut self, opcode: u8) {
+        self.bytes.push(opcode);
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.bytes.push(byte);
+                return;
+            }
+            self.bytes.push(byte | 0x80);
+        }
+    }
+
+    fn register(&mut self, register: Register) {
+        self.varint(register.0 as u64);
+    }
+
+    fn target(&mut self, target: Target) {
+        self.varint(target.0 as u64);
+    }
+
+    fn bits(&mut self, bits: Bits) {
+        self.varint(bits);
+    }
+
+    fn inst(&mut self, inst: &Inst) {
+        match *inst {
+            Inst::Add { result, lhs, rhs } => {
+                self.opcode(opcode::ADD);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::AddImm { result, src, imm } => {
+                self.opcode(opcode::ADD_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::Sub { result, lhs, rhs } => {
+                self.opcode(opcode::SUB);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::SubImm { result, src, imm } => {
+                self.opcode(opcode::SUB_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::Mul { result, lhs, rhs } => {
+                self.opcode(opcode::MUL);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::MulImm { result, src, imm } => {
+                self.opcode(opcode::MUL_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::Div { result, lhs, rhs } => {
+                self.opcode(opcode::DIV);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::DivImm { result, src, imm } => {
+                self.opcode(opcode::DIV_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::Rem { result, lhs, rhs } => {
+                self.opcode(opcode::REM);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::RemImm { result, src, imm } => {
+                self.opcode(opcode::REM_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::Shl { result, lhs, rhs } => {
+                self.opcode(opcode::SHL);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::ShlImm { result, src, imm } => {
+                self.opcode(opcode::SHL_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::ShrU { result, lhs, rhs } => {
+                self.opcode(opcode::SHR_U);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::ShrUImm { result, src, imm } => {
+                self.opcode(opcode::SHR_U_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::ShrS { result, lhs, rhs } => {
+                self.opcode(opcode::SHR_S);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::ShrSImm { result, src, imm } => {
+                self.opcode(opcode::SHR_S_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::Rotl { result, lhs, rhs } => {
+                self.opcode(opcode::ROTL);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::RotlImm { result, src, imm } => {
+                self.opcode(opcode::ROTL_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::Rotr { result, lhs, rhs } => {
+                self.opcode(opcode::ROTR);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::RotrImm { result, src, imm } => {
+                self.opcode(opcode::ROTR_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::Eq { result, lhs, rhs } => {
+                self.opcode(opcode::EQ);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::EqImm { result, src, imm } => {
+                self.opcode(opcode::EQ_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::Ne { result, lhs, rhs } => {
+                self.opcode(opcode::NE);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::NeImm { result, src, imm } => {
+                self.opcode(opcode::NE_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::LtU { result, lhs, rhs } => {
+                self.opcode(opcode::LT_U);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::LtUImm { result, src, imm } => {
+                self.opcode(opcode::LT_U_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::LtS { result, lhs, rhs } => {
+                self.opcode(opcode::LT_S);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::LtSImm { result, src, imm } => {
+                self.opcode(opcode::LT_S_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::Move { result, src } => {
+                self.opcode(opcode::MOVE);
+                self.register(result);
+                self.register(src);
+            }
+            Inst::Add32 { result, lhs, rhs } => {
+                self.opcode(opcode::ADD32);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::Sub32 { result, lhs, rhs } => {
+                self.opcode(opcode::SUB32);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::Mul32 { result, lhs, rhs } => {
+                self.opcode(opcode::MUL32);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::LtS32 { result, lhs, rhs } => {
+                self.opcode(opcode::LT_S32);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::Shl32 { result, lhs, rhs } => {
+                self.opcode(opcode::SHL32);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::ShrU32 { result, lhs, rhs } => {
+                self.opcode(opcode::SHR_U32);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::ShrS32 { result, lhs, rhs } => {
+                self.opcode(opcode::SHR_S32);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::AddChecked { result, lhs, rhs } => {
+                self.opcode(opcode::ADD_CHECKED);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::SubChecked { result, lhs, rhs } => {
+                self.opcode(opcode::SUB_CHECKED);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::MulChecked { result, lhs, rhs } => {
+                self.opcode(opcode::MUL_CHECKED);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::MulWide { hi, lo, lhs, rhs } => {
+                self.opcode(opcode::MUL_WIDE);
+                self.register(hi);
+                self.register(lo);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::Popcnt { result, src } => {
+                self.opcode(opcode::POPCNT);
+                self.register(result);
+                self.register(src);
+            }
+            Inst::Clz { result, src } => {
+                self.opcode(opcode::CLZ);
+                self.register(result);
+                self.register(src);
+            }
+            Inst::Ctz { result, src } => {
+                self.opcode(opcode::CTZ);
+                self.register(result);
+                self.register(src);
+            }
+            Inst::RandNext { result } => {
+                self.opcode(opcode::RAND_NEXT);
+                self.register(result);
+            }
+            Inst::MinU { result, lhs, rhs } => {
+                self.opcode(opcode::MIN_U);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::MinUImm { result, src, imm } => {
+                self.opcode(opcode::MIN_U_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::MaxU { result, lhs, rhs } => {
+                self.opcode(opcode::MAX_U);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::MaxUImm { result, src, imm } => {
+                self.opcode(opcode::MAX_U_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::MinS { result, lhs, rhs } => {
+                self.opcode(opcode::MIN_S);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::MinSImm { result, src, imm } => {
+                self.opcode(opcode::MIN_S_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::MaxS { result, lhs, rhs } => {
+                self.opcode(opcode::MAX_S);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::MaxSImm { result, src, imm } => {
+                self.opcode(opcode::MAX_S_IMM);
+                self.register(result);
+                self.register(src);
+                self.bits(imm);
+            }
+            Inst::Select {
+                result,
+                cond,
+                if_nonzero,
+                if_zero,
+            } => {
+                self.opcode(opcode::SELECT);
+                self.register(result);
+                self.register(cond);
+                self.register(if_nonzero);
+                self.register(if_zero);
+            }
+            Inst::FAdd { result, lhs, rhs } => {
+                self.opcode(opcode::FADD);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::FSub { result, lhs, rhs } => {
+                self.opcode(opcode::FSUB);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::FMul { result, lhs, rhs } => {
+                self.opcode(opcode::FMUL);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::FDiv { result, lhs, rhs } => {
+                self.opcode(opcode::FDIV);
+                self.register(result);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::Load8 {
+                result,
+                base,
+                offset,
+            } => {
+                self.opcode(opcode::LOAD8);
+                self.register(result);
+                self.register(base);
+                self.bits(offset);
+            }
+            Inst::Load16 {
+                result,
+                base,
+                offset,
+            } => {
+                self.opcode(opcode::LOAD16);
+                self.register(result);
+                self.register(base);
+                self.bits(offset);
+            }
+            Inst::Load32 {
+                result,
+                base,
+                offset,
+            } => {
+                self.opcode(opcode::LOAD32);
+                self.register(result);
+                self.register(base);
+                self.bits(offset);
+            }
+            Inst::Load64 {
+                result,
+                base,
+                offset,
+            } => {
+                self.opcode(opcode::LOAD64);
+                self.register(result);
+                self.register(base);
+                self.bits(offset);
+            }
+            Inst::Store8 {
+                base,
+                offset,
+                value,
+            } => {
+                self.opcode(opcode::STORE8);
+                self.register(base);
+                self.bits(offset);
+                self.register(value);
+            }
+            Inst::Store16 {
+                base,
+                offset,
+                value,
+            } => {
+                self.opcode(opcode::STORE16);
+                self.register(base);
+                self.bits(offset);
+                self.register(value);
+            }
+            Inst::Store32 {
+                base,
+                offset,
+                value,
+            } => {
+                self.opcode(opcode::STORE32);
+                self.register(base);
+                self.bits(offset);
+                self.register(value);
+            }
+            Inst::Store64 {
+                base,
+                offset,
+                value,
+            } => {
+                self.opcode(opcode::STORE64);
+                self.register(base);
+                self.bits(offset);
+                self.register(value);
+            }
+            Inst::Branch { target } => {
+                self.opcode(opcode::BRANCH);
+                self.target(target);
+            }
+            Inst::BranchEqz { target, condition } => {
+                self.opcode(opcode::BRANCH_EQZ);
+                self.target(target);
+                self.register(condition);
+            }
+            Inst::BranchNez { target, condition } => {
+                self.opcode(opcode::BRANCH_NEZ);
+                self.target(target);
+                self.register(condition);
+            }
+            Inst::BranchEq { target, lhs, rhs } => {
+                self.opcode(opcode::BRANCH_EQ);
+                self.target(target);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::BranchNe { target, lhs, rhs } => {
+                self.opcode(opcode::BRANCH_NE);
+                self.target(target);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::BranchLtU { target, lhs, rhs } => {
+                self.opcode(opcode::BRANCH_LT_U);
+                self.target(target);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::BranchLtS { target, lhs, rhs } => {
+                self.opcode(opcode::BRANCH_LT_S);
+                self.target(target);
+                self.register(lhs);
+                self.register(rhs);
+            }
+            Inst::BranchTable {
+                index,
+                ref targets,
+                default,
+            } => {
+                self.opcode(opcode::BRANCH_TABLE);
+                self.register(index);
+                self.varint(targets.len() as u64);
+                for &target in targets.iter() {
+                    self.target(target);
+                }
+                self.target(default);
+            }
+            Inst::Call { target, num_args } => {
+                self.opcode(opcode::CALL);
+                self.target(target);
+                self.varint(num_args as u64);
+            }
+            Inst::Nop => self.opcode(opcode::NOP),
+            Inst::Yield => self.opcode(opcode::YIELD),
+            Inst::Unreachable => self.opcode(opcode::UNREACHABLE),
+            Inst::Return { result } => {
+                self.opcode(opcode::RETURN);
+                self.register(result);
+            }
+        }
+    }
+}
+
+/// Decodes instructions from byte-code.
+struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl Decoder<'_> {
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let (first, rest) = self.bytes.split_first().ok_or(DecodeError::UnexpectedEnd)?;
+        self.bytes = rest;
+        Ok(*first)
+    }
+
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0_u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            let payload = u64::from(byte & 0x7F);
+            if shift == 63 && payload > 1 || shift > 63 {
+                return Err(DecodeError::OperandOverflow);
+            }
+            value |= payload << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    fn register(&mut self) -> Result<Register, DecodeError> {
+        usize::try_from(self.varint()?)
+            .map(Register)
+            .map_err(|_| DecodeError::OperandOverflow)
+    }
+
+    fn target(&mut self) -> Result<Target, DecodeError> {
+        usize::try_from(self.varint()?)
+            .map(Target)
+            .map_err(|_| DecodeError::OperandOverflow)
+    }
+
+    fn bits(&mut self) -> Result<Bits, DecodeError> {
+        self.varint()
+    }
+
+    fn inst(&mut self) -> Result<Inst, DecodeError> {
+        let inst = match self.byte()? {
+            opcode::ADD => Inst::Add {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::ADD_IMM => Inst::AddImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::SUB => Inst::Sub {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::SUB_IMM => Inst::SubImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::MUL => Inst::Mul {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::MUL_IMM => Inst::MulImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::DIV => Inst::Div {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::DIV_IMM => Inst::DivImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::REM => Inst::Rem {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::REM_IMM => Inst::RemImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::SHL => Inst::Shl {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::SHL_IMM => Inst::ShlImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::SHR_U => Inst::ShrU {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::SHR_U_IMM => Inst::ShrUImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::SHR_S => Inst::ShrS {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::SHR_S_IMM => Inst::ShrSImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::ROTL => Inst::Rotl {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::ROTL_IMM => Inst::RotlImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::ROTR => Inst::Rotr {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::ROTR_IMM => Inst::RotrImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::EQ => Inst::Eq {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::EQ_IMM => Inst::EqImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::NE => Inst::Ne {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::NE_IMM => Inst::NeImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::LT_U => Inst::LtU {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::LT_U_IMM => Inst::LtUImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::LT_S => Inst::LtS {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::LT_S_IMM => Inst::LtSImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::MOVE => Inst::Move {
+                result: self.register()?,
+                src: self.register()?,
+            },
+            opcode::ADD32 => Inst::Add32 {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::SUB32 => Inst::Sub32 {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::MUL32 => Inst::Mul32 {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::LT_S32 => Inst::LtS32 {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::SHL32 => Inst::Shl32 {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::SHR_U32 => Inst::ShrU32 {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::SHR_S32 => Inst::ShrS32 {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::ADD_CHECKED => Inst::AddChecked {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::SUB_CHECKED => Inst::SubChecked {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::MUL_CHECKED => Inst::MulChecked {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::MUL_WIDE => Inst::MulWide {
+                hi: self.register()?,
+                lo: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::POPCNT => Inst::Popcnt {
+                result: self.register()?,
+                src: self.register()?,
+            },
+            opcode::CLZ => Inst::Clz {
+                result: self.register()?,
+                src: self.register()?,
+            },
+            opcode::CTZ => Inst::Ctz {
+                result: self.register()?,
+                src: self.register()?,
+            },
+            opcode::RAND_NEXT => Inst::RandNext {
+                result: self.register()?,
+            },
+            opcode::MIN_U => Inst::MinU {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::MIN_U_IMM => Inst::MinUImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::MAX_U => Inst::MaxU {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::MAX_U_IMM => Inst::MaxUImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::MIN_S => Inst::MinS {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::MIN_S_IMM => Inst::MinSImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::MAX_S => Inst::MaxS {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::MAX_S_IMM => Inst::MaxSImm {
+                result: self.register()?,
+                src: self.register()?,
+                imm: self.bits()?,
+            },
+            opcode::SELECT => Inst::Select {
+                result: self.register()?,
+                cond: self.register()?,
+                if_nonzero: self.register()?,
+                if_zero: self.register()?,
+            },
+            opcode::FADD => Inst::FAdd {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::FSUB => Inst::FSub {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::FMUL => Inst::FMul {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::FDIV => Inst::FDiv {
+                result: self.register()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::LOAD8 => Inst::Load8 {
+                result: self.register()?,
+                base: self.register()?,
+                offset: self.bits()?,
+            },
+            opcode::LOAD16 => Inst::Load16 {
+                result: self.register()?,
+                base: self.register()?,
+                offset: self.bits()?,
+            },
+            opcode::LOAD32 => Inst::Load32 {
+                result: self.register()?,
+                base: self.register()?,
+                offset: self.bits()?,
+            },
+            opcode::LOAD64 => Inst::Load64 {
+                result: self.register()?,
+                base: self.register()?,
+                offset: self.bits()?,
+            },
+            opcode::STORE8 => Inst::Store8 {
+                base: self.register()?,
+                offset: self.bits()?,
+                value: self.register()?,
+            },
+            opcode::STORE16 => Inst::Store16 {
+                base: self.register()?,
+                offset: self.bits()?,
+                value: self.register()?,
+            },
+            opcode::STORE32 => Inst::Store32 {
+                base: self.register()?,
+                offset: self.bits()?,
+                value: self.register()?,
+            },
+            opcode::STORE64 => Inst::Store64 {
+                base: self.register()?,
+                offset: self.bits()?,
+                value: self.register()?,
+            },
+            opcode::BRANCH => Inst::Branch {
+                target: self.target()?,
+            },
+            opcode::BRANCH_EQZ => Inst::BranchEqz {
+                target: self.target()?,
+                condition: self.register()?,
+            },
+            opcode::BRANCH_NEZ => Inst::BranchNez {
+                target: self.target()?,
+                condition: self.register()?,
+            },
+            opcode::BRANCH_EQ => Inst::BranchEq {
+                target: self.target()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::BRANCH_NE => Inst::BranchNe {
+                target: self.target()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::BRANCH_LT_U => Inst::BranchLtU {
+                target: self.target()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::BRANCH_LT_S => Inst::BranchLtS {
+                target: self.target()?,
+                lhs: self.register()?,
+                rhs: self.register()?,
+            },
+            opcode::BRANCH_TABLE => {
+                let index = self.register()?;
+                let len = self.varint()?;
+                Inst::BranchTable {
+                    index,
+                    targets: (0..len).map(|_| self.target()).collect::<Result<_, _>>()?,
+                    default: self.target()?,
+                }
+            }
+            opcode::CALL => Inst::Call {
+                target: self.target()?,
+                num_args: usize::try_from(self.varint()?)
+                    .map_err(|_| DecodeError::OperandOverflow)?,
+            },
+            opcode::NOP => Inst::Nop,
+            opcode::YIELD => Inst::Yield,
+            opcode::UNREACHABLE => Inst::Unreachable,
+            opcode::RETURN => Inst::Return {
+                result: self.register()?,
+            },
+            unknown => return Err(DecodeError::UnknownOpcode(unknown)),
+        };
+        Ok(inst)
+    }
+}
+
+/// A simple `xorshift` pseudo random number generator for the round-trip tests.
+#[cfg(test)]
+pub(crate) struct Rng(pub(crate) u64);
+
+#[cfg(test)]
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn register(&mut self) -> Register {
+        Register((self.next() % 16) as usize)
+    }
+
+    fn target(&mut self) -> Target {
+        Target((self.next() >> (self.next() % 64)) as usize)
+    }
+
+    pub(crate) fn inst(&mut self) -> Inst {
+        match self.next() % 78 {
+            0 => Inst::Add {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            1 => Inst::AddImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            2 => Inst::Sub {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            3 => Inst::SubImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            4 => Inst::Mul {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            5 => Inst::MulImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            6 => Inst::Div {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            7 => Inst::DivImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            8 => Inst::Rem {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            9 => Inst::RemImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            10 => Inst::Shl {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            11 => Inst::ShlImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            12 => Inst::ShrU {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            13 => Inst::ShrUImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            14 => Inst::ShrS {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            15 => Inst::ShrSImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            16 => Inst::Rotl {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            17 => Inst::RotlImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            18 => Inst::Rotr {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            19 => Inst::RotrImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            20 => Inst::Branch {
+                target: self.target(),
+            },
+            21 => Inst::BranchEqz {
+                target: self.target(),
+                condition: self.register(),
+            },
+            22 => Inst::BranchNez {
+                target: self.target(),
+                condition: self.register(),
+            },
+            23 => Inst::Unreachable,
+            24 => Inst::Load8 {
+                result: self.register(),
+                base: self.register(),
+                offset: self.next(),
+            },
+            25 => Inst::Load16 {
+                result: self.register(),
+                base: self.register(),
+                offset: self.next(),
+            },
+            26 => Inst::Load32 {
+                result: self.register(),
+                base: self.register(),
+                offset: self.next(),
+            },
+            27 => Inst::Load64 {
+                result: self.register(),
+                base: self.register(),
+                offset: self.next(),
+            },
+            28 => Inst::Store8 {
+                base: self.register(),
+                offset: self.next(),
+                value: self.register(),
+            },
+            29 => Inst::Store16 {
+                base: self.register(),
+                offset: self.next(),
+                value: self.register(),
+            },
+            30 => Inst::Store32 {
+                base: self.register(),
+                offset: self.next(),
+                value: self.register(),
+            },
+            31 => Inst::Store64 {
+                base: self.register(),
+                offset: self.next(),
+                value: self.register(),
+            },
+            32 => Inst::Call {
+                target: self.target(),
+                num_args: (self.register()).0,
+            },
+            33 => Inst::FAdd {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            34 => Inst::FSub {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            35 => Inst::FMul {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            36 => Inst::FDiv {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            37 => Inst::Select {
+                result: self.register(),
+                cond: self.register(),
+                if_nonzero: self.register(),
+                if_zero: self.register(),
+            },
+            38 => Inst::Eq {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            39 => Inst::EqImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            40 => Inst::Ne {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            41 => Inst::NeImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            42 => Inst::LtU {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            43 => Inst::LtUImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            44 => Inst::LtS {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            45 => Inst::LtSImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            46 => Inst::Move {
+                result: self.register(),
+                src: self.register(),
+            },
+            47 => Inst::Add32 {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            48 => Inst::Sub32 {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            49 => Inst::Mul32 {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            50 => Inst::LtS32 {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            51 => Inst::Shl32 {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            52 => Inst::ShrU32 {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            53 => Inst::ShrS32 {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            54 => Inst::AddChecked {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            55 => Inst::SubChecked {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            56 => Inst::MulChecked {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            57 => Inst::Popcnt {
+                result: self.register(),
+                src: self.register(),
+            },
+            58 => Inst::Clz {
+                result: self.register(),
+                src: self.register(),
+            },
+            59 => Inst::Ctz {
+                result: self.register(),
+                src: self.register(),
+            },
+            60 => Inst::Nop,
+            61 => Inst::BranchTable {
+                index: self.register(),
+                targets: (0..self.next() % 4).map(|_| self.target()).collect(),
+                default: self.target(),
+            },
+            62 => Inst::MinU {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            63 => Inst::MinUImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            64 => Inst::MaxU {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            65 => Inst::MaxUImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            66 => Inst::MinS {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            67 => Inst::MinSImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            68 => Inst::MaxS {
+                result: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            69 => Inst::MaxSImm {
+                result: self.register(),
+                src: self.register(),
+                imm: self.next(),
+            },
+            70 => Inst::RandNext {
+                result: self.register(),
+            },
+            71 => Inst::Yield,
+            72 => Inst::BranchEq {
+                target: self.target(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            73 => Inst::BranchNe {
+                target: self.target(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            74 => Inst::BranchLtU {
+                target: self.target(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            75 => Inst::BranchLtS {
+                target: self.target(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            76 => Inst::MulWide {
+                hi: self.register(),
+                lo: self.register(),
+                lhs: self.register(),
+                rhs: self.register(),
+            },
+            _ => Inst::Return {
+                result: self.register(),
+            },
+        }
+    }
+}
+
+#[test]
+fn round_trip() {
+    let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+    for len in 0..100 {
+        let insts = (0..len).map(|_| rng.inst()).collect::<Vec<_>>();
+        let bytes = encode(&insts);
+        assert_eq!(decode(&bytes), Ok(insts));
+    }
+}
+
+#[test]
+fn opcodes() {
+    let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+    for _ in 0..1_000 {
+        let inst = rng.inst();
+        assert_eq!(encode(core::slice::from_ref(&inst))[0], inst.opcode());
+        assert!(usize::from(inst.opcode()) < crate::switch::N_OPCODES);
+    }
+}
+
+#[test]
+fn truncated() {
+    let insts = [
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: Bits::MAX,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let bytes = encode(&insts);
+    for len in 1..bytes.len() - 2 {
+        assert_eq!(decode(&bytes[..len]), Err(DecodeError::UnexpectedEnd));
+    }
+    assert_eq!(decode(&bytes[..bytes.len() - 2]), Ok(insts[..1].to_vec()));
+    assert_eq!(
+        decode(&bytes[..bytes.len() - 1]),
+        Err(DecodeError::UnexpectedEnd)
+    );
+}
+
+#[test]
+fn counter_loop() {
+    let insts = crate::switch::counter_loop_insts(1_000);
+    let code = encode(&insts);
+    let mut streamed = Context::default();
+    let mut materialized = Context::default();
+    assert_eq!(
+        execute_bytecode(&code, &mut streamed),
+        Ok(crate::switch::execute(&decode(&code).unwrap(), &mut materialized).unwrap())
+    );
+    assert_eq!(streamed.snapshot(), materialized.snapshot());
+}
+
+#[test]
+fn malformed() {
+    assert_eq!(decode(&[0xFF]), Err(DecodeError::UnknownOpcode(0xFF)));
+    let mut overflow = vec![opcode::RETURN];
+    overflow.extend([0xFF; 10]);
+    overflow.push(0x01);
+    assert_eq!(decode(&overflow), Err(DecodeError::OperandOverflow));
+}
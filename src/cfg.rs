@@ -0,0 +1,370 @@
+#![allow(dead_code)]
+
+#[cfg(test)]
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use smallvec::{smallvec, SmallVec};
+
+#[cfg(test)]
+use super::{switch, Register};
+use super::{switch::Inst, Target};
+
+/// Returns the `(index, target)` pairs of all branches of `insts`.
+///
+/// Fallthrough edges are not included and neither are calls since their
+/// targets start another function instead of continuing the current one.
+/// A branch table yields every distinct target including its default once.
+pub fn branch_targets(insts: &[Inst]) -> impl Iterator<Item = (usize, usize)> + '_ {
+    insts.iter().enumerate().flat_map(|(index, inst)| {
+        let targets = match inst {
+            Inst::Branch { target }
+            | Inst::BranchEqz { target, .. }
+            | Inst::BranchNez { target, .. }
+            | Inst::BranchEq { target, .. }
+            | Inst::BranchNe { target, .. }
+            | Inst::BranchLtU { target, .. }
+            | Inst::BranchLtS { target, .. } => smallvec![target.0],
+            Inst::BranchTable {
+                targets, default, ..
+            } => table_targets(targets, default.0),
+            _ => SmallVec::new(),
+        };
+        targets.into_iter().map(move |target| (index, target))
+    })
+}
+
+/// Returns the distinct `targets` of a branch table followed by its `default` in order of occurrence.
+fn table_targets(targets: &[Target], default: usize) -> SmallVec<[usize; 2]> {
+    let mut distinct = SmallVec::new();
+    for target in targets.iter().map(|target| target.0).chain([default]) {
+        if !distinct.contains(&target) {
+            distinct.push(target);
+        }
+    }
+    distinct
+}
+
+/// Returns the indices of all instructions that may execute right after the instruction at `pc`.
+///
+/// A call continues with the instruction following it once the callee returns.
+/// Fallthrough successors past the end of `insts` are omitted.
+///
+/// # Panics
+///
+/// If `pc` is out of bounds for `insts`.
+pub fn successors(insts: &[Inst], pc: usize) -> SmallVec<[usize; 2]> {
+    let fallthrough = Some(pc + 1).filter(|&next| next < insts.len());
+    match insts[pc] {
+        Inst::Branch { target } => smallvec![target.0],
+        Inst::BranchEqz { target, .. }
+        | Inst::BranchNez { target, .. }
+        | Inst::BranchEq { target, .. }
+        | Inst::BranchNe { target, .. }
+        | Inst::BranchLtU { target, .. }
+        | Inst::BranchLtS { target, .. } => {
+            let mut successors = fallthrough.into_iter().collect::<SmallVec<_>>();
+            if fallthrough != Some(target.0) {
+                successors.push(target.0);
+            }
+            successors
+        }
+        Inst::BranchTable {
+            ref targets,
+            default,
+            ..
+        } => table_targets(targets, default.0),
+        Inst::Return { .. } | Inst::Unreachable => SmallVec::new(),
+        _ => fallthrough.into_iter().collect(),
+    }
+}
+
+/// Splits `insts` into its maximal basic blocks in program order.
+///
+/// A block starts at the first instruction, at branch and call targets and after every
+/// instruction that does not only fall through, i.e. branches, returns and unreachables.
+pub fn basic_blocks(insts: &[Inst]) -> Vec<Range<usize>> {
+    let mut leaders = vec![false; insts.len()];
+    let mut mark = |index: usize| {
+        if let Some(leader) = leaders.get_mut(index) {
+            *leader = true;
+        }
+    };
+    mark(0);
+    for (pc, inst) in insts.iter().enumerate() {
+        if let Inst::Call { target, .. } = *inst {
+            mark(target.0);
+        }
+        if successors(insts, pc)[..] != [pc + 1] {
+            mark(pc + 1);
+        }
+    }
+    for (_, target) in branch_targets(insts) {
+        mark(target);
+    }
+    let starts = (0..insts.len())
+        .filter(|&index| leaders[index])
+        .collect::<Vec<_>>();
+    let ends = starts.iter().skip(1).copied().chain([insts.len()]);
+    starts
+        .iter()
+        .copied()
+        .zip(ends)
+        .map(|(start, end)| start..end)
+        .collect()
+}
+
+/// Estimates the hot instructions of `insts` and returns their indices in ascending order.
+///
+/// This is a static heuristic instead of a profile: every back edge, i.e. a branch whose
+/// target is not after the branch itself, closes a loop and all instructions from its
+/// target up to and including the branch are considered hot.
+pub fn estimate_hot_blocks(insts: &[Inst]) -> Vec<usize> {
+    let mut hot = vec![false; insts.len()];
+    for (index, target) in branch_targets(insts) {
+        if target <= index {
+            hot[target..=index].fill(true);
+        }
+    }
+    (0..insts.len()).filter(|&index| hot[index]).collect()
+}
+
+/// A growable set of small indices such as register indices.
+#[derive(Debug, Default, Clone)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    /// Returns `true` if `index` is in the set.
+    pub fn contains(&self, index: usize) -> bool {
+        self.words
+            .get(index / 64)
+            .is_some_and(|word| word & (1 << (index % 64)) != 0)
+    }
+
+    /// Inserts `index` into the set.
+    pub fn insert(&mut self, index: usize) {
+        let word = index / 64;
+        if self.words.len() <= word {
+            self.words.resize(word + 1, 0);
+        }
+        self.words[word] |= 1 << (index % 64);
+    }
+
+    /// Removes `index` from the set.
+    pub fn remove(&mut self, index: usize) {
+        if let Some(word) = self.words.get_mut(index / 64) {
+            *word &= !(1 << (index % 64));
+        }
+    }
+
+    /// Inserts all indices of `other` into the set and returns `true` if the set changed.
+    pub fn union_with(&mut self, other: &BitSet) -> bool {
+        if self.words.len() < other.words.len() {
+            self.words.resize(other.words.len(), 0);
+        }
+        let mut changed = false;
+        for (word, other) in self.words.iter_mut().zip(&other.words) {
+            changed |= *other & !*word != 0;
+            *word |= other;
+        }
+        changed
+    }
+
+    /// Returns the indices of the set in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(index, &word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| index * 64 + bit)
+        })
+    }
+}
+
+/// Returns the registers that are live right before each instruction of `insts`.
+///
+/// A register is live if its current value may still be read before it is overwritten.
+/// Computed via backward dataflow joining the live registers of all [`successors`]
+/// until a fixpoint is reached. Registers are identified via [`Inst::operands`].
+///
+/// # Panics
+///
+/// If a branch target of `insts` is out of bounds.
+pub fn liveness(insts: &[Inst]) -> Vec<BitSet> {
+    let mut live = vec![BitSet::default(); insts.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for pc in (0..insts.len()).rev() {
+            let mut live_in = BitSet::default();
+            for successor in successors(insts, pc) {
+                live_in.union_with(&live[successor]);
+            }
+            let (reads, writes) = insts[pc].operands();
+            for write in writes {
+                live_in.remove(write.0);
+            }
+            for read in reads {
+                live_in.insert(read.0);
+            }
+            changed |= live[pc].union_with(&live_in);
+        }
+    }
+    live
+}
+
+#[test]
+fn counter_loop() {
+    let insts = switch::counter_loop_insts(1_000);
+    assert_eq!(branch_targets(&insts).collect::<Vec<_>>(), [(1, 4), (3, 1)]);
+    let successors = (0..insts.len())
+        .map(|pc| successors(&insts, pc).into_vec())
+        .collect::<Vec<_>>();
+    assert_eq!(successors, [vec![1], vec![2, 4], vec![3], vec![1], vec![]]);
+}
+
+#[test]
+fn more_comps() {
+    let insts = switch::more_comps_insts(1_000);
+    assert_eq!(branch_targets(&insts).collect::<Vec<_>>(), [(2, 7), (6, 2)]);
+    let successors = (0..insts.len())
+        .map(|pc| successors(&insts, pc).into_vec())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        successors,
+        [
+            vec![1],
+            vec![2],
+            vec![3, 7],
+            vec![4],
+            vec![5],
+            vec![6],
+            vec![2],
+            vec![],
+        ]
+    );
+}
+
+#[test]
+fn fallthrough_off_end() {
+    let insts = [
+        Inst::BranchNez {
+            target: Target(0),
+            condition: Register(0),
+        },
+        Inst::BranchEqz {
+            target: Target(1),
+            condition: Register(0),
+        },
+    ];
+    assert_eq!(successors(&insts, 0).into_vec(), [1, 0]);
+    assert_eq!(successors(&insts, 1).into_vec(), [1]);
+}
+
+#[test]
+fn counter_loop_blocks() {
+    let insts = switch::counter_loop_insts(1_000);
+    // The loop header at 1 is a block of its own since it is the target of the back edge.
+    assert_eq!(basic_blocks(&insts), [0..1, 1..2, 2..4, 4..5]);
+}
+
+#[test]
+fn counter_loop_hot() {
+    let insts = switch::counter_loop_insts(1_000);
+    assert_eq!(estimate_hot_blocks(&insts), [1, 2, 3]);
+    let insts = switch::more_comps_insts(1_000);
+    assert_eq!(estimate_hot_blocks(&insts), [2, 3, 4, 5, 6]);
+    assert!(estimate_hot_blocks(&[]).is_empty());
+}
+
+#[test]
+fn more_comps_blocks() {
+    let insts = switch::more_comps_insts(1_000);
+    assert_eq!(basic_blocks(&insts), [0..2, 2..3, 3..7, 7..8]);
+    assert!(basic_blocks(&[]).is_empty());
+}
+
+#[test]
+fn call_target_blocks() {
+    let insts = [
+        Inst::Call {
+            target: Target(3),
+            num_args: 0,
+        },
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 2,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_eq!(basic_blocks(&insts), [0..3, 3..5]);
+}
+
+#[test]
+fn branch_table_edges() {
+    let insts = [
+        Inst::BranchTable {
+            index: Register(0),
+            targets: Box::new([Target(2), Target(1), Target(2)]),
+            default: Target(3),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_eq!(
+        branch_targets(&insts).collect::<Vec<_>>(),
+        [(0, 2), (0, 1), (0, 3)]
+    );
+    assert_eq!(successors(&insts, 0).into_vec(), [2, 1, 3]);
+    assert_eq!(basic_blocks(&insts), [0..1, 1..2, 2..3, 3..4]);
+}
+
+#[test]
+fn bit_set() {
+    let mut set = BitSet::default();
+    set.insert(3);
+    set.insert(100);
+    assert!(set.contains(3) && set.contains(100));
+    assert!(!set.contains(4) && !set.contains(1_000));
+    let mut other = BitSet::default();
+    other.insert(5);
+    assert!(set.union_with(&other));
+    assert!(!set.union_with(&other));
+    set.remove(100);
+    assert_eq!(set.iter().collect::<Vec<_>>(), [3, 5]);
+}
+
+#[test]
+fn more_comps_liveness() {
+    let insts = switch::more_comps_insts(1_000);
+    let live = liveness(&insts);
+    let registers = |pc: usize| live[pc].iter().collect::<Vec<_>>();
+    // The counter r0 and the accumulator r1 are live throughout the loop body.
+    for pc in 2..=6 {
+        assert_eq!(registers(pc), [0, 1], "pc = {pc}");
+    }
+    // Only the accumulator is returned after the loop exits.
+    assert_eq!(registers(7), [1]);
+    assert!(!live[7].contains(0));
+    assert_eq!(registers(0), [0, 1]);
+}
@@ -1,9 +1,11 @@
 #![allow(dead_code)]
 
+use alloc::boxed::Box;
+
 #[cfg(test)]
-use crate::benchmark;
+use crate::{benchmark, ArithMode};
 
-use super::{handler, Bits, Context, Outcome, Register, Target};
+use super::{handler, Bits, Context, Outcome, Register, Target, TrapCode};
 
 /// A closure based instruction.
 pub struct Inst {
@@ -33,10 +35,14 @@ impl Inst {
         N: Into<Option<Self>>,
     {
         match next.into() {
-            Some(next) => Self::new(move |context| {
-                handler::add_imm(context, result, src, imm);
-                next.execute(context)
-            }),
+            Some(next) => {
+                Self::new(
+                    move |context| match handler::add_imm(context, result, src, imm) {
+                        Outcome::Continue => next.execute(context),
+                        outcome => outcome,
+                    },
+                )
+            }
             None => Self::new(move |context| handler::add_imm(context, result, src, imm)),
         }
     }
@@ -47,10 +53,12 @@ impl Inst {
         N: Into<Option<Self>>,
     {
         match next.into() {
-            Some(next) => Self::new(move |context| {
-                handler::sub(context, result, lhs, rhs);
-                next.execute(context)
-            }),
+            Some(next) => Self::new(
+                move |context| match handler::sub(context, result, lhs, rhs) {
+                    Outcome::Continue => next.execute(context),
+                    outcome => outcome,
+                },
+            ),
             None => Self::new(move |context| handler::sub(context, result, lhs, rhs)),
         }
     }
@@ -61,10 +69,14 @@ impl Inst {
         N: Into<Option<Self>>,
     {
         match next.into() {
-            Some(next) => Self::new(move |context| {
-                handler::sub_imm(context, result, src, imm);
-                next.execute(context)
-            }),
+            Some(next) => {
+                Self::new(
+                    move |context| match handler::sub_imm(context, result, src, imm) {
+                        Outcome::Continue => next.execute(context),
+                        outcome => outcome,
+                    },
+                )
+            }
             None => Self::new(move |context| handler::sub_imm(context, result, src, imm)),
         }
     }
@@ -75,10 +87,12 @@ impl Inst {
         N: Into<Option<Self>>,
     {
         match next.into() {
-            Some(next) => Self::new(move |context| {
-                handler::mul(context, result, lhs, rhs);
-                next.execute(context)
-            }),
+            Some(next) => Self::new(
+                move |context| match handler::mul(context, result, lhs, rhs) {
+                    Outcome::Continue => next.execute(context),
+                    outcome => outcome,
+                },
+            ),
             None => Self::new(move |context| handler::mul(context, result, lhs, rhs)),
         }
     }
@@ -97,7 +111,7 @@ impl Inst {
             Some(next) => Self::new(move |context| {
                 let condition = context.get_reg(condition);
                 if condition == 0 {
-                    context.branch_to(target as usize)
+                    context.branch_to(target.0)
                 } else {
                     next.execute(context)
                 }
@@ -106,20 +120,42 @@ impl Inst {
         }
     }
 
+    /// Traps execution of the function unconditionally.
+    pub fn unreachable() -> Self {
+        Self::new(|_| handler::unreachable())
+    }
+
     /// Returns execution of the function and returns the result in `result`.
     pub fn ret(result: Register) -> Self {
         Self::new(move |context| handler::ret(context, result))
     }
 }
 
-/// Executes the list of instruction using the given [`Context`].
-fn execute(insts: &[Inst], context: &mut Context) {
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Bits {
     loop {
         let pc = context.pc;
         let inst = &insts[pc];
         match inst.execute(context) {
-            Outcome::Continue => continue,
-            Outcome::Return => return,
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return context.get_reg_abs(0),
+        }
+    }
+}
+
+/// Runs the list of instruction using the given [`Context`] and returns the result.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn run(insts: &[Inst], context: &mut Context) -> Result<Bits, TrapCode> {
+    loop {
+        let pc = context.pc;
+        let inst = insts.get(pc).ok_or(TrapCode::OutOfBoundsPc)?;
+        match inst.execute(context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return => return Ok(context.get_reg_abs(0)),
+            Outcome::Trap(trap) => return Err(trap),
         }
     }
 }
@@ -130,17 +166,22 @@ fn counter_loop() {
     let insts = vec![
         // Store `repetitions` into r0.
         // Note: r0 is our loop counter register.
-        Inst::add_imm(0, 0, repetitions, None),
+        Inst::add_imm(Register(0), Register(0), repetitions, None),
         // Branch to the end if r0 is zero.
         //
         // Then: Decrease r0 by 1.
         // Then: Jump back to the loop header.
-        Inst::branch_eqz(2, 0, Inst::sub_imm(0, 0, 1, Inst::branch(1))),
+        Inst::branch_eqz(
+            Target(2),
+            Register(0),
+            Inst::sub_imm(Register(0), Register(0), 1, Inst::branch(Target(1))),
+        ),
         // Return value and end function execution.
-        Inst::ret(0),
+        Inst::ret(Register(0)),
     ];
     let mut context = Context::default();
-    benchmark(|| execute(&insts, &mut context));
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
 }
 
 #[test]
@@ -149,24 +190,45 @@ fn more_comps() {
     let insts = vec![
         // Store `repetitions` into r0.
         // Note: r0 is our loop counter register.
-        Inst::add_imm(0, 0, repetitions, None),
+        Inst::add_imm(Register(0), Register(0), repetitions, None),
         // Branch to the end if r0 is zero.
         //
         // Then: Decrease r0 by 1.
         // Then: Jump back to the loop header.
         Inst::branch_eqz(
-            2,
-            0,
+            Target(2),
+            Register(0),
             Inst::mul(
-                1,
-                1,
-                0,
-                Inst::sub(1, 1, 0, Inst::sub_imm(0, 0, 1, Inst::branch(1))),
+                Register(1),
+                Register(1),
+                Register(0),
+                Inst::sub(
+                    Register(1),
+                    Register(1),
+                    Register(0),
+                    Inst::sub_imm(Register(0), Register(0), 1, Inst::branch(Target(1))),
+                ),
             ),
         ),
         // Return value and end function execution.
-        Inst::ret(0),
+        Inst::ret(Register(0)),
     ];
     let mut context = Context::default();
     benchmark(|| execute(&insts, &mut context));
 }
+
+#[test]
+fn checked_overflow_traps() {
+    let (max, zero) = (Register(1), Register(2));
+    let overflows = [
+        Inst::add_imm(Register(0), max, 1, Inst::ret(Register(0))),
+        Inst::sub(Register(0), zero, max, Inst::ret(Register(0))),
+        Inst::sub_imm(Register(0), zero, 1, Inst::ret(Register(0))),
+        Inst::mul(Register(0), max, max, Inst::ret(Register(0))),
+    ];
+    for overflow in overflows {
+        let insts = [Inst::add_imm(max, max, Bits::MAX, None), overflow];
+        let mut context = Context::with_arith_mode(ArithMode::Checked);
+        assert_eq!(run(&insts, &mut context), Err(TrapCode::IntegerOverflow));
+    }
+}
@@ -1,9 +1,11 @@
 #![allow(dead_code)]
 
+use alloc::{boxed::Box, vec::Vec};
+
 #[cfg(test)]
 use crate::benchmark;
 
-use super::{handler, Bits, Context, Outcome, Register, Target};
+use super::{handler, switch, Bits, Context, Outcome, Register, Target, TrapCode};
 
 /// A closure based instruction.
 pub struct Inst {
@@ -37,6 +39,39 @@ impl Inst {
         Self::new(move |context| handler::sub_imm(context, result, src, imm))
     }
 
+    /// Divides the contents of `lhs` by `rhs` and stores the result into `result`.
+    ///
+    /// Traps if the contents of `rhs` are zero.
+    pub fn div(result: Register, lhs: Register, rhs: Register) -> Self {
+        Self::new(move |context| handler::div(context, result, lhs, rhs))
+    }
+
+    /// Divides the contents of `src` by the constant `imm` and stores the result into `result`.
+    ///
+    /// Traps if `imm` is zero.
+    pub fn div_imm(result: Register, src: Register, imm: Bits) -> Self {
+        Self::new(move |context| handler::div_imm(context, result, src, imm))
+    }
+
+    /// Computes the remainder of the contents of `lhs` divided by `rhs` and stores the result into `result`.
+    ///
+    /// Traps if the contents of `rhs` are zero.
+    pub fn rem(result: Register, lhs: Register, rhs: Register) -> Self {
+        Self::new(move |context| handler::rem(context, result, lhs, rhs))
+    }
+
+    /// Computes the remainder of the contents of `src` divided by the constant `imm` and stores the result into `result`.
+    ///
+    /// Traps if `imm` is zero.
+    pub fn rem_imm(result: Register, src: Register, imm: Bits) -> Self {
+        Self::new(move |context| handler::rem_imm(context, result, src, imm))
+    }
+
+    /// Stores the next value of the random number generator of the context into `result`.
+    pub fn rand_next(result: Register) -> Self {
+        Self::new(move |context| handler::rand_next(context, result))
+    }
+
     /// Branches to the instruction indexed by `target`.
     pub fn branch(target: Target) -> Self {
         Self::new(move |context| handler::branch(context, target))
@@ -47,40 +82,201 @@ impl Inst {
         Self::new(move |context| handler::branch_eqz(context, target, condition))
     }
 
+    /// Branches to the instruction indexed by `target` if the contents of `condition` are not zero.
+    pub fn branch_nez(target: Target, condition: Register) -> Self {
+        Self::new(move |context| handler::branch_nez(context, target, condition))
+    }
+
+    /// Traps execution of the function unconditionally.
+    pub fn unreachable() -> Self {
+        Self::new(|_| handler::unreachable())
+    }
+
     /// Returns execution of the function and returns the result in `result`.
     pub fn ret(result: Register) -> Self {
         Self::new(move |context| handler::ret(context, result))
     }
 }
 
-/// Executes the list of instruction using the given [`Context`].
-fn execute(insts: &[Inst], context: &mut Context) {
+/// Lowers the [`switch::Inst`] program to closure based instructions.
+///
+/// # Errors
+///
+/// Returns the first instruction that has no counterpart.
+pub fn lower(insts: &[switch::Inst]) -> Result<Vec<Inst>, switch::Inst> {
+    insts.iter().cloned().map(lower_inst).collect()
+}
+
+/// Lowers a single [`switch::Inst`].
+fn lower_inst(inst: switch::Inst) -> Result<Inst, switch::Inst> {
+    let lowered = match inst {
+        switch::Inst::AddImm { result, src, imm } => Inst::add_imm(result, src, imm),
+        switch::Inst::SubImm { result, src, imm } => Inst::sub_imm(result, src, imm),
+        switch::Inst::Div { result, lhs, rhs } => Inst::div(result, lhs, rhs),
+        switch::Inst::DivImm { result, src, imm } => Inst::div_imm(result, src, imm),
+        switch::Inst::Rem { result, lhs, rhs } => Inst::rem(result, lhs, rhs),
+        switch::Inst::RemImm { result, src, imm } => Inst::rem_imm(result, src, imm),
+        switch::Inst::RandNext { result } => Inst::rand_next(result),
+        switch::Inst::Branch { target } => Inst::branch(target),
+        switch::Inst::BranchEqz { target, condition } => Inst::branch_eqz(target, condition),
+        switch::Inst::BranchNez { target, condition } => Inst::branch_nez(target, condition),
+        switch::Inst::Unreachable => Inst::unreachable(),
+        switch::Inst::Return { result } => Inst::ret(result),
+        unsupported => return Err(unsupported),
+    };
+    Ok(lowered)
+}
+
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+///
+/// Bounds checks the `pc` just like [`run`] which it delegates to.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Result<Bits, TrapCode> {
+    run(insts, context)
+}
+
+/// Runs the list of instruction using the given [`Context`] and returns the result.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn run(insts: &[Inst], context: &mut Context) -> Result<Bits, TrapCode> {
     loop {
         let pc = context.pc;
-        let inst = &insts[pc];
+        let inst = insts.get(pc).ok_or(TrapCode::OutOfBoundsPc)?;
         match inst.execute(context) {
-            Outcome::Continue => continue,
-            Outcome::Return => return,
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return => return Ok(context.get_reg_abs(0)),
+            Outcome::Trap(trap) => return Err(trap),
         }
     }
 }
 
+/// Returns the `counter_loop` benchmark program counting `repetitions` down to zero.
+pub(crate) fn counter_loop_insts(repetitions: Bits) -> Vec<Inst> {
+    vec![
+        // Store `repetitions` into r0.
+        // Note: r0 is our loop counter register.
+        Inst::add_imm(Register(0), Register(0), repetitions),
+        // Branch to the end if r0 is zero.
+        Inst::branch_eqz(Target(4), Register(0)),
+        // Decrease r0 by 1.
+        Inst::sub_imm(Register(0), Register(0), 1),
+        // Jump back to the loop header.
+        Inst::branch(Target(1)),
+        // Return value and end function execution.
+        Inst::ret(Register(0)),
+    ]
+}
+
 #[test]
 fn counter_loop() {
+    let insts = counter_loop_insts(100_000_000);
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, Ok(0));
+}
+
+#[test]
+fn counter_loop_nez() {
     let repetitions = 100_000_000;
     let insts = vec![
         // Store `repetitions` into r0.
         // Note: r0 is our loop counter register.
-        Inst::add_imm(0, 0, repetitions),
-        // Branch to the end if r0 is zero.
-        Inst::branch_eqz(4, 0),
+        Inst::add_imm(Register(0), Register(0), repetitions),
         // Decrease r0 by 1.
-        Inst::sub_imm(0, 0, 1),
-        // Jump back to the loop header.
-        Inst::branch(1),
+        Inst::sub_imm(Register(0), Register(0), 1),
+        // Jump back to the loop header if r0 is not zero.
+        Inst::branch_nez(Target(1), Register(0)),
+        // Return value and end function execution.
+        Inst::ret(Register(0)),
+    ];
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, Ok(0));
+}
+
+#[test]
+fn div_rem() {
+    let insts = vec![
+        // Store `17` into r0 and `5` into r1.
+        Inst::add_imm(Register(0), Register(0), 17),
+        Inst::add_imm(Register(1), Register(1), 5),
+        // Divide and take the remainder of r0 by r1.
+        Inst::div(Register(2), Register(0), Register(1)),
+        Inst::rem(Register(3), Register(0), Register(1)),
+        // Divide and take the remainder of r0 by `4`.
+        Inst::div_imm(Register(4), Register(0), 4),
+        Inst::rem_imm(Register(5), Register(0), 4),
         // Return value and end function execution.
-        Inst::ret(0),
+        Inst::ret(Register(2)),
     ];
     let mut context = Context::default();
-    benchmark(|| execute(&insts, &mut context));
+    execute(&insts, &mut context).unwrap();
+    assert_eq!(context.get_reg(Register(0)), 17 / 5);
+    assert_eq!(context.get_reg(Register(3)), 17 % 5);
+    assert_eq!(context.get_reg(Register(4)), 17 / 4);
+    assert_eq!(context.get_reg(Register(5)), 17 % 4);
+}
+
+#[test]
+fn div_rem_by_zero() {
+    let mut context = Context::default();
+    context.set_reg(Register(0), 17);
+    for inst in [
+        Inst::div(Register(2), Register(0), Register(1)),
+        Inst::div_imm(Register(2), Register(0), 0),
+        Inst::rem(Register(2), Register(0), Register(1)),
+        Inst::rem_imm(Register(2), Register(0), 0),
+    ] {
+        assert!(matches!(
+            inst.execute(&mut context),
+            Outcome::Trap(TrapCode::DivisionByZero)
+        ));
+    }
+}
+
+#[test]
+fn run_traps() {
+    let insts = [
+        Inst::add_imm(Register(0), Register(0), 42),
+        Inst::ret(Register(0)),
+    ];
+    assert_eq!(run(&insts, &mut Context::default()), Ok(42));
+    let insts = [
+        Inst::add_imm(Register(0), Register(0), 42),
+        Inst::div_imm(Register(0), Register(0), 0),
+        Inst::ret(Register(0)),
+    ];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::DivisionByZero)
+    );
+    let insts = [Inst::branch(Target(2)), Inst::ret(Register(0))];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::OutOfBoundsPc)
+    );
+}
+
+#[test]
+fn unreachable() {
+    let insts = [
+        Inst::add_imm(Register(0), Register(0), 42),
+        Inst::ret(Register(0)),
+        Inst::unreachable(),
+    ];
+    assert_eq!(run(&insts, &mut Context::default()), Ok(42));
+    let insts = [
+        Inst::branch(Target(2)),
+        Inst::ret(Register(0)),
+        Inst::unreachable(),
+    ];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::UnreachableExecuted)
+    );
 }
@@ -1,9 +1,11 @@
 #![allow(dead_code)]
 
+use alloc::{boxed::Box, vec::Vec};
+
 #[cfg(test)]
 use crate::benchmark;
 
-use super::{handler, Bits, Context, Outcome, Register, Target};
+use super::{handler, switch, Bits, Context, Outcome, Register, Target};
 
 pub struct ExecContext<'i, 'c> {
     insts: &'i [Inst],
@@ -41,18 +43,22 @@ impl Inst {
 
     /// Adds the constant `imm` and the contents of `src` and stores the result into `result`.
     pub fn add_imm(result: Register, src: Register, imm: Bits) -> Self {
-        Self::new(move |context| {
-            handler::add_imm(context.context, result, src, imm);
-            context.execute_next()
-        })
+        Self::new(
+            move |context| match handler::add_imm(context.context, result, src, imm) {
+                Outcome::Continue => context.execute_next(),
+                outcome => outcome,
+            },
+        )
     }
 
     /// Subtracts the constant `imm` from the contents of `src` and stores the result into `result`.
     pub fn sub_imm(result: Register, src: Register, imm: Bits) -> Self {
-        Self::new(move |context| {
-            handler::sub_imm(context.context, result, src, imm);
-            context.execute_next()
-        })
+        Self::new(
+            move |context| match handler::sub_imm(context.context, result, src, imm) {
+                Outcome::Continue => context.execute_next(),
+                outcome => outcome,
+            },
+        )
     }
 
     /// Branches to the instruction indexed by `target`.
@@ -71,34 +77,96 @@ impl Inst {
         })
     }
 
+    /// Branches to the instruction indexed by `target` if the contents of `condition` are not zero.
+    pub fn branch_nez(target: Target, condition: Register) -> Self {
+        Self::new(move |context| {
+            handler::branch_nez(context.context, target, condition);
+            context.execute_next()
+        })
+    }
+
+    /// Traps execution of the function unconditionally.
+    pub fn unreachable() -> Self {
+        Self::new(|_| handler::unreachable())
+    }
+
     /// Returns execution of the function and returns the result in `result`.
     pub fn ret(result: Register) -> Self {
         Self::new(move |context| handler::ret(context.context, result))
     }
 }
 
-/// Executes the list of instruction using the given [`Context`].
-fn execute(insts: &[Inst], context: &mut Context) {
+/// Lowers the [`switch::Inst`] program to tail calling closure based instructions.
+///
+/// # Errors
+///
+/// Returns the first instruction that has no counterpart.
+pub fn lower(insts: &[switch::Inst]) -> Result<Vec<Inst>, switch::Inst> {
+    insts.iter().cloned().map(lower_inst).collect()
+}
+
+/// Lowers a single [`switch::Inst`].
+fn lower_inst(inst: switch::Inst) -> Result<Inst, switch::Inst> {
+    let lowered = match inst {
+        switch::Inst::AddImm { result, src, imm } => Inst::add_imm(result, src, imm),
+        switch::Inst::SubImm { result, src, imm } => Inst::sub_imm(result, src, imm),
+        switch::Inst::Branch { target } => Inst::branch(target),
+        switch::Inst::BranchEqz { target, condition } => Inst::branch_eqz(target, condition),
+        switch::Inst::BranchNez { target, condition } => Inst::branch_nez(target, condition),
+        switch::Inst::Unreachable => Inst::unreachable(),
+        switch::Inst::Return { result } => Inst::ret(result),
+        unsupported => return Err(unsupported),
+    };
+    Ok(lowered)
+}
+
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Bits {
     let mut context = ExecContext { insts, context };
     context.execute_next();
+    context.context.get_reg_abs(0)
+}
+
+/// Returns the `counter_loop` benchmark program counting `repetitions` down to zero.
+pub(crate) fn counter_loop_insts(repetitions: Bits) -> Vec<Inst> {
+    vec![
+        // Store `repetitions` into r0.
+        // Note: r0 is our loop counter register.
+        Inst::add_imm(Register(0), Register(0), repetitions),
+        // Branch to the end if r0 is zero.
+        Inst::branch_eqz(Target(4), Register(0)),
+        // Decrease r0 by 1.
+        Inst::sub_imm(Register(0), Register(0), 1),
+        // Jump back to the loop header.
+        Inst::branch(Target(1)),
+        // Return value and end function execution.
+        Inst::ret(Register(0)),
+    ]
 }
 
 #[test]
 fn counter_loop() {
+    let insts = counter_loop_insts(100_000_000);
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn counter_loop_nez() {
     let repetitions = 100_000_000;
     let insts = vec![
         // Store `repetitions` into r0.
         // Note: r0 is our loop counter register.
-        Inst::add_imm(0, 0, repetitions),
-        // Branch to the end if r0 is zero.
-        Inst::branch_eqz(4, 0),
+        Inst::add_imm(Register(0), Register(0), repetitions),
         // Decrease r0 by 1.
-        Inst::sub_imm(0, 0, 1),
-        // Jump back to the loop header.
-        Inst::branch(1),
+        Inst::sub_imm(Register(0), Register(0), 1),
+        // Jump back to the loop header if r0 is not zero.
+        Inst::branch_nez(Target(1), Register(0)),
         // Return value and end function execution.
-        Inst::ret(0),
+        Inst::ret(Register(0)),
     ];
     let mut context = Context::default();
-    benchmark(|| execute(&insts, &mut context));
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
 }
@@ -1,9 +1,11 @@
 #![allow(dead_code)]
 
+use alloc::boxed::Box;
+
 #[cfg(test)]
-use crate::benchmark;
+use crate::{benchmark, ArithMode};
 
-use super::{handler, Bits, Context, Outcome, Register, Target};
+use super::{fetch, handler, Bits, Context, Outcome, Register, Target};
 
 pub struct ExecContext<'i, 'c> {
     insts: &'i [Inst],
@@ -12,14 +14,17 @@ pub struct ExecContext<'i, 'c> {
 
 impl<'i, 'c> ExecContext<'i, 'c> {
     pub fn execute_next(&mut self, reg0: Bits) -> Outcome {
-        let inst = unsafe { self.insts.get_unchecked(self.context.pc) };
-        inst.execute(self, reg0)
+        match fetch(self.insts, self.context.pc) {
+            Ok(inst) => inst.execute(self, reg0),
+            Err(trap) => Outcome::Trap(trap),
+        }
     }
 }
 
 /// A closure based instruction.
 pub struct Inst {
     /// The closure stores everything required for the instruction execution.
+    #[allow(clippy::type_complexity)]
     handler: Box<dyn Fn(&mut ExecContext, Bits) -> Outcome>,
 }
 
@@ -41,10 +46,12 @@ impl Inst {
 
     /// Adds the constant `imm` and the contents of `src` and stores the result into `result`.
     pub fn add_imm(result: Register, src: Register, imm: Bits) -> Self {
-        Self::new(move |context, reg0| {
-            handler::add_imm(context.context, result, src, imm);
-            context.execute_next(reg0)
-        })
+        Self::new(
+            move |context, reg0| match handler::add_imm(context.context, result, src, imm) {
+                Outcome::Continue => context.execute_next(reg0),
+                outcome => outcome,
+            },
+        )
     }
 
     pub fn add_imm_0(imm: Bits) -> Self {
@@ -56,10 +63,12 @@ impl Inst {
 
     /// Subtracts the constant `imm` from the contents of `src` and stores the result into `result`.
     pub fn sub_imm(result: Register, src: Register, imm: Bits) -> Self {
-        Self::new(move |context, reg0| {
-            handler::sub_imm(context.context, result, src, imm);
-            context.execute_next(reg0)
-        })
+        Self::new(
+            move |context, reg0| match handler::sub_imm(context.context, result, src, imm) {
+                Outcome::Continue => context.execute_next(reg0),
+                outcome => outcome,
+            },
+        )
     }
 
     pub fn sub_imm_0(imm: Bits) -> Self {
@@ -69,6 +78,33 @@ impl Inst {
         })
     }
 
+    /// Multiplies the contents of `lhs` and `rhs` and stores the result into `result`.
+    pub fn mul(result: Register, lhs: Register, rhs: Register) -> Self {
+        Self::new(
+            move |context, reg0| match handler::mul(context.context, result, lhs, rhs) {
+                Outcome::Continue => context.execute_next(reg0),
+                outcome => outcome,
+            },
+        )
+    }
+
+    /// Multiplies the constant `imm` and the contents of `src` and stores the result into `result`.
+    pub fn mul_imm(result: Register, src: Register, imm: Bits) -> Self {
+        Self::new(
+            move |context, reg0| match handler::mul_imm(context.context, result, src, imm) {
+                Outcome::Continue => context.execute_next(reg0),
+                outcome => outcome,
+            },
+        )
+    }
+
+    pub fn mul_imm_0(imm: Bits) -> Self {
+        Self::new(move |context, reg0| {
+            context.context.pc += 1;
+            context.execute_next(reg0.wrapping_mul(imm))
+        })
+    }
+
     /// Branches to the instruction indexed by `target`.
     pub fn branch(target: Target) -> Self {
         Self::new(move |context, reg0| {
@@ -88,7 +124,7 @@ impl Inst {
     pub fn branch_eqz_0(target: Target) -> Self {
         Self::new(move |context, reg0| {
             if reg0 == 0 {
-                context.context.pc = target as usize;
+                context.context.pc = target.0;
             } else {
                 context.context.pc += 1;
             }
@@ -96,16 +132,22 @@ impl Inst {
         })
     }
 
+    /// Traps execution of the function unconditionally.
+    pub fn unreachable() -> Self {
+        Self::new(|_, _| handler::unreachable())
+    }
+
     /// Returns execution of the function and returns the result in `result`.
     pub fn ret(result: Register) -> Self {
-        Self::new(move |context, reg0| handler::ret(context.context, result))
+        Self::new(move |context, _reg0| handler::ret(context.context, result))
     }
 }
 
-/// Executes the list of instruction using the given [`Context`].
-fn execute(insts: &[Inst], context: &mut Context) {
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Bits {
     let mut context = ExecContext { insts, context };
     context.execute_next(0);
+    context.context.get_reg_abs(0)
 }
 
 #[test]
@@ -116,14 +158,64 @@ fn counter_loop() {
         // Note: r0 is our loop counter register.
         Inst::add_imm_0(repetitions),
         // Branch to the end if r0 is zero.
-        Inst::branch_eqz_0(4),
+        Inst::branch_eqz_0(Target(4)),
         // Decrease r0 by 1.
         Inst::sub_imm_0(1),
         // Jump back to the loop header.
-        Inst::branch(1),
+        Inst::branch(Target(1)),
         // Return value and end function execution.
-        Inst::ret(0),
+        Inst::ret(Register(0)),
     ];
     let mut context = Context::default();
-    benchmark(|| execute(&insts, &mut context));
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn more_comps() {
+    let repetitions = 100_000_000;
+    let insts = vec![
+        // Store `repetitions` into r0.
+        // Note: r0 is our loop counter register.
+        Inst::add_imm(Register(0), Register(0), repetitions),
+        // Store `1` into r1.
+        // Note: r1 is our accumulator register.
+        Inst::add_imm(Register(1), Register(1), 1),
+        // Branch to the end if r0 is zero.
+        Inst::branch_eqz(Target(7), Register(0)),
+        // Multiply r1 with r0.
+        Inst::mul(Register(1), Register(1), Register(0)),
+        // Multiply r1 with 3.
+        Inst::mul_imm(Register(1), Register(1), 3),
+        // Decrease r0 by 1.
+        Inst::sub_imm(Register(0), Register(0), 1),
+        // Jump back to the loop header.
+        Inst::branch(Target(2)),
+        // Return value and end function execution.
+        Inst::ret(Register(1)),
+    ];
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    let expected = (1..=repetitions).fold(1, |acc: Bits, n| acc.wrapping_mul(n).wrapping_mul(3));
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn checked_overflow_traps() {
+    let (max, zero) = (Register(1), Register(2));
+    let overflows = [
+        Inst::add_imm(Register(0), max, 1),
+        Inst::sub_imm(Register(0), zero, 1),
+        Inst::mul(Register(0), max, max),
+        Inst::mul_imm(Register(0), max, 2),
+    ];
+    for overflow in overflows {
+        let insts = [
+            Inst::add_imm(max, max, Bits::MAX),
+            overflow,
+            Inst::ret(Register(0)),
+        ];
+        let mut context = Context::with_arith_mode(ArithMode::Checked);
+        assert_eq!(execute(&insts, &mut context), 0);
+    }
 }
@@ -1,9 +1,11 @@
 #![allow(dead_code)]
 
+use alloc::{boxed::Box, vec::Vec};
+
 #[cfg(test)]
 use crate::benchmark;
 
-use super::{handler, Bits, Context, Outcome};
+use super::{handler, Bits, Context, Outcome, TrapCode};
 
 #[derive(Copy, Clone)]
 pub struct Register(usize);
@@ -62,7 +64,7 @@ impl Expr {
             let lhs = lhs.eval(context);
             let rhs = rhs.eval(context);
             let new_value = lhs.wrapping_add(rhs);
-            context.set_reg(result.0, new_value);
+            context.set_reg(crate::Register(result.0), new_value);
             new_value
         })
     }
@@ -76,7 +78,7 @@ impl Expr {
             let lhs = lhs.eval(context);
             let rhs = rhs.eval(context);
             let new_value = lhs.wrapping_sub(rhs);
-            context.set_reg(result.0, new_value);
+            context.set_reg(crate::Register(result.0), new_value);
             new_value
         })
     }
@@ -90,16 +92,41 @@ impl Expr {
             let lhs = lhs.eval(context);
             let rhs = rhs.eval(context);
             let new_value = lhs.wrapping_mul(rhs);
-            context.set_reg(result.0, new_value);
+            context.set_reg(crate::Register(result.0), new_value);
             new_value
         })
     }
 }
 
+/// The control flow signal of an executed [`Inst`].
+///
+/// Unlike [`Outcome`] this can express leaving a structured block without returning from the function.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Control {
+    /// Continues execution with the next instruction.
+    Continue,
+    /// Breaks out of the innermost enclosing [`Inst::loop_block`].
+    Break,
+    /// Returns execution of the function.
+    Return,
+    /// Traps execution of the function.
+    Trap(TrapCode),
+}
+
+impl From<Outcome> for Control {
+    fn from(outcome: Outcome) -> Self {
+        match outcome {
+            Outcome::Continue | Outcome::Yield => Self::Continue,
+            Outcome::Return => Self::Return,
+            Outcome::Trap(trap) => Self::Trap(trap),
+        }
+    }
+}
+
 /// A closure based instruction.
 pub struct Inst {
     /// The closure stores everything required for the instruction execution.
-    handler: Box<dyn Fn(&mut Context) -> Outcome>,
+    handler: Box<dyn Fn(&mut Context) -> Control>,
 }
 
 pub trait Eval {
@@ -108,12 +135,12 @@ pub trait Eval {
 
 impl Eval for Register {
     fn eval(&self, context: &mut Context) -> Bits {
-        context.get_reg(self.0)
+        context.get_reg(crate::Register(self.0))
     }
 }
 
 impl Eval for Bits {
-    fn eval(&self, context: &mut Context) -> Bits {
+    fn eval(&self, _context: &mut Context) -> Bits {
         *self
     }
 }
@@ -126,14 +153,14 @@ impl Eval for Expr {
 
 impl Inst {
     /// Executes the given instruction using the given [`Context`].
-    pub fn execute(&self, context: &mut Context) -> Outcome {
+    pub fn execute(&self, context: &mut Context) -> Control {
         (self.handler)(context)
     }
 
     /// Creates a new [`Inst`] from the given closure.
     fn new<T>(handler: T) -> Self
     where
-        T: Fn(&mut Context) -> Outcome + 'static,
+        T: Fn(&mut Context) -> Control + 'static,
     {
         Self {
             handler: Box::new(handler),
@@ -143,7 +170,7 @@ impl Inst {
     pub fn exec(expr: Expr) -> Self {
         Self::new(move |context| {
             expr.eval(context);
-            Outcome::Continue
+            Control::Continue
         })
     }
 
@@ -153,24 +180,44 @@ impl Inst {
     {
         Self::new(move |context| {
             let new_value = input.eval(context);
-            context.set_reg(result.0, new_value);
-            Outcome::Continue
+            context.set_reg(crate::Register(result.0), new_value);
+            Control::Continue
         })
     }
 
-    /// Branches to the instruction indexed by `target` if the contents of `condition` are zero.
-    pub fn branch_eqz(condition: Expr) -> Self {
+    /// Breaks out of the innermost enclosing [`Inst::loop_block`] if `condition` evaluates to zero.
+    pub fn branch_eqz<C>(condition: C) -> Self
+    where
+        C: Eval + 'static,
+    {
         Self::new(move |context| {
-            let condition = condition.execute(context);
+            let condition = condition.eval(context);
             if condition == 0 {
-                Outcome::Return
+                Control::Break
+            } else {
+                Control::Continue
+            }
+        })
+    }
+
+    /// Breaks out of the innermost enclosing [`Inst::loop_block`] if `condition` evaluates to non-zero.
+    pub fn branch_nez<C>(condition: C) -> Self
+    where
+        C: Eval + 'static,
+    {
+        Self::new(move |context| {
+            let condition = condition.eval(context);
+            if condition != 0 {
+                Control::Break
             } else {
-                Outcome::Continue
+                Control::Continue
             }
         })
     }
 
     /// Executes all the instructions in the basic block one after another.
+    ///
+    /// Stops at the first instruction that does not continue and propagates its [`Control`].
     pub fn basic_block<I>(insts: I) -> Self
     where
         I: IntoIterator<Item = Inst>,
@@ -179,27 +226,35 @@ impl Inst {
         Self::new(move |context| {
             for inst in &insts[..] {
                 match inst.execute(context) {
-                    Outcome::Continue => (),
-                    Outcome::Return => return Outcome::Return,
+                    Control::Continue => (),
+                    control => return control,
                 }
             }
-            Outcome::Continue
+            Control::Continue
         })
     }
 
-    /// Loops the body until it returns.
+    /// Loops the body until it breaks out of the loop or returns.
+    ///
+    /// Breaking out of the loop continues with the instruction following the loop.
     pub fn loop_block(body: Inst) -> Self {
         Self::new(move |context| loop {
             match body.execute(context) {
-                Outcome::Continue => (),
-                Outcome::Return => return Outcome::Return,
+                Control::Continue => (),
+                Control::Break => return Control::Continue,
+                control => return control,
             }
         })
     }
 
+    /// Traps execution of the function unconditionally.
+    pub fn unreachable() -> Self {
+        Self::new(|_| handler::unreachable().into())
+    }
+
     /// Returns execution of the function and returns the result in `result`.
     pub fn ret(result: Register) -> Self {
-        Self::new(move |context| handler::ret(context, result.0))
+        Self::new(move |context| handler::ret(context, crate::Register(result.0)).into())
     }
 }
 
@@ -209,7 +264,35 @@ fn counter_loop() {
     let inst = Inst::basic_block(vec![
         Inst::exec(Expr::add(Register(0), Register(0), repetitions)),
         Inst::loop_block(Inst::branch_eqz(Expr::sub(Register(0), Register(0), 1))),
+        Inst::ret(Register(0)),
+    ]);
+    let mut context = Context::default();
+    let (_, control) = benchmark(|| inst.execute(&mut context));
+    assert_eq!(control, Control::Return);
+    assert_eq!(context.get_reg(crate::Register(0)), 0);
+}
+
+#[test]
+fn loop_with_separate_condition() {
+    let inst = Inst::basic_block(vec![
+        Inst::loop_block(Inst::basic_block(vec![
+            // Increase r0 by 1.
+            Inst::exec(Expr::add(Register(0), Register(0), 1)),
+            // Break out of the loop once r0 equals 10.
+            Inst::local_set(Register(1), Expr::sub(Register(1), Register(0), 10)),
+            Inst::branch_eqz(Register(1)),
+            // Accumulate r0 into r2.
+            Inst::exec(Expr::add(Register(2), Register(2), Register(0))),
+        ])),
+        // Execution continues after the loop.
+        Inst::exec(Expr::mul(Register(0), Register(2), 2)),
+        Inst::ret(Register(0)),
     ]);
     let mut context = Context::default();
-    benchmark(|| inst.execute(&mut context));
+    assert_eq!(inst.execute(&mut context), Control::Return);
+    assert_eq!(context.get_reg(crate::Register(1)), 0);
+    assert_eq!(
+        context.get_reg(crate::Register(0)),
+        2 * (1..10).sum::<Bits>()
+    );
 }
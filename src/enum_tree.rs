@@ -1,9 +1,11 @@
 #![allow(dead_code)]
 
+use alloc::{boxed::Box, vec::Vec};
+
 #[cfg(test)]
 use crate::benchmark;
 
-use super::{Bits, Context, Outcome};
+use super::{switch, Bits, Context, Outcome, Target, TrapCode};
 
 #[derive(Copy, Clone)]
 pub struct Global(u32);
@@ -28,6 +30,9 @@ pub enum Expr {
         register: Register,
         new_value: Box<Expr>,
     },
+    GlobalGet {
+        global: Global,
+    },
 
     AddRr {
         lhs: Register,
@@ -88,6 +93,15 @@ pub enum Expr {
     MulEe {
         lhs_rhs: Box<[Expr; 2]>,
     },
+
+    /// Evaluates to `if_true` if `cond` is not zero and to `if_false` otherwise.
+    ///
+    /// Only the selected branch is evaluated so the other branch has no side effects.
+    Select {
+        cond: Box<Expr>,
+        if_true: Box<Expr>,
+        if_false: Box<Expr>,
+    },
 }
 
 impl Expr {
@@ -95,28 +109,29 @@ impl Expr {
         match self {
             Expr::Immediate { immediate } => immediate.0,
 
-            Expr::LocalGet { register } => context.get_reg(register.0),
+            Expr::LocalGet { register } => context.get_reg(crate::Register(register.0)),
             Expr::LocalTee {
                 register,
                 new_value,
             } => {
                 let new_value = new_value.evaluate(context);
-                context.set_reg(register.0, new_value);
+                context.set_reg(crate::Register(register.0), new_value);
                 new_value
             }
+            Expr::GlobalGet { global } => context.get_global(global.0 as usize),
 
             Expr::AddRr { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
-                let rhs = context.get_reg(rhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
+                let rhs = context.get_reg(crate::Register(rhs.0));
                 lhs.wrapping_add(rhs)
             }
             Expr::AddRi { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
                 let rhs = rhs.0;
                 lhs.wrapping_add(rhs)
             }
             Expr::AddRe { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
                 let rhs = rhs.evaluate(context);
                 lhs.wrapping_add(rhs)
             }
@@ -132,17 +147,17 @@ impl Expr {
             }
 
             Expr::SubRr { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
-                let rhs = context.get_reg(rhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
+                let rhs = context.get_reg(crate::Register(rhs.0));
                 lhs.wrapping_sub(rhs)
             }
             Expr::SubRi { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
                 let rhs = rhs.0;
                 lhs.wrapping_sub(rhs)
             }
             Expr::SubRe { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
                 let rhs = rhs.evaluate(context);
                 lhs.wrapping_sub(rhs)
             }
@@ -158,17 +173,17 @@ impl Expr {
             }
 
             Expr::MulRr { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
-                let rhs = context.get_reg(rhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
+                let rhs = context.get_reg(crate::Register(rhs.0));
                 lhs.wrapping_mul(rhs)
             }
             Expr::MulRi { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
                 let rhs = rhs.0;
                 lhs.wrapping_mul(rhs)
             }
             Expr::MulRe { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
                 let rhs = rhs.evaluate(context);
                 lhs.wrapping_mul(rhs)
             }
@@ -182,10 +197,113 @@ impl Expr {
                 let rhs = lhs_rhs[1].evaluate(context);
                 lhs.wrapping_mul(rhs)
             }
+
+            Expr::Select {
+                cond,
+                if_true,
+                if_false,
+            } => {
+                if cond.evaluate(context) != 0 {
+                    if_true.evaluate(context)
+                } else {
+                    if_false.evaluate(context)
+                }
+            }
         }
     }
 }
 
+/// A pending step of [`Expr::evaluate_iter`].
+enum Step<'a> {
+    /// Evaluates the expression and pushes its value.
+    Eval(&'a Expr),
+    /// Combines the pushed values of the already evaluated sub-expressions of the expression.
+    Apply(&'a Expr),
+}
+
+impl Expr {
+    /// Evaluates the expression like [`Expr::evaluate`] but using an explicit work stack.
+    ///
+    /// Unlike the recursive [`Expr::evaluate`] this does not overflow the native stack
+    /// for deeply nested expressions. Registers and `LocalTee` side effects are accessed
+    /// in the same order as by [`Expr::evaluate`].
+    pub fn evaluate_iter(&self, context: &mut Context) -> Bits {
+        let mut steps = vec![Step::Eval(self)];
+        let mut values = Vec::new();
+        let pop = |values: &mut Vec<Bits>| values.pop().expect("missing evaluated operand");
+        while let Some(step) = steps.pop() {
+            match step {
+                Step::Eval(expr) => match expr {
+                    Expr::Immediate { .. }
+                    | Expr::LocalGet { .. }
+                    | Expr::GlobalGet { .. }
+                    | Expr::AddRr { .. }
+                    | Expr::AddRi { .. }
+                    | Expr::SubRr { .. }
+                    | Expr::SubRi { .. }
+                    | Expr::MulRr { .. }
+                    | Expr::MulRi { .. } => values.push(expr.evaluate(context)),
+                    Expr::LocalTee { new_value: rhs, .. }
+                    | Expr::AddIe { rhs, .. }
+                    | Expr::SubIe { rhs, .. }
+                    | Expr::MulIe { rhs, .. } => {
+                        steps.push(Step::Apply(expr));
+                        steps.push(Step::Eval(rhs));
+                    }
+                    Expr::AddRe { lhs, rhs }
+                    | Expr::SubRe { lhs, rhs }
+                    | Expr::MulRe { lhs, rhs } => {
+                        values.push(context.get_reg(crate::Register(lhs.0)));
+                        steps.push(Step::Apply(expr));
+                        steps.push(Step::Eval(rhs));
+                    }
+                    Expr::AddEe { lhs_rhs } | Expr::SubEe { lhs_rhs } | Expr::MulEe { lhs_rhs } => {
+                        steps.push(Step::Apply(expr));
+                        steps.push(Step::Eval(&lhs_rhs[1]));
+                        steps.push(Step::Eval(&lhs_rhs[0]));
+                    }
+                    Expr::Select { cond, .. } => {
+                        steps.push(Step::Apply(expr));
+                        steps.push(Step::Eval(cond));
+                    }
+                },
+                Step::Apply(expr) => {
+                    let rhs = pop(&mut values);
+                    match expr {
+                        Expr::LocalTee { register, .. } => {
+                            context.set_reg(crate::Register(register.0), rhs);
+                            values.push(rhs);
+                        }
+                        Expr::AddIe { lhs, .. } => values.push(lhs.0.wrapping_add(rhs)),
+                        Expr::SubIe { lhs, .. } => values.push(lhs.0.wrapping_sub(rhs)),
+                        Expr::MulIe { lhs, .. } => values.push(lhs.0.wrapping_mul(rhs)),
+                        Expr::AddRe { .. } | Expr::AddEe { .. } => {
+                            let lhs = pop(&mut values);
+                            values.push(lhs.wrapping_add(rhs));
+                        }
+                        Expr::SubRe { .. } | Expr::SubEe { .. } => {
+                            let lhs = pop(&mut values);
+                            values.push(lhs.wrapping_sub(rhs));
+                        }
+                        Expr::MulRe { .. } | Expr::MulEe { .. } => {
+                            let lhs = pop(&mut values);
+                            values.push(lhs.wrapping_mul(rhs));
+                        }
+                        Expr::Select {
+                            if_true, if_false, ..
+                        } => {
+                            let cond = rhs;
+                            steps.push(Step::Eval(if cond != 0 { if_true } else { if_false }));
+                        }
+                        _ => unreachable!("leaf expressions are evaluated without an apply step"),
+                    }
+                }
+            }
+        }
+        pop(&mut values)
+    }
+}
+
 pub enum Inst {
     LocalSet { register: Register, expr: Expr },
     GlobalSet { global: Global, expr: Expr },
@@ -199,10 +317,14 @@ impl Inst {
         match self {
             Inst::LocalSet { register, expr } => {
                 let new_value = expr.evaluate(context);
-                context.set_reg(register.0, new_value);
+                context.set_reg(crate::Register(register.0), new_value);
+                context.next_inst()
+            }
+            Inst::GlobalSet { global, expr } => {
+                let new_value = expr.evaluate(context);
+                context.set_global(global.0 as usize, new_value);
                 context.next_inst()
             }
-            Inst::GlobalSet { global, expr } => todo!(),
             Inst::Branch { label } => context.branch_to(label.0),
             Inst::BranchIf { label, condition } => {
                 let condition = condition.evaluate(context);
@@ -214,29 +336,515 @@ impl Inst {
             }
             Inst::Return { result } => {
                 let new_value = result.evaluate(context);
-                context.set_reg(0, new_value);
+                context.set_reg(crate::Register(0), new_value);
                 Outcome::Return
             }
         }
     }
 }
 
-/// Executes the list of instruction using the given [`Context`].
-fn execute(insts: &[Inst], context: &mut Context) {
+/// Lowers the [`switch::Inst`] program to expression tree instructions.
+///
+/// # Errors
+///
+/// Returns the first instruction that has no counterpart.
+pub fn lower(insts: &[switch::Inst]) -> Result<Vec<Inst>, switch::Inst> {
+    insts.iter().cloned().map(lower_inst).collect()
+}
+
+/// Lowers a single [`switch::Inst`].
+fn lower_inst(inst: switch::Inst) -> Result<Inst, switch::Inst> {
+    let lowered = match inst {
+        switch::Inst::Add { result, lhs, rhs } => Inst::LocalSet {
+            register: Register(result.0),
+            expr: Expr::AddRr {
+                lhs: Register(lhs.0),
+                rhs: Register(rhs.0),
+            },
+        },
+        switch::Inst::AddImm { result, src, imm } => Inst::LocalSet {
+            register: Register(result.0),
+            expr: Expr::AddRi {
+                lhs: Register(src.0),
+                rhs: Immediate(imm),
+            },
+        },
+        switch::Inst::Sub { result, lhs, rhs } => Inst::LocalSet {
+            register: Register(result.0),
+            expr: Expr::SubRr {
+                lhs: Register(lhs.0),
+                rhs: Register(rhs.0),
+            },
+        },
+        switch::Inst::SubImm { result, src, imm } => Inst::LocalSet {
+            register: Register(result.0),
+            expr: Expr::SubRi {
+                lhs: Register(src.0),
+                rhs: Immediate(imm),
+            },
+        },
+        switch::Inst::Mul { result, lhs, rhs } => Inst::LocalSet {
+            register: Register(result.0),
+            expr: Expr::MulRr {
+                lhs: Register(lhs.0),
+                rhs: Register(rhs.0),
+            },
+        },
+        switch::Inst::MulImm { result, src, imm } => Inst::LocalSet {
+            register: Register(result.0),
+            expr: Expr::MulRi {
+                lhs: Register(src.0),
+                rhs: Immediate(imm),
+            },
+        },
+        switch::Inst::Select {
+            result,
+            cond,
+            if_nonzero,
+            if_zero,
+        } => Inst::LocalSet {
+            register: Register(result.0),
+            expr: Expr::Select {
+                cond: Box::new(Expr::LocalGet {
+                    register: Register(cond.0),
+                }),
+                if_true: Box::new(Expr::LocalGet {
+                    register: Register(if_nonzero.0),
+                }),
+                if_false: Box::new(Expr::LocalGet {
+                    register: Register(if_zero.0),
+                }),
+            },
+        },
+        switch::Inst::Branch { target } => Inst::Branch {
+            label: Label(target.0),
+        },
+        switch::Inst::BranchEqz { target, condition } => Inst::BranchIf {
+            label: Label(target.0),
+            condition: Expr::LocalGet {
+                register: Register(condition.0),
+            },
+        },
+        switch::Inst::Return { result } => Inst::Return {
+            result: Expr::LocalGet {
+                register: Register(result.0),
+            },
+        },
+        unsupported => return Err(unsupported),
+    };
+    Ok(lowered)
+}
+
+impl Expr {
+    /// Returns the direct sub-expressions in evaluation order.
+    fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Immediate { .. }
+            | Expr::LocalGet { .. }
+            | Expr::GlobalGet { .. }
+            | Expr::AddRr { .. }
+            | Expr::AddRi { .. }
+            | Expr::SubRr { .. }
+            | Expr::SubRi { .. }
+            | Expr::MulRr { .. }
+            | Expr::MulRi { .. } => vec![],
+            Expr::LocalTee { new_value: rhs, .. }
+            | Expr::AddRe { rhs, .. }
+            | Expr::AddIe { rhs, .. }
+            | Expr::SubRe { rhs, .. }
+            | Expr::SubIe { rhs, .. }
+            | Expr::MulRe { rhs, .. }
+            | Expr::MulIe { rhs, .. } => vec![rhs],
+            Expr::AddEe { lhs_rhs } | Expr::SubEe { lhs_rhs } | Expr::MulEe { lhs_rhs } => {
+                lhs_rhs.iter().collect()
+            }
+            Expr::Select {
+                cond,
+                if_true,
+                if_false,
+            } => vec![cond, if_true, if_false],
+        }
+    }
+
+    /// Returns the highest register accessed by the expression or any of its sub-expressions.
+    fn max_register(&self) -> usize {
+        let own = match self {
+            Expr::LocalGet { register } | Expr::LocalTee { register, .. } => register.0,
+            Expr::AddRr { lhs, rhs } | Expr::SubRr { lhs, rhs } | Expr::MulRr { lhs, rhs } => {
+                lhs.0.max(rhs.0)
+            }
+            Expr::AddRi { lhs, .. }
+            | Expr::SubRi { lhs, .. }
+            | Expr::MulRi { lhs, .. }
+            | Expr::AddRe { lhs, .. }
+            | Expr::SubRe { lhs, .. }
+            | Expr::MulRe { lhs, .. } => lhs.0,
+            _ => 0,
+        };
+        self.children()
+            .into_iter()
+            .map(Expr::max_register)
+            .fold(own, usize::max)
+    }
+
+    /// Returns `true` if evaluating the expression may write to `register` via `LocalTee`.
+    fn writes(&self, register: usize) -> bool {
+        if let Expr::LocalTee {
+            register: written, ..
+        } = self
+        {
+            if written.0 == register {
+                return true;
+            }
+        }
+        self.children()
+            .into_iter()
+            .any(|child| child.writes(register))
+    }
+}
+
+/// The binary operators of the expression tree.
+#[derive(Copy, Clone)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+}
+
+impl BinOp {
+    /// Returns the three-address instruction applying the operator to two registers.
+    fn rr(self, result: usize, lhs: usize, rhs: usize) -> switch::Inst {
+        match self {
+            BinOp::Add => switch::Inst::Add {
+                result: crate::Register(result),
+                lhs: crate::Register(lhs),
+                rhs: crate::Register(rhs),
+            },
+            BinOp::Sub => switch::Inst::Sub {
+                result: crate::Register(result),
+                lhs: crate::Register(lhs),
+                rhs: crate::Register(rhs),
+            },
+            BinOp::Mul => switch::Inst::Mul {
+                result: crate::Register(result),
+                lhs: crate::Register(lhs),
+                rhs: crate::Register(rhs),
+            },
+        }
+    }
+
+    /// Returns the three-address instruction applying the operator to a register and a constant.
+    fn ri(self, result: usize, src: usize, imm: Bits) -> switch::Inst {
+        match self {
+            BinOp::Add => switch::Inst::AddImm {
+                result: crate::Register(result),
+                src: crate::Register(src),
+                imm,
+            },
+            BinOp::Sub => switch::Inst::SubImm {
+                result: crate::Register(result),
+                src: crate::Register(src),
+                imm,
+            },
+            BinOp::Mul => switch::Inst::MulImm {
+                result: crate::Register(result),
+                src: crate::Register(src),
+                imm,
+            },
+        }
+    }
+}
+
+/// Flattens expression tree instructions into three-address [`switch::Inst`]s.
+#[derive(Default)]
+struct Flattener {
+    /// The flattened instructions.
+    insts: Vec<switch::Inst>,
+    /// The index of every flattened branch to a [`Label`] together with its [`Label`].
+    fixups: Vec<(usize, Label)>,
+}
+
+impl Flattener {
+    fn push(&mut self, inst: switch::Inst) {
+        self.insts.push(inst);
+    }
+
+    /// Emits a branch to `label` whose target is resolved once all instructions are flattened.
+    fn push_branch(&mut self, label: Label, inst: switch::Inst) {
+        self.fixups.push((self.insts.len(), label));
+        self.push(inst);
+    }
+
+    /// Emits `inst` using the scratch registers from `tmp` upwards.
+    fn inst(&mut self, inst: &Inst, tmp: usize) -> Result<(), Global> {
+        match inst {
+            Inst::LocalSet { register, expr } => self.expr_into(expr, register.0, tmp)?,
+            Inst::GlobalSet { global, .. } => return Err(*global),
+            Inst::Branch { label } => {
+                self.push_branch(*label, switch::Inst::Branch { target: Target(0) })
+            }
+            Inst::BranchIf { label, condition } => {
+                let condition = self.operand(condition, tmp)?;
+                self.push_branch(
+                    *label,
+                    switch::Inst::BranchEqz {
+                        target: Target(0),
+                        condition: crate::Register(condition),
+                    },
+                );
+            }
+            Inst::Return { result } => {
+                let result = self.operand(result, tmp)?;
+                self.push(switch::Inst::Return {
+                    result: crate::Register(result),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits the evaluation of `expr` and returns the register holding its value.
+    ///
+    /// Register reads and `LocalTee` evaluate to the accessed register itself,
+    /// all other expressions are evaluated into the scratch register `tmp`.
+    fn operand(&mut self, expr: &Expr, tmp: usize) -> Result<usize, Global> {
+        match expr {
+            Expr::LocalGet { register } => Ok(register.0),
+            Expr::LocalTee {
+                register,
+                new_value,
+            } => {
+                self.expr_into(new_value, register.0, tmp)?;
+                Ok(register.0)
+            }
+            _ => {
+                self.expr_into(expr, tmp, tmp + 1)?;
+                Ok(tmp)
+            }
+        }
+    }
+
+    /// Emits the evaluation of `expr` into `dst` using the scratch registers from `tmp` upwards.
+    ///
+    /// `dst` is only written once all registers read by `expr` have been read.
+    fn expr_into(&mut self, expr: &Expr, dst: usize, tmp: usize) -> Result<(), Global> {
+        match expr {
+            Expr::Immediate { immediate } => self.constant(dst, immediate.0),
+            Expr::LocalGet { register } => self.copy(dst, register.0),
+            Expr::LocalTee {
+                register,
+                new_value,
+            } => {
+                self.expr_into(new_value, register.0, tmp)?;
+                self.copy(dst, register.0);
+            }
+            Expr::GlobalGet { global } => return Err(*global),
+            Expr::AddRr { lhs, rhs } => self.push(BinOp::Add.rr(dst, lhs.0, rhs.0)),
+            Expr::SubRr { lhs, rhs } => self.push(BinOp::Sub.rr(dst, lhs.0, rhs.0)),
+            Expr::MulRr { lhs, rhs } => self.push(BinOp::Mul.rr(dst, lhs.0, rhs.0)),
+            Expr::AddRi { lhs, rhs } => self.push(BinOp::Add.ri(dst, lhs.0, rhs.0)),
+            Expr::SubRi { lhs, rhs } => self.push(BinOp::Sub.ri(dst, lhs.0, rhs.0)),
+            Expr::MulRi { lhs, rhs } => self.push(BinOp::Mul.ri(dst, lhs.0, rhs.0)),
+            Expr::AddRe { lhs, rhs } => self.binary_re(BinOp::Add, dst, lhs.0, rhs, tmp)?,
+            Expr::SubRe { lhs, rhs } => self.binary_re(BinOp::Sub, dst, lhs.0, rhs, tmp)?,
+            Expr::MulRe { lhs, rhs } => self.binary_re(BinOp::Mul, dst, lhs.0, rhs, tmp)?,
+            Expr::AddIe { lhs, rhs } => self.binary_ie(BinOp::Add, dst, lhs.0, rhs, tmp)?,
+            Expr::SubIe { lhs, rhs } => self.binary_ie(BinOp::Sub, dst, lhs.0, rhs, tmp)?,
+            Expr::MulIe { lhs, rhs } => self.binary_ie(BinOp::Mul, dst, lhs.0, rhs, tmp)?,
+            Expr::AddEe { lhs_rhs } => self.binary_ee(BinOp::Add, dst, lhs_rhs, tmp)?,
+            Expr::SubEe { lhs_rhs } => self.binary_ee(BinOp::Sub, dst, lhs_rhs, tmp)?,
+            Expr::MulEe { lhs_rhs } => self.binary_ee(BinOp::Mul, dst, lhs_rhs, tmp)?,
+            Expr::Select {
+                cond,
+                if_true,
+                if_false,
+            } => {
+                let condition = self.operand(cond, tmp)?;
+                let branch_eqz = self.insts.len();
+                self.push(switch::Inst::BranchEqz {
+                    target: Target(0),
+                    condition: crate::Register(condition),
+                });
+                self.expr_into(if_true, dst, tmp)?;
+                let branch = self.insts.len();
+                self.push(switch::Inst::Branch { target: Target(0) });
+                let else_start = self.insts.len();
+                self.expr_into(if_false, dst, tmp)?;
+                let end = self.insts.len();
+                self.insts[branch_eqz] = switch::Inst::BranchEqz {
+                    target: Target(else_start),
+                    condition: crate::Register(condition),
+                };
+                self.insts[branch] = switch::Inst::Branch {
+                    target: Target(end),
+                };
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits loading the constant `imm` into `dst`.
+    fn constant(&mut self, dst: usize, imm: Bits) {
+        self.push(switch::Inst::Sub {
+            result: crate::Register(dst),
+            lhs: crate::Register(dst),
+            rhs: crate::Register(dst),
+        });
+        self.push(switch::Inst::AddImm {
+            result: crate::Register(dst),
+            src: crate::Register(dst),
+            imm,
+        });
+    }
+
+    /// Emits copying `src` into `dst` unless both are the same register.
+    fn copy(&mut self, dst: usize, src: usize) {
+        if dst != src {
+            self.push(switch::Inst::Move {
+                result: crate::Register(dst),
+                src: crate::Register(src),
+            });
+        }
+    }
+
+    /// Emits `lhs op rhs` for a register `lhs` that is read before `rhs` is evaluated.
+    fn binary_re(
+        &mut self,
+        op: BinOp,
+        dst: usize,
+        lhs: usize,
+        rhs: &Expr,
+        tmp: usize,
+    ) -> Result<(), Global> {
+        let lhs = if rhs.writes(lhs) {
+            self.copy(tmp, lhs);
+            tmp
+        } else {
+            lhs
+        };
+        let rhs = self.operand(rhs, tmp + 1)?;
+        self.push(op.rr(dst, lhs, rhs));
+        Ok(())
+    }
+
+    /// Emits `lhs op rhs` for a constant `lhs`.
+    fn binary_ie(
+        &mut self,
+        op: BinOp,
+        dst: usize,
+        lhs: Bits,
+        rhs: &Expr,
+        tmp: usize,
+    ) -> Result<(), Global> {
+        let rhs = self.operand(rhs, tmp)?;
+        match op {
+            BinOp::Add | BinOp::Mul => self.push(op.ri(dst, rhs, lhs)),
+            BinOp::Sub => {
+                self.constant(tmp + 1, lhs);
+                self.push(op.rr(dst, tmp + 1, rhs));
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits `lhs op rhs` evaluating `lhs` before `rhs`.
+    fn binary_ee(
+        &mut self,
+        op: BinOp,
+        dst: usize,
+        [lhs, rhs]: &[Expr; 2],
+        tmp: usize,
+    ) -> Result<(), Global> {
+        let mut lhs = self.operand(lhs, tmp)?;
+        if lhs != tmp && rhs.writes(lhs) {
+            self.copy(tmp, lhs);
+            lhs = tmp;
+        }
+        let rhs = self.operand(rhs, tmp + 1)?;
+        self.push(op.rr(dst, lhs, rhs));
+        Ok(())
+    }
+}
+
+/// Flattens the expression tree program into three-address [`switch::Inst`]s.
+///
+/// Nested expressions are evaluated into scratch registers above the highest register
+/// used by `insts`, so executing the result may require a [`Context`] with more registers.
+/// `LocalTee` side effects and the left to right evaluation order are preserved.
+///
+/// # Errors
+///
+/// Returns the first accessed [`Global`] since [`switch::Inst`] has no globals.
+pub fn lower_to_switch(insts: &[Inst]) -> Result<Vec<switch::Inst>, Global> {
+    let scratch = insts
+        .iter()
+        .map(|inst| match inst {
+            Inst::LocalSet { register, expr } => register.0.max(expr.max_register()),
+            Inst::BranchIf {
+                condition: expr, ..
+            }
+            | Inst::Return { result: expr }
+            | Inst::GlobalSet { expr, .. } => expr.max_register(),
+            Inst::Branch { .. } => 0,
+        })
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let mut flattener = Flattener::default();
+    let mut starts = Vec::with_capacity(insts.len() + 1);
+    for inst in insts {
+        starts.push(flattener.insts.len());
+        flattener.inst(inst, scratch)?;
+    }
+    let len = flattener.insts.len();
+    for (index, label) in flattener.fixups {
+        // Labels past the end stay past the end of the flattened instructions.
+        let new_target = starts
+            .get(label.0)
+            .copied()
+            .unwrap_or(len + label.0 - insts.len());
+        match &mut flattener.insts[index] {
+            switch::Inst::Branch { target } | switch::Inst::BranchEqz { target, .. } => {
+                *target = Target(new_target)
+            }
+            _ => unreachable!("only branches are fixed up"),
+        }
+    }
+    Ok(flattener.insts)
+}
+
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Bits {
     loop {
         let pc = context.pc;
         let inst = &insts[pc];
         match inst.execute(context) {
-            Outcome::Continue => continue,
-            Outcome::Return => return,
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return context.get_reg_abs(0),
         }
     }
 }
 
-#[test]
-fn counter_loop() {
-    let repetitions = 100_000_000;
-    let insts = vec![
+/// Runs the list of instruction using the given [`Context`] and returns the result.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn run(insts: &[Inst], context: &mut Context) -> Result<Bits, TrapCode> {
+    loop {
+        let pc = context.pc;
+        let inst = insts.get(pc).ok_or(TrapCode::OutOfBoundsPc)?;
+        match inst.execute(context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return => return Ok(context.get_reg_abs(0)),
+            Outcome::Trap(trap) => return Err(trap),
+        }
+    }
+}
+
+/// Returns the `counter_loop` benchmark program counting `repetitions` down to zero.
+pub(crate) fn counter_loop_insts(repetitions: Bits) -> Vec<Inst> {
+    vec![
         // Store `repetitions` into r0.
         // Note: r0 is our loop counter register.
         Inst::LocalSet {
@@ -265,7 +873,278 @@ fn counter_loop() {
                 register: Register(0),
             },
         },
+    ]
+}
+
+#[test]
+fn counter_loop() {
+    let insts = counter_loop_insts(100_000_000);
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn global_accumulator() {
+    let repetitions = 100_000;
+    let insts = vec![
+        // Store `repetitions` into r0.
+        // Note: r0 is our loop counter register.
+        Inst::LocalSet {
+            register: Register(0),
+            expr: Expr::Immediate {
+                immediate: Immediate(repetitions),
+            },
+        },
+        // Accumulate r0 into g0.
+        Inst::GlobalSet {
+            global: Global(0),
+            expr: Expr::AddEe {
+                lhs_rhs: Box::new([
+                    Expr::GlobalGet { global: Global(0) },
+                    Expr::LocalGet {
+                        register: Register(0),
+                    },
+                ]),
+            },
+        },
+        // Branch to the end if r0 is zero.
+        // Decrease r0 by 1 in loop.
+        Inst::BranchIf {
+            label: Label(4),
+            condition: Expr::LocalTee {
+                register: Register(0),
+                new_value: Box::new(Expr::SubRi {
+                    lhs: Register(0),
+                    rhs: Immediate(1),
+                }),
+            },
+        },
+        // Jump back to the loop header.
+        Inst::Branch { label: Label(1) },
+        // Return the accumulated global and end function execution.
+        Inst::Return {
+            result: Expr::GlobalGet { global: Global(0) },
+        },
     ];
     let mut context = Context::default();
     benchmark(|| execute(&insts, &mut context));
+    assert_eq!(
+        context.get_reg(crate::Register(0)),
+        repetitions * (repetitions + 1) / 2
+    );
+}
+
+#[test]
+fn select_evaluates_taken_branch() {
+    // r0 = select(r3, r1 = 11, r2 = 22)
+    let insts = [
+        Inst::LocalSet {
+            register: Register(0),
+            expr: Expr::Select {
+                cond: Box::new(Expr::LocalGet {
+                    register: Register(3),
+                }),
+                if_true: Box::new(Expr::LocalTee {
+                    register: Register(1),
+                    new_value: Box::new(Expr::Immediate {
+                        immediate: Immediate(11),
+                    }),
+                }),
+                if_false: Box::new(Expr::LocalTee {
+                    register: Register(2),
+                    new_value: Box::new(Expr::Immediate {
+                        immediate: Immediate(22),
+                    }),
+                }),
+            },
+        },
+        Inst::Return {
+            result: Expr::LocalGet {
+                register: Register(0),
+            },
+        },
+    ];
+    let mut context = Context::default();
+    context.set_reg(crate::Register(3), 1);
+    assert_eq!(run(&insts, &mut context), Ok(11));
+    assert_eq!(
+        (
+            context.get_reg(crate::Register(1)),
+            context.get_reg(crate::Register(2))
+        ),
+        (11, 0)
+    );
+    let mut context = Context::default();
+    assert_eq!(run(&insts, &mut context), Ok(22));
+    assert_eq!(
+        (
+            context.get_reg(crate::Register(1)),
+            context.get_reg(crate::Register(2))
+        ),
+        (0, 22)
+    );
+}
+
+#[test]
+fn evaluate_iter_matches_evaluate() {
+    // (r1 = 2 * 3) - (r1 + 10) * select(r2, 100, r1 = 7)
+    let expr = Expr::SubEe {
+        lhs_rhs: Box::new([
+            Expr::LocalTee {
+                register: Register(1),
+                new_value: Box::new(Expr::MulIe {
+                    lhs: Immediate(2),
+                    rhs: Box::new(Expr::Immediate {
+                        immediate: Immediate(3),
+                    }),
+                }),
+            },
+            Expr::MulEe {
+                lhs_rhs: Box::new([
+                    Expr::AddRi {
+                        lhs: Register(1),
+                        rhs: Immediate(10),
+                    },
+                    Expr::Select {
+                        cond: Box::new(Expr::LocalGet {
+                            register: Register(2),
+                        }),
+                        if_true: Box::new(Expr::Immediate {
+                            immediate: Immediate(100),
+                        }),
+                        if_false: Box::new(Expr::LocalTee {
+                            register: Register(1),
+                            new_value: Box::new(Expr::Immediate {
+                                immediate: Immediate(7),
+                            }),
+                        }),
+                    },
+                ]),
+            },
+        ]),
+    };
+    for cond in [0, 1] {
+        let mut recursive = Context::default();
+        let mut iterative = Context::default();
+        recursive.set_reg(crate::Register(2), cond);
+        iterative.set_reg(crate::Register(2), cond);
+        assert_eq!(
+            expr.evaluate_iter(&mut iterative),
+            expr.evaluate(&mut recursive)
+        );
+        assert_eq!(iterative.snapshot(), recursive.snapshot());
+    }
+}
+
+#[test]
+fn lower_to_switch_counter_loop() {
+    let insts = counter_loop_insts(1_000);
+    let lowered = lower_to_switch(&insts).ok().unwrap();
+    let mut context = Context::default();
+    assert_eq!(
+        switch::execute(&lowered, &mut context),
+        Ok(execute(&insts, &mut Context::default()))
+    );
+    assert_eq!(context.get_reg(crate::Register(0)), 0);
+}
+
+#[test]
+fn lower_to_switch_side_effects() {
+    // r0 = (r1 = r1 * 3) + (r1 + (r1 = 5)) - (10 - select(r2, r1, r1 = 1))
+    let expr = Expr::SubEe {
+        lhs_rhs: Box::new([
+            Expr::AddEe {
+                lhs_rhs: Box::new([
+                    Expr::LocalTee {
+                        register: Register(1),
+                        new_value: Box::new(Expr::MulRi {
+                            lhs: Register(1),
+                            rhs: Immediate(3),
+                        }),
+                    },
+                    Expr::AddRe {
+                        lhs: Register(1),
+                        rhs: Box::new(Expr::LocalTee {
+                            register: Register(1),
+                            new_value: Box::new(Expr::Immediate {
+                                immediate: Immediate(5),
+                            }),
+                        }),
+                    },
+                ]),
+            },
+            Expr::SubIe {
+                lhs: Immediate(10),
+                rhs: Box::new(Expr::Select {
+                    cond: Box::new(Expr::LocalGet {
+                        register: Register(2),
+                    }),
+                    if_true: Box::new(Expr::LocalGet {
+                        register: Register(1),
+                    }),
+                    if_false: Box::new(Expr::LocalTee {
+                        register: Register(1),
+                        new_value: Box::new(Expr::Immediate {
+                            immediate: Immediate(1),
+                        }),
+                    }),
+                }),
+            },
+        ]),
+    };
+    let insts = [
+        Inst::LocalSet {
+            register: Register(0),
+            expr,
+        },
+        Inst::Return {
+            result: Expr::LocalGet {
+                register: Register(0),
+            },
+        },
+    ];
+    let lowered = lower_to_switch(&insts).ok().unwrap();
+    for cond in [0, 1] {
+        let mut expected = Context::default();
+        let mut context = Context::default();
+        for context in [&mut expected, &mut context] {
+            context.set_reg(crate::Register(1), 7);
+            context.set_reg(crate::Register(2), cond);
+        }
+        assert_eq!(
+            switch::run(&lowered, &mut context),
+            Ok(execute(&insts, &mut expected))
+        );
+        assert_eq!(
+            context.get_reg(crate::Register(1)),
+            expected.get_reg(crate::Register(1))
+        );
+    }
+    let globals = [Inst::Return {
+        result: Expr::GlobalGet { global: Global(3) },
+    }];
+    assert!(matches!(lower_to_switch(&globals), Err(Global(3))));
+}
+
+#[test]
+fn evaluate_iter_deep() {
+    let depth = 1_000_000;
+    // r0 + (r0 + (r0 + ... (r0 + 1)))
+    let mut expr = Expr::Immediate {
+        immediate: Immediate(1),
+    };
+    for _ in 0..depth {
+        expr = Expr::AddRe {
+            lhs: Register(0),
+            rhs: Box::new(expr),
+        };
+    }
+    let mut context = Context::default();
+    context.set_reg(crate::Register(0), 2);
+    assert_eq!(expr.evaluate_iter(&mut context), 2 * depth + 1);
+    // Dismantle the chain iteratively since dropping it recursively would overflow as well.
+    while let Expr::AddRe { rhs, .. } = expr {
+        expr = *rhs;
+    }
 }
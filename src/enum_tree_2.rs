@@ -1,9 +1,11 @@
 #![allow(dead_code)]
 
+use alloc::boxed::Box;
+
 #[cfg(test)]
 use crate::benchmark;
 
-use super::{Bits, Context, Outcome};
+use super::{Bits, Context, Outcome, TrapCode};
 
 #[derive(Copy, Clone)]
 pub struct Global(u32);
@@ -28,6 +30,9 @@ pub enum Expr {
         register: Register,
         new_value: Box<Expr>,
     },
+    GlobalGet {
+        global: Global,
+    },
 
     AddRr {
         lhs: Register,
@@ -95,28 +100,29 @@ impl Expr {
         match self {
             Expr::Immediate { immediate } => immediate.0,
 
-            Expr::LocalGet { register } => context.get_reg(register.0),
+            Expr::LocalGet { register } => context.get_reg(crate::Register(register.0)),
             Expr::LocalTee {
                 register,
                 new_value,
             } => {
                 let new_value = new_value.evaluate(context);
-                context.set_reg(register.0, new_value);
+                context.set_reg(crate::Register(register.0), new_value);
                 new_value
             }
+            Expr::GlobalGet { global } => context.get_global(global.0 as usize),
 
             Expr::AddRr { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
-                let rhs = context.get_reg(rhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
+                let rhs = context.get_reg(crate::Register(rhs.0));
                 lhs.wrapping_add(rhs)
             }
             Expr::AddRi { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
                 let rhs = rhs.0;
                 lhs.wrapping_add(rhs)
             }
             Expr::AddRe { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
                 let rhs = rhs.evaluate(context);
                 lhs.wrapping_add(rhs)
             }
@@ -132,17 +138,17 @@ impl Expr {
             }
 
             Expr::SubRr { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
-                let rhs = context.get_reg(rhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
+                let rhs = context.get_reg(crate::Register(rhs.0));
                 lhs.wrapping_sub(rhs)
             }
             Expr::SubRi { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
                 let rhs = rhs.0;
                 lhs.wrapping_sub(rhs)
             }
             Expr::SubRe { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
                 let rhs = rhs.evaluate(context);
                 lhs.wrapping_sub(rhs)
             }
@@ -158,17 +164,17 @@ impl Expr {
             }
 
             Expr::MulRr { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
-                let rhs = context.get_reg(rhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
+                let rhs = context.get_reg(crate::Register(rhs.0));
                 lhs.wrapping_mul(rhs)
             }
             Expr::MulRi { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
                 let rhs = rhs.0;
                 lhs.wrapping_mul(rhs)
             }
             Expr::MulRe { lhs, rhs } => {
-                let lhs = context.get_reg(lhs.0);
+                let lhs = context.get_reg(crate::Register(lhs.0));
                 let rhs = rhs.evaluate(context);
                 lhs.wrapping_mul(rhs)
             }
@@ -200,10 +206,14 @@ impl Inst {
         match self {
             Inst::LocalSet { register, expr } => {
                 let new_value = expr.evaluate(context);
-                context.set_reg(register.0, new_value);
+                context.set_reg(crate::Register(register.0), new_value);
+                context.next_inst()
+            }
+            Inst::GlobalSet { global, expr } => {
+                let new_value = expr.evaluate(context);
+                context.set_global(global.0 as usize, new_value);
                 context.next_inst()
             }
-            Inst::GlobalSet { global, expr } => todo!(),
             Inst::Branch { label } => context.branch_to(label.0),
             Inst::BranchIf { label, condition } => {
                 let condition = condition.evaluate(context);
@@ -222,21 +232,38 @@ impl Inst {
             }
             Inst::Return { result } => {
                 let new_value = result.evaluate(context);
-                context.set_reg(0, new_value);
+                context.set_reg(crate::Register(0), new_value);
                 Outcome::Return
             }
         }
     }
 }
 
-/// Executes the list of instruction using the given [`Context`].
-fn execute(insts: &[Inst], context: &mut Context) {
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Bits {
     loop {
         let pc = context.pc;
         let inst = &insts[pc];
         match inst.execute(context) {
-            Outcome::Continue => continue,
-            Outcome::Return => return,
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return context.get_reg_abs(0),
+        }
+    }
+}
+
+/// Runs the list of instruction using the given [`Context`] and returns the result.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn run(insts: &[Inst], context: &mut Context) -> Result<Bits, TrapCode> {
+    loop {
+        let pc = context.pc;
+        let inst = insts.get(pc).ok_or(TrapCode::OutOfBoundsPc)?;
+        match inst.execute(context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return => return Ok(context.get_reg_abs(0)),
+            Outcome::Trap(trap) => return Err(trap),
         }
     }
 }
@@ -284,5 +311,6 @@ fn counter_loop() {
         },
     ];
     let mut context = Context::default();
-    benchmark(|| execute(&insts, &mut context));
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
 }
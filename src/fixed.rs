@@ -0,0 +1,244 @@
+//! Execution of fixed size programs without any heap allocation.
+
+#[cfg(test)]
+use crate::{benchmark, Target};
+
+use super::{handler, switch::Inst, Bits, ExecState, Outcome, Register, TrapCode};
+
+/// An execution context storing its `pc` and `R` registers inline.
+///
+/// Unlike [`Context`](crate::Context) it has no register windows, float registers,
+/// globals, memory or call frames and thus never allocates.
+#[derive(Debug, Clone)]
+pub struct FixedContext<const R: usize> {
+    pc: usize,
+    regs: [Bits; R],
+}
+
+impl<const R: usize> Default for FixedContext<R> {
+    fn default() -> Self {
+        Self {
+            pc: 0,
+            regs: [0x00; R],
+        }
+    }
+}
+
+impl<const R: usize> FixedContext<R> {
+    /// Returns the current `pc`.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Returns the current value of `reg`.
+    ///
+    /// # Panics
+    ///
+    /// If `reg` is out of bounds for the `R` registers.
+    pub fn get_reg(&self, reg: Register) -> Bits {
+        self.regs[reg.0]
+    }
+
+    /// Sets the register `reg` to the `new_value`.
+    ///
+    /// # Panics
+    ///
+    /// If `reg` is out of bounds for the `R` registers.
+    pub fn set_reg(&mut self, reg: Register, new_value: Bits) {
+        self.regs[reg.0] = new_value;
+    }
+}
+
+impl<const R: usize> ExecState for FixedContext<R> {
+    fn get_reg(&self, reg: Register) -> Bits {
+        FixedContext::get_reg(self, reg)
+    }
+
+    fn set_reg(&mut self, reg: Register, new_value: Bits) {
+        FixedContext::set_reg(self, reg, new_value)
+    }
+
+    fn branch_to(&mut self, new_pc: usize) -> Outcome {
+        self.pc = new_pc;
+        Outcome::Continue
+    }
+
+    fn next_inst(&mut self) -> Outcome {
+        self.pc += 1;
+        Outcome::Continue
+    }
+}
+
+/// Executes `inst` using the given [`FixedContext`].
+///
+/// Instructions that access float registers, the random number generator, memory or call
+/// frames have no counterpart and trap with [`TrapCode::UnreachableExecuted`] instead.
+/// `return` stores its result into `r0` and ends execution.
+fn execute_inst<const R: usize>(inst: &Inst, context: &mut FixedContext<R>) -> Outcome {
+    match inst {
+        Inst::Add { result, lhs, rhs } => handler::add(context, *result, *lhs, *rhs),
+        Inst::AddImm { result, src, imm } => handler::add_imm(context, *result, *src, *imm),
+        Inst::Sub { result, lhs, rhs } => handler::sub(context, *result, *lhs, *rhs),
+        Inst::SubImm { result, src, imm } => handler::sub_imm(context, *result, *src, *imm),
+        Inst::Mul { result, lhs, rhs } => handler::mul(context, *result, *lhs, *rhs),
+        Inst::MulImm { result, src, imm } => handler::mul_imm(context, *result, *src, *imm),
+        Inst::Div { result, lhs, rhs } => handler::div(context, *result, *lhs, *rhs),
+        Inst::DivImm { result, src, imm } => handler::div_imm(context, *result, *src, *imm),
+        Inst::Rem { result, lhs, rhs } => handler::rem(context, *result, *lhs, *rhs),
+        Inst::RemImm { result, src, imm } => handler::rem_imm(context, *result, *src, *imm),
+        Inst::Shl { result, lhs, rhs } => handler::shl(context, *result, *lhs, *rhs),
+        Inst::ShlImm { result, src, imm } => handler::shl_imm(context, *result, *src, *imm),
+        Inst::ShrU { result, lhs, rhs } => handler::shr_u(context, *result, *lhs, *rhs),
+        Inst::ShrUImm { result, src, imm } => handler::shr_u_imm(context, *result, *src, *imm),
+        Inst::ShrS { result, lhs, rhs } => handler::shr_s(context, *result, *lhs, *rhs),
+        Inst::ShrSImm { result, src, imm } => handler::shr_s_imm(context, *result, *src, *imm),
+        Inst::Rotl { result, lhs, rhs } => handler::rotl(context, *result, *lhs, *rhs),
+        Inst::RotlImm { result, src, imm } => handler::rotl_imm(context, *result, *src, *imm),
+        Inst::Rotr { result, lhs, rhs } => handler::rotr(context, *result, *lhs, *rhs),
+        Inst::RotrImm { result, src, imm } => handler::rotr_imm(context, *result, *src, *imm),
+        Inst::Eq { result, lhs, rhs } => handler::eq(context, *result, *lhs, *rhs),
+        Inst::EqImm { result, src, imm } => handler::eq_imm(context, *result, *src, *imm),
+        Inst::Ne { result, lhs, rhs } => handler::ne(context, *result, *lhs, *rhs),
+        Inst::NeImm { result, src, imm } => handler::ne_imm(context, *result, *src, *imm),
+        Inst::LtU { result, lhs, rhs } => handler::lt_u(context, *result, *lhs, *rhs),
+        Inst::LtUImm { result, src, imm } => handler::lt_u_imm(context, *result, *src, *imm),
+        Inst::LtS { result, lhs, rhs } => handler::lt_s(context, *result, *lhs, *rhs),
+        Inst::LtSImm { result, src, imm } => handler::lt_s_imm(context, *result, *src, *imm),
+        Inst::Move { result, src } => handler::mov(context, *result, *src),
+        Inst::Add32 { result, lhs, rhs } => handler::add32(context, *result, *lhs, *rhs),
+        Inst::Sub32 { result, lhs, rhs } => handler::sub32(context, *result, *lhs, *rhs),
+        Inst::Mul32 { result, lhs, rhs } => handler::mul32(context, *result, *lhs, *rhs),
+        Inst::LtS32 { result, lhs, rhs } => handler::lt_s32(context, *result, *lhs, *rhs),
+        Inst::Shl32 { result, lhs, rhs } => handler::shl32(context, *result, *lhs, *rhs),
+        Inst::ShrU32 { result, lhs, rhs } => handler::shr_u32(context, *result, *lhs, *rhs),
+        Inst::ShrS32 { result, lhs, rhs } => handler::shr_s32(context, *result, *lhs, *rhs),
+        Inst::AddChecked { result, lhs, rhs } => handler::add_checked(context, *result, *lhs, *rhs),
+        Inst::SubChecked { result, lhs, rhs } => handler::sub_checked(context, *result, *lhs, *rhs),
+        Inst::MulChecked { result, lhs, rhs } => handler::mul_checked(context, *result, *lhs, *rhs),
+        Inst::MulWide { hi, lo, lhs, rhs } => handler::mul_wide(context, *hi, *lo, *lhs, *rhs),
+        Inst::Popcnt { result, src } => handler::popcnt(context, *result, *src),
+        Inst::Clz { result, src } => handler::clz(context, *result, *src),
+        Inst::Ctz { result, src } => handler::ctz(context, *result, *src),
+        Inst::MinU { result, lhs, rhs } => handler::min_u(context, *result, *lhs, *rhs),
+        Inst::MinUImm { result, src, imm } => handler::min_u_imm(context, *result, *src, *imm),
+        Inst::MaxU { result, lhs, rhs } => handler::max_u(context, *result, *lhs, *rhs),
+        Inst::MaxUImm { result, src, imm } => handler::max_u_imm(context, *result, *src, *imm),
+        Inst::MinS { result, lhs, rhs } => handler::min_s(context, *result, *lhs, *rhs),
+        Inst::MinSImm { result, src, imm } => handler::min_s_imm(context, *result, *src, *imm),
+        Inst::MaxS { result, lhs, rhs } => handler::max_s(context, *result, *lhs, *rhs),
+        Inst::MaxSImm { result, src, imm } => handler::max_s_imm(context, *result, *src, *imm),
+        Inst::Select {
+            result,
+            cond,
+            if_nonzero,
+            if_zero,
+        } => handler::select(context, *result, *cond, *if_nonzero, *if_zero),
+        Inst::Branch { target } => handler::branch(context, *target),
+        Inst::BranchEqz { target, condition } => handler::branch_eqz(context, *target, *condition),
+        Inst::BranchNez { target, condition } => handler::branch_nez(context, *target, *condition),
+        Inst::BranchEq { target, lhs, rhs } => handler::branch_eq(context, *target, *lhs, *rhs),
+        Inst::BranchNe { target, lhs, rhs } => handler::branch_ne(context, *target, *lhs, *rhs),
+        Inst::BranchLtU { target, lhs, rhs } => handler::branch_lt_u(context, *target, *lhs, *rhs),
+        Inst::BranchLtS { target, lhs, rhs } => handler::branch_lt_s(context, *target, *lhs, *rhs),
+        Inst::BranchTable {
+            index,
+            targets,
+            default,
+        } => handler::branch_table(context, *index, targets, *default),
+        Inst::Nop => handler::nop(context),
+        Inst::Yield => handler::yield_(context),
+        Inst::Return { result } => {
+            let value = context.get_reg(*result);
+            context.set_reg(Register(0), value);
+            Outcome::Return
+        }
+        Inst::RandNext { .. }
+        | Inst::FAdd { .. }
+        | Inst::FSub { .. }
+        | Inst::FMul { .. }
+        | Inst::FDiv { .. }
+        | Inst::Load8 { .. }
+        | Inst::Load16 { .. }
+        | Inst::Load32 { .. }
+        | Inst::Load64 { .. }
+        | Inst::Store8 { .. }
+        | Inst::Store16 { .. }
+        | Inst::Store32 { .. }
+        | Inst::Store64 { .. }
+        | Inst::Call { .. }
+        | Inst::Unreachable => handler::unreachable(),
+    }
+}
+
+/// Runs the `N` instructions of `insts` using the given [`FixedContext`] and returns the result in `r0`.
+///
+/// Neither the instructions nor the registers are stored on the heap.
+/// Yields are resumed immediately.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn execute_fixed<const N: usize, const R: usize>(
+    insts: &[Inst; N],
+    context: &mut FixedContext<R>,
+) -> Result<Bits, TrapCode> {
+    loop {
+        let inst = insts.get(context.pc).ok_or(TrapCode::OutOfBoundsPc)?;
+        match execute_inst(inst, context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return => return Ok(context.get_reg(Register(0))),
+            Outcome::Trap(trap) => return Err(trap),
+        }
+    }
+}
+
+#[test]
+fn counter_loop() {
+    let repetitions = 100_000_000;
+    let insts: [Inst; 5] = [
+        // Store `repetitions` into r0.
+        // Note: r0 is our loop counter register.
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: repetitions,
+        },
+        // Branch to the end if r0 is zero.
+        Inst::BranchEqz {
+            target: Target(4),
+            condition: Register(0),
+        },
+        // Decrease r0 by 1.
+        Inst::SubImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        // Jump back to the loop header.
+        Inst::Branch { target: Target(1) },
+        // Return value and end function execution.
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let mut context = FixedContext::<8>::default();
+    let (_, result) = benchmark(|| execute_fixed(&insts, &mut context));
+    assert_eq!(result, Ok(0));
+    assert_eq!(context.pc(), 4);
+}
+
+#[test]
+fn unsupported() {
+    let insts = [
+        Inst::RandNext {
+            result: Register(0),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_eq!(
+        execute_fixed(&insts, &mut FixedContext::<1>::default()),
+        Err(TrapCode::UnreachableExecuted)
+    );
+}
@@ -1,9 +1,12 @@
 #![allow(dead_code)]
 
+use alloc::vec::Vec;
+
 #[cfg(test)]
 use crate::benchmark;
 
-use super::{Bits, Const, Context, Global, Outcome, Register, Target};
+use super::{Bits, Const, Context, Global, Outcome, Register, Target, TrapCode};
+use crate::switch;
 
 // ===
 
@@ -176,8 +179,8 @@ impl InstData {
 
 #[derive(Copy, Clone)]
 pub struct Inst {
-    handler: fn(&mut Context, InstData) -> Outcome,
-    data: InstData,
+    pub(super) handler: fn(&mut Context, InstData) -> Outcome,
+    pub(super) data: InstData,
 }
 
 pub trait Result: Store + Into<RawSink> + From<RawSink> {}
@@ -206,6 +209,22 @@ impl Inst {
         }
     }
 
+    /// Adds the constant `imm` to `src` and stores the result into `result`.
+    ///
+    /// Unlike `Inst::add(result, src, Const(imm))` this only occupies two of the three data words.
+    pub fn add_imm<R>(result: R, src: Register, imm: Bits) -> Self
+    where
+        R: Result,
+    {
+        let inst = AddImmInst { result, src, imm };
+        Self {
+            handler: move |context, data| {
+                <AddImmInst<R> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
+    }
+
     pub fn sub<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
     where
         R: Result,
@@ -221,190 +240,1394 @@ impl Inst {
         }
     }
 
-    pub fn branch(target: Target) -> Self {
-        let inst = BranchInst { target };
+    /// Subtracts the constant `imm` from `src` and stores the result into `result`.
+    ///
+    /// Unlike `Inst::sub(result, src, Const(imm))` this only occupies two of the three data words.
+    pub fn sub_imm<R>(result: R, src: Register, imm: Bits) -> Self
+    where
+        R: Result,
+    {
+        let inst = SubImmInst { result, src, imm };
         Self {
             handler: move |context, data| {
-                <BranchInst as FromData>::from_data(data).execute(context)
+                <SubImmInst<R> as FromData>::from_data(data).execute(context)
             },
             data: IntoData::into_data(inst),
         }
     }
 
-    pub fn branch_eqz<C>(target: Target, condition: C) -> Self
+    pub fn mul<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
     where
-        C: Param,
+        R: Result,
+        P0: Param,
+        P1: Param,
     {
-        let inst = BranchEqzInst { target, condition };
+        let inst = MulInst { result, lhs, rhs };
         Self {
             handler: move |context, data| {
-                <BranchEqzInst<C> as FromData>::from_data(data).execute(context)
+                <MulInst<R, P0, P1> as FromData>::from_data(data).execute(context)
             },
             data: IntoData::into_data(inst),
         }
     }
 
-    pub fn ret<R>(result: R) -> Self
+    pub fn div<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
     where
-        R: Param,
+        R: Result,
+        P0: Param,
+        P1: Param,
     {
-        let inst = ReturnInst { result };
+        let inst = DivInst { result, lhs, rhs };
         Self {
             handler: move |context, data| {
-                <ReturnInst<R> as FromData>::from_data(data).execute(context)
+                <DivInst<R, P0, P1> as FromData>::from_data(data).execute(context)
             },
             data: IntoData::into_data(inst),
         }
     }
-}
-
-// ===
-
-pub trait IntoData {
-    fn into_data(self) -> InstData;
-}
-
-pub trait FromData {
-    fn from_data(data: InstData) -> Self;
-}
-
-pub trait Execute {
-    fn execute(self, context: &mut Context) -> Outcome;
-}
-
-// ===
-
-#[derive(Copy, Clone)]
-pub struct AddInst<R, P0, P1> {
-    result: R,
-    lhs: P0,
-    rhs: P1,
-}
-
-impl<R, P0, P1> AddInst<R, P0, P1> {
-    pub fn new(result: R, lhs: P0, rhs: P1) -> Self {
-        Self { result, lhs, rhs }
-    }
-}
 
-impl<R, P0, P1> IntoData for AddInst<R, P0, P1>
-where
-    R: Into<RawSink>,
-    P0: Into<RawSource>,
-    P1: Into<RawSource>,
-{
-    fn into_data(self) -> InstData {
-        InstData::from((self.result, self.lhs, self.rhs))
+    pub fn rem<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = RemInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <RemInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
     }
-}
 
-impl<R, P0, P1> FromData for AddInst<R, P0, P1>
-where
-    R: From<RawSink>,
-    P0: From<RawSource>,
-    P1: From<RawSource>,
-{
-    fn from_data(data: InstData) -> Self {
-        let (result, lhs, rhs) = data.into_raw_parts();
-        Self { result, lhs, rhs }
+    pub fn and<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = AndInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <AndInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
     }
-}
 
-impl<R, P0, P1> Execute for AddInst<R, P0, P1>
-where
-    R: Store,
-    P0: Load,
-    P1: Load,
-{
-    fn execute(self, context: &mut Context) -> Outcome {
-        let lhs = self.lhs.load(context);
-        let rhs = self.rhs.load(context);
-        self.result.store(context, lhs.wrapping_add(rhs));
-        context.next_inst()
+    pub fn or<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = OrInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <OrInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
     }
-}
-
-// ===
-
-#[derive(Copy, Clone)]
-pub struct SubInst<R, P0, P1> {
-    result: R,
-    lhs: P0,
-    rhs: P1,
-}
 
-impl<R, P0, P1> SubInst<R, P0, P1> {
-    pub fn new(result: R, lhs: P0, rhs: P1) -> Self {
-        Self { result, lhs, rhs }
+    pub fn xor<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = XorInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <XorInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
     }
-}
 
-impl<R, P0, P1> IntoData for SubInst<R, P0, P1>
-where
-    R: Into<RawSink>,
-    P0: Into<RawSource>,
-    P1: Into<RawSource>,
-{
-    fn into_data(self) -> InstData {
-        InstData::from((self.result, self.lhs, self.rhs))
+    pub fn shl<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = ShlInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <ShlInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
     }
-}
 
-impl<R, P0, P1> FromData for SubInst<R, P0, P1>
-where
-    R: From<RawSink>,
-    P0: From<RawSource>,
-    P1: From<RawSource>,
-{
-    fn from_data(data: InstData) -> Self {
-        let (result, lhs, rhs) = data.into_raw_parts();
-        Self { result, lhs, rhs }
+    pub fn shr_u<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = ShrUInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <ShrUInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
     }
-}
 
-impl<R, P0, P1> Execute for SubInst<R, P0, P1>
-where
-    R: Store,
-    P0: Load,
-    P1: Load,
-{
-    fn execute(self, context: &mut Context) -> Outcome {
-        let lhs = self.lhs.load(context);
-        let rhs = self.rhs.load(context);
-        self.result.store(context, lhs.wrapping_sub(rhs));
-        context.next_inst()
+    pub fn shr_s<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = ShrSInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <ShrSInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
     }
-}
-
-// ===
 
-#[derive(Copy, Clone)]
-pub struct BranchInst {
-    target: Target,
-}
-
-impl BranchInst {
-    pub fn new(target: Target) -> Self {
-        Self { target }
+    pub fn rotl<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = RotlInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <RotlInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
     }
-}
 
-impl IntoData for BranchInst {
-    fn into_data(self) -> InstData {
-        InstData {
-            sink: RawSink { index: self.target },
-            src0: RawSource { index: 0 },
-            src1: RawSource { index: 0 },
+    pub fn rotr<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = RotrInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <RotrInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
         }
     }
-}
 
-impl FromData for BranchInst {
-    fn from_data(data: InstData) -> Self {
-        let target = data.sink.index;
-        Self { target }
+    pub fn not<R, P>(result: R, input: P) -> Self
+    where
+        R: Result,
+        P: Param,
+    {
+        let inst = NotInst { result, input };
+        Self {
+            handler: move |context, data| {
+                <NotInst<R, P> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
     }
-}
+
+    pub fn eq<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = EqInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <EqInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
+    }
+
+    pub fn ne<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = NeInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <NeInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
+    }
+
+    pub fn lt_u<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = LtUInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <LtUInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
+    }
+
+    pub fn le_u<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = LeUInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <LeUInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
+    }
+
+    pub fn gt_u<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = GtUInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <GtUInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
+    }
+
+    pub fn ge_u<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = GeUInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <GeUInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
+    }
+
+    pub fn lt_s<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = LtSInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <LtSInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
+    }
+
+    pub fn le_s<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = LeSInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <LeSInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
+    }
+
+    pub fn gt_s<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = GtSInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <GtSInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
+    }
+
+    pub fn ge_s<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Result,
+        P0: Param,
+        P1: Param,
+    {
+        let inst = GeSInst { result, lhs, rhs };
+        Self {
+            handler: move |context, data| {
+                <GeSInst<R, P0, P1> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
+    }
+
+    pub fn branch(target: Target) -> Self {
+        let inst = BranchInst { target };
+        Self {
+            handler: move |context, data| {
+                <BranchInst as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
+    }
+
+    pub fn branch_eqz<C>(target: Target, condition: C) -> Self
+    where
+        C: Param,
+    {
+        let inst = BranchEqzInst { target, condition };
+        Self {
+            handler: move |context, data| {
+                <BranchEqzInst<C> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
+    }
+
+    pub fn branch_nez<C>(target: Target, condition: C) -> Self
+    where
+        C: Param,
+    {
+        let inst = BranchNezInst { target, condition };
+        Self {
+            handler: move |context, data| {
+                <BranchNezInst<C> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
+    }
+
+    pub fn unreachable() -> Self {
+        let inst = UnreachableInst;
+        Self {
+            handler: move |context, data| {
+                <UnreachableInst as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
+    }
+
+    pub fn ret<R>(result: R) -> Self
+    where
+        R: Param,
+    {
+        let inst = ReturnInst { result };
+        Self {
+            handler: move |context, data| {
+                <ReturnInst<R> as FromData>::from_data(data).execute(context)
+            },
+            data: IntoData::into_data(inst),
+        }
+    }
+}
+
+// ===
+
+pub trait IntoData {
+    fn into_data(self) -> InstData;
+}
+
+pub trait FromData {
+    fn from_data(data: InstData) -> Self;
+}
+
+pub trait Execute {
+    fn execute(self, context: &mut Context) -> Outcome;
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct AddInst<R, P0, P1> {
+    result: R,
+    lhs: P0,
+    rhs: P1,
+}
+
+impl<R, P0, P1> AddInst<R, P0, P1> {
+    pub fn new(result: R, lhs: P0, rhs: P1) -> Self {
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> IntoData for AddInst<R, P0, P1>
+where
+    R: Into<RawSink>,
+    P0: Into<RawSource>,
+    P1: Into<RawSource>,
+{
+    fn into_data(self) -> InstData {
+        InstData::from((self.result, self.lhs, self.rhs))
+    }
+}
+
+impl<R, P0, P1> FromData for AddInst<R, P0, P1>
+where
+    R: From<RawSink>,
+    P0: From<RawSource>,
+    P1: From<RawSource>,
+{
+    fn from_data(data: InstData) -> Self {
+        let (result, lhs, rhs) = data.into_raw_parts();
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> Execute for AddInst<R, P0, P1>
+where
+    R: Store,
+    P0: Load,
+    P1: Load,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let lhs = self.lhs.load(context);
+        let rhs = self.rhs.load(context);
+        self.result.store(context, lhs.wrapping_add(rhs));
+        context.next_inst()
+    }
+}
+
+// ===
+
+/// Packs the sink index of an immediate instruction and the index of its `src` register into one word.
+///
+/// # Panics
+///
+/// If either index does not fit into 32 bits.
+fn pack_imm_operands(sink: RawSink, src: Register) -> RawSink {
+    let sink = u32::try_from(sink.index).expect("sink index of immediate form out of bounds");
+    let src = u32::try_from(src.into_usize()).expect("src index of immediate form out of bounds");
+    RawSink {
+        index: (sink as usize) | ((src as usize) << 32),
+    }
+}
+
+/// Unpacks the sink and `src` register packed by [`pack_imm_operands`].
+fn unpack_imm_operands(packed: RawSink) -> (RawSink, Register) {
+    let sink = RawSink {
+        index: packed.index & 0xFFFF_FFFF,
+    };
+    (sink, Register(packed.index >> 32))
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct AddImmInst<R> {
+    result: R,
+    src: Register,
+    imm: Bits,
+}
+
+impl<R> AddImmInst<R> {
+    pub fn new(result: R, src: Register, imm: Bits) -> Self {
+        Self { result, src, imm }
+    }
+}
+
+impl<R> IntoData for AddImmInst<R>
+where
+    R: Into<RawSink>,
+{
+    fn into_data(self) -> InstData {
+        InstData {
+            sink: pack_imm_operands(self.result.into(), self.src),
+            src0: RawSource { index: self.imm },
+            src1: RawSource { index: 0 },
+        }
+    }
+}
+
+impl<R> FromData for AddImmInst<R>
+where
+    R: From<RawSink>,
+{
+    fn from_data(data: InstData) -> Self {
+        let (result, src) = unpack_imm_operands(data.sink);
+        Self {
+            result: R::from(result),
+            src,
+            imm: data.src0.index,
+        }
+    }
+}
+
+impl<R> Execute for AddImmInst<R>
+where
+    R: Store,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let src = self.src.load(context);
+        self.result.store(context, src.wrapping_add(self.imm));
+        context.next_inst()
+    }
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct SubImmInst<R> {
+    result: R,
+    src: Register,
+    imm: Bits,
+}
+
+impl<R> SubImmInst<R> {
+    pub fn new(result: R, src: Register, imm: Bits) -> Self {
+        Self { result, src, imm }
+    }
+}
+
+impl<R> IntoData for SubImmInst<R>
+where
+    R: Into<RawSink>,
+{
+    fn into_data(self) -> InstData {
+        InstData {
+            sink: pack_imm_operands(self.result.into(), self.src),
+            src0: RawSource { index: self.imm },
+            src1: RawSource { index: 0 },
+        }
+    }
+}
+
+impl<R> FromData for SubImmInst<R>
+where
+    R: From<RawSink>,
+{
+    fn from_data(data: InstData) -> Self {
+        let (result, src) = unpack_imm_operands(data.sink);
+        Self {
+            result: R::from(result),
+            src,
+            imm: data.src0.index,
+        }
+    }
+}
+
+impl<R> Execute for SubImmInst<R>
+where
+    R: Store,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let src = self.src.load(context);
+        self.result.store(context, src.wrapping_sub(self.imm));
+        context.next_inst()
+    }
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct SubInst<R, P0, P1> {
+    result: R,
+    lhs: P0,
+    rhs: P1,
+}
+
+impl<R, P0, P1> SubInst<R, P0, P1> {
+    pub fn new(result: R, lhs: P0, rhs: P1) -> Self {
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> IntoData for SubInst<R, P0, P1>
+where
+    R: Into<RawSink>,
+    P0: Into<RawSource>,
+    P1: Into<RawSource>,
+{
+    fn into_data(self) -> InstData {
+        InstData::from((self.result, self.lhs, self.rhs))
+    }
+}
+
+impl<R, P0, P1> FromData for SubInst<R, P0, P1>
+where
+    R: From<RawSink>,
+    P0: From<RawSource>,
+    P1: From<RawSource>,
+{
+    fn from_data(data: InstData) -> Self {
+        let (result, lhs, rhs) = data.into_raw_parts();
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> Execute for SubInst<R, P0, P1>
+where
+    R: Store,
+    P0: Load,
+    P1: Load,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let lhs = self.lhs.load(context);
+        let rhs = self.rhs.load(context);
+        self.result.store(context, lhs.wrapping_sub(rhs));
+        context.next_inst()
+    }
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct MulInst<R, P0, P1> {
+    result: R,
+    lhs: P0,
+    rhs: P1,
+}
+
+impl<R, P0, P1> MulInst<R, P0, P1> {
+    pub fn new(result: R, lhs: P0, rhs: P1) -> Self {
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> IntoData for MulInst<R, P0, P1>
+where
+    R: Into<RawSink>,
+    P0: Into<RawSource>,
+    P1: Into<RawSource>,
+{
+    fn into_data(self) -> InstData {
+        InstData::from((self.result, self.lhs, self.rhs))
+    }
+}
+
+impl<R, P0, P1> FromData for MulInst<R, P0, P1>
+where
+    R: From<RawSink>,
+    P0: From<RawSource>,
+    P1: From<RawSource>,
+{
+    fn from_data(data: InstData) -> Self {
+        let (result, lhs, rhs) = data.into_raw_parts();
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> Execute for MulInst<R, P0, P1>
+where
+    R: Store,
+    P0: Load,
+    P1: Load,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let lhs = self.lhs.load(context);
+        let rhs = self.rhs.load(context);
+        self.result.store(context, lhs.wrapping_mul(rhs));
+        context.next_inst()
+    }
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct DivInst<R, P0, P1> {
+    result: R,
+    lhs: P0,
+    rhs: P1,
+}
+
+impl<R, P0, P1> DivInst<R, P0, P1> {
+    pub fn new(result: R, lhs: P0, rhs: P1) -> Self {
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> IntoData for DivInst<R, P0, P1>
+where
+    R: Into<RawSink>,
+    P0: Into<RawSource>,
+    P1: Into<RawSource>,
+{
+    fn into_data(self) -> InstData {
+        InstData::from((self.result, self.lhs, self.rhs))
+    }
+}
+
+impl<R, P0, P1> FromData for DivInst<R, P0, P1>
+where
+    R: From<RawSink>,
+    P0: From<RawSource>,
+    P1: From<RawSource>,
+{
+    fn from_data(data: InstData) -> Self {
+        let (result, lhs, rhs) = data.into_raw_parts();
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> Execute for DivInst<R, P0, P1>
+where
+    R: Store,
+    P0: Load,
+    P1: Load,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let lhs = self.lhs.load(context);
+        let rhs = self.rhs.load(context);
+        match lhs.checked_div(rhs) {
+            Some(quotient) => {
+                self.result.store(context, quotient);
+                context.next_inst()
+            }
+            None => Outcome::Trap(TrapCode::DivisionByZero),
+        }
+    }
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct RemInst<R, P0, P1> {
+    result: R,
+    lhs: P0,
+    rhs: P1,
+}
+
+impl<R, P0, P1> RemInst<R, P0, P1> {
+    pub fn new(result: R, lhs: P0, rhs: P1) -> Self {
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> IntoData for RemInst<R, P0, P1>
+where
+    R: Into<RawSink>,
+    P0: Into<RawSource>,
+    P1: Into<RawSource>,
+{
+    fn into_data(self) -> InstData {
+        InstData::from((self.result, self.lhs, self.rhs))
+    }
+}
+
+impl<R, P0, P1> FromData for RemInst<R, P0, P1>
+where
+    R: From<RawSink>,
+    P0: From<RawSource>,
+    P1: From<RawSource>,
+{
+    fn from_data(data: InstData) -> Self {
+        let (result, lhs, rhs) = data.into_raw_parts();
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> Execute for RemInst<R, P0, P1>
+where
+    R: Store,
+    P0: Load,
+    P1: Load,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let lhs = self.lhs.load(context);
+        let rhs = self.rhs.load(context);
+        match lhs.checked_rem(rhs) {
+            Some(remainder) => {
+                self.result.store(context, remainder);
+                context.next_inst()
+            }
+            None => Outcome::Trap(TrapCode::DivisionByZero),
+        }
+    }
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct AndInst<R, P0, P1> {
+    result: R,
+    lhs: P0,
+    rhs: P1,
+}
+
+impl<R, P0, P1> AndInst<R, P0, P1> {
+    pub fn new(result: R, lhs: P0, rhs: P1) -> Self {
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> IntoData for AndInst<R, P0, P1>
+where
+    R: Into<RawSink>,
+    P0: Into<RawSource>,
+    P1: Into<RawSource>,
+{
+    fn into_data(self) -> InstData {
+        InstData::from((self.result, self.lhs, self.rhs))
+    }
+}
+
+impl<R, P0, P1> FromData for AndInst<R, P0, P1>
+where
+    R: From<RawSink>,
+    P0: From<RawSource>,
+    P1: From<RawSource>,
+{
+    fn from_data(data: InstData) -> Self {
+        let (result, lhs, rhs) = data.into_raw_parts();
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> Execute for AndInst<R, P0, P1>
+where
+    R: Store,
+    P0: Load,
+    P1: Load,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let lhs = self.lhs.load(context);
+        let rhs = self.rhs.load(context);
+        self.result.store(context, lhs & rhs);
+        context.next_inst()
+    }
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct OrInst<R, P0, P1> {
+    result: R,
+    lhs: P0,
+    rhs: P1,
+}
+
+impl<R, P0, P1> OrInst<R, P0, P1> {
+    pub fn new(result: R, lhs: P0, rhs: P1) -> Self {
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> IntoData for OrInst<R, P0, P1>
+where
+    R: Into<RawSink>,
+    P0: Into<RawSource>,
+    P1: Into<RawSource>,
+{
+    fn into_data(self) -> InstData {
+        InstData::from((self.result, self.lhs, self.rhs))
+    }
+}
+
+impl<R, P0, P1> FromData for OrInst<R, P0, P1>
+where
+    R: From<RawSink>,
+    P0: From<RawSource>,
+    P1: From<RawSource>,
+{
+    fn from_data(data: InstData) -> Self {
+        let (result, lhs, rhs) = data.into_raw_parts();
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> Execute for OrInst<R, P0, P1>
+where
+    R: Store,
+    P0: Load,
+    P1: Load,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let lhs = self.lhs.load(context);
+        let rhs = self.rhs.load(context);
+        self.result.store(context, lhs | rhs);
+        context.next_inst()
+    }
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct XorInst<R, P0, P1> {
+    result: R,
+    lhs: P0,
+    rhs: P1,
+}
+
+impl<R, P0, P1> XorInst<R, P0, P1> {
+    pub fn new(result: R, lhs: P0, rhs: P1) -> Self {
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> IntoData for XorInst<R, P0, P1>
+where
+    R: Into<RawSink>,
+    P0: Into<RawSource>,
+    P1: Into<RawSource>,
+{
+    fn into_data(self) -> InstData {
+        InstData::from((self.result, self.lhs, self.rhs))
+    }
+}
+
+impl<R, P0, P1> FromData for XorInst<R, P0, P1>
+where
+    R: From<RawSink>,
+    P0: From<RawSource>,
+    P1: From<RawSource>,
+{
+    fn from_data(data: InstData) -> Self {
+        let (result, lhs, rhs) = data.into_raw_parts();
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> Execute for XorInst<R, P0, P1>
+where
+    R: Store,
+    P0: Load,
+    P1: Load,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let lhs = self.lhs.load(context);
+        let rhs = self.rhs.load(context);
+        self.result.store(context, lhs ^ rhs);
+        context.next_inst()
+    }
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct ShlInst<R, P0, P1> {
+    result: R,
+    lhs: P0,
+    rhs: P1,
+}
+
+impl<R, P0, P1> ShlInst<R, P0, P1> {
+    pub fn new(result: R, lhs: P0, rhs: P1) -> Self {
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> IntoData for ShlInst<R, P0, P1>
+where
+    R: Into<RawSink>,
+    P0: Into<RawSource>,
+    P1: Into<RawSource>,
+{
+    fn into_data(self) -> InstData {
+        InstData::from((self.result, self.lhs, self.rhs))
+    }
+}
+
+impl<R, P0, P1> FromData for ShlInst<R, P0, P1>
+where
+    R: From<RawSink>,
+    P0: From<RawSource>,
+    P1: From<RawSource>,
+{
+    fn from_data(data: InstData) -> Self {
+        let (result, lhs, rhs) = data.into_raw_parts();
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> Execute for ShlInst<R, P0, P1>
+where
+    R: Store,
+    P0: Load,
+    P1: Load,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let lhs = self.lhs.load(context);
+        let rhs = self.rhs.load(context);
+        self.result.store(context, lhs << (rhs & 0x3F));
+        context.next_inst()
+    }
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct ShrUInst<R, P0, P1> {
+    result: R,
+    lhs: P0,
+    rhs: P1,
+}
+
+impl<R, P0, P1> ShrUInst<R, P0, P1> {
+    pub fn new(result: R, lhs: P0, rhs: P1) -> Self {
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> IntoData for ShrUInst<R, P0, P1>
+where
+    R: Into<RawSink>,
+    P0: Into<RawSource>,
+    P1: Into<RawSource>,
+{
+    fn into_data(self) -> InstData {
+        InstData::from((self.result, self.lhs, self.rhs))
+    }
+}
+
+impl<R, P0, P1> FromData for ShrUInst<R, P0, P1>
+where
+    R: From<RawSink>,
+    P0: From<RawSource>,
+    P1: From<RawSource>,
+{
+    fn from_data(data: InstData) -> Self {
+        let (result, lhs, rhs) = data.into_raw_parts();
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> Execute for ShrUInst<R, P0, P1>
+where
+    R: Store,
+    P0: Load,
+    P1: Load,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let lhs = self.lhs.load(context);
+        let rhs = self.rhs.load(context);
+        self.result.store(context, lhs >> (rhs & 0x3F));
+        context.next_inst()
+    }
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct ShrSInst<R, P0, P1> {
+    result: R,
+    lhs: P0,
+    rhs: P1,
+}
+
+impl<R, P0, P1> ShrSInst<R, P0, P1> {
+    pub fn new(result: R, lhs: P0, rhs: P1) -> Self {
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> IntoData for ShrSInst<R, P0, P1>
+where
+    R: Into<RawSink>,
+    P0: Into<RawSource>,
+    P1: Into<RawSource>,
+{
+    fn into_data(self) -> InstData {
+        InstData::from((self.result, self.lhs, self.rhs))
+    }
+}
+
+impl<R, P0, P1> FromData for ShrSInst<R, P0, P1>
+where
+    R: From<RawSink>,
+    P0: From<RawSource>,
+    P1: From<RawSource>,
+{
+    fn from_data(data: InstData) -> Self {
+        let (result, lhs, rhs) = data.into_raw_parts();
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> Execute for ShrSInst<R, P0, P1>
+where
+    R: Store,
+    P0: Load,
+    P1: Load,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let lhs = self.lhs.load(context);
+        let rhs = self.rhs.load(context);
+        self.result
+            .store(context, ((lhs as i64) >> (rhs & 0x3F)) as Bits);
+        context.next_inst()
+    }
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct RotlInst<R, P0, P1> {
+    result: R,
+    lhs: P0,
+    rhs: P1,
+}
+
+impl<R, P0, P1> RotlInst<R, P0, P1> {
+    pub fn new(result: R, lhs: P0, rhs: P1) -> Self {
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> IntoData for RotlInst<R, P0, P1>
+where
+    R: Into<RawSink>,
+    P0: Into<RawSource>,
+    P1: Into<RawSource>,
+{
+    fn into_data(self) -> InstData {
+        InstData::from((self.result, self.lhs, self.rhs))
+    }
+}
+
+impl<R, P0, P1> FromData for RotlInst<R, P0, P1>
+where
+    R: From<RawSink>,
+    P0: From<RawSource>,
+    P1: From<RawSource>,
+{
+    fn from_data(data: InstData) -> Self {
+        let (result, lhs, rhs) = data.into_raw_parts();
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> Execute for RotlInst<R, P0, P1>
+where
+    R: Store,
+    P0: Load,
+    P1: Load,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let lhs = self.lhs.load(context);
+        let rhs = self.rhs.load(context);
+        self.result
+            .store(context, lhs.rotate_left((rhs & 0x3F) as u32));
+        context.next_inst()
+    }
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct RotrInst<R, P0, P1> {
+    result: R,
+    lhs: P0,
+    rhs: P1,
+}
+
+impl<R, P0, P1> RotrInst<R, P0, P1> {
+    pub fn new(result: R, lhs: P0, rhs: P1) -> Self {
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> IntoData for RotrInst<R, P0, P1>
+where
+    R: Into<RawSink>,
+    P0: Into<RawSource>,
+    P1: Into<RawSource>,
+{
+    fn into_data(self) -> InstData {
+        InstData::from((self.result, self.lhs, self.rhs))
+    }
+}
+
+impl<R, P0, P1> FromData for RotrInst<R, P0, P1>
+where
+    R: From<RawSink>,
+    P0: From<RawSource>,
+    P1: From<RawSource>,
+{
+    fn from_data(data: InstData) -> Self {
+        let (result, lhs, rhs) = data.into_raw_parts();
+        Self { result, lhs, rhs }
+    }
+}
+
+impl<R, P0, P1> Execute for RotrInst<R, P0, P1>
+where
+    R: Store,
+    P0: Load,
+    P1: Load,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let lhs = self.lhs.load(context);
+        let rhs = self.rhs.load(context);
+        self.result
+            .store(context, lhs.rotate_right((rhs & 0x3F) as u32));
+        context.next_inst()
+    }
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct NotInst<R, P> {
+    result: R,
+    input: P,
+}
+
+impl<R, P> NotInst<R, P> {
+    pub fn new(result: R, input: P) -> Self {
+        Self { result, input }
+    }
+}
+
+impl<R, P> IntoData for NotInst<R, P>
+where
+    R: Into<RawSink>,
+    P: Into<RawSource>,
+{
+    fn into_data(self) -> InstData {
+        InstData {
+            sink: self.result.into(),
+            src0: self.input.into(),
+            src1: RawSource { index: 0 },
+        }
+    }
+}
+
+impl<R, P> FromData for NotInst<R, P>
+where
+    R: From<RawSink>,
+    P: From<RawSource>,
+{
+    fn from_data(data: InstData) -> Self {
+        let result = R::from(data.sink);
+        let input = P::from(data.src0);
+        Self { result, input }
+    }
+}
+
+impl<R, P> Execute for NotInst<R, P>
+where
+    R: Store,
+    P: Load,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let input = self.input.load(context);
+        self.result.store(context, !input);
+        context.next_inst()
+    }
+}
+
+// ===
+
+macro_rules! impl_cmp_insts {
+    ( $( $inst_name:ident($op_name:ident $(as $ty:ty)?) ),* $(,)? ) => {
+        $(
+            #[derive(Copy, Clone)]
+            pub struct $inst_name<R, P0, P1> {
+                result: R,
+                lhs: P0,
+                rhs: P1,
+            }
+
+            impl<R, P0, P1> $inst_name<R, P0, P1> {
+                pub fn new(result: R, lhs: P0, rhs: P1) -> Self {
+                    Self { result, lhs, rhs }
+                }
+            }
+
+            impl<R, P0, P1> IntoData for $inst_name<R, P0, P1>
+            where
+                R: Into<RawSink>,
+                P0: Into<RawSource>,
+                P1: Into<RawSource>,
+            {
+                fn into_data(self) -> InstData {
+                    InstData::from((self.result, self.lhs, self.rhs))
+                }
+            }
+
+            impl<R, P0, P1> FromData for $inst_name<R, P0, P1>
+            where
+                R: From<RawSink>,
+                P0: From<RawSource>,
+                P1: From<RawSource>,
+            {
+                fn from_data(data: InstData) -> Self {
+                    let (result, lhs, rhs) = data.into_raw_parts();
+                    Self { result, lhs, rhs }
+                }
+            }
+
+            impl<R, P0, P1> Execute for $inst_name<R, P0, P1>
+            where
+                R: Store,
+                P0: Load,
+                P1: Load,
+            {
+                fn execute(self, context: &mut Context) -> Outcome {
+                    let lhs = self.lhs.load(context) $(as $ty)?;
+                    let rhs = self.rhs.load(context) $(as $ty)?;
+                    self.result.store(context, lhs.$op_name(&rhs) as u64);
+                    context.next_inst()
+                }
+            }
+        )*
+    };
+}
+impl_cmp_insts! {
+    EqInst(eq),
+    NeInst(ne),
+    LtUInst(lt),
+    LeUInst(le),
+    GtUInst(gt),
+    GeUInst(ge),
+    LtSInst(lt as i64),
+    LeSInst(le as i64),
+    GtSInst(gt as i64),
+    GeSInst(ge as i64),
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct BranchInst {
+    target: Target,
+}
+
+impl BranchInst {
+    pub fn new(target: Target) -> Self {
+        Self { target }
+    }
+}
+
+impl IntoData for BranchInst {
+    fn into_data(self) -> InstData {
+        InstData {
+            sink: RawSink { index: self.target },
+            src0: RawSource { index: 0 },
+            src1: RawSource { index: 0 },
+        }
+    }
+}
+
+impl FromData for BranchInst {
+    fn from_data(data: InstData) -> Self {
+        let target = data.sink.index;
+        Self { target }
+    }
+}
 
 impl Execute for BranchInst {
     fn execute(self, context: &mut Context) -> Outcome {
@@ -466,6 +1689,85 @@ where
 
 // ===
 
+#[derive(Copy, Clone)]
+pub struct BranchNezInst<C> {
+    target: Target,
+    condition: C,
+}
+
+impl<C> BranchNezInst<C> {
+    pub fn new(target: Target, condition: C) -> Self {
+        Self { target, condition }
+    }
+}
+
+impl<C> IntoData for BranchNezInst<C>
+where
+    C: Into<RawSource>,
+{
+    fn into_data(self) -> InstData {
+        InstData {
+            sink: RawSink { index: self.target },
+            src0: self.condition.into(),
+            src1: RawSource { index: 0 },
+        }
+    }
+}
+
+impl<C> FromData for BranchNezInst<C>
+where
+    C: From<RawSource>,
+{
+    fn from_data(data: InstData) -> Self {
+        let target = data.sink.index;
+        let condition = C::from(data.src0);
+        Self { target, condition }
+    }
+}
+
+impl<C> Execute for BranchNezInst<C>
+where
+    C: Load,
+{
+    fn execute(self, context: &mut Context) -> Outcome {
+        let condition = self.condition.load(context);
+        if condition != 0 {
+            context.branch_to(self.target)
+        } else {
+            context.next_inst()
+        }
+    }
+}
+
+// ===
+
+#[derive(Copy, Clone)]
+pub struct UnreachableInst;
+
+impl IntoData for UnreachableInst {
+    fn into_data(self) -> InstData {
+        InstData {
+            sink: RawSink { index: 0 },
+            src0: RawSource { index: 0 },
+            src1: RawSource { index: 0 },
+        }
+    }
+}
+
+impl FromData for UnreachableInst {
+    fn from_data(_data: InstData) -> Self {
+        Self
+    }
+}
+
+impl Execute for UnreachableInst {
+    fn execute(self, _context: &mut Context) -> Outcome {
+        Outcome::Trap(TrapCode::UnreachableExecuted)
+    }
+}
+
+// ===
+
 #[derive(Copy, Clone)]
 pub struct ReturnInst<R> {
     result: R,
@@ -513,24 +1815,126 @@ where
 
 // ===
 
-/// Executes the list of instruction using the given [`Context`].
-fn execute(insts: &[Inst], context: &mut Context) {
+/// Lowers the [`switch::Inst`] program to monomorphized fused instructions.
+///
+/// # Errors
+///
+/// Returns the first instruction that has no counterpart.
+pub fn lower(insts: &[switch::Inst]) -> core::result::Result<Vec<Inst>, switch::Inst> {
+    insts.iter().cloned().map(lower_inst).collect()
+}
+
+/// Lowers a single [`switch::Inst`].
+fn lower_inst(inst: switch::Inst) -> core::result::Result<Inst, switch::Inst> {
+    let lowered = match inst {
+        switch::Inst::Add { result, lhs, rhs } => {
+            Inst::add(Register(result.0), Register(lhs.0), Register(rhs.0))
+        }
+        switch::Inst::AddImm { result, src, imm } => {
+            Inst::add(Register(result.0), Register(src.0), Const(imm))
+        }
+        switch::Inst::Sub { result, lhs, rhs } => {
+            Inst::sub(Register(result.0), Register(lhs.0), Register(rhs.0))
+        }
+        switch::Inst::SubImm { result, src, imm } => {
+            Inst::sub(Register(result.0), Register(src.0), Const(imm))
+        }
+        switch::Inst::Mul { result, lhs, rhs } => {
+            Inst::mul(Register(result.0), Register(lhs.0), Register(rhs.0))
+        }
+        switch::Inst::MulImm { result, src, imm } => {
+            Inst::mul(Register(result.0), Register(src.0), Const(imm))
+        }
+        switch::Inst::Div { result, lhs, rhs } => {
+            Inst::div(Register(result.0), Register(lhs.0), Register(rhs.0))
+        }
+        switch::Inst::DivImm { result, src, imm } => {
+            Inst::div(Register(result.0), Register(src.0), Const(imm))
+        }
+        switch::Inst::Rem { result, lhs, rhs } => {
+            Inst::rem(Register(result.0), Register(lhs.0), Register(rhs.0))
+        }
+        switch::Inst::RemImm { result, src, imm } => {
+            Inst::rem(Register(result.0), Register(src.0), Const(imm))
+        }
+        switch::Inst::Shl { result, lhs, rhs } => {
+            Inst::shl(Register(result.0), Register(lhs.0), Register(rhs.0))
+        }
+        switch::Inst::ShlImm { result, src, imm } => {
+            Inst::shl(Register(result.0), Register(src.0), Const(imm))
+        }
+        switch::Inst::ShrU { result, lhs, rhs } => {
+            Inst::shr_u(Register(result.0), Register(lhs.0), Register(rhs.0))
+        }
+        switch::Inst::ShrUImm { result, src, imm } => {
+            Inst::shr_u(Register(result.0), Register(src.0), Const(imm))
+        }
+        switch::Inst::ShrS { result, lhs, rhs } => {
+            Inst::shr_s(Register(result.0), Register(lhs.0), Register(rhs.0))
+        }
+        switch::Inst::ShrSImm { result, src, imm } => {
+            Inst::shr_s(Register(result.0), Register(src.0), Const(imm))
+        }
+        switch::Inst::Rotl { result, lhs, rhs } => {
+            Inst::rotl(Register(result.0), Register(lhs.0), Register(rhs.0))
+        }
+        switch::Inst::RotlImm { result, src, imm } => {
+            Inst::rotl(Register(result.0), Register(src.0), Const(imm))
+        }
+        switch::Inst::Rotr { result, lhs, rhs } => {
+            Inst::rotr(Register(result.0), Register(lhs.0), Register(rhs.0))
+        }
+        switch::Inst::RotrImm { result, src, imm } => {
+            Inst::rotr(Register(result.0), Register(src.0), Const(imm))
+        }
+        switch::Inst::Branch { target } => Inst::branch(target.0),
+        switch::Inst::BranchEqz { target, condition } => {
+            Inst::branch_eqz(target.0, Register(condition.0))
+        }
+        switch::Inst::BranchNez { target, condition } => {
+            Inst::branch_nez(target.0, Register(condition.0))
+        }
+        switch::Inst::Unreachable => Inst::unreachable(),
+        switch::Inst::Return { result } => Inst::ret(Register(result.0)),
+        unsupported => return Err(unsupported),
+    };
+    Ok(lowered)
+}
+
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Bits {
     loop {
         let pc = context.pc;
         let inst = &insts[pc];
         match inst.execute(context) {
-            Outcome::Continue => continue,
-            Outcome::Return => return,
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return context.get_reg(Register(0)),
+        }
+    }
+}
+
+/// Runs the list of instruction using the given [`Context`] and returns the result.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn run(insts: &[Inst], context: &mut Context) -> core::result::Result<Bits, TrapCode> {
+    loop {
+        let pc = context.pc;
+        let inst = insts.get(pc).ok_or(TrapCode::OutOfBoundsPc)?;
+        match inst.execute(context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return => return Ok(context.get_reg(Register(0))),
+            Outcome::Trap(trap) => return Err(trap),
         }
     }
 }
 
 // ===
 
-#[test]
-fn counter_loop() {
-    let repetitions = 100_000_000;
-    let insts = vec![
+/// Returns the `counter_loop` benchmark program counting `repetitions` down to zero.
+pub(crate) fn counter_loop_insts(repetitions: Bits) -> Vec<Inst> {
+    vec![
         // Store `repetitions` into r0.
         // Note: r0 is our loop counter register.
         Inst::add(Register(0), Register(0), Const(repetitions)),
@@ -542,7 +1946,191 @@ fn counter_loop() {
         Inst::branch(1),
         // Return value and end function execution.
         Inst::ret(Register(0)),
+    ]
+}
+
+/// Returns the `counter_loop` benchmark program using the immediate forms [`Inst::add_imm`] and [`Inst::sub_imm`].
+pub(crate) fn counter_loop_imm_insts(repetitions: Bits) -> Vec<Inst> {
+    vec![
+        // Store `repetitions` into r0.
+        // Note: r0 is our loop counter register.
+        Inst::add_imm(Register(0), Register(0), repetitions),
+        // Branch to the end if r0 is zero.
+        Inst::branch_eqz(4, Register(0)),
+        // Decrease r0 by 1.
+        Inst::sub_imm(Register(0), Register(0), 1),
+        // Jump back to the loop header.
+        Inst::branch(1),
+        // Return value and end function execution.
+        Inst::ret(Register(0)),
+    ]
+}
+
+#[test]
+fn counter_loop() {
+    let insts = counter_loop_insts(100_000_000);
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn counter_loop_imm() {
+    let insts = counter_loop_imm_insts(100_000_000);
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn imm_matches_general_form() {
+    for (lhs, imm) in [(0, 0), (5, 3), (3, 5), (Bits::MAX, 1), (1 << 40, Bits::MAX)] {
+        let mut expected = Context::default();
+        let mut context = Context::default();
+        expected.set_reg(Register(7), lhs);
+        context.set_reg(Register(7), lhs);
+        let general = [
+            Inst::add(Register(1), Register(7), Const(imm)),
+            Inst::sub(Global(2), Register(7), Const(imm)),
+            Inst::ret(Register(1)),
+        ];
+        let compact = [
+            Inst::add_imm(Register(1), Register(7), imm),
+            Inst::sub_imm(Global(2), Register(7), imm),
+            Inst::ret(Register(1)),
+        ];
+        assert_eq!(run(&compact, &mut context), run(&general, &mut expected),);
+        assert_eq!(
+            context.get_global(Global(2)),
+            expected.get_global(Global(2))
+        );
+        assert_eq!(compact[0].data.src1.index, 0);
+    }
+}
+
+#[test]
+fn counter_loop_nez() {
+    let repetitions = 100_000_000;
+    let insts = vec![
+        // Store `repetitions` into r0.
+        // Note: r0 is our loop counter register.
+        Inst::add(Register(0), Register(0), Const(repetitions)),
+        // Decrease r0 by 1.
+        Inst::sub(Register(0), Register(0), Const(1)),
+        // Jump back to the loop header if r0 is not zero.
+        Inst::branch_nez(1, Register(0)),
+        // Return value and end function execution.
+        Inst::ret(Register(0)),
+    ];
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn div_rem() {
+    let insts = vec![
+        // Store `17` into r0 and `5` into g0.
+        Inst::add(Register(0), Register(0), Const(17)),
+        Inst::add(Global(0), Global(0), Const(5)),
+        // Divide and take the remainder of r0 by g0.
+        Inst::div(Register(1), Register(0), Global(0)),
+        Inst::rem(Register(2), Register(0), Global(0)),
+        // Divide and take the remainder of r0 by `4`.
+        Inst::div(Register(3), Register(0), Const(4)),
+        Inst::rem(Register(4), Register(0), Const(4)),
+        // Return value and end function execution.
+        Inst::ret(Register(1)),
+    ];
+    let mut context = Context::default();
+    execute(&insts, &mut context);
+    assert_eq!(context.get_reg(Register(0)), 17 / 5);
+    assert_eq!(context.get_reg(Register(2)), 17 % 5);
+    assert_eq!(context.get_reg(Register(3)), 17 / 4);
+    assert_eq!(context.get_reg(Register(4)), 17 % 4);
+}
+
+#[test]
+fn div_rem_by_zero() {
+    let mut context = Context::default();
+    context.set_reg(Register(0), 17);
+    for inst in [
+        Inst::div(Register(1), Register(0), Register(2)),
+        Inst::div(Register(1), Register(0), Const(0)),
+        Inst::rem(Register(1), Register(0), Register(2)),
+        Inst::rem(Register(1), Register(0), Const(0)),
+    ] {
+        assert!(matches!(
+            inst.execute(&mut context),
+            Outcome::Trap(TrapCode::DivisionByZero)
+        ));
+    }
+}
+
+#[test]
+fn bitwise() {
+    let insts = vec![
+        // Store the bit patterns into r0 and g0.
+        Inst::add(Register(0), Const(0), Const(0b1100)),
+        Inst::add(Global(0), Const(0), Const(0b1010)),
+        // Combine r0 and g0 bitwise.
+        Inst::and(Register(1), Register(0), Global(0)),
+        Inst::or(Global(1), Global(0), Register(0)),
+        Inst::xor(Register(2), Register(0), Const(0b1010)),
+        Inst::not(Register(3), Global(0)),
+        Inst::ret(Global(1)),
     ];
     let mut context = Context::default();
-    benchmark(|| execute(&insts, &mut context));
+    assert_eq!(run(&insts, &mut context), Ok(0b1110));
+    assert_eq!(context.get_reg(Register(1)), 0b1000);
+    assert_eq!(context.get_reg(Register(2)), 0b0110);
+    assert_eq!(context.get_reg(Register(3)), !0b1010);
+}
+
+#[test]
+fn shifts() {
+    for (value, amount) in crate::switch::SHIFT_INPUTS {
+        let insts = [
+            Inst::shl(Register(2), Register(0), Global(0)),
+            Inst::shr_u(Register(3), Register(0), Const(amount)),
+            Inst::shr_s(Global(1), Register(0), Global(0)),
+            Inst::rotl(Register(5), Register(0), Const(amount)),
+            Inst::rotr(Register(6), Const(value), Global(0)),
+            Inst::ret(Register(2)),
+        ];
+        let mut context = Context::default();
+        context.set_reg(Register(0), value);
+        context.set_global(Global(0), amount);
+        assert_eq!(
+            run(&insts, &mut context),
+            Ok(value.wrapping_shl(amount as u32))
+        );
+        assert_eq!(
+            context.get_reg(Register(3)),
+            value.wrapping_shr(amount as u32)
+        );
+        assert_eq!(
+            context.get_global(Global(1)),
+            (value as i64).wrapping_shr(amount as u32) as Bits
+        );
+        assert_eq!(
+            context.get_reg(Register(5)),
+            value.rotate_left(amount as u32)
+        );
+        assert_eq!(
+            context.get_reg(Register(6)),
+            value.rotate_right(amount as u32)
+        );
+    }
+}
+
+#[test]
+fn unreachable() {
+    let insts = [Inst::ret(Const(42)), Inst::unreachable()];
+    assert_eq!(run(&insts, &mut Context::default()), Ok(42));
+    let insts = [Inst::branch(2), Inst::ret(Const(42)), Inst::unreachable()];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::UnreachableExecuted)
+    );
 }
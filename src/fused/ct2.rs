@@ -1,297 +1,291 @@
 #![allow(dead_code)]
 
+use alloc::vec::Vec;
+
 #[cfg(test)]
 use crate::benchmark;
 
 use super::{
-    ct::{AddInst, BranchEqzInst, BranchInst, Execute, ReturnInst, SubInst},
+    ct::{AddInst, BranchEqzInst, BranchInst, Execute, MulInst, ReturnInst, SubInst},
     rt::{
         AddInst as DynamicAddInst, BranchEqzInst as DynamicBranchEqzInst,
-        BranchInst as DynamicBranchInst, Inst as DynamicInst, ReturnInst as DynamicReturnInst,
-        Sink, Source, SubInst as DynamicSubInst,
+        BranchInst as DynamicBranchInst, Inst as DynamicInst, MulInst as DynamicMulInst,
+        ReturnInst as DynamicReturnInst, Sink, Source, SubInst as DynamicSubInst,
     },
-    Const, Context, Global, Outcome, Register,
+    Bits, Const, Context, Global, Outcome, Register, TrapCode,
 };
 use derive_more::From;
 
-#[derive(Copy, Clone, From)]
-pub enum Inst {
-    AddRrr(AddInst<Register, Register, Register>),
-    AddRrg(AddInst<Register, Register, Global>),
-    AddRrc(AddInst<Register, Register, Const>),
-    AddRgr(AddInst<Register, Global, Register>),
-    AddRgg(AddInst<Register, Global, Global>),
-    AddRgc(AddInst<Register, Global, Const>),
-    AddRcr(AddInst<Register, Const, Register>),
-    AddRcg(AddInst<Register, Const, Global>),
-    AddRcc(AddInst<Register, Const, Const>),
-    AddGrr(AddInst<Global, Register, Register>),
-    AddGrg(AddInst<Global, Register, Global>),
-    AddGrc(AddInst<Global, Register, Const>),
-    AddGgr(AddInst<Global, Global, Register>),
-    AddGgg(AddInst<Global, Global, Global>),
-    AddGgc(AddInst<Global, Global, Const>),
-    AddGcr(AddInst<Global, Const, Register>),
-    AddGcg(AddInst<Global, Const, Global>),
-    AddGcc(AddInst<Global, Const, Const>),
-
-    SubRrr(SubInst<Register, Register, Register>),
-    SubRrg(SubInst<Register, Register, Global>),
-    SubRrc(SubInst<Register, Register, Const>),
-    SubRgr(SubInst<Register, Global, Register>),
-    SubRgg(SubInst<Register, Global, Global>),
-    SubRgc(SubInst<Register, Global, Const>),
-    SubRcr(SubInst<Register, Const, Register>),
-    SubRcg(SubInst<Register, Const, Global>),
-    SubRcc(SubInst<Register, Const, Const>),
-    SubGrr(SubInst<Global, Register, Register>),
-    SubGrg(SubInst<Global, Register, Global>),
-    SubGrc(SubInst<Global, Register, Const>),
-    SubGgr(SubInst<Global, Global, Register>),
-    SubGgg(SubInst<Global, Global, Global>),
-    SubGgc(SubInst<Global, Global, Const>),
-    SubGcr(SubInst<Global, Const, Register>),
-    SubGcg(SubInst<Global, Const, Global>),
-    SubGcc(SubInst<Global, Const, Const>),
-
-    Branch(BranchInst),
-
-    BranchEqzR(BranchEqzInst<Register>),
-    BranchEqzC(BranchEqzInst<Const>),
-    BranchEqzG(BranchEqzInst<Global>),
-
-    ReturnR(ReturnInst<Register>),
-    ReturnC(ReturnInst<Const>),
-    ReturnG(ReturnInst<Global>),
-}
-
-impl Execute for Inst {
-    fn execute(self, context: &mut Context) -> Outcome {
-        match self {
-            Inst::AddRrr(inst) => inst.execute(context),
-            Inst::AddRrg(inst) => inst.execute(context),
-            Inst::AddRrc(inst) => inst.execute(context),
-            Inst::AddRgr(inst) => inst.execute(context),
-            Inst::AddRgg(inst) => inst.execute(context),
-            Inst::AddRgc(inst) => inst.execute(context),
-            Inst::AddRcr(inst) => inst.execute(context),
-            Inst::AddRcg(inst) => inst.execute(context),
-            Inst::AddRcc(inst) => inst.execute(context),
-            Inst::AddGrr(inst) => inst.execute(context),
-            Inst::AddGrg(inst) => inst.execute(context),
-            Inst::AddGrc(inst) => inst.execute(context),
-            Inst::AddGgr(inst) => inst.execute(context),
-            Inst::AddGgg(inst) => inst.execute(context),
-            Inst::AddGgc(inst) => inst.execute(context),
-            Inst::AddGcr(inst) => inst.execute(context),
-            Inst::AddGcg(inst) => inst.execute(context),
-            Inst::AddGcc(inst) => inst.execute(context),
-
-            Inst::SubRrr(inst) => inst.execute(context),
-            Inst::SubRrg(inst) => inst.execute(context),
-            Inst::SubRrc(inst) => inst.execute(context),
-            Inst::SubRgr(inst) => inst.execute(context),
-            Inst::SubRgg(inst) => inst.execute(context),
-            Inst::SubRgc(inst) => inst.execute(context),
-            Inst::SubRcr(inst) => inst.execute(context),
-            Inst::SubRcg(inst) => inst.execute(context),
-            Inst::SubRcc(inst) => inst.execute(context),
-            Inst::SubGrr(inst) => inst.execute(context),
-            Inst::SubGrg(inst) => inst.execute(context),
-            Inst::SubGrc(inst) => inst.execute(context),
-            Inst::SubGgr(inst) => inst.execute(context),
-            Inst::SubGgg(inst) => inst.execute(context),
-            Inst::SubGgc(inst) => inst.execute(context),
-            Inst::SubGcr(inst) => inst.execute(context),
-            Inst::SubGcg(inst) => inst.execute(context),
-            Inst::SubGcc(inst) => inst.execute(context),
-
-            Inst::Branch(inst) => inst.execute(context),
-
-            Inst::BranchEqzR(inst) => inst.execute(context),
-            Inst::BranchEqzC(inst) => inst.execute(context),
-            Inst::BranchEqzG(inst) => inst.execute(context),
+/// Defines [`Inst`] with one variant per combination of [`Sink`] and [`Source`] kinds of
+/// every binary operation followed by the `others` variants.
+///
+/// The variants of a binary operation are named in the order `Rrr, Rrg, Rrc, Rgr, .., Gcc`
+/// where the first letter is the kind of the result and the others the kinds of `lhs` and `rhs`.
+/// Also implements [`Execute`] for [`Inst`] and [`Compile`] for the dynamic binary operations and
+/// for [`DynamicInst`] which additionally dispatches to the listed `compile` instructions.
+///
+/// Instructions using the operand stack and all other instructions are rejected by [`Compile`].
+macro_rules! define_binop {
+    (
+        binops: { $(
+            $op:ident($ct:ident, $dynamic:ident) => [
+                $rrr:ident,
+                $rrg:ident,
+                $rrc:ident,
+                $rgr:ident,
+                $rgg:ident,
+                $rgc:ident,
+                $rcr:ident,
+                $rcg:ident,
+                $rcc:ident,
+                $grr:ident,
+                $grg:ident,
+                $grc:ident,
+                $ggr:ident,
+                $ggg:ident,
+                $ggc:ident,
+                $gcr:ident,
+                $gcg:ident,
+                $gcc:ident $(,)?
+            ]
+        ),* $(,)? },
+        others: { $( $other:ident($other_ty:ty) ),* $(,)? },
+        compile: { $( $compiled:ident ),* $(,)? } $(,)?
+    ) => {
+        #[derive(Copy, Clone, From)]
+        pub enum Inst {
+            $(
+                $rrr($ct<Register, Register, Register>),
+                $rrg($ct<Register, Register, Global>),
+                $rrc($ct<Register, Register, Const>),
+                $rgr($ct<Register, Global, Register>),
+                $rgg($ct<Register, Global, Global>),
+                $rgc($ct<Register, Global, Const>),
+                $rcr($ct<Register, Const, Register>),
+                $rcg($ct<Register, Const, Global>),
+                $rcc($ct<Register, Const, Const>),
+                $grr($ct<Global, Register, Register>),
+                $grg($ct<Global, Register, Global>),
+                $grc($ct<Global, Register, Const>),
+                $ggr($ct<Global, Global, Register>),
+                $ggg($ct<Global, Global, Global>),
+                $ggc($ct<Global, Global, Const>),
+                $gcr($ct<Global, Const, Register>),
+                $gcg($ct<Global, Const, Global>),
+                $gcc($ct<Global, Const, Const>),
+            )*
+            $( $other($other_ty), )*
+        }
 
-            Inst::ReturnR(inst) => inst.execute(context),
-            Inst::ReturnC(inst) => inst.execute(context),
-            Inst::ReturnG(inst) => inst.execute(context),
+        impl Execute for Inst {
+            fn execute(self, context: &mut Context) -> Outcome {
+                match self {
+                    $(
+                        Inst::$rrr(inst) => inst.execute(context),
+                        Inst::$rrg(inst) => inst.execute(context),
+                        Inst::$rrc(inst) => inst.execute(context),
+                        Inst::$rgr(inst) => inst.execute(context),
+                        Inst::$rgg(inst) => inst.execute(context),
+                        Inst::$rgc(inst) => inst.execute(context),
+                        Inst::$rcr(inst) => inst.execute(context),
+                        Inst::$rcg(inst) => inst.execute(context),
+                        Inst::$rcc(inst) => inst.execute(context),
+                        Inst::$grr(inst) => inst.execute(context),
+                        Inst::$grg(inst) => inst.execute(context),
+                        Inst::$grc(inst) => inst.execute(context),
+                        Inst::$ggr(inst) => inst.execute(context),
+                        Inst::$ggg(inst) => inst.execute(context),
+                        Inst::$ggc(inst) => inst.execute(context),
+                        Inst::$gcr(inst) => inst.execute(context),
+                        Inst::$gcg(inst) => inst.execute(context),
+                        Inst::$gcc(inst) => inst.execute(context),
+                    )*
+                    $( Inst::$other(inst) => inst.execute(context), )*
+                }
+            }
         }
-    }
-}
 
-pub trait Compile {
-    fn compile(self) -> Inst;
-}
+        $(
+            impl Compile for $dynamic {
+                fn compile(self) -> Result<Inst, DynamicInst> {
+                    match (self.result, self.lhs, self.rhs) {
+                        (Sink::Register(result), Source::Register(lhs), Source::Register(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Register(result), Source::Register(lhs), Source::Global(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Register(result), Source::Register(lhs), Source::Const(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Register(result), Source::Global(lhs), Source::Register(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Register(result), Source::Global(lhs), Source::Global(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Register(result), Source::Global(lhs), Source::Const(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Register(result), Source::Const(lhs), Source::Register(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Register(result), Source::Const(lhs), Source::Global(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Register(result), Source::Const(lhs), Source::Const(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Global(result), Source::Register(lhs), Source::Register(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Global(result), Source::Register(lhs), Source::Global(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Global(result), Source::Register(lhs), Source::Const(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Global(result), Source::Global(lhs), Source::Register(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Global(result), Source::Global(lhs), Source::Global(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Global(result), Source::Global(lhs), Source::Const(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Global(result), Source::Const(lhs), Source::Register(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Global(result), Source::Const(lhs), Source::Global(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (Sink::Global(result), Source::Const(lhs), Source::Const(rhs)) => {
+                            Ok(Inst::from($ct::new(result, lhs, rhs)))
+                        }
+                        (_, Source::StackTop, _) | (_, _, Source::StackTop) => {
+                            Err(DynamicInst::$op(self))
+                        }
+                    }
+                }
+            }
+        )*
 
-impl Compile for DynamicInst {
-    fn compile(self) -> Inst {
-        match self {
-            DynamicInst::Add(inst) => inst.compile(),
-            DynamicInst::Sub(inst) => inst.compile(),
-            DynamicInst::Branch(inst) => inst.compile(),
-            DynamicInst::BranchEqz(inst) => inst.compile(),
-            DynamicInst::Return(inst) => inst.compile(),
-            _ => todo!(),
+        impl Compile for DynamicInst {
+            fn compile(self) -> Result<Inst, DynamicInst> {
+                if self.uses_stack() {
+                    return Err(self);
+                }
+                match self {
+                    $( DynamicInst::$op(inst) => inst.compile(), )*
+                    $( DynamicInst::$compiled(inst) => inst.compile(), )*
+                    unsupported => Err(unsupported),
+                }
+            }
         }
-    }
+    };
 }
 
-impl Compile for DynamicAddInst {
-    fn compile(self) -> Inst {
-        match (self.result, self.lhs, self.rhs) {
-            (Sink::Register(sink), Source::Const(src0), Source::Const(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Register(sink), Source::Const(src0), Source::Register(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Register(sink), Source::Const(src0), Source::Global(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Register(sink), Source::Register(src0), Source::Const(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Register(sink), Source::Register(src0), Source::Register(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Register(sink), Source::Register(src0), Source::Global(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Register(sink), Source::Global(src0), Source::Const(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Register(sink), Source::Global(src0), Source::Register(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Register(sink), Source::Global(src0), Source::Global(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Const(src0), Source::Const(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Const(src0), Source::Register(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Const(src0), Source::Global(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Register(src0), Source::Const(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Register(src0), Source::Register(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Register(src0), Source::Global(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Global(src0), Source::Const(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Global(src0), Source::Register(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Global(src0), Source::Global(src1)) => {
-                Inst::from(AddInst::new(sink, src0, src1))
-            }
-        }
-    }
+pub trait Compile {
+    /// Compiles `self` into its monomorphized [`Inst`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `self` as [`DynamicInst`] if it uses the operand stack or has no [`Inst`] counterpart.
+    fn compile(self) -> Result<Inst, DynamicInst>;
 }
 
-impl Compile for DynamicSubInst {
-    fn compile(self) -> Inst {
-        match (self.result, self.lhs, self.rhs) {
-            (Sink::Register(sink), Source::Const(src0), Source::Const(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Register(sink), Source::Const(src0), Source::Register(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Register(sink), Source::Const(src0), Source::Global(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Register(sink), Source::Register(src0), Source::Const(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Register(sink), Source::Register(src0), Source::Register(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Register(sink), Source::Register(src0), Source::Global(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Register(sink), Source::Global(src0), Source::Const(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Register(sink), Source::Global(src0), Source::Register(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Register(sink), Source::Global(src0), Source::Global(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Const(src0), Source::Const(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Const(src0), Source::Register(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Const(src0), Source::Global(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Register(src0), Source::Const(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Register(src0), Source::Register(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Register(src0), Source::Global(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Global(src0), Source::Const(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Global(src0), Source::Register(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-            (Sink::Global(sink), Source::Global(src0), Source::Global(src1)) => {
-                Inst::from(SubInst::new(sink, src0, src1))
-            }
-        }
-    }
+define_binop! {
+    binops: {
+        Add(AddInst, DynamicAddInst) => [
+            AddRrr, AddRrg, AddRrc, AddRgr, AddRgg, AddRgc, AddRcr, AddRcg, AddRcc,
+            AddGrr, AddGrg, AddGrc, AddGgr, AddGgg, AddGgc, AddGcr, AddGcg, AddGcc,
+        ],
+        Sub(SubInst, DynamicSubInst) => [
+            SubRrr, SubRrg, SubRrc, SubRgr, SubRgg, SubRgc, SubRcr, SubRcg, SubRcc,
+            SubGrr, SubGrg, SubGrc, SubGgr, SubGgg, SubGgc, SubGcr, SubGcg, SubGcc,
+        ],
+        Mul(MulInst, DynamicMulInst) => [
+            MulRrr, MulRrg, MulRrc, MulRgr, MulRgg, MulRgc, MulRcr, MulRcg, MulRcc,
+            MulGrr, MulGrg, MulGrc, MulGgr, MulGgg, MulGgc, MulGcr, MulGcg, MulGcc,
+        ],
+    },
+    others: {
+        Branch(BranchInst),
+        BranchEqzR(BranchEqzInst<Register>),
+        BranchEqzC(BranchEqzInst<Const>),
+        BranchEqzG(BranchEqzInst<Global>),
+        ReturnR(ReturnInst<Register>),
+        ReturnC(ReturnInst<Const>),
+        ReturnG(ReturnInst<Global>),
+    },
+    compile: { Branch, BranchEqz, Return },
 }
 
 impl Compile for DynamicBranchInst {
-    fn compile(self) -> Inst {
-        Inst::from(BranchInst::new(self.target))
+    fn compile(self) -> Result<Inst, DynamicInst> {
+        Ok(Inst::from(BranchInst::new(self.target)))
     }
 }
 
 impl Compile for DynamicBranchEqzInst {
-    fn compile(self) -> Inst {
+    fn compile(self) -> Result<Inst, DynamicInst> {
         match self.condition {
-            Source::Const(condition) => Inst::from(BranchEqzInst::new(self.target, condition)),
-            Source::Register(condition) => Inst::from(BranchEqzInst::new(self.target, condition)),
-            Source::Global(condition) => Inst::from(BranchEqzInst::new(self.target, condition)),
+            Source::Const(condition) => Ok(Inst::from(BranchEqzInst::new(self.target, condition))),
+            Source::Register(condition) => {
+                Ok(Inst::from(BranchEqzInst::new(self.target, condition)))
+            }
+            Source::Global(condition) => Ok(Inst::from(BranchEqzInst::new(self.target, condition))),
+            Source::StackTop => Err(DynamicInst::BranchEqz(self)),
         }
     }
 }
 
 impl Compile for DynamicReturnInst {
-    fn compile(self) -> Inst {
+    fn compile(self) -> Result<Inst, DynamicInst> {
         match self.result {
-            Source::Const(result) => Inst::from(ReturnInst::new(result)),
-            Source::Register(result) => Inst::from(ReturnInst::new(result)),
-            Source::Global(result) => Inst::from(ReturnInst::new(result)),
+            Source::Const(result) => Ok(Inst::from(ReturnInst::new(result))),
+            Source::Register(result) => Ok(Inst::from(ReturnInst::new(result))),
+            Source::Global(result) => Ok(Inst::from(ReturnInst::new(result))),
+            Source::StackTop => Err(DynamicInst::Return(self)),
         }
     }
 }
 
-/// Executes the list of instruction using the given [`Context`].
-fn execute(insts: &[Inst], context: &mut Context) {
+/// Compiles the [`DynamicInst`] program into an array of monomorphized [`Inst`].
+///
+/// # Errors
+///
+/// Returns the first instruction that [`Compile`] rejects.
+pub fn compile_program(insts: &[DynamicInst]) -> Result<Vec<Inst>, DynamicInst> {
+    insts.iter().copied().map(Compile::compile).collect()
+}
+
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Bits {
     loop {
         let pc = context.pc;
         let inst = &insts[pc];
         match inst.execute(context) {
-            Outcome::Continue => continue,
-            Outcome::Return => return,
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return context.get_reg(Register(0)),
+        }
+    }
+}
+
+/// Runs the list of instruction using the given [`Context`] and returns the result.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn run(insts: &[Inst], context: &mut Context) -> Result<Bits, TrapCode> {
+    loop {
+        let pc = context.pc;
+        let inst = insts.get(pc).ok_or(TrapCode::OutOfBoundsPc)?;
+        match inst.execute(context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return => return Ok(context.get_reg(Register(0))),
+            Outcome::Trap(trap) => return Err(trap),
         }
     }
 }
@@ -311,8 +305,43 @@ fn counter_loop() {
         DynamicInst::branch(1),
         // Return value and end function execution.
         DynamicInst::ret(Register(0)),
-    ]
-    .map(DynamicInst::compile);
+    ];
+    let insts = compile_program(&insts).ok().unwrap();
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn mul_mixed_kinds() {
+    let insts = compile_program(&[
+        DynamicInst::mul(Global(1), Register(0), Const(7)),
+        DynamicInst::mul(Register(1), Global(1), Global(1)),
+        DynamicInst::mul(Register(0), Const(2), Register(1)),
+        DynamicInst::ret(Register(0)),
+    ])
+    .ok()
+    .unwrap();
+    assert!(matches!(insts[0], Inst::MulGrc(_)));
+    assert!(matches!(insts[1], Inst::MulRgg(_)));
+    assert!(matches!(insts[2], Inst::MulRcr(_)));
     let mut context = Context::default();
-    benchmark(|| execute(&insts, &mut context));
+    context.set_reg(Register(0), 3);
+    assert_eq!(run(&insts, &mut context), Ok(2 * 21 * 21));
+    assert_eq!(context.get_global(Global(1)), 21);
+}
+
+#[test]
+fn compile_rejects_stack_operands() {
+    let rejected = [
+        DynamicInst::add(Register(0), Source::StackTop, Const(1)),
+        DynamicInst::sub(Global(0), Const(1), Source::StackTop),
+        DynamicInst::branch_eqz(0, Source::StackTop),
+        DynamicInst::ret(Source::StackTop),
+        DynamicInst::pop(Register(0)),
+    ];
+    for inst in rejected {
+        assert!(inst.compile().err() == Some(inst));
+        assert!(compile_program(&[inst, DynamicInst::ret(Register(0))]).err() == Some(inst));
+    }
 }
@@ -4,13 +4,20 @@
 use crate::benchmark;
 
 use super::{
-    ct::{AddInst, BranchEqzInst, BranchInst, Execute, ReturnInst, SubInst},
+    ct::{
+        AddInst, BranchEqzInst, BranchInst, EqInst, Execute, GeSInst, GeUInst, GtSInst, GtUInst,
+        LeSInst, LeUInst, LtSInst, LtUInst, MulInst, NeInst, ReturnInst, SubInst,
+    },
     rt2::{
         AddInst as DynamicAddInst, BranchEqzInst as DynamicBranchEqzInst,
-        BranchInst as DynamicBranchInst, Inst as DynamicInst, ReturnInst as DynamicReturnInst,
-        Source, SubInst as DynamicSubInst,
+        BranchInst as DynamicBranchInst, EqInst as DynamicEqInst, GeSInst as DynamicGeSInst,
+        GeUInst as DynamicGeUInst, GtSInst as DynamicGtSInst, GtUInst as DynamicGtUInst,
+        Inst as DynamicInst, LeSInst as DynamicLeSInst, LeUInst as DynamicLeUInst,
+        LtSInst as DynamicLtSInst, LtUInst as DynamicLtUInst, MulInst as DynamicMulInst,
+        NeInst as DynamicNeInst, ReturnInst as DynamicReturnInst, Source,
+        SubInst as DynamicSubInst,
     },
-    Const, Context, Outcome, Register,
+    Bits, Const, Context, Global, Outcome, Register, TrapCode,
 };
 use derive_more::From;
 
@@ -18,21 +25,143 @@ use derive_more::From;
 pub enum Inst {
     AddRr(AddInst<Register, Register, Register>),
     AddRc(AddInst<Register, Register, Const>),
+    AddRg(AddInst<Register, Register, Global>),
     AddCr(AddInst<Register, Const, Register>),
     AddCc(AddInst<Register, Const, Const>),
+    AddCg(AddInst<Register, Const, Global>),
+    AddGr(AddInst<Register, Global, Register>),
+    AddGc(AddInst<Register, Global, Const>),
+    AddGg(AddInst<Register, Global, Global>),
 
     SubRr(SubInst<Register, Register, Register>),
     SubRc(SubInst<Register, Register, Const>),
+    SubRg(SubInst<Register, Register, Global>),
     SubCr(SubInst<Register, Const, Register>),
     SubCc(SubInst<Register, Const, Const>),
+    SubCg(SubInst<Register, Const, Global>),
+    SubGr(SubInst<Register, Global, Register>),
+    SubGc(SubInst<Register, Global, Const>),
+    SubGg(SubInst<Register, Global, Global>),
+
+    MulRr(MulInst<Register, Register, Register>),
+    MulRc(MulInst<Register, Register, Const>),
+    MulRg(MulInst<Register, Register, Global>),
+    MulCr(MulInst<Register, Const, Register>),
+    MulCc(MulInst<Register, Const, Const>),
+    MulCg(MulInst<Register, Const, Global>),
+    MulGr(MulInst<Register, Global, Register>),
+    MulGc(MulInst<Register, Global, Const>),
+    MulGg(MulInst<Register, Global, Global>),
+
+    EqRr(EqInst<Register, Register, Register>),
+    EqRc(EqInst<Register, Register, Const>),
+    EqRg(EqInst<Register, Register, Global>),
+    EqCr(EqInst<Register, Const, Register>),
+    EqCc(EqInst<Register, Const, Const>),
+    EqCg(EqInst<Register, Const, Global>),
+    EqGr(EqInst<Register, Global, Register>),
+    EqGc(EqInst<Register, Global, Const>),
+    EqGg(EqInst<Register, Global, Global>),
+
+    NeRr(NeInst<Register, Register, Register>),
+    NeRc(NeInst<Register, Register, Const>),
+    NeRg(NeInst<Register, Register, Global>),
+    NeCr(NeInst<Register, Const, Register>),
+    NeCc(NeInst<Register, Const, Const>),
+    NeCg(NeInst<Register, Const, Global>),
+    NeGr(NeInst<Register, Global, Register>),
+    NeGc(NeInst<Register, Global, Const>),
+    NeGg(NeInst<Register, Global, Global>),
+
+    LtURr(LtUInst<Register, Register, Register>),
+    LtURc(LtUInst<Register, Register, Const>),
+    LtURg(LtUInst<Register, Register, Global>),
+    LtUCr(LtUInst<Register, Const, Register>),
+    LtUCc(LtUInst<Register, Const, Const>),
+    LtUCg(LtUInst<Register, Const, Global>),
+    LtUGr(LtUInst<Register, Global, Register>),
+    LtUGc(LtUInst<Register, Global, Const>),
+    LtUGg(LtUInst<Register, Global, Global>),
+
+    LeURr(LeUInst<Register, Register, Register>),
+    LeURc(LeUInst<Register, Register, Const>),
+    LeURg(LeUInst<Register, Register, Global>),
+    LeUCr(LeUInst<Register, Const, Register>),
+    LeUCc(LeUInst<Register, Const, Const>),
+    LeUCg(LeUInst<Register, Const, Global>),
+    LeUGr(LeUInst<Register, Global, Register>),
+    LeUGc(LeUInst<Register, Global, Const>),
+    LeUGg(LeUInst<Register, Global, Global>),
+
+    GtURr(GtUInst<Register, Register, Register>),
+    GtURc(GtUInst<Register, Register, Const>),
+    GtURg(GtUInst<Register, Register, Global>),
+    GtUCr(GtUInst<Register, Const, Register>),
+    GtUCc(GtUInst<Register, Const, Const>),
+    GtUCg(GtUInst<Register, Const, Global>),
+    GtUGr(GtUInst<Register, Global, Register>),
+    GtUGc(GtUInst<Register, Global, Const>),
+    GtUGg(GtUInst<Register, Global, Global>),
+
+    GeURr(GeUInst<Register, Register, Register>),
+    GeURc(GeUInst<Register, Register, Const>),
+    GeURg(GeUInst<Register, Register, Global>),
+    GeUCr(GeUInst<Register, Const, Register>),
+    GeUCc(GeUInst<Register, Const, Const>),
+    GeUCg(GeUInst<Register, Const, Global>),
+    GeUGr(GeUInst<Register, Global, Register>),
+    GeUGc(GeUInst<Register, Global, Const>),
+    GeUGg(GeUInst<Register, Global, Global>),
+
+    LtSRr(LtSInst<Register, Register, Register>),
+    LtSRc(LtSInst<Register, Register, Const>),
+    LtSRg(LtSInst<Register, Register, Global>),
+    LtSCr(LtSInst<Register, Const, Register>),
+    LtSCc(LtSInst<Register, Const, Const>),
+    LtSCg(LtSInst<Register, Const, Global>),
+    LtSGr(LtSInst<Register, Global, Register>),
+    LtSGc(LtSInst<Register, Global, Const>),
+    LtSGg(LtSInst<Register, Global, Global>),
+
+    LeSRr(LeSInst<Register, Register, Register>),
+    LeSRc(LeSInst<Register, Register, Const>),
+    LeSRg(LeSInst<Register, Register, Global>),
+    LeSCr(LeSInst<Register, Const, Register>),
+    LeSCc(LeSInst<Register, Const, Const>),
+    LeSCg(LeSInst<Register, Const, Global>),
+    LeSGr(LeSInst<Register, Global, Register>),
+    LeSGc(LeSInst<Register, Global, Const>),
+    LeSGg(LeSInst<Register, Global, Global>),
+
+    GtSRr(GtSInst<Register, Register, Register>),
+    GtSRc(GtSInst<Register, Register, Const>),
+    GtSRg(GtSInst<Register, Register, Global>),
+    GtSCr(GtSInst<Register, Const, Register>),
+    GtSCc(GtSInst<Register, Const, Const>),
+    GtSCg(GtSInst<Register, Const, Global>),
+    GtSGr(GtSInst<Register, Global, Register>),
+    GtSGc(GtSInst<Register, Global, Const>),
+    GtSGg(GtSInst<Register, Global, Global>),
+
+    GeSRr(GeSInst<Register, Register, Register>),
+    GeSRc(GeSInst<Register, Register, Const>),
+    GeSRg(GeSInst<Register, Register, Global>),
+    GeSCr(GeSInst<Register, Const, Register>),
+    GeSCc(GeSInst<Register, Const, Const>),
+    GeSCg(GeSInst<Register, Const, Global>),
+    GeSGr(GeSInst<Register, Global, Register>),
+    GeSGc(GeSInst<Register, Global, Const>),
+    GeSGg(GeSInst<Register, Global, Global>),
 
     Branch(BranchInst),
 
     BranchEqzR(BranchEqzInst<Register>),
     BranchEqzC(BranchEqzInst<Const>),
+    BranchEqzG(BranchEqzInst<Global>),
 
     ReturnR(ReturnInst<Register>),
     ReturnC(ReturnInst<Const>),
+    ReturnG(ReturnInst<Global>),
 }
 
 impl Execute for Inst {
@@ -40,21 +169,143 @@ impl Execute for Inst {
         match self {
             Inst::AddRr(inst) => inst.execute(context),
             Inst::AddRc(inst) => inst.execute(context),
+            Inst::AddRg(inst) => inst.execute(context),
             Inst::AddCr(inst) => inst.execute(context),
             Inst::AddCc(inst) => inst.execute(context),
+            Inst::AddCg(inst) => inst.execute(context),
+            Inst::AddGr(inst) => inst.execute(context),
+            Inst::AddGc(inst) => inst.execute(context),
+            Inst::AddGg(inst) => inst.execute(context),
 
             Inst::SubRr(inst) => inst.execute(context),
             Inst::SubRc(inst) => inst.execute(context),
+            Inst::SubRg(inst) => inst.execute(context),
             Inst::SubCr(inst) => inst.execute(context),
             Inst::SubCc(inst) => inst.execute(context),
+            Inst::SubCg(inst) => inst.execute(context),
+            Inst::SubGr(inst) => inst.execute(context),
+            Inst::SubGc(inst) => inst.execute(context),
+            Inst::SubGg(inst) => inst.execute(context),
+
+            Inst::MulRr(inst) => inst.execute(context),
+            Inst::MulRc(inst) => inst.execute(context),
+            Inst::MulRg(inst) => inst.execute(context),
+            Inst::MulCr(inst) => inst.execute(context),
+            Inst::MulCc(inst) => inst.execute(context),
+            Inst::MulCg(inst) => inst.execute(context),
+            Inst::MulGr(inst) => inst.execute(context),
+            Inst::MulGc(inst) => inst.execute(context),
+            Inst::MulGg(inst) => inst.execute(context),
+
+            Inst::EqRr(inst) => inst.execute(context),
+            Inst::EqRc(inst) => inst.execute(context),
+            Inst::EqRg(inst) => inst.execute(context),
+            Inst::EqCr(inst) => inst.execute(context),
+            Inst::EqCc(inst) => inst.execute(context),
+            Inst::EqCg(inst) => inst.execute(context),
+            Inst::EqGr(inst) => inst.execute(context),
+            Inst::EqGc(inst) => inst.execute(context),
+            Inst::EqGg(inst) => inst.execute(context),
+
+            Inst::NeRr(inst) => inst.execute(context),
+            Inst::NeRc(inst) => inst.execute(context),
+            Inst::NeRg(inst) => inst.execute(context),
+            Inst::NeCr(inst) => inst.execute(context),
+            Inst::NeCc(inst) => inst.execute(context),
+            Inst::NeCg(inst) => inst.execute(context),
+            Inst::NeGr(inst) => inst.execute(context),
+            Inst::NeGc(inst) => inst.execute(context),
+            Inst::NeGg(inst) => inst.execute(context),
+
+            Inst::LtURr(inst) => inst.execute(context),
+            Inst::LtURc(inst) => inst.execute(context),
+            Inst::LtURg(inst) => inst.execute(context),
+            Inst::LtUCr(inst) => inst.execute(context),
+            Inst::LtUCc(inst) => inst.execute(context),
+            Inst::LtUCg(inst) => inst.execute(context),
+            Inst::LtUGr(inst) => inst.execute(context),
+            Inst::LtUGc(inst) => inst.execute(context),
+            Inst::LtUGg(inst) => inst.execute(context),
+
+            Inst::LeURr(inst) => inst.execute(context),
+            Inst::LeURc(inst) => inst.execute(context),
+            Inst::LeURg(inst) => inst.execute(context),
+            Inst::LeUCr(inst) => inst.execute(context),
+            Inst::LeUCc(inst) => inst.execute(context),
+            Inst::LeUCg(inst) => inst.execute(context),
+            Inst::LeUGr(inst) => inst.execute(context),
+            Inst::LeUGc(inst) => inst.execute(context),
+            Inst::LeUGg(inst) => inst.execute(context),
+
+            Inst::GtURr(inst) => inst.execute(context),
+            Inst::GtURc(inst) => inst.execute(context),
+            Inst::GtURg(inst) => inst.execute(context),
+            Inst::GtUCr(inst) => inst.execute(context),
+            Inst::GtUCc(inst) => inst.execute(context),
+            Inst::GtUCg(inst) => inst.execute(context),
+            Inst::GtUGr(inst) => inst.execute(context),
+            Inst::GtUGc(inst) => inst.execute(context),
+            Inst::GtUGg(inst) => inst.execute(context),
+
+            Inst::GeURr(inst) => inst.execute(context),
+            Inst::GeURc(inst) => inst.execute(context),
+            Inst::GeURg(inst) => inst.execute(context),
+            Inst::GeUCr(inst) => inst.execute(context),
+            Inst::GeUCc(inst) => inst.execute(context),
+            Inst::GeUCg(inst) => inst.execute(context),
+            Inst::GeUGr(inst) => inst.execute(context),
+            Inst::GeUGc(inst) => inst.execute(context),
+            Inst::GeUGg(inst) => inst.execute(context),
+
+            Inst::LtSRr(inst) => inst.execute(context),
+            Inst::LtSRc(inst) => inst.execute(context),
+            Inst::LtSRg(inst) => inst.execute(context),
+            Inst::LtSCr(inst) => inst.execute(context),
+            Inst::LtSCc(inst) => inst.execute(context),
+            Inst::LtSCg(inst) => inst.execute(context),
+            Inst::LtSGr(inst) => inst.execute(context),
+            Inst::LtSGc(inst) => inst.execute(context),
+            Inst::LtSGg(inst) => inst.execute(context),
+
+            Inst::LeSRr(inst) => inst.execute(context),
+            Inst::LeSRc(inst) => inst.execute(context),
+            Inst::LeSRg(inst) => inst.execute(context),
+            Inst::LeSCr(inst) => inst.execute(context),
+            Inst::LeSCc(inst) => inst.execute(context),
+            Inst::LeSCg(inst) => inst.execute(context),
+            Inst::LeSGr(inst) => inst.execute(context),
+            Inst::LeSGc(inst) => inst.execute(context),
+            Inst::LeSGg(inst) => inst.execute(context),
+
+            Inst::GtSRr(inst) => inst.execute(context),
+            Inst::GtSRc(inst) => inst.execute(context),
+            Inst::GtSRg(inst) => inst.execute(context),
+            Inst::GtSCr(inst) => inst.execute(context),
+            Inst::GtSCc(inst) => inst.execute(context),
+            Inst::GtSCg(inst) => inst.execute(context),
+            Inst::GtSGr(inst) => inst.execute(context),
+            Inst::GtSGc(inst) => inst.execute(context),
+            Inst::GtSGg(inst) => inst.execute(context),
+
+            Inst::GeSRr(inst) => inst.execute(context),
+            Inst::GeSRc(inst) => inst.execute(context),
+            Inst::GeSRg(inst) => inst.execute(context),
+            Inst::GeSCr(inst) => inst.execute(context),
+            Inst::GeSCc(inst) => inst.execute(context),
+            Inst::GeSCg(inst) => inst.execute(context),
+            Inst::GeSGr(inst) => inst.execute(context),
+            Inst::GeSGc(inst) => inst.execute(context),
+            Inst::GeSGg(inst) => inst.execute(context),
 
             Inst::Branch(inst) => inst.execute(context),
 
             Inst::BranchEqzR(inst) => inst.execute(context),
             Inst::BranchEqzC(inst) => inst.execute(context),
+            Inst::BranchEqzG(inst) => inst.execute(context),
 
             Inst::ReturnR(inst) => inst.execute(context),
             Inst::ReturnC(inst) => inst.execute(context),
+            Inst::ReturnG(inst) => inst.execute(context),
         }
     }
 }
@@ -68,10 +319,20 @@ impl Compile for DynamicInst {
         match self {
             DynamicInst::Add(inst) => inst.compile(),
             DynamicInst::Sub(inst) => inst.compile(),
+            DynamicInst::Mul(inst) => inst.compile(),
+            DynamicInst::Eq(inst) => inst.compile(),
+            DynamicInst::Ne(inst) => inst.compile(),
+            DynamicInst::LtU(inst) => inst.compile(),
+            DynamicInst::LeU(inst) => inst.compile(),
+            DynamicInst::GtU(inst) => inst.compile(),
+            DynamicInst::GeU(inst) => inst.compile(),
+            DynamicInst::LtS(inst) => inst.compile(),
+            DynamicInst::LeS(inst) => inst.compile(),
+            DynamicInst::GtS(inst) => inst.compile(),
+            DynamicInst::GeS(inst) => inst.compile(),
             DynamicInst::Branch(inst) => inst.compile(),
             DynamicInst::BranchEqz(inst) => inst.compile(),
             DynamicInst::Return(inst) => inst.compile(),
-            _ => todo!(),
         }
     }
 }
@@ -82,12 +343,27 @@ impl Compile for DynamicAddInst {
             (Source::Const(src0), Source::Const(src1)) => {
                 Inst::from(AddInst::new(self.result, src0, src1))
             }
+            (Source::Const(src0), Source::Global(src1)) => {
+                Inst::from(AddInst::new(self.result, src0, src1))
+            }
             (Source::Const(src0), Source::Register(src1)) => {
                 Inst::from(AddInst::new(self.result, src0, src1))
             }
+            (Source::Global(src0), Source::Const(src1)) => {
+                Inst::from(AddInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Global(src1)) => {
+                Inst::from(AddInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Register(src1)) => {
+                Inst::from(AddInst::new(self.result, src0, src1))
+            }
             (Source::Register(src0), Source::Const(src1)) => {
                 Inst::from(AddInst::new(self.result, src0, src1))
             }
+            (Source::Register(src0), Source::Global(src1)) => {
+                Inst::from(AddInst::new(self.result, src0, src1))
+            }
             (Source::Register(src0), Source::Register(src1)) => {
                 Inst::from(AddInst::new(self.result, src0, src1))
             }
@@ -101,12 +377,27 @@ impl Compile for DynamicSubInst {
             (Source::Const(src0), Source::Const(src1)) => {
                 Inst::from(SubInst::new(self.result, src0, src1))
             }
+            (Source::Const(src0), Source::Global(src1)) => {
+                Inst::from(SubInst::new(self.result, src0, src1))
+            }
             (Source::Const(src0), Source::Register(src1)) => {
                 Inst::from(SubInst::new(self.result, src0, src1))
             }
+            (Source::Global(src0), Source::Const(src1)) => {
+                Inst::from(SubInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Global(src1)) => {
+                Inst::from(SubInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Register(src1)) => {
+                Inst::from(SubInst::new(self.result, src0, src1))
+            }
             (Source::Register(src0), Source::Const(src1)) => {
                 Inst::from(SubInst::new(self.result, src0, src1))
             }
+            (Source::Register(src0), Source::Global(src1)) => {
+                Inst::from(SubInst::new(self.result, src0, src1))
+            }
             (Source::Register(src0), Source::Register(src1)) => {
                 Inst::from(SubInst::new(self.result, src0, src1))
             }
@@ -114,6 +405,380 @@ impl Compile for DynamicSubInst {
     }
 }
 
+impl Compile for DynamicMulInst {
+    fn compile(self) -> Inst {
+        match (self.lhs, self.rhs) {
+            (Source::Const(src0), Source::Const(src1)) => {
+                Inst::from(MulInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Global(src1)) => {
+                Inst::from(MulInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Register(src1)) => {
+                Inst::from(MulInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Const(src1)) => {
+                Inst::from(MulInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Global(src1)) => {
+                Inst::from(MulInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Register(src1)) => {
+                Inst::from(MulInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Const(src1)) => {
+                Inst::from(MulInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Global(src1)) => {
+                Inst::from(MulInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Register(src1)) => {
+                Inst::from(MulInst::new(self.result, src0, src1))
+            }
+        }
+    }
+}
+
+impl Compile for DynamicEqInst {
+    fn compile(self) -> Inst {
+        match (self.lhs, self.rhs) {
+            (Source::Const(src0), Source::Const(src1)) => {
+                Inst::from(EqInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Global(src1)) => {
+                Inst::from(EqInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Register(src1)) => {
+                Inst::from(EqInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Const(src1)) => {
+                Inst::from(EqInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Global(src1)) => {
+                Inst::from(EqInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Register(src1)) => {
+                Inst::from(EqInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Const(src1)) => {
+                Inst::from(EqInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Global(src1)) => {
+                Inst::from(EqInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Register(src1)) => {
+                Inst::from(EqInst::new(self.result, src0, src1))
+            }
+        }
+    }
+}
+
+impl Compile for DynamicNeInst {
+    fn compile(self) -> Inst {
+        match (self.lhs, self.rhs) {
+            (Source::Const(src0), Source::Const(src1)) => {
+                Inst::from(NeInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Global(src1)) => {
+                Inst::from(NeInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Register(src1)) => {
+                Inst::from(NeInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Const(src1)) => {
+                Inst::from(NeInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Global(src1)) => {
+                Inst::from(NeInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Register(src1)) => {
+                Inst::from(NeInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Const(src1)) => {
+                Inst::from(NeInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Global(src1)) => {
+                Inst::from(NeInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Register(src1)) => {
+                Inst::from(NeInst::new(self.result, src0, src1))
+            }
+        }
+    }
+}
+
+impl Compile for DynamicLtUInst {
+    fn compile(self) -> Inst {
+        match (self.lhs, self.rhs) {
+            (Source::Const(src0), Source::Const(src1)) => {
+                Inst::from(LtUInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Global(src1)) => {
+                Inst::from(LtUInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Register(src1)) => {
+                Inst::from(LtUInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Const(src1)) => {
+                Inst::from(LtUInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Global(src1)) => {
+                Inst::from(LtUInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Register(src1)) => {
+                Inst::from(LtUInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Const(src1)) => {
+                Inst::from(LtUInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Global(src1)) => {
+                Inst::from(LtUInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Register(src1)) => {
+                Inst::from(LtUInst::new(self.result, src0, src1))
+            }
+        }
+    }
+}
+
+impl Compile for DynamicLeUInst {
+    fn compile(self) -> Inst {
+        match (self.lhs, self.rhs) {
+            (Source::Const(src0), Source::Const(src1)) => {
+                Inst::from(LeUInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Global(src1)) => {
+                Inst::from(LeUInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Register(src1)) => {
+                Inst::from(LeUInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Const(src1)) => {
+                Inst::from(LeUInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Global(src1)) => {
+                Inst::from(LeUInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Register(src1)) => {
+                Inst::from(LeUInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Const(src1)) => {
+                Inst::from(LeUInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Global(src1)) => {
+                Inst::from(LeUInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Register(src1)) => {
+                Inst::from(LeUInst::new(self.result, src0, src1))
+            }
+        }
+    }
+}
+
+impl Compile for DynamicGtUInst {
+    fn compile(self) -> Inst {
+        match (self.lhs, self.rhs) {
+            (Source::Const(src0), Source::Const(src1)) => {
+                Inst::from(GtUInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Global(src1)) => {
+                Inst::from(GtUInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Register(src1)) => {
+                Inst::from(GtUInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Const(src1)) => {
+                Inst::from(GtUInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Global(src1)) => {
+                Inst::from(GtUInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Register(src1)) => {
+                Inst::from(GtUInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Const(src1)) => {
+                Inst::from(GtUInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Global(src1)) => {
+                Inst::from(GtUInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Register(src1)) => {
+                Inst::from(GtUInst::new(self.result, src0, src1))
+            }
+        }
+    }
+}
+
+impl Compile for DynamicGeUInst {
+    fn compile(self) -> Inst {
+        match (self.lhs, self.rhs) {
+            (Source::Const(src0), Source::Const(src1)) => {
+                Inst::from(GeUInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Global(src1)) => {
+                Inst::from(GeUInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Register(src1)) => {
+                Inst::from(GeUInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Const(src1)) => {
+                Inst::from(GeUInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Global(src1)) => {
+                Inst::from(GeUInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Register(src1)) => {
+                Inst::from(GeUInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Const(src1)) => {
+                Inst::from(GeUInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Global(src1)) => {
+                Inst::from(GeUInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Register(src1)) => {
+                Inst::from(GeUInst::new(self.result, src0, src1))
+            }
+        }
+    }
+}
+
+impl Compile for DynamicLtSInst {
+    fn compile(self) -> Inst {
+        match (self.lhs, self.rhs) {
+            (Source::Const(src0), Source::Const(src1)) => {
+                Inst::from(LtSInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Global(src1)) => {
+                Inst::from(LtSInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Register(src1)) => {
+                Inst::from(LtSInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Const(src1)) => {
+                Inst::from(LtSInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Global(src1)) => {
+                Inst::from(LtSInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Register(src1)) => {
+                Inst::from(LtSInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Const(src1)) => {
+                Inst::from(LtSInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Global(src1)) => {
+                Inst::from(LtSInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Register(src1)) => {
+                Inst::from(LtSInst::new(self.result, src0, src1))
+            }
+        }
+    }
+}
+
+impl Compile for DynamicLeSInst {
+    fn compile(self) -> Inst {
+        match (self.lhs, self.rhs) {
+            (Source::Const(src0), Source::Const(src1)) => {
+                Inst::from(LeSInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Global(src1)) => {
+                Inst::from(LeSInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Register(src1)) => {
+                Inst::from(LeSInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Const(src1)) => {
+                Inst::from(LeSInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Global(src1)) => {
+                Inst::from(LeSInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Register(src1)) => {
+                Inst::from(LeSInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Const(src1)) => {
+                Inst::from(LeSInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Global(src1)) => {
+                Inst::from(LeSInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Register(src1)) => {
+                Inst::from(LeSInst::new(self.result, src0, src1))
+            }
+        }
+    }
+}
+
+impl Compile for DynamicGtSInst {
+    fn compile(self) -> Inst {
+        match (self.lhs, self.rhs) {
+            (Source::Const(src0), Source::Const(src1)) => {
+                Inst::from(GtSInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Global(src1)) => {
+                Inst::from(GtSInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Register(src1)) => {
+                Inst::from(GtSInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Const(src1)) => {
+                Inst::from(GtSInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Global(src1)) => {
+                Inst::from(GtSInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Register(src1)) => {
+                Inst::from(GtSInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Const(src1)) => {
+                Inst::from(GtSInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Global(src1)) => {
+                Inst::from(GtSInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Register(src1)) => {
+                Inst::from(GtSInst::new(self.result, src0, src1))
+            }
+        }
+    }
+}
+
+impl Compile for DynamicGeSInst {
+    fn compile(self) -> Inst {
+        match (self.lhs, self.rhs) {
+            (Source::Const(src0), Source::Const(src1)) => {
+                Inst::from(GeSInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Global(src1)) => {
+                Inst::from(GeSInst::new(self.result, src0, src1))
+            }
+            (Source::Const(src0), Source::Register(src1)) => {
+                Inst::from(GeSInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Const(src1)) => {
+                Inst::from(GeSInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Global(src1)) => {
+                Inst::from(GeSInst::new(self.result, src0, src1))
+            }
+            (Source::Global(src0), Source::Register(src1)) => {
+                Inst::from(GeSInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Const(src1)) => {
+                Inst::from(GeSInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Global(src1)) => {
+                Inst::from(GeSInst::new(self.result, src0, src1))
+            }
+            (Source::Register(src0), Source::Register(src1)) => {
+                Inst::from(GeSInst::new(self.result, src0, src1))
+            }
+        }
+    }
+}
+
 impl Compile for DynamicBranchInst {
     fn compile(self) -> Inst {
         Inst::from(BranchInst::new(self.target))
@@ -125,6 +790,7 @@ impl Compile for DynamicBranchEqzInst {
         match self.condition {
             Source::Const(condition) => Inst::from(BranchEqzInst::new(self.target, condition)),
             Source::Register(condition) => Inst::from(BranchEqzInst::new(self.target, condition)),
+            Source::Global(condition) => Inst::from(BranchEqzInst::new(self.target, condition)),
         }
     }
 }
@@ -134,18 +800,36 @@ impl Compile for DynamicReturnInst {
         match self.result {
             Source::Const(result) => Inst::from(ReturnInst::new(result)),
             Source::Register(result) => Inst::from(ReturnInst::new(result)),
+            Source::Global(result) => Inst::from(ReturnInst::new(result)),
         }
     }
 }
 
-/// Executes the list of instruction using the given [`Context`].
-fn execute(insts: &[Inst], context: &mut Context) {
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Bits {
     loop {
         let pc = context.pc;
         let inst = &insts[pc];
         match inst.execute(context) {
-            Outcome::Continue => continue,
-            Outcome::Return => return,
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return context.get_reg(Register(0)),
+        }
+    }
+}
+
+/// Runs the list of instruction using the given [`Context`] and returns the result.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn run(insts: &[Inst], context: &mut Context) -> Result<Bits, TrapCode> {
+    loop {
+        let pc = context.pc;
+        let inst = insts.get(pc).ok_or(TrapCode::OutOfBoundsPc)?;
+        match inst.execute(context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return => return Ok(context.get_reg(Register(0))),
+            Outcome::Trap(trap) => return Err(trap),
         }
     }
 }
@@ -168,5 +852,77 @@ fn counter_loop() {
     ]
     .map(DynamicInst::compile);
     let mut context = Context::default();
-    benchmark(|| execute(&insts, &mut context));
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn mul_eq() {
+    let insts = [
+        // Store `6` into r0.
+        DynamicInst::add(Register(0), Const(0), Const(6)),
+        // Multiply r0 with `7` and store the result into r1.
+        DynamicInst::mul(Register(1), Register(0), Const(7)),
+        // Compare r1 against the expected product.
+        DynamicInst::eq(Register(2), Register(1), Const(42)),
+        // Check that r2 is no longer zero.
+        DynamicInst::ne(Register(3), Register(2), Const(0)),
+        // Return value and end function execution.
+        DynamicInst::ret(Register(3)),
+    ]
+    .map(DynamicInst::compile);
+    let mut context = Context::default();
+    execute(&insts, &mut context);
+    assert_eq!(context.get_reg(Register(1)), 42);
+    assert_eq!(context.get_reg(Register(0)), 1);
+}
+
+#[test]
+fn ordering_comparisons() {
+    let minus_one = -1_i64 as Bits;
+    let insts = [
+        DynamicInst::lt_u(Register(1), Register(0), Const(1)),
+        DynamicInst::le_u(Register(2), Register(0), Const(1)),
+        DynamicInst::gt_u(Register(3), Register(0), Const(1)),
+        DynamicInst::ge_u(Register(4), Register(0), Const(1)),
+        DynamicInst::lt_s(Register(5), Register(0), Const(1)),
+        DynamicInst::le_s(Register(6), Register(0), Const(1)),
+        DynamicInst::gt_s(Register(7), Register(0), Const(1)),
+        DynamicInst::ge_s(Register(8), Const(minus_one), Register(0)),
+        DynamicInst::ret(Register(1)),
+    ]
+    .map(DynamicInst::compile);
+    let mut context = Context::default();
+    context.set_reg(Register(0), minus_one);
+    assert_eq!(run(&insts, &mut context), Ok(0));
+    assert_eq!(context.get_reg(Register(2)), 0);
+    assert_eq!(context.get_reg(Register(3)), 1);
+    assert_eq!(context.get_reg(Register(4)), 1);
+    assert_eq!(context.get_reg(Register(5)), 1);
+    assert_eq!(context.get_reg(Register(6)), 1);
+    assert_eq!(context.get_reg(Register(7)), 0);
+    assert_eq!(context.get_reg(Register(8)), 1);
+}
+
+#[test]
+fn global_operands() {
+    let insts = [
+        // Add g0 and r0 and store the result into r1.
+        DynamicInst::add(Register(1), Global(0), Register(0)),
+        // Multiply r1 with g1 and store the result into r2.
+        DynamicInst::mul(Register(2), Register(1), Global(1)),
+        // Compare g0 against g1.
+        DynamicInst::lt_u(Register(3), Global(0), Global(1)),
+        // Return the global g1.
+        DynamicInst::ret(Global(1)),
+    ]
+    .map(DynamicInst::compile);
+    let mut context = Context::default();
+    context.set_global(Global(0), 5);
+    context.set_global(Global(1), 6);
+    context.set_reg(Register(0), 2);
+    assert_eq!(run(&insts, &mut context), Ok(6));
+    assert_eq!(context.get_reg(Register(1)), 7);
+    assert_eq!(context.get_reg(Register(2)), 42);
+    assert_eq!(context.get_reg(Register(3)), 1);
 }
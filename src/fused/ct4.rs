@@ -0,0 +1,195 @@
+#![allow(dead_code)]
+
+use alloc::vec::Vec;
+
+#[cfg(test)]
+use crate::benchmark;
+
+use super::{
+    ct::{self, InstData},
+    rt::{self, Sink, Source},
+    Bits, Context, Outcome, Register,
+};
+#[cfg(test)]
+use crate::switch;
+
+/// The handler of a compiled instruction.
+type Handler = fn(&mut Context, InstData) -> Outcome;
+
+/// A compiled program with its handlers and their data laid out in two parallel arrays.
+///
+/// In contrast to the array of [`ct::Inst`] the dispatch only touches the handlers and
+/// the data that it actually needs.
+pub struct Program {
+    /// The handlers of all instructions indexed by their `pc`.
+    handlers: Vec<Handler>,
+    /// The data of all instructions indexed by their `pc`.
+    data: Vec<InstData>,
+}
+
+/// Calls `$body` with `$name` bound to the monomorphic result of the [`Sink`] `$sink`.
+macro_rules! with_sink {
+    ($sink:expr, |$name:ident| $body:expr) => {
+        match $sink {
+            Sink::Register($name) => $body,
+            Sink::Global($name) => $body,
+        }
+    };
+}
+
+/// Calls `$body` with `$name` bound to the monomorphic param of the [`Source`] `$source`.
+macro_rules! with_source {
+    ($source:expr, |$name:ident| $body:expr) => {
+        match $source {
+            Source::Const($name) => $body,
+            Source::Register($name) => $body,
+            Source::Global($name) => $body,
+            Source::StackTop => unreachable!("operand stack instructions are rejected up front"),
+        }
+    };
+}
+
+/// Compiles the binary `$inst` using the [`ct::Inst`] constructor `$ctor`.
+macro_rules! compile_binary {
+    ($ctor:ident, $inst:expr) => {{
+        let inst = $inst;
+        with_sink!(inst.result, |result| {
+            with_source!(inst.lhs, |lhs| {
+                with_source!(inst.rhs, |rhs| ct::Inst::$ctor(result, lhs, rhs))
+            })
+        })
+    }};
+}
+
+/// Compiles the [`rt::Inst`] program into a [`Program`].
+///
+/// # Errors
+///
+/// Returns the first instruction that has no [`ct::Inst`] counterpart.
+pub fn compile(insts: &[rt::Inst]) -> Result<Program, rt::Inst> {
+    let mut program = Program {
+        handlers: Vec::with_capacity(insts.len()),
+        data: Vec::with_capacity(insts.len()),
+    };
+    for inst in insts {
+        let compiled = compile_inst(*inst)?;
+        program.handlers.push(compiled.handler);
+        program.data.push(compiled.data);
+    }
+    Ok(program)
+}
+
+/// Compiles the [`rt::Inst`] program into an array of monomorphized [`ct::Inst`]
+/// executable via [`ct::execute`].
+///
+/// # Errors
+///
+/// Returns the first instruction that has no [`ct::Inst`] counterpart.
+pub fn compile_program(insts: &[rt::Inst]) -> Result<Vec<ct::Inst>, rt::Inst> {
+    insts.iter().copied().map(compile_inst).collect()
+}
+
+/// Compiles a single [`rt::Inst`] into its monomorphized [`ct::Inst`].
+fn compile_inst(inst: rt::Inst) -> Result<ct::Inst, rt::Inst> {
+    if inst.uses_stack() {
+        return Err(inst);
+    }
+    let compiled = match inst {
+        rt::Inst::Add(inst) => compile_binary!(add, inst),
+        rt::Inst::Sub(inst) => compile_binary!(sub, inst),
+        rt::Inst::Mul(inst) => compile_binary!(mul, inst),
+        rt::Inst::Div(inst) => compile_binary!(div, inst),
+        rt::Inst::Rem(inst) => compile_binary!(rem, inst),
+        rt::Inst::And(inst) => compile_binary!(and, inst),
+        rt::Inst::Or(inst) => compile_binary!(or, inst),
+        rt::Inst::Xor(inst) => compile_binary!(xor, inst),
+        rt::Inst::Not(inst) => with_sink!(inst.result, |result| {
+            with_source!(inst.input, |input| ct::Inst::not(result, input))
+        }),
+        rt::Inst::Shl(inst) => compile_binary!(shl, inst),
+        rt::Inst::ShrU(inst) => compile_binary!(shr_u, inst),
+        rt::Inst::ShrS(inst) => compile_binary!(shr_s, inst),
+        rt::Inst::Rotl(inst) => compile_binary!(rotl, inst),
+        rt::Inst::Rotr(inst) => compile_binary!(rotr, inst),
+        rt::Inst::Eq(inst) => compile_binary!(eq, inst),
+        rt::Inst::Ne(inst) => compile_binary!(ne, inst),
+        rt::Inst::LtU(inst) => compile_binary!(lt_u, inst),
+        rt::Inst::LeU(inst) => compile_binary!(le_u, inst),
+        rt::Inst::GtU(inst) => compile_binary!(gt_u, inst),
+        rt::Inst::GeU(inst) => compile_binary!(ge_u, inst),
+        rt::Inst::LtS(inst) => compile_binary!(lt_s, inst),
+        rt::Inst::LeS(inst) => compile_binary!(le_s, inst),
+        rt::Inst::GtS(inst) => compile_binary!(gt_s, inst),
+        rt::Inst::GeS(inst) => compile_binary!(ge_s, inst),
+        rt::Inst::Branch(inst) => ct::Inst::branch(inst.target),
+        rt::Inst::BranchEqz(inst) => with_source!(inst.condition, |condition| {
+            ct::Inst::branch_eqz(inst.target, condition)
+        }),
+        rt::Inst::BranchNez(inst) => with_source!(inst.condition, |condition| {
+            ct::Inst::branch_nez(inst.target, condition)
+        }),
+        rt::Inst::Unreachable => ct::Inst::unreachable(),
+        rt::Inst::Return(inst) => with_source!(inst.result, |result| ct::Inst::ret(result)),
+        unsupported => return Err(unsupported),
+    };
+    Ok(compiled)
+}
+
+/// Executes the [`Program`] using the given [`Context`] and returns the result in register 0.
+pub fn execute(program: &Program, context: &mut Context) -> Bits {
+    loop {
+        let pc = context.pc;
+        let handler = program.handlers[pc];
+        let data = program.data[pc];
+        match handler(context, data) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return context.get_reg(Register(0)),
+        }
+    }
+}
+
+#[test]
+fn counter_loop() {
+    let program = compile(&rt::counter_loop_insts(100_000_000)).ok().unwrap();
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&program, &mut context));
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn matches_ct() {
+    let repetitions = 1_000;
+    let programs = [
+        switch::counter_loop_insts(repetitions),
+        switch::more_comps_insts(repetitions),
+    ];
+    for program in &programs {
+        let compiled = compile(&rt::lower(program).unwrap()).ok().unwrap();
+        let expected = ct::execute(&ct::lower(program).unwrap(), &mut Context::default());
+        assert_eq!(execute(&compiled, &mut Context::default()), expected);
+    }
+}
+
+#[test]
+fn compile_counter_loop() {
+    let insts = compile_program(&rt::counter_loop_insts(1_000))
+        .ok()
+        .unwrap();
+    assert_eq!(ct::execute(&insts, &mut Context::default()), 0);
+    let insts = [rt::Inst::pop(Register(0)), rt::Inst::ret(Register(0))];
+    assert!(matches!(compile_program(&insts), Err(rt::Inst::Pop(_))));
+}
+
+#[test]
+fn global_operands() {
+    use super::{Const, Global};
+    let insts = [
+        rt::Inst::add(Global(0), Const(5), Const(7)),
+        rt::Inst::mul(Register(0), Global(0), Const(3)),
+        rt::Inst::ret(Register(0)),
+    ];
+    let program = compile(&insts).ok().unwrap();
+    let mut context = Context::default();
+    assert_eq!(execute(&program, &mut context), 36);
+    assert_eq!(context.get_global(Global(0)), 12);
+}
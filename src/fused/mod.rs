@@ -1,31 +1,193 @@
-mod ct;
+pub(crate) mod ct;
 mod ct2;
 mod ct3;
-mod rt;
+mod ct4;
+pub(crate) mod rt;
 mod rt2;
 mod rt3;
 
-use crate::{Outcome, Target};
+use crate::{Frame, Outcome, Snapshot, TrapCode, DEFAULT_SEED, MAX_CALL_DEPTH, MEMORY_SIZE};
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    hash::{Hash, Hasher},
+    ops::Range,
+};
 
 pub type Bits = u64;
 
+/// The index of an instruction that execution may continue at.
+pub type Target = usize;
+
 pub struct Context {
     pc: usize,
     regs: Vec<Bits>,
+    fregs: Vec<f64>,
     globals: Vec<Bits>,
+    mem: Vec<u8>,
+    /// The end of the prefix of `mem` that stores may have written to since the last reset.
+    mem_dirty: usize,
+    frames: Vec<Frame>,
+    base: usize,
+    frame_size: usize,
+    rng: u64,
+    stack: Vec<Bits>,
+    /// The host functions called by [`rt::HostCallInst`] indexed by their id.
+    hosts: Vec<Box<dyn FnMut(Bits) -> Bits>>,
+    #[cfg(feature = "watermark")]
+    max_reg_written: Option<usize>,
+    #[cfg(feature = "watermark")]
+    max_stack_depth: usize,
 }
 
 impl Default for Context {
     fn default() -> Self {
+        Self::with_registers_and_globals(16, 16)
+    }
+}
+
+impl Context {
+    /// Creates a new [`Context`] with `regs` registers and `globals` globals, all zero initialized.
+    pub fn with_registers_and_globals(regs: usize, globals: usize) -> Self {
         Self {
             pc: 0,
-            regs: vec![0x00; 16],
-            globals: vec![0x00; 16],
+            regs: vec![0x00; regs],
+            fregs: vec![0.0; regs],
+            globals: vec![0x00; globals],
+            mem: vec![0x00; MEMORY_SIZE],
+            mem_dirty: 0,
+            frames: Vec::new(),
+            base: 0,
+            frame_size: regs,
+            rng: DEFAULT_SEED,
+            stack: Vec::new(),
+            hosts: Vec::new(),
+            #[cfg(feature = "watermark")]
+            max_reg_written: None,
+            #[cfg(feature = "watermark")]
+            max_stack_depth: 0,
         }
     }
-}
 
-impl Context {
+    /// Resets the [`Context`] to its initial state without reallocating.
+    ///
+    /// Zeroes the `pc`, all registers, globals and memory, drops all call frames
+    /// and operands, clears the watermarks and reseeds the random number generator
+    /// with [`DEFAULT_SEED`]. Registered host functions are kept.
+    /// Only the prefix of memory up to the furthest store since the last reset is zeroed.
+    pub fn reset(&mut self) {
+        self.pc = 0;
+        self.regs.truncate(self.frame_size);
+        self.regs.fill(0x00);
+        self.fregs.fill(0.0);
+        self.globals.fill(0x00);
+        self.mem[..self.mem_dirty].fill(0x00);
+        self.mem_dirty = 0;
+        self.frames.clear();
+        self.base = 0;
+        self.rng = DEFAULT_SEED;
+        self.stack.clear();
+        #[cfg(feature = "watermark")]
+        {
+            self.max_reg_written = None;
+            self.max_stack_depth = 0;
+        }
+    }
+
+    /// Returns a [`Snapshot`] of the `pc`, all registers, globals, call frames and operands.
+    #[allow(dead_code)]
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            pc: self.pc,
+            regs: self.regs.as_slice().into(),
+            fregs: self.fregs.as_slice().into(),
+            globals: self.globals.as_slice().into(),
+            frames: self.frames.as_slice().into(),
+            base: self.base,
+            stack: self.stack.as_slice().into(),
+        }
+    }
+
+    /// Restores the `pc`, all registers, globals, call frames and operands from the `snapshot`.
+    #[allow(dead_code)]
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.pc = snapshot.pc;
+        self.regs.clear();
+        self.regs.extend_from_slice(&snapshot.regs);
+        self.fregs.clear();
+        self.fregs.extend_from_slice(&snapshot.fregs);
+        self.globals.clear();
+        self.globals.extend_from_slice(&snapshot.globals);
+        self.frames.clear();
+        self.frames.extend_from_slice(&snapshot.frames);
+        self.base = snapshot.base;
+        self.stack.clear();
+        self.stack.extend_from_slice(&snapshot.stack);
+    }
+
+    /// Advances the xorshift64 random number generator and returns its next value.
+    ///
+    /// Yields the same sequence as [`crate::Context::next_random`] for the same seed.
+    pub fn next_random(&mut self) -> Bits {
+        let mut x = self.rng;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng = x;
+        x
+    }
+
+    /// Registers the host function `host` and returns the id to call it with.
+    pub fn register_host(&mut self, host: impl FnMut(Bits) -> Bits + 'static) -> u32 {
+        let id = u32::try_from(self.hosts.len()).expect("too many host functions");
+        self.hosts.push(Box::new(host));
+        id
+    }
+
+    /// Calls the host function `id` with `arg` and returns its result.
+    ///
+    /// # Errors
+    ///
+    /// If no host function with `id` has been registered.
+    pub fn call_host(&mut self, id: u32, arg: Bits) -> Result<Bits, TrapCode> {
+        let host = usize::try_from(id)
+            .ok()
+            .and_then(|id| self.hosts.get_mut(id))
+            .ok_or(TrapCode::UnknownHostFunction)?;
+        Ok(host(arg))
+    }
+
+    /// Pushes `value` onto the operand stack.
+    pub fn push(&mut self, value: Bits) {
+        self.stack.push(value);
+        #[cfg(feature = "watermark")]
+        {
+            self.max_stack_depth = self.max_stack_depth.max(self.stack.len());
+        }
+    }
+
+    /// Returns the absolute index of the highest register written via [`Context::set_reg`] if any.
+    ///
+    /// Registers of callee windows count from the first register of the outermost window.
+    #[cfg(feature = "watermark")]
+    pub fn max_reg_written(&self) -> Option<usize> {
+        self.max_reg_written
+    }
+
+    /// Returns the maximum number of operands that were on the operand stack at once.
+    #[cfg(feature = "watermark")]
+    pub fn max_stack_depth(&self) -> usize {
+        self.max_stack_depth
+    }
+
+    /// Pops the top of the operand stack.
+    ///
+    /// # Errors
+    ///
+    /// If the operand stack is empty.
+    pub fn pop(&mut self) -> Result<Bits, TrapCode> {
+        self.stack.pop().ok_or(TrapCode::StackUnderflow)
+    }
+
     pub fn next_inst(&mut self) -> Outcome {
         self.pc += 1;
         Outcome::Continue
@@ -36,20 +198,161 @@ impl Context {
         Outcome::Continue
     }
 
+    /// Calls the function starting at `target` passing the first `num_args` registers as arguments.
+    ///
+    /// The arguments are copied into the low registers of the callee's fresh register window.
+    pub fn call(&mut self, target: Target, num_args: usize) -> Outcome {
+        debug_assert!(num_args <= self.frame_size);
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Outcome::Trap(TrapCode::CallStackOverflow);
+        }
+        self.frames.push(Frame {
+            return_pc: self.pc + 1,
+            base: self.base,
+        });
+        let base = self.base + self.frame_size;
+        let end = base + self.frame_size;
+        if self.regs.len() < end {
+            self.regs.resize(end, 0x00);
+        }
+        self.regs.copy_within(self.base..self.base + num_args, base);
+        self.regs[base + num_args..end].fill(0x00);
+        self.base = base;
+        self.branch_to(target)
+    }
+
+    /// Returns `result` to the caller in its `r0` or ends execution if there is no caller.
+    pub fn return_with(&mut self, result: Bits) -> Outcome {
+        match self.frames.pop() {
+            Some(frame) => {
+                self.base = frame.base;
+                self.set_reg(Register(0), result);
+                self.branch_to(frame.return_pc)
+            }
+            None => {
+                self.set_reg(Register(0), result);
+                Outcome::Return
+            }
+        }
+    }
+
+    /// Returns the range of `len` bytes of memory at `address` plus `offset`.
+    fn memory_range(
+        &self,
+        address: Bits,
+        offset: Bits,
+        len: usize,
+    ) -> Result<Range<usize>, TrapCode> {
+        address
+            .checked_add(offset)
+            .and_then(|start| usize::try_from(start).ok())
+            .and_then(|start| Some(start..start.checked_add(len)?))
+            .filter(|range| range.end <= self.mem.len())
+            .ok_or(TrapCode::OutOfBoundsMemory)
+    }
+
+    /// Returns the `N` bytes of memory at `address` plus `offset`.
+    fn load_bytes<const N: usize>(&self, address: Bits, offset: Bits) -> Result<[u8; N], TrapCode> {
+        let range = self.memory_range(address, offset, N)?;
+        let mut bytes = [0x00; N];
+        bytes.copy_from_slice(&self.mem[range]);
+        Ok(bytes)
+    }
+
+    /// Overwrites the `N` bytes of memory at `address` plus `offset` with `bytes`.
+    fn store_bytes<const N: usize>(
+        &mut self,
+        address: Bits,
+        offset: Bits,
+        bytes: [u8; N],
+    ) -> Result<(), TrapCode> {
+        let range = self.memory_range(address, offset, N)?;
+        self.mem_dirty = self.mem_dirty.max(range.end);
+        self.mem[range].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    /// Loads the little-endian `u8` at `address` plus `offset` from memory.
+    pub fn load8(&self, address: Bits, offset: Bits) -> Result<u8, TrapCode> {
+        self.load_bytes(address, offset).map(u8::from_le_bytes)
+    }
+
+    /// Loads the little-endian `u16` at `address` plus `offset` from memory.
+    pub fn load16(&self, address: Bits, offset: Bits) -> Result<u16, TrapCode> {
+        self.load_bytes(address, offset).map(u16::from_le_bytes)
+    }
+
+    /// Loads the little-endian `u32` at `address` plus `offset` from memory.
+    pub fn load32(&self, address: Bits, offset: Bits) -> Result<u32, TrapCode> {
+        self.load_bytes(address, offset).map(u32::from_le_bytes)
+    }
+
+    /// Loads the little-endian `u64` at `address` plus `offset` from memory.
+    pub fn load64(&self, address: Bits, offset: Bits) -> Result<u64, TrapCode> {
+        self.load_bytes(address, offset).map(u64::from_le_bytes)
+    }
+
+    /// Stores `value` as little-endian `u8` at `address` plus `offset` into memory.
+    pub fn store8(&mut self, address: Bits, offset: Bits, value: u8) -> Result<(), TrapCode> {
+        self.store_bytes(address, offset, value.to_le_bytes())
+    }
+
+    /// Stores `value` as little-endian `u16` at `address` plus `offset` into memory.
+    pub fn store16(&mut self, address: Bits, offset: Bits, value: u16) -> Result<(), TrapCode> {
+        self.store_bytes(address, offset, value.to_le_bytes())
+    }
+
+    /// Stores `value` as little-endian `u32` at `address` plus `offset` into memory.
+    pub fn store32(&mut self, address: Bits, offset: Bits, value: u32) -> Result<(), TrapCode> {
+        self.store_bytes(address, offset, value.to_le_bytes())
+    }
+
+    /// Stores `value` as little-endian `u64` at `address` plus `offset` into memory.
+    pub fn store64(&mut self, address: Bits, offset: Bits, value: u64) -> Result<(), TrapCode> {
+        self.store_bytes(address, offset, value.to_le_bytes())
+    }
+
     pub fn set_reg(&mut self, reg: Register, new_value: Bits) {
-        let reg = reg.into_usize();
+        let reg = self.base + reg.into_usize();
         debug_assert!(reg < self.regs.len());
+        #[cfg(feature = "watermark")]
+        {
+            self.max_reg_written = self.max_reg_written.max(Some(reg));
+        }
         unsafe {
             *self.regs.get_unchecked_mut(reg) = new_value;
         }
     }
 
     pub fn get_reg(&self, reg: Register) -> Bits {
-        let reg = reg.into_usize();
+        let reg = self.base + reg.into_usize();
         debug_assert!(reg < self.regs.len());
         unsafe { *self.regs.get_unchecked(reg) }
     }
 
+    /// Returns the current value of the register at the absolute index `reg`.
+    ///
+    /// Unlike [`Context::get_reg`] this ignores the register window, e.g. to read
+    /// the result of the outermost function in `r0` no matter where execution stopped.
+    pub fn get_reg_abs(&self, reg: usize) -> Bits {
+        debug_assert!(reg < self.regs.len());
+        unsafe { *self.regs.get_unchecked(reg) }
+    }
+
+    pub fn set_freg(&mut self, reg: FRegister, new_value: f64) {
+        let reg = reg.into_usize();
+        debug_assert!(reg < self.fregs.len());
+        unsafe {
+            *self.fregs.get_unchecked_mut(reg) = new_value;
+        }
+    }
+
+    pub fn get_freg(&self, reg: FRegister) -> f64 {
+        let reg = reg.into_usize();
+        debug_assert!(reg < self.fregs.len());
+        unsafe { *self.fregs.get_unchecked(reg) }
+    }
+
     pub fn set_global(&mut self, global: Global, new_value: Bits) {
         let global = global.into_usize();
         debug_assert!(global < self.globals.len());
@@ -60,20 +363,56 @@ impl Context {
 
     pub fn get_global(&self, global: Global) -> Bits {
         let global = global.into_usize();
-        debug_assert!(global > self.globals.len());
+        debug_assert!(global < self.globals.len());
         unsafe { *self.globals.get_unchecked(global) }
     }
+
+    /// Returns the registers of the current register window.
+    ///
+    /// The slice is indexed like [`Context::get_reg`].
+    #[allow(dead_code)]
+    pub fn regs(&self) -> &[Bits] {
+        &self.regs[self.base..self.base + self.frame_size]
+    }
+
+    /// Returns the registers of the current register window for bulk initialization.
+    ///
+    /// The slice is indexed like [`Context::set_reg`].
+    #[allow(dead_code)]
+    pub fn regs_mut(&mut self) -> &mut [Bits] {
+        &mut self.regs[self.base..self.base + self.frame_size]
+    }
+
+    /// Returns all globals.
+    #[allow(dead_code)]
+    pub fn globals(&self) -> &[Bits] {
+        &self.globals
+    }
+
+    /// Returns all globals for bulk initialization.
+    #[allow(dead_code)]
+    pub fn globals_mut(&mut self) -> &mut [Bits] {
+        &mut self.globals
+    }
 }
 
-#[derive(Copy, Clone)]
-pub struct Register(usize);
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Register(pub(crate) usize);
 impl Register {
     pub fn into_usize(self) -> usize {
         self.0
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FRegister(usize);
+impl FRegister {
+    pub fn into_usize(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Global(usize);
 impl Global {
     pub fn into_usize(self) -> usize {
@@ -81,10 +420,169 @@ impl Global {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Const(Bits);
 impl Const {
+    /// Creates a constant from a signed immediate stored in two's complement.
+    pub fn from_i64(value: i64) -> Self {
+        Self(value as Bits)
+    }
+
+    /// Creates a constant from an unsigned immediate.
+    pub fn from_u64(value: u64) -> Self {
+        Self(value)
+    }
+
     pub fn into_bits(self) -> Bits {
         self.0
     }
+
+    /// Returns the constant reinterpreted as a signed two's complement value.
+    #[allow(dead_code)]
+    pub fn into_i64(self) -> i64 {
+        self.0 as i64
+    }
+}
+
+impl From<i64> for Const {
+    fn from(value: i64) -> Self {
+        Self::from_i64(value)
+    }
+}
+
+impl From<u64> for Const {
+    fn from(value: u64) -> Self {
+        Self::from_u64(value)
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct FConst(f64);
+impl FConst {
+    pub fn into_f64(self) -> f64 {
+        self.0
+    }
+}
+
+/// Compares the bit patterns so that [`FConst`] can be [`Eq`] and [`Hash`].
+///
+/// Unlike `f64` a `NaN` constant thus equals itself while `0.0` and `-0.0` differ.
+impl PartialEq for FConst {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for FConst {}
+
+impl Hash for FConst {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state)
+    }
+}
+
+#[test]
+fn context_with_registers_and_globals() {
+    let mut context = Context::with_registers_and_globals(64, 32);
+    context.set_reg(Register(63), 42);
+    context.set_global(Global(31), 7);
+    assert_eq!(context.get_reg(Register(63)), 42);
+    assert_eq!(context.get_global(Global(31)), 7);
+    let context = Context::default();
+    assert_eq!(context.regs.len(), 16);
+    assert_eq!(context.globals.len(), 16);
+}
+
+#[test]
+fn context_register_slices() {
+    let mut context = Context::default();
+    for (reg, value) in context.regs_mut().iter_mut().enumerate() {
+        *value = reg as Bits * 10;
+    }
+    context.globals_mut()[3] = 7;
+    for reg in 0..16 {
+        assert_eq!(context.get_reg(Register(reg)), reg as Bits * 10);
+    }
+    assert_eq!(context.get_global(Global(3)), 7);
+    assert_eq!(context.regs()[15], 150);
+    assert_eq!(context.globals()[3], 7);
+}
+
+#[test]
+fn context_reset() {
+    let mut context = Context::default();
+    let capacities = (context.regs.capacity(), context.globals.capacity());
+    context.pc = 7;
+    context.set_reg(Register(3), 42);
+    context.set_global(Global(5), 7);
+    context.store64(8, 0, 1).unwrap();
+    assert_eq!(context.mem_dirty, 16);
+    context.reset();
+    assert_eq!(context.mem_dirty, 0);
+    assert_eq!(context.pc, 0);
+    assert!(context.regs.iter().all(|&reg| reg == 0));
+    assert!(context.globals.iter().all(|&global| global == 0));
+    assert_eq!(context.load64(8, 0), Ok(0));
+    assert_eq!(context.regs.capacity(), capacities.0);
+    assert_eq!(context.globals.capacity(), capacities.1);
+}
+
+#[test]
+#[cfg(feature = "watermark")]
+fn context_watermark() {
+    use rt::{Inst, Source};
+    let insts = rt::lower(&crate::switch::more_comps_insts(100)).unwrap();
+    let mut context = Context::default();
+    assert_eq!(context.max_reg_written(), None);
+    rt::run(&insts, &mut context).unwrap();
+    assert_eq!(context.max_reg_written(), Some(1));
+    assert_eq!(context.max_stack_depth(), 0);
+    let insts = [
+        Inst::push(Const(2)),
+        Inst::push(Const(3)),
+        Inst::add(Register(0), Source::StackTop, Source::StackTop),
+        Inst::push(Register(0)),
+        Inst::ret(Register(0)),
+    ];
+    context.reset();
+    assert_eq!(rt::run(&insts, &mut context), Ok(5));
+    assert_eq!(context.max_reg_written(), Some(0));
+    assert_eq!(context.max_stack_depth(), 2);
+}
+
+#[test]
+fn context_snapshot_restore() {
+    use rt::Inst;
+    let mut context = Context::default();
+    context.set_reg(Register(0), 3);
+    let snapshot = context.snapshot();
+    let insts = [
+        Inst::mul(Register(0), Register(0), Const(7)),
+        Inst::add(Global(1), Register(0), Const(1)),
+        Inst::ret(Register(0)),
+    ];
+    assert_eq!(rt::run(&insts, &mut context), Ok(21));
+    assert_eq!(context.get_global(Global(1)), 22);
+    context.restore(&snapshot);
+    assert_eq!(context.snapshot(), snapshot);
+    assert_eq!(context.pc, 0);
+    assert_eq!(context.get_reg(Register(0)), 3);
+    assert_eq!(context.get_global(Global(1)), 0);
+}
+
+#[test]
+fn context_snapshot_restore_stack() {
+    let mut context = Context::default();
+    context.push(1);
+    context.push(2);
+    let snapshot = context.snapshot();
+    assert_eq!(context.pop(), Ok(2));
+    context.push(3);
+    context.push(4);
+    assert_ne!(context.snapshot(), snapshot);
+    context.restore(&snapshot);
+    assert_eq!(context.snapshot(), snapshot);
+    assert_eq!(context.pop(), Ok(2));
+    assert_eq!(context.pop(), Ok(1));
+    assert_eq!(context.pop(), Err(TrapCode::StackUnderflow));
 }
@@ -3,13 +3,25 @@
 #[cfg(test)]
 use crate::benchmark;
 
-use super::{Bits, Const, Context, Global, Outcome, Register, Target};
+use super::{Bits, Const, Context, FConst, FRegister, Global, Outcome, Register, Target, TrapCode};
+use crate::arith::{cmp_s, cmp_u};
+use crate::switch;
+#[cfg(test)]
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Source {
     Const(Const),
     Register(Register),
     Global(Global),
+    /// Pops the top of the operand stack when loaded.
+    ///
+    /// The operands of an instruction are loaded from left to right.
+    StackTop,
 }
 
 impl From<Const> for Source {
@@ -18,6 +30,19 @@ impl From<Const> for Source {
     }
 }
 
+/// Signed immediates are stored in two's complement, see [`Const::from_i64`].
+impl From<i64> for Source {
+    fn from(value: i64) -> Self {
+        Self::Const(Const::from_i64(value))
+    }
+}
+
+impl From<u64> for Source {
+    fn from(value: u64) -> Self {
+        Self::Const(Const::from_u64(value))
+    }
+}
+
 impl From<Register> for Source {
     fn from(register: Register) -> Self {
         Self::Register(register)
@@ -31,16 +56,79 @@ impl From<Global> for Source {
 }
 
 impl Source {
-    pub fn load(&self, context: &Context) -> Bits {
+    /// Loads the value of the [`Source`].
+    ///
+    /// # Errors
+    ///
+    /// If a [`Source::StackTop`] is loaded from an empty operand stack.
+    pub fn load(&self, context: &mut Context) -> Result<Bits, TrapCode> {
+        match self {
+            Source::Const(constant) => Ok(constant.into_bits()),
+            Source::Register(register) => Ok(context.get_reg(*register)),
+            Source::Global(global) => Ok(context.get_global(*global)),
+            Source::StackTop => context.pop(),
+        }
+    }
+}
+
+/// Loads the value of a [`Source`] or returns from `execute` with its trap.
+macro_rules! load {
+    ( $source:expr, $context:expr ) => {
+        match $source.load($context) {
+            Ok(value) => value,
+            Err(trap) => return Outcome::Trap(trap),
+        }
+    };
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Const(constant) => write!(f, "#{}", constant.into_bits()),
+            Source::Register(register) => write!(f, "r{}", register.into_usize()),
+            Source::Global(global) => write!(f, "g{}", global.into_usize()),
+            Source::StackTop => write!(f, "top"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FSource {
+    FConst(FConst),
+    FRegister(FRegister),
+}
+
+impl From<FConst> for FSource {
+    fn from(constant: FConst) -> Self {
+        Self::FConst(constant)
+    }
+}
+
+impl From<FRegister> for FSource {
+    fn from(register: FRegister) -> Self {
+        Self::FRegister(register)
+    }
+}
+
+impl FSource {
+    pub fn load(&self, context: &Context) -> f64 {
+        match self {
+            FSource::FConst(constant) => constant.into_f64(),
+            FSource::FRegister(register) => context.get_freg(*register),
+        }
+    }
+}
+
+impl fmt::Display for FSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Source::Const(constant) => constant.into_bits(),
-            Source::Register(register) => context.get_reg(*register),
-            Source::Global(global) => context.get_global(*global),
+            FSource::FConst(constant) => write!(f, "#{:?}", constant.into_f64()),
+            FSource::FRegister(register) => write!(f, "f{}", register.into_usize()),
         }
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Sink {
     Register(Register),
     Global(Global),
@@ -67,19 +155,91 @@ impl Sink {
     }
 }
 
+impl fmt::Display for Sink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Sink::Register(register) => write!(f, "r{}", register.into_usize()),
+            Sink::Global(global) => write!(f, "g{}", global.into_usize()),
+        }
+    }
+}
+
 pub trait Execute {
     fn execute(&self, context: &mut Context) -> Outcome;
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Inst {
     Add(AddInst),
     Sub(SubInst),
     Mul(MulInst),
+    Div(DivInst),
+    Rem(RemInst),
+    And(AndInst),
+    Or(OrInst),
+    Xor(XorInst),
+    Not(NotInst),
+    Shl(ShlInst),
+    ShrU(ShrUInst),
+    ShrS(ShrSInst),
+    Rotl(RotlInst),
+    Rotr(RotrInst),
     Eq(EqInst),
     Ne(NeInst),
+    LtU(LtUInst),
+    LeU(LeUInst),
+    GtU(GtUInst),
+    GeU(GeUInst),
+    LtS(LtSInst),
+    LeS(LeSInst),
+    GtS(GtSInst),
+    GeS(GeSInst),
+    Select(SelectInst),
+    Move(MoveInst),
+    Add32(Add32Inst),
+    Sub32(Sub32Inst),
+    Mul32(Mul32Inst),
+    LtS32(LtS32Inst),
+    Shl32(Shl32Inst),
+    ShrU32(ShrU32Inst),
+    ShrS32(ShrS32Inst),
+    AddChecked(AddCheckedInst),
+    SubChecked(SubCheckedInst),
+    MulChecked(MulCheckedInst),
+    MulWide(MulWideInst),
+    Popcnt(PopcntInst),
+    Clz(ClzInst),
+    Ctz(CtzInst),
+    RandNext(RandNextInst),
+    HostCall(HostCallInst),
+    Push(PushInst),
+    Pop(PopInst),
+    MinU(MinUInst),
+    MaxU(MaxUInst),
+    MinS(MinSInst),
+    MaxS(MaxSInst),
+    FAdd(FAddInst),
+    FSub(FSubInst),
+    FMul(FMulInst),
+    FDiv(FDivInst),
+    Load8(Load8Inst),
+    Load16(Load16Inst),
+    Load32(Load32Inst),
+    Load64(Load64Inst),
+    Store8(Store8Inst),
+    Store16(Store16Inst),
+    Store32(Store32Inst),
+    Store64(Store64Inst),
     Branch(BranchInst),
     BranchEqz(BranchEqzInst),
+    BranchNez(BranchNezInst),
+    BranchEq(BranchEqInst),
+    BranchNe(BranchNeInst),
+    BranchLtU(BranchLtUInst),
+    BranchLtS(BranchLtSInst),
+    Call(CallInst),
+    Nop,
+    Unreachable,
     Return(ReturnInst),
 }
 
@@ -123,178 +283,3081 @@ impl Inst {
         })
     }
 
-    pub fn branch(target: Target) -> Self {
-        Self::Branch(BranchInst { target })
+    pub fn div<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::Div(DivInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
     }
 
-    pub fn branch_eqz<C>(target: Target, condition: C) -> Self
+    pub fn rem<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
     where
-        C: Into<Source>,
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
     {
-        Self::BranchEqz(BranchEqzInst {
-            target,
-            condition: condition.into(),
+        Self::Rem(RemInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
         })
     }
 
-    pub fn ret<R>(result: R) -> Self
+    pub fn and<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
     where
-        R: Into<Source>,
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
     {
-        Self::Return(ReturnInst {
+        Self::And(AndInst {
             result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
         })
     }
-}
 
-impl Execute for Inst {
-    fn execute(&self, context: &mut Context) -> Outcome {
-        match self {
-            Inst::Add(inst) => inst.execute(context),
-            Inst::Sub(inst) => inst.execute(context),
-            Inst::Mul(inst) => inst.execute(context),
-            Inst::Eq(inst) => inst.execute(context),
-            Inst::Ne(inst) => inst.execute(context),
-            Inst::Branch(inst) => inst.execute(context),
-            Inst::BranchEqz(inst) => inst.execute(context),
-            Inst::Return(inst) => inst.execute(context),
-        }
+    pub fn or<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::Or(OrInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
     }
-}
-
-macro_rules! impl_cmp_insts {
-    ( $( $inst_name:ident($op_name:ident) ),* $(,)? ) => {
-        $(
-            #[derive(Copy, Clone)]
-            pub struct $inst_name {
-                pub result: Sink,
-                pub lhs: Source,
-                pub rhs: Source,
-            }
-
-            impl Execute for $inst_name {
-                fn execute(&self, context: &mut Context) -> Outcome {
-                    let lhs = self.lhs.load(context);
-                    let rhs = self.rhs.load(context);
-                    self.result.store(context, lhs.$op_name(&rhs) as u64);
-                    context.next_inst()
-                }
-            }
-        )*
-    };
-}
-impl_cmp_insts! {
-    EqInst(eq),
-    NeInst(ne),
-}
-
-#[derive(Copy, Clone)]
-pub struct AddInst {
-    pub result: Sink,
-    pub lhs: Source,
-    pub rhs: Source,
-}
 
-impl Execute for AddInst {
-    fn execute(&self, context: &mut Context) -> Outcome {
-        let lhs = self.lhs.load(context);
-        let rhs = self.rhs.load(context);
-        self.result.store(context, lhs.wrapping_add(rhs));
-        context.next_inst()
+    pub fn xor<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::Xor(XorInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
     }
-}
 
-#[derive(Copy, Clone)]
-pub struct SubInst {
-    pub result: Sink,
-    pub lhs: Source,
-    pub rhs: Source,
-}
-
-impl Execute for SubInst {
-    fn execute(&self, context: &mut Context) -> Outcome {
-        let lhs = self.lhs.load(context);
-        let rhs = self.rhs.load(context);
-        self.result.store(context, lhs.wrapping_sub(rhs));
-        context.next_inst()
+    pub fn shl<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::Shl(ShlInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
     }
-}
-
-#[derive(Copy, Clone)]
-pub struct MulInst {
-    pub result: Sink,
-    pub lhs: Source,
-    pub rhs: Source,
-}
 
-impl Execute for MulInst {
-    fn execute(&self, context: &mut Context) -> Outcome {
-        let lhs = self.lhs.load(context);
-        let rhs = self.rhs.load(context);
-        self.result.store(context, lhs.wrapping_mul(rhs));
-        context.next_inst()
+    pub fn shr_u<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::ShrU(ShrUInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
     }
-}
 
-#[derive(Copy, Clone)]
-pub struct BranchInst {
-    pub target: Target,
-}
-
-impl Execute for BranchInst {
-    fn execute(&self, context: &mut Context) -> Outcome {
-        context.branch_to(self.target)
+    pub fn shr_s<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::ShrS(ShrSInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
     }
-}
-
-#[derive(Copy, Clone)]
-pub struct BranchEqzInst {
-    pub target: Target,
-    pub condition: Source,
-}
 
-impl Execute for BranchEqzInst {
-    fn execute(&self, context: &mut Context) -> Outcome {
-        let condition = self.condition.load(context);
-        if condition == 0 {
-            context.branch_to(self.target)
-        } else {
-            context.next_inst()
-        }
+    pub fn rotl<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::Rotl(RotlInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
     }
-}
 
-#[derive(Copy, Clone)]
-pub struct ReturnInst {
-    pub result: Source,
-}
+    pub fn rotr<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::Rotr(RotrInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
 
-impl Execute for ReturnInst {
-    fn execute(&self, context: &mut Context) -> Outcome {
-        let result = self.result.load(context);
-        context.set_reg(Register(0), result);
-        Outcome::Return
+    pub fn not<R, P>(result: R, input: P) -> Self
+    where
+        R: Into<Sink>,
+        P: Into<Source>,
+    {
+        Self::Not(NotInst {
+            result: result.into(),
+            input: input.into(),
+        })
     }
-}
 
-/// Executes the list of instruction using the given [`Context`].
-fn execute(insts: &[Inst], context: &mut Context) {
-    loop {
-        let pc = context.pc;
-        let inst = &insts[pc];
-        match inst.execute(context) {
-            Outcome::Continue => continue,
-            Outcome::Return => return,
-        }
+    pub fn eq<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::Eq(EqInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
     }
-}
 
-#[test]
-fn counter_loop() {
-    let repetitions = 100_000_000;
-    let insts = vec![
-        // Store `repetitions` into r0.
-        // Note: r0 is our loop counter register.
+    pub fn ne<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::Ne(NeInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn lt_u<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::LtU(LtUInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn le_u<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::LeU(LeUInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn gt_u<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::GtU(GtUInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn ge_u<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::GeU(GeUInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn lt_s<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::LtS(LtSInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn le_s<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::LeS(LeSInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn gt_s<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::GtS(GtSInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn ge_s<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::GeS(GeSInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn select<R, C, P0, P1>(result: R, cond: C, if_nonzero: P0, if_zero: P1) -> Self
+    where
+        R: Into<Sink>,
+        C: Into<Source>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::Select(SelectInst {
+            result: result.into(),
+            cond: cond.into(),
+            if_nonzero: if_nonzero.into(),
+            if_zero: if_zero.into(),
+        })
+    }
+
+    pub fn mov<R, P>(result: R, src: P) -> Self
+    where
+        R: Into<Sink>,
+        P: Into<Source>,
+    {
+        Self::Move(MoveInst {
+            result: result.into(),
+            src: src.into(),
+        })
+    }
+
+    pub fn add32<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::Add32(Add32Inst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn sub32<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::Sub32(Sub32Inst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn mul32<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::Mul32(Mul32Inst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn lt_s32<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::LtS32(LtS32Inst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn shl32<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::Shl32(Shl32Inst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn shr_u32<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::ShrU32(ShrU32Inst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn shr_s32<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::ShrS32(ShrS32Inst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn add_checked<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::AddChecked(AddCheckedInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn sub_checked<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::SubChecked(SubCheckedInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn mul_checked<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::MulChecked(MulCheckedInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn mul_wide<H, L, P0, P1>(hi: H, lo: L, lhs: P0, rhs: P1) -> Self
+    where
+        H: Into<Sink>,
+        L: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::MulWide(MulWideInst {
+            hi: hi.into(),
+            lo: lo.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn popcnt<R, P>(result: R, input: P) -> Self
+    where
+        R: Into<Sink>,
+        P: Into<Source>,
+    {
+        Self::Popcnt(PopcntInst {
+            result: result.into(),
+            input: input.into(),
+        })
+    }
+
+    pub fn rand_next<R>(result: R) -> Self
+    where
+        R: Into<Sink>,
+    {
+        Self::RandNext(RandNextInst {
+            result: result.into(),
+        })
+    }
+
+    pub fn host_call<R, P>(id: u32, arg: P, result: R) -> Self
+    where
+        R: Into<Sink>,
+        P: Into<Source>,
+    {
+        Self::HostCall(HostCallInst {
+            id,
+            arg: arg.into(),
+            result: result.into(),
+        })
+    }
+
+    pub fn push<P>(value: P) -> Self
+    where
+        P: Into<Source>,
+    {
+        Self::Push(PushInst {
+            value: value.into(),
+        })
+    }
+
+    pub fn pop<R>(result: R) -> Self
+    where
+        R: Into<Sink>,
+    {
+        Self::Pop(PopInst {
+            result: result.into(),
+        })
+    }
+
+    pub fn clz<R, P>(result: R, input: P) -> Self
+    where
+        R: Into<Sink>,
+        P: Into<Source>,
+    {
+        Self::Clz(ClzInst {
+            result: result.into(),
+            input: input.into(),
+        })
+    }
+
+    pub fn ctz<R, P>(result: R, input: P) -> Self
+    where
+        R: Into<Sink>,
+        P: Into<Source>,
+    {
+        Self::Ctz(CtzInst {
+            result: result.into(),
+            input: input.into(),
+        })
+    }
+
+    pub fn min_u<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::MinU(MinUInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn max_u<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::MaxU(MaxUInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn min_s<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::MinS(MinSInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn max_s<R, P0, P1>(result: R, lhs: P0, rhs: P1) -> Self
+    where
+        R: Into<Sink>,
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::MaxS(MaxSInst {
+            result: result.into(),
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn fadd<P0, P1>(result: FRegister, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<FSource>,
+        P1: Into<FSource>,
+    {
+        Self::FAdd(FAddInst {
+            result,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn fsub<P0, P1>(result: FRegister, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<FSource>,
+        P1: Into<FSource>,
+    {
+        Self::FSub(FSubInst {
+            result,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn fmul<P0, P1>(result: FRegister, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<FSource>,
+        P1: Into<FSource>,
+    {
+        Self::FMul(FMulInst {
+            result,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn fdiv<P0, P1>(result: FRegister, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<FSource>,
+        P1: Into<FSource>,
+    {
+        Self::FDiv(FDivInst {
+            result,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn load8<R>(result: R, base: Register, offset: Bits) -> Self
+    where
+        R: Into<Sink>,
+    {
+        Self::Load8(Load8Inst {
+            result: result.into(),
+            base,
+            offset,
+        })
+    }
+
+    pub fn load16<R>(result: R, base: Register, offset: Bits) -> Self
+    where
+        R: Into<Sink>,
+    {
+        Self::Load16(Load16Inst {
+            result: result.into(),
+            base,
+            offset,
+        })
+    }
+
+    pub fn load32<R>(result: R, base: Register, offset: Bits) -> Self
+    where
+        R: Into<Sink>,
+    {
+        Self::Load32(Load32Inst {
+            result: result.into(),
+            base,
+            offset,
+        })
+    }
+
+    pub fn load64<R>(result: R, base: Register, offset: Bits) -> Self
+    where
+        R: Into<Sink>,
+    {
+        Self::Load64(Load64Inst {
+            result: result.into(),
+            base,
+            offset,
+        })
+    }
+
+    pub fn store8<V>(base: Register, offset: Bits, value: V) -> Self
+    where
+        V: Into<Source>,
+    {
+        Self::Store8(Store8Inst {
+            base,
+            offset,
+            value: value.into(),
+        })
+    }
+
+    pub fn store16<V>(base: Register, offset: Bits, value: V) -> Self
+    where
+        V: Into<Source>,
+    {
+        Self::Store16(Store16Inst {
+            base,
+            offset,
+            value: value.into(),
+        })
+    }
+
+    pub fn store32<V>(base: Register, offset: Bits, value: V) -> Self
+    where
+        V: Into<Source>,
+    {
+        Self::Store32(Store32Inst {
+            base,
+            offset,
+            value: value.into(),
+        })
+    }
+
+    pub fn store64<V>(base: Register, offset: Bits, value: V) -> Self
+    where
+        V: Into<Source>,
+    {
+        Self::Store64(Store64Inst {
+            base,
+            offset,
+            value: value.into(),
+        })
+    }
+
+    pub fn branch(target: Target) -> Self {
+        Self::Branch(BranchInst { target })
+    }
+
+    pub fn branch_eqz<C>(target: Target, condition: C) -> Self
+    where
+        C: Into<Source>,
+    {
+        Self::BranchEqz(BranchEqzInst {
+            target,
+            condition: condition.into(),
+        })
+    }
+
+    pub fn branch_nez<C>(target: Target, condition: C) -> Self
+    where
+        C: Into<Source>,
+    {
+        Self::BranchNez(BranchNezInst {
+            target,
+            condition: condition.into(),
+        })
+    }
+
+    pub fn branch_eq<P0, P1>(target: Target, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::BranchEq(BranchEqInst {
+            target,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn branch_ne<P0, P1>(target: Target, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::BranchNe(BranchNeInst {
+            target,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn branch_lt_u<P0, P1>(target: Target, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::BranchLtU(BranchLtUInst {
+            target,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn branch_lt_s<P0, P1>(target: Target, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::BranchLtS(BranchLtSInst {
+            target,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn call(target: Target, num_args: usize) -> Self {
+        Self::Call(CallInst { target, num_args })
+    }
+
+    pub fn ret<R>(result: R) -> Self
+    where
+        R: Into<Source>,
+    {
+        Self::Return(ReturnInst {
+            result: result.into(),
+        })
+    }
+
+    /// Returns `true` if `self` pushes or pops the operand stack.
+    pub fn uses_stack(&self) -> bool {
+        let mut uses_stack = matches!(self, Inst::Push(_) | Inst::Pop(_));
+        for_each_operand(
+            &mut { *self },
+            |_| {},
+            |source| {
+                uses_stack |= *source == Source::StackTop;
+            },
+        );
+        uses_stack
+    }
+}
+
+/// Formats the instruction like the `asm` module but with typed operands.
+///
+/// Registers are written as `r0`, globals as `g0` and constants as `#0`.
+impl fmt::Display for Inst {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Inst::Add(AddInst { result, lhs, rhs }) => write!(f, "add {result}, {lhs}, {rhs}"),
+            Inst::Sub(SubInst { result, lhs, rhs }) => write!(f, "sub {result}, {lhs}, {rhs}"),
+            Inst::Mul(MulInst { result, lhs, rhs }) => write!(f, "mul {result}, {lhs}, {rhs}"),
+            Inst::Div(DivInst { result, lhs, rhs }) => write!(f, "div {result}, {lhs}, {rhs}"),
+            Inst::Rem(RemInst { result, lhs, rhs }) => write!(f, "rem {result}, {lhs}, {rhs}"),
+            Inst::And(AndInst { result, lhs, rhs }) => write!(f, "and {result}, {lhs}, {rhs}"),
+            Inst::Or(OrInst { result, lhs, rhs }) => write!(f, "or {result}, {lhs}, {rhs}"),
+            Inst::Xor(XorInst { result, lhs, rhs }) => write!(f, "xor {result}, {lhs}, {rhs}"),
+            Inst::Shl(ShlInst { result, lhs, rhs }) => write!(f, "shl {result}, {lhs}, {rhs}"),
+            Inst::ShrU(ShrUInst { result, lhs, rhs }) => write!(f, "shr_u {result}, {lhs}, {rhs}"),
+            Inst::ShrS(ShrSInst { result, lhs, rhs }) => write!(f, "shr_s {result}, {lhs}, {rhs}"),
+            Inst::Rotl(RotlInst { result, lhs, rhs }) => write!(f, "rotl {result}, {lhs}, {rhs}"),
+            Inst::Rotr(RotrInst { result, lhs, rhs }) => write!(f, "rotr {result}, {lhs}, {rhs}"),
+            Inst::Eq(EqInst { result, lhs, rhs }) => write!(f, "eq {result}, {lhs}, {rhs}"),
+            Inst::Ne(NeInst { result, lhs, rhs }) => write!(f, "ne {result}, {lhs}, {rhs}"),
+            Inst::LtU(LtUInst { result, lhs, rhs }) => write!(f, "lt_u {result}, {lhs}, {rhs}"),
+            Inst::LeU(LeUInst { result, lhs, rhs }) => write!(f, "le_u {result}, {lhs}, {rhs}"),
+            Inst::GtU(GtUInst { result, lhs, rhs }) => write!(f, "gt_u {result}, {lhs}, {rhs}"),
+            Inst::GeU(GeUInst { result, lhs, rhs }) => write!(f, "ge_u {result}, {lhs}, {rhs}"),
+            Inst::LtS(LtSInst { result, lhs, rhs }) => write!(f, "lt_s {result}, {lhs}, {rhs}"),
+            Inst::LeS(LeSInst { result, lhs, rhs }) => write!(f, "le_s {result}, {lhs}, {rhs}"),
+            Inst::GtS(GtSInst { result, lhs, rhs }) => write!(f, "gt_s {result}, {lhs}, {rhs}"),
+            Inst::GeS(GeSInst { result, lhs, rhs }) => write!(f, "ge_s {result}, {lhs}, {rhs}"),
+            Inst::Add32(Add32Inst { result, lhs, rhs }) => {
+                write!(f, "add32 {result}, {lhs}, {rhs}")
+            }
+            Inst::Sub32(Sub32Inst { result, lhs, rhs }) => {
+                write!(f, "sub32 {result}, {lhs}, {rhs}")
+            }
+            Inst::Mul32(Mul32Inst { result, lhs, rhs }) => {
+                write!(f, "mul32 {result}, {lhs}, {rhs}")
+            }
+            Inst::LtS32(LtS32Inst { result, lhs, rhs }) => {
+                write!(f, "lt_s32 {result}, {lhs}, {rhs}")
+            }
+            Inst::Shl32(Shl32Inst { result, lhs, rhs }) => {
+                write!(f, "shl32 {result}, {lhs}, {rhs}")
+            }
+            Inst::ShrU32(ShrU32Inst { result, lhs, rhs }) => {
+                write!(f, "shr_u32 {result}, {lhs}, {rhs}")
+            }
+            Inst::ShrS32(ShrS32Inst { result, lhs, rhs }) => {
+                write!(f, "shr_s32 {result}, {lhs}, {rhs}")
+            }
+            Inst::AddChecked(AddCheckedInst { result, lhs, rhs }) => {
+                write!(f, "add_checked {result}, {lhs}, {rhs}")
+            }
+            Inst::SubChecked(SubCheckedInst { result, lhs, rhs }) => {
+                write!(f, "sub_checked {result}, {lhs}, {rhs}")
+            }
+            Inst::MulChecked(MulCheckedInst { result, lhs, rhs }) => {
+                write!(f, "mul_checked {result}, {lhs}, {rhs}")
+            }
+            Inst::MulWide(MulWideInst { hi, lo, lhs, rhs }) => {
+                write!(f, "mul_wide {hi}, {lo}, {lhs}, {rhs}")
+            }
+            Inst::Not(NotInst { result, input }) => write!(f, "not {result}, {input}"),
+            Inst::Popcnt(PopcntInst { result, input }) => write!(f, "popcnt {result}, {input}"),
+            Inst::Clz(ClzInst { result, input }) => write!(f, "clz {result}, {input}"),
+            Inst::Ctz(CtzInst { result, input }) => write!(f, "ctz {result}, {input}"),
+            Inst::RandNext(RandNextInst { result }) => write!(f, "rand_next {result}"),
+            Inst::HostCall(HostCallInst { id, arg, result }) => {
+                write!(f, "host_call {result}, {id}, {arg}")
+            }
+            Inst::Push(PushInst { value }) => write!(f, "push {value}"),
+            Inst::Pop(PopInst { result }) => write!(f, "pop {result}"),
+            Inst::MinU(MinUInst { result, lhs, rhs }) => write!(f, "min_u {result}, {lhs}, {rhs}"),
+            Inst::MaxU(MaxUInst { result, lhs, rhs }) => write!(f, "max_u {result}, {lhs}, {rhs}"),
+            Inst::MinS(MinSInst { result, lhs, rhs }) => write!(f, "min_s {result}, {lhs}, {rhs}"),
+            Inst::MaxS(MaxSInst { result, lhs, rhs }) => write!(f, "max_s {result}, {lhs}, {rhs}"),
+            Inst::Select(SelectInst {
+                result,
+                cond,
+                if_nonzero,
+                if_zero,
+            }) => write!(f, "select {result}, {cond}, {if_nonzero}, {if_zero}"),
+            Inst::Move(MoveInst { result, src }) => write!(f, "mov {result}, {src}"),
+            Inst::FAdd(FAddInst { result, lhs, rhs }) => {
+                write!(f, "fadd f{}, {lhs}, {rhs}", result.into_usize())
+            }
+            Inst::FSub(FSubInst { result, lhs, rhs }) => {
+                write!(f, "fsub f{}, {lhs}, {rhs}", result.into_usize())
+            }
+            Inst::FMul(FMulInst { result, lhs, rhs }) => {
+                write!(f, "fmul f{}, {lhs}, {rhs}", result.into_usize())
+            }
+            Inst::FDiv(FDivInst { result, lhs, rhs }) => {
+                write!(f, "fdiv f{}, {lhs}, {rhs}", result.into_usize())
+            }
+            Inst::Load8(Load8Inst {
+                result,
+                base,
+                offset,
+            }) => write!(f, "load8 {result}, r{}, {offset}", base.0),
+            Inst::Load16(Load16Inst {
+                result,
+                base,
+                offset,
+            }) => write!(f, "load16 {result}, r{}, {offset}", base.0),
+            Inst::Load32(Load32Inst {
+                result,
+                base,
+                offset,
+            }) => write!(f, "load32 {result}, r{}, {offset}", base.0),
+            Inst::Load64(Load64Inst {
+                result,
+                base,
+                offset,
+            }) => write!(f, "load64 {result}, r{}, {offset}", base.0),
+            Inst::Store8(Store8Inst {
+                base,
+                offset,
+                value,
+            }) => write!(f, "store8 r{}, {offset}, {value}", base.0),
+            Inst::Store16(Store16Inst {
+                base,
+                offset,
+                value,
+            }) => write!(f, "store16 r{}, {offset}, {value}", base.0),
+            Inst::Store32(Store32Inst {
+                base,
+                offset,
+                value,
+            }) => write!(f, "store32 r{}, {offset}, {value}", base.0),
+            Inst::Store64(Store64Inst {
+                base,
+                offset,
+                value,
+            }) => write!(f, "store64 r{}, {offset}, {value}", base.0),
+            Inst::Branch(BranchInst { target }) => write!(f, "branch @{target}"),
+            Inst::BranchEqz(BranchEqzInst { target, condition }) => {
+                write!(f, "branch_eqz @{target}, {condition}")
+            }
+            Inst::BranchNez(BranchNezInst { target, condition }) => {
+                write!(f, "branch_nez @{target}, {condition}")
+            }
+            Inst::BranchEq(BranchEqInst { target, lhs, rhs }) => {
+                write!(f, "branch_eq @{target}, {lhs}, {rhs}")
+            }
+            Inst::BranchNe(BranchNeInst { target, lhs, rhs }) => {
+                write!(f, "branch_ne @{target}, {lhs}, {rhs}")
+            }
+            Inst::BranchLtU(BranchLtUInst { target, lhs, rhs }) => {
+                write!(f, "branch_lt_u @{target}, {lhs}, {rhs}")
+            }
+            Inst::BranchLtS(BranchLtSInst { target, lhs, rhs }) => {
+                write!(f, "branch_lt_s @{target}, {lhs}, {rhs}")
+            }
+            Inst::Call(CallInst { target, num_args }) => write!(f, "call @{target}, {num_args}"),
+            Inst::Nop => write!(f, "nop"),
+            Inst::Unreachable => write!(f, "unreachable"),
+            Inst::Return(ReturnInst { result }) => write!(f, "ret {result}"),
+        }
+    }
+}
+
+impl fmt::Debug for Inst {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl Execute for Inst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        match self {
+            Inst::Add(inst) => inst.execute(context),
+            Inst::Sub(inst) => inst.execute(context),
+            Inst::Mul(inst) => inst.execute(context),
+            Inst::Div(inst) => inst.execute(context),
+            Inst::Rem(inst) => inst.execute(context),
+            Inst::And(inst) => inst.execute(context),
+            Inst::Or(inst) => inst.execute(context),
+            Inst::Xor(inst) => inst.execute(context),
+            Inst::Not(inst) => inst.execute(context),
+            Inst::Shl(inst) => inst.execute(context),
+            Inst::ShrU(inst) => inst.execute(context),
+            Inst::ShrS(inst) => inst.execute(context),
+            Inst::Rotl(inst) => inst.execute(context),
+            Inst::Rotr(inst) => inst.execute(context),
+            Inst::Eq(inst) => inst.execute(context),
+            Inst::Ne(inst) => inst.execute(context),
+            Inst::LtU(inst) => inst.execute(context),
+            Inst::LeU(inst) => inst.execute(context),
+            Inst::GtU(inst) => inst.execute(context),
+            Inst::GeU(inst) => inst.execute(context),
+            Inst::LtS(inst) => inst.execute(context),
+            Inst::LeS(inst) => inst.execute(context),
+            Inst::GtS(inst) => inst.execute(context),
+            Inst::GeS(inst) => inst.execute(context),
+            Inst::Select(inst) => inst.execute(context),
+            Inst::Move(inst) => inst.execute(context),
+            Inst::Add32(inst) => inst.execute(context),
+            Inst::Sub32(inst) => inst.execute(context),
+            Inst::Mul32(inst) => inst.execute(context),
+            Inst::LtS32(inst) => inst.execute(context),
+            Inst::Shl32(inst) => inst.execute(context),
+            Inst::ShrU32(inst) => inst.execute(context),
+            Inst::ShrS32(inst) => inst.execute(context),
+            Inst::AddChecked(inst) => inst.execute(context),
+            Inst::SubChecked(inst) => inst.execute(context),
+            Inst::MulChecked(inst) => inst.execute(context),
+            Inst::MulWide(inst) => inst.execute(context),
+            Inst::Popcnt(inst) => inst.execute(context),
+            Inst::Clz(inst) => inst.execute(context),
+            Inst::Ctz(inst) => inst.execute(context),
+            Inst::RandNext(inst) => inst.execute(context),
+            Inst::HostCall(inst) => inst.execute(context),
+            Inst::Push(inst) => inst.execute(context),
+            Inst::Pop(inst) => inst.execute(context),
+            Inst::MinU(inst) => inst.execute(context),
+            Inst::MaxU(inst) => inst.execute(context),
+            Inst::MinS(inst) => inst.execute(context),
+            Inst::MaxS(inst) => inst.execute(context),
+            Inst::FAdd(inst) => inst.execute(context),
+            Inst::FSub(inst) => inst.execute(context),
+            Inst::FMul(inst) => inst.execute(context),
+            Inst::FDiv(inst) => inst.execute(context),
+            Inst::Load8(inst) => inst.execute(context),
+            Inst::Load16(inst) => inst.execute(context),
+            Inst::Load32(inst) => inst.execute(context),
+            Inst::Load64(inst) => inst.execute(context),
+            Inst::Store8(inst) => inst.execute(context),
+            Inst::Store16(inst) => inst.execute(context),
+            Inst::Store32(inst) => inst.execute(context),
+            Inst::Store64(inst) => inst.execute(context),
+            Inst::Branch(inst) => inst.execute(context),
+            Inst::BranchEqz(inst) => inst.execute(context),
+            Inst::BranchNez(inst) => inst.execute(context),
+            Inst::BranchEq(inst) => inst.execute(context),
+            Inst::BranchNe(inst) => inst.execute(context),
+            Inst::BranchLtU(inst) => inst.execute(context),
+            Inst::BranchLtS(inst) => inst.execute(context),
+            Inst::Call(inst) => inst.execute(context),
+            Inst::Nop => context.next_inst(),
+            Inst::Unreachable => Outcome::Trap(TrapCode::UnreachableExecuted),
+            Inst::Return(inst) => inst.execute(context),
+        }
+    }
+}
+
+macro_rules! impl_float_insts {
+    ( $( $inst_name:ident($op:tt) ),* $(,)? ) => {
+        $(
+            #[derive(Copy, Clone, PartialEq, Eq, Hash)]
+            pub struct $inst_name {
+                pub result: FRegister,
+                pub lhs: FSource,
+                pub rhs: FSource,
+            }
+
+            impl Execute for $inst_name {
+                fn execute(&self, context: &mut Context) -> Outcome {
+                    let lhs = self.lhs.load(context);
+                    let rhs = self.rhs.load(context);
+                    context.set_freg(self.result, lhs $op rhs);
+                    context.next_inst()
+                }
+            }
+        )*
+    };
+}
+impl_float_insts! {
+    FAddInst(+),
+    FSubInst(-),
+    FMulInst(*),
+    FDivInst(/),
+}
+
+macro_rules! impl_load_insts {
+    ( $( $inst_name:ident($load_name:ident) ),* $(,)? ) => {
+        $(
+            #[derive(Copy, Clone, PartialEq, Eq, Hash)]
+            pub struct $inst_name {
+                pub result: Sink,
+                pub base: Register,
+                pub offset: Bits,
+            }
+
+            impl Execute for $inst_name {
+                fn execute(&self, context: &mut Context) -> Outcome {
+                    let address = context.get_reg(self.base);
+                    match context.$load_name(address, self.offset) {
+                        Ok(value) => {
+                            self.result.store(context, Bits::from(value));
+                            context.next_inst()
+                        }
+                        Err(trap) => Outcome::Trap(trap),
+                    }
+                }
+            }
+        )*
+    };
+}
+impl_load_insts! {
+    Load8Inst(load8),
+    Load16Inst(load16),
+    Load32Inst(load32),
+    Load64Inst(load64),
+}
+
+macro_rules! impl_store_insts {
+    ( $( $inst_name:ident($store_name:ident as $ty:ty) ),* $(,)? ) => {
+        $(
+            #[derive(Copy, Clone, PartialEq, Eq, Hash)]
+            pub struct $inst_name {
+                pub base: Register,
+                pub offset: Bits,
+                pub value: Source,
+            }
+
+            impl Execute for $inst_name {
+                fn execute(&self, context: &mut Context) -> Outcome {
+                    let address = context.get_reg(self.base);
+                    let value = load!(self.value, context) as $ty;
+                    match context.$store_name(address, self.offset, value) {
+                        Ok(()) => context.next_inst(),
+                        Err(trap) => Outcome::Trap(trap),
+                    }
+                }
+            }
+        )*
+    };
+}
+impl_store_insts! {
+    Store8Inst(store8 as u8),
+    Store16Inst(store16 as u16),
+    Store32Inst(store32 as u32),
+    Store64Inst(store64 as u64),
+}
+
+macro_rules! impl_cmp_insts {
+    ( $( $inst_name:ident($op_name:ident $(as $ty:ty)?) ),* $(,)? ) => {
+        $(
+            #[derive(Copy, Clone, PartialEq, Eq, Hash)]
+            pub struct $inst_name {
+                pub result: Sink,
+                pub lhs: Source,
+                pub rhs: Source,
+            }
+
+            impl Execute for $inst_name {
+                fn execute(&self, context: &mut Context) -> Outcome {
+                    let lhs = load!(self.lhs, context) $(as $ty)?;
+                    let rhs = load!(self.rhs, context) $(as $ty)?;
+                    self.result.store(context, lhs.$op_name(&rhs) as u64);
+                    context.next_inst()
+                }
+            }
+        )*
+    };
+}
+impl_cmp_insts! {
+    EqInst(eq),
+    NeInst(ne),
+    LtUInst(lt),
+    LeUInst(le),
+    GtUInst(gt),
+    GeUInst(ge),
+    LtSInst(lt as i64),
+    LeSInst(le as i64),
+    GtSInst(gt as i64),
+    GeSInst(ge as i64),
+}
+
+macro_rules! impl_32_bit_insts {
+    ( $( $inst_name:ident(|$lhs:ident: $ty:ty, $rhs:ident| $eval:expr) ),* $(,)? ) => {
+        $(
+            #[derive(Copy, Clone, PartialEq, Eq, Hash)]
+            pub struct $inst_name {
+                pub result: Sink,
+                pub lhs: Source,
+                pub rhs: Source,
+            }
+
+            impl Execute for $inst_name {
+                fn execute(&self, context: &mut Context) -> Outcome {
+                    let $lhs = load!(self.lhs, context) as $ty;
+                    let $rhs = load!(self.rhs, context) as $ty;
+                    self.result.store(context, Bits::from(($eval) as u32));
+                    context.next_inst()
+                }
+            }
+        )*
+    };
+}
+impl_32_bit_insts! {
+    Add32Inst(|lhs: u32, rhs| lhs.wrapping_add(rhs)),
+    Sub32Inst(|lhs: u32, rhs| lhs.wrapping_sub(rhs)),
+    Mul32Inst(|lhs: u32, rhs| lhs.wrapping_mul(rhs)),
+    LtS32Inst(|lhs: i32, rhs| i32::from(lhs < rhs)),
+    Shl32Inst(|lhs: u32, rhs| lhs << (rhs & 0x1F)),
+    ShrU32Inst(|lhs: u32, rhs| lhs >> (rhs & 0x1F)),
+    ShrS32Inst(|lhs: i32, rhs| lhs >> (rhs & 0x1F)),
+}
+
+macro_rules! impl_checked_insts {
+    ( $( $inst_name:ident($checked_name:ident) ),* $(,)? ) => {
+        $(
+            #[derive(Copy, Clone, PartialEq, Eq, Hash)]
+            pub struct $inst_name {
+                pub result: Sink,
+                pub lhs: Source,
+                pub rhs: Source,
+            }
+
+            impl Execute for $inst_name {
+                fn execute(&self, context: &mut Context) -> Outcome {
+                    let lhs = load!(self.lhs, context);
+                    let rhs = load!(self.rhs, context);
+                    match lhs.$checked_name(rhs) {
+                        Some(value) => {
+                            self.result.store(context, value);
+                            context.next_inst()
+                        }
+                        None => Outcome::Trap(TrapCode::IntegerOverflow),
+                    }
+                }
+            }
+        )*
+    };
+}
+impl_checked_insts! {
+    AddCheckedInst(checked_add),
+    SubCheckedInst(checked_sub),
+    MulCheckedInst(checked_mul),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MulWideInst {
+    pub hi: Sink,
+    pub lo: Sink,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for MulWideInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = u128::from(load!(self.lhs, context));
+        let rhs = u128::from(load!(self.rhs, context));
+        let product = lhs * rhs;
+        self.lo.store(context, product as Bits);
+        self.hi.store(context, (product >> 64) as Bits);
+        context.next_inst()
+    }
+}
+
+macro_rules! impl_bit_count_insts {
+    ( $( $inst_name:ident($count_name:ident) ),* $(,)? ) => {
+        $(
+            #[derive(Copy, Clone, PartialEq, Eq, Hash)]
+            pub struct $inst_name {
+                pub result: Sink,
+                pub input: Source,
+            }
+
+            impl Execute for $inst_name {
+                fn execute(&self, context: &mut Context) -> Outcome {
+                    let input = load!(self.input, context);
+                    self.result.store(context, Bits::from(input.$count_name()));
+                    context.next_inst()
+                }
+            }
+        )*
+    };
+}
+impl_bit_count_insts! {
+    PopcntInst(count_ones),
+    ClzInst(leading_zeros),
+    CtzInst(trailing_zeros),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RandNextInst {
+    pub result: Sink,
+}
+
+impl Execute for RandNextInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let value = context.next_random();
+        self.result.store(context, value);
+        context.next_inst()
+    }
+}
+
+/// Calls the host function `id` of the [`Context`] with `arg` and stores its result into `result`.
+///
+/// Traps with [`TrapCode::UnknownHostFunction`] if the [`Context`] has no host function `id`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct HostCallInst {
+    pub id: u32,
+    pub arg: Source,
+    pub result: Sink,
+}
+
+impl Execute for HostCallInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let arg = load!(self.arg, context);
+        match context.call_host(self.id, arg) {
+            Ok(value) => {
+                self.result.store(context, value);
+                context.next_inst()
+            }
+            Err(trap) => Outcome::Trap(trap),
+        }
+    }
+}
+
+/// Pushes `value` onto the operand stack.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PushInst {
+    pub value: Source,
+}
+
+impl Execute for PushInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let value = load!(self.value, context);
+        context.push(value);
+        context.next_inst()
+    }
+}
+
+/// Pops the top of the operand stack into `result`.
+///
+/// Traps if the operand stack is empty.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PopInst {
+    pub result: Sink,
+}
+
+impl Execute for PopInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let value = load!(Source::StackTop, context);
+        self.result.store(context, value);
+        context.next_inst()
+    }
+}
+
+macro_rules! impl_min_max_insts {
+    ( $( $inst_name:ident($op_name:ident $(as $ty:ty)?) ),* $(,)? ) => {
+        $(
+            #[derive(Copy, Clone, PartialEq, Eq, Hash)]
+            pub struct $inst_name {
+                pub result: Sink,
+                pub lhs: Source,
+                pub rhs: Source,
+            }
+
+            impl Execute for $inst_name {
+                fn execute(&self, context: &mut Context) -> Outcome {
+                    let lhs = load!(self.lhs, context) $(as $ty)?;
+                    let rhs = load!(self.rhs, context) $(as $ty)?;
+                    self.result.store(context, Ord::$op_name(lhs, rhs) as Bits);
+                    context.next_inst()
+                }
+            }
+        )*
+    };
+}
+impl_min_max_insts! {
+    MinUInst(min),
+    MaxUInst(max),
+    MinSInst(min as i64),
+    MaxSInst(max as i64),
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AddInst {
+    pub result: Sink,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for AddInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        self.result.store(context, lhs.wrapping_add(rhs));
+        context.next_inst()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SubInst {
+    pub result: Sink,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for SubInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        self.result.store(context, lhs.wrapping_sub(rhs));
+        context.next_inst()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MulInst {
+    pub result: Sink,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for MulInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        self.result.store(context, lhs.wrapping_mul(rhs));
+        context.next_inst()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DivInst {
+    pub result: Sink,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for DivInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        match lhs.checked_div(rhs) {
+            Some(quotient) => {
+                self.result.store(context, quotient);
+                context.next_inst()
+            }
+            None => Outcome::Trap(TrapCode::DivisionByZero),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RemInst {
+    pub result: Sink,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for RemInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        match lhs.checked_rem(rhs) {
+            Some(remainder) => {
+                self.result.store(context, remainder);
+                context.next_inst()
+            }
+            None => Outcome::Trap(TrapCode::DivisionByZero),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AndInst {
+    pub result: Sink,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for AndInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        self.result.store(context, lhs & rhs);
+        context.next_inst()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct OrInst {
+    pub result: Sink,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for OrInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        self.result.store(context, lhs | rhs);
+        context.next_inst()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct XorInst {
+    pub result: Sink,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for XorInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        self.result.store(context, lhs ^ rhs);
+        context.next_inst()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct NotInst {
+    pub result: Sink,
+    pub input: Source,
+}
+
+impl Execute for NotInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let input = load!(self.input, context);
+        self.result.store(context, !input);
+        context.next_inst()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SelectInst {
+    pub result: Sink,
+    pub cond: Source,
+    pub if_nonzero: Source,
+    pub if_zero: Source,
+}
+
+impl Execute for SelectInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let cond = load!(self.cond, context);
+        let if_nonzero = load!(self.if_nonzero, context);
+        let if_zero = load!(self.if_zero, context);
+        self.result
+            .store(context, if cond != 0 { if_nonzero } else { if_zero });
+        context.next_inst()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveInst {
+    pub result: Sink,
+    pub src: Source,
+}
+
+impl Execute for MoveInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let value = load!(self.src, context);
+        self.result.store(context, value);
+        context.next_inst()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ShlInst {
+    pub result: Sink,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for ShlInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        self.result.store(context, lhs << (rhs & 0x3F));
+        context.next_inst()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ShrUInst {
+    pub result: Sink,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for ShrUInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        self.result.store(context, lhs >> (rhs & 0x3F));
+        context.next_inst()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ShrSInst {
+    pub result: Sink,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for ShrSInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        self.result
+            .store(context, ((lhs as i64) >> (rhs & 0x3F)) as Bits);
+        context.next_inst()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RotlInst {
+    pub result: Sink,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for RotlInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        self.result
+            .store(context, lhs.rotate_left((rhs & 0x3F) as u32));
+        context.next_inst()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RotrInst {
+    pub result: Sink,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for RotrInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        self.result
+            .store(context, lhs.rotate_right((rhs & 0x3F) as u32));
+        context.next_inst()
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BranchInst {
+    pub target: Target,
+}
+
+impl Execute for BranchInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        context.branch_to(self.target)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BranchEqzInst {
+    pub target: Target,
+    pub condition: Source,
+}
+
+impl Execute for BranchEqzInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let condition = load!(self.condition, context);
+        if condition == 0 {
+            context.branch_to(self.target)
+        } else {
+            context.next_inst()
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BranchNezInst {
+    pub target: Target,
+    pub condition: Source,
+}
+
+impl Execute for BranchNezInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let condition = load!(self.condition, context);
+        if condition != 0 {
+            context.branch_to(self.target)
+        } else {
+            context.next_inst()
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BranchEqInst {
+    pub target: Target,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for BranchEqInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        if lhs == rhs {
+            context.branch_to(self.target)
+        } else {
+            context.next_inst()
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BranchNeInst {
+    pub target: Target,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for BranchNeInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        if lhs != rhs {
+            context.branch_to(self.target)
+        } else {
+            context.next_inst()
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BranchLtUInst {
+    pub target: Target,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for BranchLtUInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        if lhs < rhs {
+            context.branch_to(self.target)
+        } else {
+            context.next_inst()
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct BranchLtSInst {
+    pub target: Target,
+    pub lhs: Source,
+    pub rhs: Source,
+}
+
+impl Execute for BranchLtSInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let lhs = load!(self.lhs, context);
+        let rhs = load!(self.rhs, context);
+        if (lhs as i64) < (rhs as i64) {
+            context.branch_to(self.target)
+        } else {
+            context.next_inst()
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CallInst {
+    pub target: Target,
+    pub num_args: usize,
+}
+
+impl Execute for CallInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        context.call(self.target, self.num_args)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ReturnInst {
+    pub result: Source,
+}
+
+impl Execute for ReturnInst {
+    fn execute(&self, context: &mut Context) -> Outcome {
+        let result = load!(self.result, context);
+        context.return_with(result)
+    }
+}
+
+/// Lowers the [`switch::Inst`] program to fused instructions.
+///
+/// # Errors
+///
+/// Returns the first instruction that has no counterpart.
+pub fn lower(insts: &[switch::Inst]) -> Result<Vec<Inst>, switch::Inst> {
+    insts.iter().cloned().map(Inst::try_from).collect()
+}
+
+/// Lowers a single [`switch::Inst`].
+///
+/// Fails with the instruction itself for `branch_table` and `yield` which have no counterpart.
+impl TryFrom<switch::Inst> for Inst {
+    type Error = switch::Inst;
+
+    fn try_from(inst: switch::Inst) -> Result<Self, Self::Error> {
+        let lowered = match inst {
+            switch::Inst::Add { result, lhs, rhs } => {
+                Inst::add(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::AddImm { result, src, imm } => {
+                Inst::add(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::Sub { result, lhs, rhs } => {
+                Inst::sub(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::SubImm { result, src, imm } => {
+                Inst::sub(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::Mul { result, lhs, rhs } => {
+                Inst::mul(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::MulImm { result, src, imm } => {
+                Inst::mul(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::Div { result, lhs, rhs } => {
+                Inst::div(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::DivImm { result, src, imm } => {
+                Inst::div(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::Rem { result, lhs, rhs } => {
+                Inst::rem(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::RemImm { result, src, imm } => {
+                Inst::rem(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::Shl { result, lhs, rhs } => {
+                Inst::shl(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::ShlImm { result, src, imm } => {
+                Inst::shl(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::ShrU { result, lhs, rhs } => {
+                Inst::shr_u(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::ShrUImm { result, src, imm } => {
+                Inst::shr_u(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::ShrS { result, lhs, rhs } => {
+                Inst::shr_s(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::ShrSImm { result, src, imm } => {
+                Inst::shr_s(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::Rotl { result, lhs, rhs } => {
+                Inst::rotl(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::RotlImm { result, src, imm } => {
+                Inst::rotl(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::Rotr { result, lhs, rhs } => {
+                Inst::rotr(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::RotrImm { result, src, imm } => {
+                Inst::rotr(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::Eq { result, lhs, rhs } => {
+                Inst::eq(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::EqImm { result, src, imm } => {
+                Inst::eq(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::Ne { result, lhs, rhs } => {
+                Inst::ne(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::NeImm { result, src, imm } => {
+                Inst::ne(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::LtU { result, lhs, rhs } => {
+                Inst::lt_u(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::LtUImm { result, src, imm } => {
+                Inst::lt_u(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::LtS { result, lhs, rhs } => {
+                Inst::lt_s(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::LtSImm { result, src, imm } => {
+                Inst::lt_s(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::Move { result, src } => Inst::mov(Register(result.0), Register(src.0)),
+            switch::Inst::Add32 { result, lhs, rhs } => {
+                Inst::add32(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::Sub32 { result, lhs, rhs } => {
+                Inst::sub32(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::Mul32 { result, lhs, rhs } => {
+                Inst::mul32(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::LtS32 { result, lhs, rhs } => {
+                Inst::lt_s32(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::Shl32 { result, lhs, rhs } => {
+                Inst::shl32(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::ShrU32 { result, lhs, rhs } => {
+                Inst::shr_u32(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::ShrS32 { result, lhs, rhs } => {
+                Inst::shr_s32(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::AddChecked { result, lhs, rhs } => {
+                Inst::add_checked(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::SubChecked { result, lhs, rhs } => {
+                Inst::sub_checked(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::MulChecked { result, lhs, rhs } => {
+                Inst::mul_checked(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::MulWide { hi, lo, lhs, rhs } => Inst::mul_wide(
+                Register(hi.0),
+                Register(lo.0),
+                Register(lhs.0),
+                Register(rhs.0),
+            ),
+            switch::Inst::Popcnt { result, src } => {
+                Inst::popcnt(Register(result.0), Register(src.0))
+            }
+            switch::Inst::Clz { result, src } => Inst::clz(Register(result.0), Register(src.0)),
+            switch::Inst::Ctz { result, src } => Inst::ctz(Register(result.0), Register(src.0)),
+            switch::Inst::RandNext { result } => Inst::rand_next(Register(result.0)),
+            switch::Inst::MinU { result, lhs, rhs } => {
+                Inst::min_u(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::MinUImm { result, src, imm } => {
+                Inst::min_u(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::MaxU { result, lhs, rhs } => {
+                Inst::max_u(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::MaxUImm { result, src, imm } => {
+                Inst::max_u(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::MinS { result, lhs, rhs } => {
+                Inst::min_s(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::MinSImm { result, src, imm } => {
+                Inst::min_s(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::MaxS { result, lhs, rhs } => {
+                Inst::max_s(Register(result.0), Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::MaxSImm { result, src, imm } => {
+                Inst::max_s(Register(result.0), Register(src.0), Const(imm))
+            }
+            switch::Inst::Select {
+                result,
+                cond,
+                if_nonzero,
+                if_zero,
+            } => Inst::select(
+                Register(result.0),
+                Register(cond.0),
+                Register(if_nonzero.0),
+                Register(if_zero.0),
+            ),
+            switch::Inst::FAdd { result, lhs, rhs } => {
+                Inst::fadd(FRegister(result.0), FRegister(lhs.0), FRegister(rhs.0))
+            }
+            switch::Inst::FSub { result, lhs, rhs } => {
+                Inst::fsub(FRegister(result.0), FRegister(lhs.0), FRegister(rhs.0))
+            }
+            switch::Inst::FMul { result, lhs, rhs } => {
+                Inst::fmul(FRegister(result.0), FRegister(lhs.0), FRegister(rhs.0))
+            }
+            switch::Inst::FDiv { result, lhs, rhs } => {
+                Inst::fdiv(FRegister(result.0), FRegister(lhs.0), FRegister(rhs.0))
+            }
+            switch::Inst::Load8 {
+                result,
+                base,
+                offset,
+            } => Inst::load8(Register(result.0), Register(base.0), offset),
+            switch::Inst::Load16 {
+                result,
+                base,
+                offset,
+            } => Inst::load16(Register(result.0), Register(base.0), offset),
+            switch::Inst::Load32 {
+                result,
+                base,
+                offset,
+            } => Inst::load32(Register(result.0), Register(base.0), offset),
+            switch::Inst::Load64 {
+                result,
+                base,
+                offset,
+            } => Inst::load64(Register(result.0), Register(base.0), offset),
+            switch::Inst::Store8 {
+                base,
+                offset,
+                value,
+            } => Inst::store8(Register(base.0), offset, Register(value.0)),
+            switch::Inst::Store16 {
+                base,
+                offset,
+                value,
+            } => Inst::store16(Register(base.0), offset, Register(value.0)),
+            switch::Inst::Store32 {
+                base,
+                offset,
+                value,
+            } => Inst::store32(Register(base.0), offset, Register(value.0)),
+            switch::Inst::Store64 {
+                base,
+                offset,
+                value,
+            } => Inst::store64(Register(base.0), offset, Register(value.0)),
+            switch::Inst::Branch { target } => Inst::branch(target.0),
+            switch::Inst::BranchEqz { target, condition } => {
+                Inst::branch_eqz(target.0, Register(condition.0))
+            }
+            switch::Inst::BranchNez { target, condition } => {
+                Inst::branch_nez(target.0, Register(condition.0))
+            }
+            switch::Inst::BranchEq { target, lhs, rhs } => {
+                Inst::branch_eq(target.0, Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::BranchNe { target, lhs, rhs } => {
+                Inst::branch_ne(target.0, Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::BranchLtU { target, lhs, rhs } => {
+                Inst::branch_lt_u(target.0, Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::BranchLtS { target, lhs, rhs } => {
+                Inst::branch_lt_s(target.0, Register(lhs.0), Register(rhs.0))
+            }
+            switch::Inst::Call { target, num_args } => Inst::call(target.0, num_args),
+            switch::Inst::Nop => Inst::Nop,
+            switch::Inst::Unreachable => Inst::Unreachable,
+            switch::Inst::Return { result } => Inst::ret(Register(result.0)),
+            unsupported @ (switch::Inst::BranchTable { .. } | switch::Inst::Yield) => {
+                return Err(unsupported)
+            }
+        };
+        Ok(lowered)
+    }
+}
+
+/// Raises a fused instruction back to its [`switch::Inst`].
+///
+/// Fails with the instruction itself if it has no `switch` counterpart, e.g. because it
+/// uses a [`Sink::Global`], [`Source::Global`], [`Source::StackTop`] or constant operand
+/// that `switch` can only encode as the right-hand side immediate.
+impl TryFrom<Inst> for switch::Inst {
+    type Error = Inst;
+
+    fn try_from(inst: Inst) -> Result<Self, Self::Error> {
+        raise_inst(inst).ok_or(inst)
+    }
+}
+
+/// Returns the [`switch::Inst`] equivalent to `inst` if there is any.
+fn raise_inst(inst: Inst) -> Option<switch::Inst> {
+    /// Returns the `switch` register of `sink` unless it is a global.
+    fn sink(sink: Sink) -> Option<crate::Register> {
+        match sink {
+            Sink::Register(register) => Some(crate::Register(register.0)),
+            Sink::Global(_) => None,
+        }
+    }
+    /// Returns the `switch` register of `source` if it is a register.
+    fn register(source: Source) -> Option<crate::Register> {
+        match source {
+            Source::Register(register) => Some(crate::Register(register.0)),
+            Source::Const(_) | Source::Global(_) | Source::StackTop => None,
+        }
+    }
+    /// Returns the `switch` float register of `source` if it is a register.
+    fn fregister(source: FSource) -> Option<crate::Register> {
+        match source {
+            FSource::FRegister(register) => Some(crate::Register(register.0)),
+            FSource::FConst(_) => None,
+        }
+    }
+    /// Uses `reg` for a register and `imm` for a constant right-hand side operand.
+    fn binary(
+        result: Sink,
+        lhs: Source,
+        rhs: Source,
+        reg: fn(crate::Register, crate::Register, crate::Register) -> switch::Inst,
+        imm: fn(crate::Register, crate::Register, Bits) -> switch::Inst,
+    ) -> Option<switch::Inst> {
+        let (result, lhs) = (sink(result)?, register(lhs)?);
+        match rhs {
+            Source::Const(rhs) => Some(imm(result, lhs, rhs.into_bits())),
+            rhs => Some(reg(result, lhs, register(rhs)?)),
+        }
+    }
+    let raised = match inst {
+        Inst::Add(AddInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::Add { result, lhs, rhs },
+            |result, src, imm| switch::Inst::AddImm { result, src, imm },
+        )?,
+        Inst::Sub(SubInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::Sub { result, lhs, rhs },
+            |result, src, imm| switch::Inst::SubImm { result, src, imm },
+        )?,
+        Inst::Mul(MulInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::Mul { result, lhs, rhs },
+            |result, src, imm| switch::Inst::MulImm { result, src, imm },
+        )?,
+        Inst::Div(DivInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::Div { result, lhs, rhs },
+            |result, src, imm| switch::Inst::DivImm { result, src, imm },
+        )?,
+        Inst::Rem(RemInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::Rem { result, lhs, rhs },
+            |result, src, imm| switch::Inst::RemImm { result, src, imm },
+        )?,
+        Inst::Shl(ShlInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::Shl { result, lhs, rhs },
+            |result, src, imm| switch::Inst::ShlImm { result, src, imm },
+        )?,
+        Inst::ShrU(ShrUInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::ShrU { result, lhs, rhs },
+            |result, src, imm| switch::Inst::ShrUImm { result, src, imm },
+        )?,
+        Inst::ShrS(ShrSInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::ShrS { result, lhs, rhs },
+            |result, src, imm| switch::Inst::ShrSImm { result, src, imm },
+        )?,
+        Inst::Rotl(RotlInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::Rotl { result, lhs, rhs },
+            |result, src, imm| switch::Inst::RotlImm { result, src, imm },
+        )?,
+        Inst::Rotr(RotrInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::Rotr { result, lhs, rhs },
+            |result, src, imm| switch::Inst::RotrImm { result, src, imm },
+        )?,
+        Inst::Eq(EqInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::Eq { result, lhs, rhs },
+            |result, src, imm| switch::Inst::EqImm { result, src, imm },
+        )?,
+        Inst::Ne(NeInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::Ne { result, lhs, rhs },
+            |result, src, imm| switch::Inst::NeImm { result, src, imm },
+        )?,
+        Inst::LtU(LtUInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::LtU { result, lhs, rhs },
+            |result, src, imm| switch::Inst::LtUImm { result, src, imm },
+        )?,
+        Inst::LtS(LtSInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::LtS { result, lhs, rhs },
+            |result, src, imm| switch::Inst::LtSImm { result, src, imm },
+        )?,
+        Inst::MinU(MinUInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::MinU { result, lhs, rhs },
+            |result, src, imm| switch::Inst::MinUImm { result, src, imm },
+        )?,
+        Inst::MaxU(MaxUInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::MaxU { result, lhs, rhs },
+            |result, src, imm| switch::Inst::MaxUImm { result, src, imm },
+        )?,
+        Inst::MinS(MinSInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::MinS { result, lhs, rhs },
+            |result, src, imm| switch::Inst::MinSImm { result, src, imm },
+        )?,
+        Inst::MaxS(MaxSInst { result, lhs, rhs }) => binary(
+            result,
+            lhs,
+            rhs,
+            |result, lhs, rhs| switch::Inst::MaxS { result, lhs, rhs },
+            |result, src, imm| switch::Inst::MaxSImm { result, src, imm },
+        )?,
+        Inst::Add32(Add32Inst { result, lhs, rhs }) => switch::Inst::Add32 {
+            result: sink(result)?,
+            lhs: register(lhs)?,
+            rhs: register(rhs)?,
+        },
+        Inst::Sub32(Sub32Inst { result, lhs, rhs }) => switch::Inst::Sub32 {
+            result: sink(result)?,
+            lhs: register(lhs)?,
+            rhs: register(rhs)?,
+        },
+        Inst::Mul32(Mul32Inst { result, lhs, rhs }) => switch::Inst::Mul32 {
+            result: sink(result)?,
+            lhs: register(lhs)?,
+            rhs: register(rhs)?,
+        },
+        Inst::LtS32(LtS32Inst { result, lhs, rhs }) => switch::Inst::LtS32 {
+            result: sink(result)?,
+            lhs: register(lhs)?,
+            rhs: register(rhs)?,
+        },
+        Inst::Shl32(Shl32Inst { result, lhs, rhs }) => switch::Inst::Shl32 {
+            result: sink(result)?,
+            lhs: register(lhs)?,
+            rhs: register(rhs)?,
+        },
+        Inst::ShrU32(ShrU32Inst { result, lhs, rhs }) => switch::Inst::ShrU32 {
+            result: sink(result)?,
+            lhs: register(lhs)?,
+            rhs: register(rhs)?,
+        },
+        Inst::ShrS32(ShrS32Inst { result, lhs, rhs }) => switch::Inst::ShrS32 {
+            result: sink(result)?,
+            lhs: register(lhs)?,
+            rhs: register(rhs)?,
+        },
+        Inst::AddChecked(AddCheckedInst { result, lhs, rhs }) => switch::Inst::AddChecked {
+            result: sink(result)?,
+            lhs: register(lhs)?,
+            rhs: register(rhs)?,
+        },
+        Inst::SubChecked(SubCheckedInst { result, lhs, rhs }) => switch::Inst::SubChecked {
+            result: sink(result)?,
+            lhs: register(lhs)?,
+            rhs: register(rhs)?,
+        },
+        Inst::MulChecked(MulCheckedInst { result, lhs, rhs }) => switch::Inst::MulChecked {
+            result: sink(result)?,
+            lhs: register(lhs)?,
+            rhs: register(rhs)?,
+        },
+        Inst::Move(MoveInst { result, src }) => switch::Inst::Move {
+            result: sink(result)?,
+            src: register(src)?,
+        },
+        Inst::Popcnt(PopcntInst { result, input }) => switch::Inst::Popcnt {
+            result: sink(result)?,
+            src: register(input)?,
+        },
+        Inst::Clz(ClzInst { result, input }) => switch::Inst::Clz {
+            result: sink(result)?,
+            src: register(input)?,
+        },
+        Inst::Ctz(CtzInst { result, input }) => switch::Inst::Ctz {
+            result: sink(result)?,
+            src: register(input)?,
+        },
+        Inst::RandNext(RandNextInst { result }) => switch::Inst::RandNext {
+            result: sink(result)?,
+        },
+        Inst::MulWide(MulWideInst { hi, lo, lhs, rhs }) => switch::Inst::MulWide {
+            hi: sink(hi)?,
+            lo: sink(lo)?,
+            lhs: register(lhs)?,
+            rhs: register(rhs)?,
+        },
+        Inst::Select(SelectInst {
+            result,
+            cond,
+            if_nonzero,
+            if_zero,
+        }) => switch::Inst::Select {
+            result: sink(result)?,
+            cond: register(cond)?,
+            if_nonzero: register(if_nonzero)?,
+            if_zero: register(if_zero)?,
+        },
+        Inst::FAdd(FAddInst { result, lhs, rhs }) => switch::Inst::FAdd {
+            result: crate::Register(result.0),
+            lhs: fregister(lhs)?,
+            rhs: fregister(rhs)?,
+        },
+        Inst::FSub(FSubInst { result, lhs, rhs }) => switch::Inst::FSub {
+            result: crate::Register(result.0),
+            lhs: fregister(lhs)?,
+            rhs: fregister(rhs)?,
+        },
+        Inst::FMul(FMulInst { result, lhs, rhs }) => switch::Inst::FMul {
+            result: crate::Register(result.0),
+            lhs: fregister(lhs)?,
+            rhs: fregister(rhs)?,
+        },
+        Inst::FDiv(FDivInst { result, lhs, rhs }) => switch::Inst::FDiv {
+            result: crate::Register(result.0),
+            lhs: fregister(lhs)?,
+            rhs: fregister(rhs)?,
+        },
+        Inst::Load8(Load8Inst {
+            result,
+            base,
+            offset,
+        }) => switch::Inst::Load8 {
+            result: sink(result)?,
+            base: crate::Register(base.0),
+            offset,
+        },
+        Inst::Load16(Load16Inst {
+            result,
+            base,
+            offset,
+        }) => switch::Inst::Load16 {
+            result: sink(result)?,
+            base: crate::Register(base.0),
+            offset,
+        },
+        Inst::Load32(Load32Inst {
+            result,
+            base,
+            offset,
+        }) => switch::Inst::Load32 {
+            result: sink(result)?,
+            base: crate::Register(base.0),
+            offset,
+        },
+        Inst::Load64(Load64Inst {
+            result,
+            base,
+            offset,
+        }) => switch::Inst::Load64 {
+            result: sink(result)?,
+            base: crate::Register(base.0),
+            offset,
+        },
+        Inst::Store8(Store8Inst {
+            base,
+            offset,
+            value,
+        }) => switch::Inst::Store8 {
+            base: crate::Register(base.0),
+            offset,
+            value: register(value)?,
+        },
+        Inst::Store16(Store16Inst {
+            base,
+            offset,
+            value,
+        }) => switch::Inst::Store16 {
+            base: crate::Register(base.0),
+            offset,
+            value: register(value)?,
+        },
+        Inst::Store32(Store32Inst {
+            base,
+            offset,
+            value,
+        }) => switch::Inst::Store32 {
+            base: crate::Register(base.0),
+            offset,
+            value: register(value)?,
+        },
+        Inst::Store64(Store64Inst {
+            base,
+            offset,
+            value,
+        }) => switch::Inst::Store64 {
+            base: crate::Register(base.0),
+            offset,
+            value: register(value)?,
+        },
+        Inst::Branch(BranchInst { target }) => switch::Inst::Branch {
+            target: crate::Target(target),
+        },
+        Inst::BranchEqz(BranchEqzInst { target, condition }) => switch::Inst::BranchEqz {
+            target: crate::Target(target),
+            condition: register(condition)?,
+        },
+        Inst::BranchNez(BranchNezInst { target, condition }) => switch::Inst::BranchNez {
+            target: crate::Target(target),
+            condition: register(condition)?,
+        },
+        Inst::BranchEq(BranchEqInst { target, lhs, rhs }) => switch::Inst::BranchEq {
+            target: crate::Target(target),
+            lhs: register(lhs)?,
+            rhs: register(rhs)?,
+        },
+        Inst::BranchNe(BranchNeInst { target, lhs, rhs }) => switch::Inst::BranchNe {
+            target: crate::Target(target),
+            lhs: register(lhs)?,
+            rhs: register(rhs)?,
+        },
+        Inst::BranchLtU(BranchLtUInst { target, lhs, rhs }) => switch::Inst::BranchLtU {
+            target: crate::Target(target),
+            lhs: register(lhs)?,
+            rhs: register(rhs)?,
+        },
+        Inst::BranchLtS(BranchLtSInst { target, lhs, rhs }) => switch::Inst::BranchLtS {
+            target: crate::Target(target),
+            lhs: register(lhs)?,
+            rhs: register(rhs)?,
+        },
+        Inst::Call(CallInst { target, num_args }) => switch::Inst::Call {
+            target: crate::Target(target),
+            num_args,
+        },
+        Inst::Nop => switch::Inst::Nop,
+        Inst::Unreachable => switch::Inst::Unreachable,
+        Inst::Return(ReturnInst { result }) => switch::Inst::Return {
+            result: register(result)?,
+        },
+        Inst::And(_)
+        | Inst::Or(_)
+        | Inst::Xor(_)
+        | Inst::Not(_)
+        | Inst::LeU(_)
+        | Inst::GtU(_)
+        | Inst::GeU(_)
+        | Inst::LeS(_)
+        | Inst::GtS(_)
+        | Inst::GeS(_)
+        | Inst::HostCall(_)
+        | Inst::Push(_)
+        | Inst::Pop(_) => return None,
+    };
+    Some(raised)
+}
+
+/// Folds instructions whose operands are all [`Source::Const`].
+///
+/// Folded computations are replaced by a move of their constant result into their [`Sink`]
+/// and branches on a constant condition by unconditional branches or fallthroughs.
+/// Instructions are never removed so that all branch targets stay intact.
+pub fn const_fold(insts: &[Inst]) -> Vec<Inst> {
+    insts
+        .iter()
+        .enumerate()
+        .map(|(pc, inst)| const_fold_inst(pc, inst))
+        .collect()
+}
+
+/// Folds the instruction `inst` at `pc`.
+fn const_fold_inst(pc: Target, inst: &Inst) -> Inst {
+    match *inst {
+        Inst::BranchEqz(BranchEqzInst {
+            target,
+            condition: Source::Const(condition),
+        }) => match condition.into_bits() {
+            0 => Inst::branch(target),
+            _ => Inst::branch(pc + 1),
+        },
+        Inst::BranchNez(BranchNezInst {
+            target,
+            condition: Source::Const(condition),
+        }) => match condition.into_bits() {
+            0 => Inst::branch(pc + 1),
+            _ => Inst::branch(target),
+        },
+        // A folded `select` would skip popping its unselected operand.
+        _ if inst.uses_stack() => *inst,
+        _ => match evaluate_const(inst) {
+            Some((result, value)) => Inst::mov(result, Const(value)),
+            None => *inst,
+        },
+    }
+}
+
+/// Returns the constant of `source` if any.
+fn as_const(source: Source) -> Option<Bits> {
+    match source {
+        Source::Const(constant) => Some(constant.into_bits()),
+        Source::Register(_) | Source::Global(_) | Source::StackTop => None,
+    }
+}
+
+/// Evaluates `inst` if all its operands are constant and its evaluation does not trap.
+///
+/// Returns the [`Sink`] of `inst` together with the computed value.
+fn evaluate_const(inst: &Inst) -> Option<(Sink, Bits)> {
+    let binary = |result: Sink, lhs: Source, rhs: Source, op: fn(Bits, Bits) -> Option<Bits>| {
+        Some((result, op(as_const(lhs)?, as_const(rhs)?)?))
+    };
+    match *inst {
+        Inst::Add(AddInst { result, lhs, rhs }) => {
+            binary(result, lhs, rhs, |lhs, rhs| Some(lhs.wrapping_add(rhs)))
+        }
+        Inst::Sub(SubInst { result, lhs, rhs }) => {
+            binary(result, lhs, rhs, |lhs, rhs| Some(lhs.wrapping_sub(rhs)))
+        }
+        Inst::Mul(MulInst { result, lhs, rhs }) => {
+            binary(result, lhs, rhs, |lhs, rhs| Some(lhs.wrapping_mul(rhs)))
+        }
+        Inst::Div(DivInst { result, lhs, rhs }) => binary(result, lhs, rhs, Bits::checked_div),
+        Inst::Rem(RemInst { result, lhs, rhs }) => binary(result, lhs, rhs, Bits::checked_rem),
+        Inst::And(AndInst { result, lhs, rhs }) => {
+            binary(result, lhs, rhs, |lhs, rhs| Some(lhs & rhs))
+        }
+        Inst::Or(OrInst { result, lhs, rhs }) => {
+            binary(result, lhs, rhs, |lhs, rhs| Some(lhs | rhs))
+        }
+        Inst::Xor(XorInst { result, lhs, rhs }) => {
+            binary(result, lhs, rhs, |lhs, rhs| Some(lhs ^ rhs))
+        }
+        Inst::Not(NotInst { result, input }) => Some((result, !as_const(input)?)),
+        Inst::Shl(ShlInst { result, lhs, rhs }) => {
+            binary(result, lhs, rhs, |lhs, rhs| Some(lhs << (rhs & 0x3F)))
+        }
+        Inst::ShrU(ShrUInst { result, lhs, rhs }) => {
+            binary(result, lhs, rhs, |lhs, rhs| Some(lhs >> (rhs & 0x3F)))
+        }
+        Inst::ShrS(ShrSInst { result, lhs, rhs }) => binary(result, lhs, rhs, |lhs, rhs| {
+            Some(((lhs as i64) >> (rhs & 0x3F)) as Bits)
+        }),
+        Inst::Rotl(RotlInst { result, lhs, rhs }) => binary(result, lhs, rhs, |lhs, rhs| {
+            Some(lhs.rotate_left((rhs & 0x3F) as u32))
+        }),
+        Inst::Rotr(RotrInst { result, lhs, rhs }) => binary(result, lhs, rhs, |lhs, rhs| {
+            Some(lhs.rotate_right((rhs & 0x3F) as u32))
+        }),
+        Inst::Eq(EqInst { result, lhs, rhs }) => {
+            binary(result, lhs, rhs, |lhs, rhs| Some(Bits::from(lhs == rhs)))
+        }
+        Inst::Ne(NeInst { result, lhs, rhs }) => {
+            binary(result, lhs, rhs, |lhs, rhs| Some(Bits::from(lhs != rhs)))
+        }
+        Inst::LtU(LtUInst { result, lhs, rhs }) => binary(result, lhs, rhs, |lhs, rhs| {
+            Some(Bits::from(cmp_u(lhs, rhs).is_lt()))
+        }),
+        Inst::LeU(LeUInst { result, lhs, rhs }) => binary(result, lhs, rhs, |lhs, rhs| {
+            Some(Bits::from(cmp_u(lhs, rhs).is_le()))
+        }),
+        Inst::GtU(GtUInst { result, lhs, rhs }) => binary(result, lhs, rhs, |lhs, rhs| {
+            Some(Bits::from(cmp_u(lhs, rhs).is_gt()))
+        }),
+        Inst::GeU(GeUInst { result, lhs, rhs }) => binary(result, lhs, rhs, |lhs, rhs| {
+            Some(Bits::from(cmp_u(lhs, rhs).is_ge()))
+        }),
+        Inst::LtS(LtSInst { result, lhs, rhs }) => binary(result, lhs, rhs, |lhs, rhs| {
+            Some(Bits::from(cmp_s(lhs, rhs).is_lt()))
+        }),
+        Inst::LeS(LeSInst { result, lhs, rhs }) => binary(result, lhs, rhs, |lhs, rhs| {
+            Some(Bits::from(cmp_s(lhs, rhs).is_le()))
+        }),
+        Inst::GtS(GtSInst { result, lhs, rhs }) => binary(result, lhs, rhs, |lhs, rhs| {
+            Some(Bits::from(cmp_s(lhs, rhs).is_gt()))
+        }),
+        Inst::GeS(GeSInst { result, lhs, rhs }) => binary(result, lhs, rhs, |lhs, rhs| {
+            Some(Bits::from(cmp_s(lhs, rhs).is_ge()))
+        }),
+        Inst::MinU(MinUInst { result, lhs, rhs }) => {
+            binary(result, lhs, rhs, |lhs, rhs| Some(lhs.min(rhs)))
+        }
+        Inst::MaxU(MaxUInst { result, lhs, rhs }) => {
+            binary(result, lhs, rhs, |lhs, rhs| Some(lhs.max(rhs)))
+        }
+        Inst::MinS(MinSInst { result, lhs, rhs }) => binary(result, lhs, rhs, |lhs, rhs| {
+            Some((lhs as i64).min(rhs as i64) as Bits)
+        }),
+        Inst::MaxS(MaxSInst { result, lhs, rhs }) => binary(result, lhs, rhs, |lhs, rhs| {
+            Some((lhs as i64).max(rhs as i64) as Bits)
+        }),
+        Inst::Select(SelectInst {
+            result,
+            cond,
+            if_nonzero,
+            if_zero,
+        }) => {
+            let selected = if as_const(cond)? != 0 {
+                if_nonzero
+            } else {
+                if_zero
+            };
+            Some((result, as_const(selected)?))
+        }
+        _ => None,
+    }
+}
+
+/// Rewrites arithmetic with an identity or absorbing [`Source::Const`] operand into a [`MoveInst`].
+///
+/// `add x, y, 0`, `sub x, y, 0` and `mul x, y, 1` become `move x, y`
+/// and `mul x, y, 0` becomes `move x, 0`. The commutative `add` and `mul` are
+/// recognized with the constant on either side. Like [`const_fold`] instructions are
+/// never removed so that all branch targets stay intact.
+pub fn strength_reduce(insts: &[Inst]) -> Vec<Inst> {
+    insts.iter().map(strength_reduce_inst).collect()
+}
+
+/// Strength reduces the instruction `inst`.
+fn strength_reduce_inst(inst: &Inst) -> Inst {
+    let reduced = match *inst {
+        // A reduced `mul x, top, 0` would skip popping its operand.
+        _ if inst.uses_stack() => None,
+        Inst::Add(AddInst { result, lhs, rhs }) => match (as_const(lhs), as_const(rhs)) {
+            (_, Some(0)) => Some(Inst::mov(result, lhs)),
+            (Some(0), _) => Some(Inst::mov(result, rhs)),
+            _ => None,
+        },
+        Inst::Sub(SubInst { result, lhs, rhs }) => match as_const(rhs) {
+            Some(0) => Some(Inst::mov(result, lhs)),
+            _ => None,
+        },
+        Inst::Mul(MulInst { result, lhs, rhs }) => match (as_const(lhs), as_const(rhs)) {
+            (_, Some(0)) | (Some(0), _) => Some(Inst::mov(result, Const(0))),
+            (_, Some(1)) => Some(Inst::mov(result, lhs)),
+            (Some(1), _) => Some(Inst::mov(result, rhs)),
+            _ => None,
+        },
+        _ => None,
+    };
+    reduced.unwrap_or(*inst)
+}
+
+/// Removes stores to registers that are overwritten within the same basic block before being read.
+///
+/// Only instructions that neither trap nor branch are considered for removal and
+/// every branch or call target conservatively starts a new basic block.
+/// The targets of all remaining branches and calls are adjusted to the removed instructions
+/// while out of bounds targets are kept as they are.
+pub fn eliminate_dead_stores(insts: &[Inst]) -> Vec<Inst> {
+    let mut leaders = vec![false; insts.len() + 1];
+    for inst in insts {
+        if let Some(leader) = branch_target(inst).and_then(|target| leaders.get_mut(target)) {
+            *leader = true;
+        }
+    }
+    let mut dead = vec![false; insts.len()];
+    let mut overwritten = Vec::new();
+    for (pc, inst) in insts.iter().enumerate().rev() {
+        if leaders[pc + 1] {
+            overwritten.clear();
+        }
+        let Some((result, sources)) = pure_operands(inst) else {
+            overwritten.clear();
+            continue;
+        };
+        if let Sink::Register(Register(result)) = result {
+            if overwritten.contains(&result) {
+                dead[pc] = true;
+                continue;
+            }
+            overwritten.push(result);
+        }
+        for source in sources {
+            if let Source::Register(Register(register)) = source {
+                overwritten.retain(|&overwritten| overwritten != register);
+            }
+        }
+    }
+    let mut remap = Vec::with_capacity(insts.len() + 1);
+    let mut live = 0;
+    for &dead in &dead {
+        remap.push(live);
+        live += usize::from(!dead);
+    }
+    remap.push(live);
+    let remap = |target: usize| remap.get(target).copied().unwrap_or(target);
+    insts
+        .iter()
+        .zip(&dead)
+        .filter(|(_, &dead)| !dead)
+        .map(|(inst, _)| match *inst {
+            Inst::Branch(BranchInst { target }) => Inst::branch(remap(target)),
+            Inst::BranchEqz(BranchEqzInst { target, condition }) => {
+                Inst::branch_eqz(remap(target), condition)
+            }
+            Inst::BranchNez(BranchNezInst { target, condition }) => {
+                Inst::branch_nez(remap(target), condition)
+            }
+            Inst::BranchEq(BranchEqInst { target, lhs, rhs }) => {
+                Inst::branch_eq(remap(target), lhs, rhs)
+            }
+            Inst::BranchNe(BranchNeInst { target, lhs, rhs }) => {
+                Inst::branch_ne(remap(target), lhs, rhs)
+            }
+            Inst::BranchLtU(BranchLtUInst { target, lhs, rhs }) => {
+                Inst::branch_lt_u(remap(target), lhs, rhs)
+            }
+            Inst::BranchLtS(BranchLtSInst { target, lhs, rhs }) => {
+                Inst::branch_lt_s(remap(target), lhs, rhs)
+            }
+            Inst::Call(CallInst { target, num_args }) => Inst::call(remap(target), num_args),
+            inst => inst,
+        })
+        .collect()
+}
+
+/// Returns the instruction index that `inst` may continue at other than the next instruction.
+fn branch_target(inst: &Inst) -> Option<Target> {
+    match *inst {
+        Inst::Branch(BranchInst { target })
+        | Inst::BranchEqz(BranchEqzInst { target, .. })
+        | Inst::BranchNez(BranchNezInst { target, .. })
+        | Inst::BranchEq(BranchEqInst { target, .. })
+        | Inst::BranchNe(BranchNeInst { target, .. })
+        | Inst::BranchLtU(BranchLtUInst { target, .. })
+        | Inst::BranchLtS(BranchLtSInst { target, .. })
+        | Inst::Call(CallInst { target, .. }) => Some(target),
+        _ => None,
+    }
+}
+
+/// Returns the [`Sink`] and the [`Source`]s of `inst` if it neither traps, branches nor pops the operand stack.
+fn pure_operands(inst: &Inst) -> Option<(Sink, Vec<Source>)> {
+    let operands = match *inst {
+        Inst::Add(AddInst { result, lhs, rhs })
+        | Inst::Sub(SubInst { result, lhs, rhs })
+        | Inst::Mul(MulInst { result, lhs, rhs })
+        | Inst::And(AndInst { result, lhs, rhs })
+        | Inst::Or(OrInst { result, lhs, rhs })
+        | Inst::Xor(XorInst { result, lhs, rhs })
+        | Inst::Shl(ShlInst { result, lhs, rhs })
+        | Inst::ShrU(ShrUInst { result, lhs, rhs })
+        | Inst::ShrS(ShrSInst { result, lhs, rhs })
+        | Inst::Rotl(RotlInst { result, lhs, rhs })
+        | Inst::Rotr(RotrInst { result, lhs, rhs })
+        | Inst::Eq(EqInst { result, lhs, rhs })
+        | Inst::Ne(NeInst { result, lhs, rhs })
+        | Inst::LtU(LtUInst { result, lhs, rhs })
+        | Inst::LeU(LeUInst { result, lhs, rhs })
+        | Inst::GtU(GtUInst { result, lhs, rhs })
+        | Inst::GeU(GeUInst { result, lhs, rhs })
+        | Inst::LtS(LtSInst { result, lhs, rhs })
+        | Inst::LeS(LeSInst { result, lhs, rhs })
+        | Inst::GtS(GtSInst { result, lhs, rhs })
+        | Inst::GeS(GeSInst { result, lhs, rhs })
+        | Inst::Add32(Add32Inst { result, lhs, rhs })
+        | Inst::Sub32(Sub32Inst { result, lhs, rhs })
+        | Inst::Mul32(Mul32Inst { result, lhs, rhs })
+        | Inst::LtS32(LtS32Inst { result, lhs, rhs })
+        | Inst::Shl32(Shl32Inst { result, lhs, rhs })
+        | Inst::ShrU32(ShrU32Inst { result, lhs, rhs })
+        | Inst::ShrS32(ShrS32Inst { result, lhs, rhs })
+        | Inst::MinU(MinUInst { result, lhs, rhs })
+        | Inst::MaxU(MaxUInst { result, lhs, rhs })
+        | Inst::MinS(MinSInst { result, lhs, rhs })
+        | Inst::MaxS(MaxSInst { result, lhs, rhs }) => Some((result, vec![lhs, rhs])),
+        Inst::Not(NotInst { result, input })
+        | Inst::Popcnt(PopcntInst { result, input })
+        | Inst::Clz(ClzInst { result, input })
+        | Inst::Ctz(CtzInst { result, input }) => Some((result, vec![input])),
+        Inst::Move(MoveInst { result, src }) => Some((result, vec![src])),
+        Inst::Select(SelectInst {
+            result,
+            cond,
+            if_nonzero,
+            if_zero,
+        }) => Some((result, vec![cond, if_nonzero, if_zero])),
+        _ => None,
+    };
+    // Loading a `Source::StackTop` pops the operand stack.
+    operands.filter(|(_, sources)| !sources.contains(&Source::StackTop))
+}
+
+/// An error that prevents [`allocate`] from spilling the registers of a program.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AllocError {
+    /// The instruction at `index` is a call whose frame would share the spill slots of its caller.
+    Call { index: usize },
+    /// The `base` register of the load or store at `index` is beyond the budget.
+    SpilledBase { index: usize },
+}
+
+/// Returns the `base` register of `inst` if it loads or stores memory.
+fn memory_base(inst: &Inst) -> Option<Register> {
+    match inst {
+        Inst::Load8(Load8Inst { base, .. })
+        | Inst::Load16(Load16Inst { base, .. })
+        | Inst::Load32(Load32Inst { base, .. })
+        | Inst::Load64(Load64Inst { base, .. })
+        | Inst::Store8(Store8Inst { base, .. })
+        | Inst::Store16(Store16Inst { base, .. })
+        | Inst::Store32(Store32Inst { base, .. })
+        | Inst::Store64(Store64Inst { base, .. }) => Some(*base),
+        _ => None,
+    }
+}
+
+/// Rewrites all register operands beyond the budget of `num_regs` registers into globals.
+///
+/// `Register(num_regs + n)` is spilled into the `n`-th global after the globals used by `insts`.
+/// Instructions are rewritten in place so that all branch targets stay intact.
+///
+/// # Errors
+///
+/// - If the `base` register of a load or store is beyond the budget since it cannot be spilled.
+/// - If spilling is required but `insts` contains a call since globals are not windowed
+///   and thus every call frame would share the same spill slots.
+pub fn allocate(insts: &[Inst], num_regs: usize) -> Result<Vec<Inst>, AllocError> {
+    if let Some(index) = insts
+        .iter()
+        .position(|inst| memory_base(inst).is_some_and(|base| base.0 >= num_regs))
+    {
+        return Err(AllocError::SpilledBase { index });
+    }
+    let (mut sink_slots, mut source_slots) = (0, 0);
+    let (mut sink_spills, mut source_spills) = (false, false);
+    for inst in insts {
+        let mut inst = *inst;
+        for_each_operand(
+            &mut inst,
+            |sink| match *sink {
+                Sink::Global(Global(global)) => sink_slots = sink_slots.max(global + 1),
+                Sink::Register(Register(register)) => sink_spills |= register >= num_regs,
+            },
+            |source| match *source {
+                Source::Global(Global(global)) => source_slots = source_slots.max(global + 1),
+                Source::Register(Register(register)) => source_spills |= register >= num_regs,
+                _ => {}
+            },
+        );
+    }
+    if sink_spills || source_spills {
+        if let Some(index) = insts.iter().position(|inst| matches!(inst, Inst::Call(_))) {
+            return Err(AllocError::Call { index });
+        }
+    }
+    let first_slot = sink_slots.max(source_slots);
+    let spill = |register: usize| Global(first_slot + register - num_regs);
+    let allocated = insts
+        .iter()
+        .map(|inst| {
+            let mut inst = *inst;
+            for_each_operand(
+                &mut inst,
+                |sink| {
+                    if let Sink::Register(Register(register)) = *sink {
+                        if register >= num_regs {
+                            *sink = Sink::Global(spill(register));
+                        }
+                    }
+                },
+                |source| {
+                    if let Source::Register(Register(register)) = *source {
+                        if register >= num_regs {
+                            *source = Source::Global(spill(register));
+                        }
+                    }
+                },
+            );
+            inst
+        })
+        .collect();
+    Ok(allocated)
+}
+
+/// Calls `sink` and `source` for every [`Sink`] and [`Source`] operand of `inst`.
+fn for_each_operand(
+    inst: &mut Inst,
+    mut sink: impl FnMut(&mut Sink),
+    mut source: impl FnMut(&mut Source),
+) {
+    match inst {
+        Inst::Add(AddInst { result, lhs, rhs })
+        | Inst::Sub(SubInst { result, lhs, rhs })
+        | Inst::Mul(MulInst { result, lhs, rhs })
+        | Inst::Div(DivInst { result, lhs, rhs })
+        | Inst::Rem(RemInst { result, lhs, rhs })
+        | Inst::And(AndInst { result, lhs, rhs })
+        | Inst::Or(OrInst { result, lhs, rhs })
+        | Inst::Xor(XorInst { result, lhs, rhs })
+        | Inst::Shl(ShlInst { result, lhs, rhs })
+        | Inst::ShrU(ShrUInst { result, lhs, rhs })
+        | Inst::ShrS(ShrSInst { result, lhs, rhs })
+        | Inst::Rotl(RotlInst { result, lhs, rhs })
+        | Inst::Rotr(RotrInst { result, lhs, rhs })
+        | Inst::Eq(EqInst { result, lhs, rhs })
+        | Inst::Ne(NeInst { result, lhs, rhs })
+        | Inst::LtU(LtUInst { result, lhs, rhs })
+        | Inst::LeU(LeUInst { result, lhs, rhs })
+        | Inst::GtU(GtUInst { result, lhs, rhs })
+        | Inst::GeU(GeUInst { result, lhs, rhs })
+        | Inst::LtS(LtSInst { result, lhs, rhs })
+        | Inst::LeS(LeSInst { result, lhs, rhs })
+        | Inst::GtS(GtSInst { result, lhs, rhs })
+        | Inst::GeS(GeSInst { result, lhs, rhs })
+        | Inst::Add32(Add32Inst { result, lhs, rhs })
+        | Inst::Sub32(Sub32Inst { result, lhs, rhs })
+        | Inst::Mul32(Mul32Inst { result, lhs, rhs })
+        | Inst::LtS32(LtS32Inst { result, lhs, rhs })
+        | Inst::Shl32(Shl32Inst { result, lhs, rhs })
+        | Inst::ShrU32(ShrU32Inst { result, lhs, rhs })
+        | Inst::ShrS32(ShrS32Inst { result, lhs, rhs })
+        | Inst::AddChecked(AddCheckedInst { result, lhs, rhs })
+        | Inst::SubChecked(SubCheckedInst { result, lhs, rhs })
+        | Inst::MulChecked(MulCheckedInst { result, lhs, rhs })
+        | Inst::MinU(MinUInst { result, lhs, rhs })
+        | Inst::MaxU(MaxUInst { result, lhs, rhs })
+        | Inst::MinS(MinSInst { result, lhs, rhs })
+        | Inst::MaxS(MaxSInst { result, lhs, rhs }) => {
+            sink(result);
+            source(lhs);
+            source(rhs);
+        }
+        Inst::MulWide(MulWideInst { hi, lo, lhs, rhs }) => {
+            sink(hi);
+            sink(lo);
+            source(lhs);
+            source(rhs);
+        }
+        Inst::Not(NotInst { result, input })
+        | Inst::Popcnt(PopcntInst { result, input })
+        | Inst::Clz(ClzInst { result, input })
+        | Inst::Ctz(CtzInst { result, input }) => {
+            sink(result);
+            source(input);
+        }
+        Inst::Move(MoveInst { result, src }) => {
+            sink(result);
+            source(src);
+        }
+        Inst::RandNext(RandNextInst { result }) | Inst::Pop(PopInst { result }) => sink(result),
+        Inst::HostCall(HostCallInst { arg, result, .. }) => {
+            sink(result);
+            source(arg);
+        }
+        Inst::Push(PushInst { value }) => source(value),
+        Inst::Select(SelectInst {
+            result,
+            cond,
+            if_nonzero,
+            if_zero,
+        }) => {
+            sink(result);
+            source(cond);
+            source(if_nonzero);
+            source(if_zero);
+        }
+        Inst::Load8(Load8Inst { result, .. })
+        | Inst::Load16(Load16Inst { result, .. })
+        | Inst::Load32(Load32Inst { result, .. })
+        | Inst::Load64(Load64Inst { result, .. }) => sink(result),
+        Inst::Store8(Store8Inst { value, .. })
+        | Inst::Store16(Store16Inst { value, .. })
+        | Inst::Store32(Store32Inst { value, .. })
+        | Inst::Store64(Store64Inst { value, .. }) => source(value),
+        Inst::BranchEqz(BranchEqzInst { condition, .. })
+        | Inst::BranchNez(BranchNezInst { condition, .. }) => source(condition),
+        Inst::BranchEq(BranchEqInst { lhs, rhs, .. })
+        | Inst::BranchNe(BranchNeInst { lhs, rhs, .. })
+        | Inst::BranchLtU(BranchLtUInst { lhs, rhs, .. })
+        | Inst::BranchLtS(BranchLtSInst { lhs, rhs, .. }) => {
+            source(lhs);
+            source(rhs);
+        }
+        Inst::Return(ReturnInst { result }) => source(result),
+        Inst::FAdd(_)
+        | Inst::FSub(_)
+        | Inst::FMul(_)
+        | Inst::FDiv(_)
+        | Inst::Branch(_)
+        | Inst::Call(_)
+        | Inst::Nop
+        | Inst::Unreachable => {}
+    }
+}
+
+/// Interns the instructions of `insts`.
+///
+/// Returns the stream of indices into the table of unique instructions together with the table.
+/// Equal instructions share the same index and the table is ordered by first occurrence.
+#[cfg(feature = "std")]
+pub fn intern(insts: &[Inst]) -> (Vec<u32>, Vec<Inst>) {
+    let mut table = Vec::new();
+    let mut indices = HashMap::new();
+    let stream = insts
+        .iter()
+        .map(|inst| {
+            *indices.entry(*inst).or_insert_with(|| {
+                let index = u32::try_from(table.len()).expect("too many unique instructions");
+                table.push(*inst);
+                index
+            })
+        })
+        .collect();
+    (stream, table)
+}
+
+/// Appends [`Inst::Nop`] to `insts` until it holds `len` instructions.
+///
+/// Since the padding is appended at the end all branch targets of `insts` stay intact.
+/// Does nothing if `insts` already holds at least `len` instructions.
+pub fn pad_to(insts: &mut Vec<Inst>, len: usize) {
+    if insts.len() < len {
+        insts.resize(len, Inst::Nop);
+    }
+}
+
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+///
+/// Bounds checks the `pc` just like [`run`] which it delegates to.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Result<Bits, TrapCode> {
+    run(insts, context)
+}
+
+/// Runs the list of instruction like [`run`] but dispatches at most `fuel` instructions.
+///
+/// # Errors
+///
+/// If the execution traps, the `pc` leaves the bounds of `insts` or `fuel` is exhausted
+/// before the execution returns.
+pub fn execute_with_fuel(
+    insts: &[Inst],
+    context: &mut Context,
+    mut fuel: u64,
+) -> Result<Bits, TrapCode> {
+    loop {
+        fuel = fuel.checked_sub(1).ok_or(TrapCode::OutOfFuel)?;
+        let pc = context.pc;
+        let inst = insts.get(pc).ok_or(TrapCode::OutOfBoundsPc)?;
+        match inst.execute(context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return => return Ok(context.get_reg_abs(0)),
+            Outcome::Trap(trap) => return Err(trap),
+        }
+    }
+}
+
+/// Runs the list of instruction using the given [`Context`] and returns the result.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn run(insts: &[Inst], context: &mut Context) -> Result<Bits, TrapCode> {
+    loop {
+        let pc = context.pc;
+        let inst = insts.get(pc).ok_or(TrapCode::OutOfBoundsPc)?;
+        match inst.execute(context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return => return Ok(context.get_reg_abs(0)),
+            Outcome::Trap(trap) => return Err(trap),
+        }
+    }
+}
+
+/// Returns the `counter_loop` benchmark program counting `repetitions` down to zero.
+pub(crate) fn counter_loop_insts(repetitions: Bits) -> Vec<Inst> {
+    vec![
+        // Store `repetitions` into r0.
+        // Note: r0 is our loop counter register.
         Inst::add(Register(0), Register(0), Const(repetitions)),
         // Branch to the end if r0 is zero.
         Inst::branch_eqz(4, Register(0)),
@@ -304,7 +3367,989 @@ fn counter_loop() {
         Inst::branch(1),
         // Return value and end function execution.
         Inst::ret(Register(0)),
+    ]
+}
+
+#[test]
+fn counter_loop() {
+    let insts = counter_loop_insts(100_000_000);
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, Ok(0));
+}
+
+#[test]
+fn counter_loop_nez() {
+    let repetitions = 100_000_000;
+    let insts = vec![
+        // Store `repetitions` into r0.
+        // Note: r0 is our loop counter register.
+        Inst::add(Register(0), Register(0), Const(repetitions)),
+        // Decrease r0 by 1.
+        Inst::sub(Register(0), Register(0), Const(1)),
+        // Jump back to the loop header if r0 is not zero.
+        Inst::branch_nez(1, Register(0)),
+        // Return value and end function execution.
+        Inst::ret(Register(0)),
+    ];
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, Ok(0));
+}
+
+#[test]
+fn div_rem() {
+    let insts = vec![
+        // Store `17` into r0 and `5` into g0.
+        Inst::add(Register(0), Register(0), Const(17)),
+        Inst::add(Global(0), Global(0), Const(5)),
+        // Divide and take the remainder of r0 by g0.
+        Inst::div(Register(1), Register(0), Global(0)),
+        Inst::rem(Register(2), Register(0), Global(0)),
+        // Divide and take the remainder of r0 by `4`.
+        Inst::div(Register(3), Register(0), Const(4)),
+        Inst::rem(Register(4), Register(0), Const(4)),
+        // Return value and end function execution.
+        Inst::ret(Register(1)),
+    ];
+    let mut context = Context::default();
+    execute(&insts, &mut context).unwrap();
+    assert_eq!(context.get_reg(Register(0)), 17 / 5);
+    assert_eq!(context.get_reg(Register(2)), 17 % 5);
+    assert_eq!(context.get_reg(Register(3)), 17 / 4);
+    assert_eq!(context.get_reg(Register(4)), 17 % 4);
+}
+
+#[test]
+fn div_rem_by_zero() {
+    let mut context = Context::default();
+    context.set_reg(Register(0), 17);
+    for inst in [
+        Inst::div(Register(1), Register(0), Register(2)),
+        Inst::div(Register(1), Register(0), Const(0)),
+        Inst::rem(Register(1), Register(0), Register(2)),
+        Inst::rem(Register(1), Register(0), Const(0)),
+    ] {
+        assert!(matches!(
+            inst.execute(&mut context),
+            Outcome::Trap(TrapCode::DivisionByZero)
+        ));
+    }
+}
+
+#[test]
+fn run_traps() {
+    let insts = [Inst::ret(Const(42))];
+    assert_eq!(run(&insts, &mut Context::default()), Ok(42));
+    let insts = [
+        Inst::add(Register(0), Register(0), Const(42)),
+        Inst::div(Register(0), Register(0), Global(0)),
+        Inst::ret(Register(0)),
+    ];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::DivisionByZero)
+    );
+    let insts = [Inst::branch(2), Inst::ret(Register(0))];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::OutOfBoundsPc)
+    );
+}
+
+#[test]
+fn bitwise() {
+    let insts = vec![
+        // Store the bit patterns into r0 and g0.
+        Inst::add(Register(0), Const(0), Const(0b1100)),
+        Inst::add(Global(0), Const(0), Const(0b1010)),
+        // Combine r0 and g0 bitwise.
+        Inst::and(Register(1), Register(0), Global(0)),
+        Inst::or(Global(1), Global(0), Register(0)),
+        Inst::xor(Register(2), Register(0), Const(0b1010)),
+        Inst::not(Register(3), Global(0)),
+        Inst::ret(Global(1)),
+    ];
+    let mut context = Context::default();
+    assert_eq!(run(&insts, &mut context), Ok(0b1110));
+    assert_eq!(context.get_reg(Register(1)), 0b1000);
+    assert_eq!(context.get_reg(Register(2)), 0b0110);
+    assert_eq!(context.get_reg(Register(3)), !0b1010);
+}
+
+#[test]
+fn shifts() {
+    for (value, amount) in crate::switch::SHIFT_INPUTS {
+        let insts = [
+            Inst::shl(Register(2), Register(0), Global(0)),
+            Inst::shr_u(Register(3), Register(0), Const(amount)),
+            Inst::shr_s(Global(1), Register(0), Global(0)),
+            Inst::rotl(Register(5), Register(0), Const(amount)),
+            Inst::rotr(Register(6), Const(value), Global(0)),
+            Inst::ret(Register(2)),
+        ];
+        let mut context = Context::default();
+        context.set_reg(Register(0), value);
+        context.set_global(Global(0), amount);
+        assert_eq!(
+            run(&insts, &mut context),
+            Ok(value.wrapping_shl(amount as u32))
+        );
+        assert_eq!(
+            context.get_reg(Register(3)),
+            value.wrapping_shr(amount as u32)
+        );
+        assert_eq!(
+            context.get_global(Global(1)),
+            (value as i64).wrapping_shr(amount as u32) as Bits
+        );
+        assert_eq!(
+            context.get_reg(Register(5)),
+            value.rotate_left(amount as u32)
+        );
+        assert_eq!(
+            context.get_reg(Register(6)),
+            value.rotate_right(amount as u32)
+        );
+    }
+}
+
+#[test]
+fn ordering_comparisons() {
+    let minus_one = -1_i64 as Bits;
+    let insts = [
+        Inst::lt_u(Register(1), Register(0), Const(1)),
+        Inst::le_u(Register(2), Register(0), Const(1)),
+        Inst::gt_u(Register(3), Register(0), Const(1)),
+        Inst::ge_u(Register(4), Register(0), Const(1)),
+        Inst::lt_s(Register(5), Register(0), Const(1)),
+        Inst::le_s(Register(6), Register(0), Const(1)),
+        Inst::gt_s(Register(7), Register(0), Const(1)),
+        Inst::ge_s(Register(8), Const(minus_one), Register(0)),
+        Inst::ret(Register(1)),
+    ];
+    let mut context = Context::default();
+    context.set_reg(Register(0), minus_one);
+    assert_eq!(run(&insts, &mut context), Ok(0));
+    assert_eq!(context.get_reg(Register(2)), 0);
+    assert_eq!(context.get_reg(Register(3)), 1);
+    assert_eq!(context.get_reg(Register(4)), 1);
+    assert_eq!(context.get_reg(Register(5)), 1);
+    assert_eq!(context.get_reg(Register(6)), 1);
+    assert_eq!(context.get_reg(Register(7)), 0);
+    assert_eq!(context.get_reg(Register(8)), 1);
+}
+
+#[test]
+fn select_max() {
+    let insts = [
+        Inst::lt_u(Register(2), Register(0), Register(1)),
+        Inst::select(Register(0), Register(2), Register(1), Register(0)),
+        Inst::ret(Register(0)),
+    ];
+    for (a, b) in [(0, 0), (3, 7), (7, 3), (42, 42), (Bits::MAX, 5)] {
+        let mut context = Context::default();
+        context.set_reg(Register(0), a);
+        context.set_reg(Register(1), b);
+        assert_eq!(run(&insts, &mut context), Ok(a.max(b)));
+    }
+    let insts = [
+        Inst::select(Register(0), Const(0), Const(1), Const(2)),
+        Inst::ret(Register(0)),
+    ];
+    assert_eq!(run(&insts, &mut Context::default()), Ok(2));
+}
+
+#[test]
+fn fuel() {
+    let insts = [Inst::branch(0)];
+    assert_eq!(
+        execute_with_fuel(&insts, &mut Context::default(), 1_000),
+        Err(TrapCode::OutOfFuel)
+    );
+    let insts = counter_loop_insts(100);
+    let mut context = Context::default();
+    assert_eq!(execute_with_fuel(&insts, &mut context, 1_000), Ok(0));
+    assert_eq!(
+        execute_with_fuel(&insts, &mut Context::default(), 10),
+        Err(TrapCode::OutOfFuel)
+    );
+}
+
+#[test]
+fn unreachable() {
+    let insts = [Inst::ret(Const(42)), Inst::Unreachable];
+    assert_eq!(run(&insts, &mut Context::default()), Ok(42));
+    let insts = [Inst::branch(2), Inst::ret(Const(42)), Inst::Unreachable];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::UnreachableExecuted)
+    );
+}
+
+#[test]
+fn memory() {
+    let insts = [
+        Inst::add(Register(1), Register(1), Const(100)),
+        Inst::store64(Register(1), 8, Const(0x1122_3344_5566_7788)),
+        Inst::load8(Register(2), Register(1), 8),
+        Inst::load16(Register(3), Register(1), 8),
+        Inst::load32(Register(4), Register(1), 8),
+        Inst::load64(Global(0), Register(1), 8),
+        Inst::store8(Register(1), 8, Const(0xFFFF)),
+        Inst::load64(Register(0), Register(1), 8),
+        Inst::ret(Register(0)),
+    ];
+    let mut context = Context::default();
+    assert_eq!(run(&insts, &mut context), Ok(0x1122_3344_5566_77FF));
+    assert_eq!(context.get_reg(Register(2)), 0x88);
+    assert_eq!(context.get_reg(Register(3)), 0x7788);
+    assert_eq!(context.get_reg(Register(4)), 0x5566_7788);
+    assert_eq!(context.get_global(Global(0)), 0x1122_3344_5566_7788);
+}
+
+#[test]
+fn memory_out_of_bounds() {
+    let insts = [
+        Inst::add(
+            Register(1),
+            Register(1),
+            Const(crate::MEMORY_SIZE as Bits - 1),
+        ),
+        Inst::load16(Register(0), Register(1), 0),
+        Inst::ret(Register(0)),
+    ];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::OutOfBoundsMemory)
+    );
+    let insts = [
+        Inst::store8(Register(1), Bits::MAX, Const(1)),
+        Inst::ret(Register(0)),
+    ];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::OutOfBoundsMemory)
+    );
+}
+
+#[test]
+fn factorial() {
+    let insts = [
+        // Compute the factorial of 20 and return it.
+        Inst::call(2, 1),
+        Inst::ret(Register(0)),
+        // fn factorial(r0) -> r0
+        //
+        // Return 1 if r0 is zero.
+        Inst::branch_nez(4, Register(0)),
+        Inst::ret(Const(1)),
+        // Otherwise return r0 * factorial(r0 - 1).
+        Inst::add(Register(1), Register(0), Const(0)),
+        Inst::sub(Register(0), Register(0), Const(1)),
+        Inst::call(2, 1),
+        Inst::mul(Register(0), Register(0), Register(1)),
+        Inst::ret(Register(0)),
+    ];
+    let mut context = Context::default();
+    context.set_reg(Register(0), 20);
+    assert_eq!(run(&insts, &mut context), Ok(2_432_902_008_176_640_000));
+}
+
+#[test]
+fn trap_in_call() {
+    let insts = [
+        Inst::add(Register(0), Const(7), Const(0)),
+        Inst::call(3, 0),
+        Inst::ret(Register(0)),
+        // fn trap() -> r0
+        Inst::add(Register(0), Const(42), Const(0)),
+        Inst::Unreachable,
+    ];
+    let mut context = Context::default();
+    assert_eq!(
+        execute(&insts, &mut context),
+        Err(TrapCode::UnreachableExecuted)
+    );
+    assert_eq!(context.get_reg_abs(0), 7);
+}
+
+#[test]
+fn call_stack_overflow() {
+    let insts = [Inst::call(0, 0), Inst::ret(Register(0))];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::CallStackOverflow)
+    );
+}
+
+#[test]
+fn float_nan() {
+    let insts = [
+        Inst::fdiv(FRegister(0), FConst(0.0), FConst(0.0)),
+        Inst::fdiv(FRegister(1), FConst(1.0), FConst(0.0)),
+        Inst::fsub(FRegister(2), FRegister(1), FRegister(1)),
+        Inst::fadd(FRegister(3), FRegister(0), FConst(1.0)),
+        Inst::ret(Register(0)),
+    ];
+    let mut context = Context::default();
+    assert_eq!(run(&insts, &mut context), Ok(0));
+    assert!(context.get_freg(FRegister(0)).is_nan());
+    assert_eq!(context.get_freg(FRegister(1)), f64::INFINITY);
+    assert!(context.get_freg(FRegister(2)).is_nan());
+    assert!(context.get_freg(FRegister(3)).is_nan());
+}
+
+#[test]
+fn float_dot_product() {
+    let insts = [
+        // f0 = [1.5, 2.0, -3.0] . [4.0, 0.5, 2.0]
+        Inst::fmul(FRegister(1), FConst(1.5), FConst(4.0)),
+        Inst::fadd(FRegister(0), FRegister(0), FRegister(1)),
+        Inst::fmul(FRegister(1), FConst(2.0), FConst(0.5)),
+        Inst::fadd(FRegister(0), FRegister(0), FRegister(1)),
+        Inst::fmul(FRegister(1), FConst(-3.0), FConst(2.0)),
+        Inst::fadd(FRegister(0), FRegister(0), FRegister(1)),
+        Inst::ret(Register(0)),
+    ];
+    let mut context = Context::default();
+    assert_eq!(run(&insts, &mut context), Ok(0));
+    assert_eq!(context.get_freg(FRegister(0)), 1.0);
+}
+
+#[test]
+fn lower_matches_switch() {
+    let programs = [
+        switch::counter_loop_insts(1_000),
+        switch::more_comps_insts(1_000),
+        vec![
+            switch::Inst::AddImm {
+                result: crate::Register(0),
+                src: crate::Register(0),
+                imm: 7,
+            },
+            switch::Inst::SubImm {
+                result: crate::Register(1),
+                src: crate::Register(0),
+                imm: 10,
+            },
+            switch::Inst::MulImm {
+                result: crate::Register(0),
+                src: crate::Register(1),
+                imm: 3,
+            },
+            switch::Inst::BranchEqz {
+                target: crate::Target(5),
+                condition: crate::Register(0),
+            },
+            switch::Inst::AddImm {
+                result: crate::Register(0),
+                src: crate::Register(0),
+                imm: 1,
+            },
+            switch::Inst::Return {
+                result: crate::Register(0),
+            },
+        ],
+    ];
+    for program in &programs {
+        let insts = lower(program).ok().unwrap();
+        assert_eq!(
+            run(&insts, &mut Context::default()),
+            switch::run(program, &mut crate::Context::default()),
+        );
+    }
+}
+
+#[test]
+fn switch_round_trip() {
+    let program = switch::more_comps_insts(1_000);
+    let insts = program
+        .iter()
+        .cloned()
+        .map(Inst::try_from)
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let raised = insts
+        .iter()
+        .map(|inst| switch::Inst::try_from(*inst))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(raised, program);
+    let global = Inst::add(Global(0), Register(0), Register(1));
+    assert_eq!(switch::Inst::try_from(global), Err(global));
+    let from_global = Inst::add(Register(0), Global(0), Register(1));
+    assert_eq!(switch::Inst::try_from(from_global), Err(from_global));
+    assert_eq!(
+        Inst::try_from(switch::Inst::Yield),
+        Err(switch::Inst::Yield)
+    );
+}
+
+#[test]
+fn const_fold_arithmetic() {
+    let insts = [
+        Inst::add(Register(0), Const(2), Const(3)),
+        Inst::mul(Register(1), Const(4), Const(5)),
+        Inst::lt_s(Register(2), Const(-1_i64 as Bits), Const(0)),
+        Inst::add(Register(0), Register(0), Register(1)),
+        Inst::add(Register(0), Register(0), Register(2)),
+        Inst::ret(Register(0)),
+    ];
+    let folded = const_fold(&insts);
+    assert!(matches!(
+        folded[0],
+        Inst::Move(MoveInst {
+            src: Source::Const(Const(5)),
+            ..
+        })
+    ));
+    assert!(matches!(
+        folded[1],
+        Inst::Move(MoveInst {
+            src: Source::Const(Const(20)),
+            ..
+        })
+    ));
+    assert!(matches!(
+        folded[2],
+        Inst::Move(MoveInst {
+            src: Source::Const(Const(1)),
+            ..
+        })
+    ));
+    assert!(matches!(
+        folded[3],
+        Inst::Add(AddInst {
+            lhs: Source::Register(Register(0)),
+            ..
+        })
+    ));
+    assert_eq!(run(&folded, &mut Context::default()), Ok(26));
+    assert_eq!(
+        run(&folded, &mut Context::default()),
+        run(&insts, &mut Context::default())
+    );
+}
+
+#[test]
+fn const_fold_keeps_traps() {
+    let insts = [
+        Inst::div(Register(0), Const(1), Const(0)),
+        Inst::ret(Register(0)),
+    ];
+    let folded = const_fold(&insts);
+    assert!(matches!(folded[0], Inst::Div(_)));
+    assert_eq!(
+        run(&folded, &mut Context::default()),
+        Err(TrapCode::DivisionByZero)
+    );
+}
+
+#[test]
+fn const_fold_branches() {
+    let insts = [
+        Inst::branch_eqz(3, Const(0)),
+        Inst::branch_nez(3, Const(0)),
+        Inst::ret(Const(1)),
+        Inst::branch_eqz(2, Const(1)),
+        Inst::ret(Const(2)),
+    ];
+    let folded = const_fold(&insts);
+    assert!(matches!(folded[0], Inst::Branch(BranchInst { target: 3 })));
+    assert!(matches!(folded[1], Inst::Branch(BranchInst { target: 2 })));
+    assert!(matches!(folded[3], Inst::Branch(BranchInst { target: 4 })));
+    assert_eq!(run(&folded, &mut Context::default()), Ok(2));
+    assert_eq!(
+        run(&folded, &mut Context::default()),
+        run(&insts, &mut Context::default())
+    );
+}
+
+#[test]
+fn compare_branches() {
+    let insts = [
+        Inst::add(Register(0), Register(0), Const(1)),
+        Inst::branch_lt_u(0, Register(0), Const(10)),
+        Inst::branch_ne(6, Register(0), Const(10)),
+        // Taken since `-1 < 10` for signed integers.
+        Inst::branch_lt_s(5, Const(-1_i64 as Bits), Register(0)),
+        Inst::Unreachable,
+        Inst::branch_eq(7, Global(0), Const(0)),
+        Inst::Unreachable,
+        Inst::ret(Register(0)),
+    ];
+    assert_eq!(run(&insts, &mut Context::default()), Ok(10));
+    assert_eq!(
+        insts[3].to_string(),
+        "branch_lt_s @5, #18446744073709551615, r0"
+    );
+}
+
+#[test]
+fn strength_reduce_identities() {
+    let reduced = |inst: Inst| strength_reduce(&[inst])[0];
+    let mov = |src: Source| Inst::mov(Register(0), src);
+    let r1 = Source::Register(Register(1));
+    let g2 = Source::Global(Global(2));
+    assert_eq!(
+        reduced(Inst::add(Register(0), Register(1), Const(0))),
+        mov(r1)
+    );
+    assert_eq!(
+        reduced(Inst::add(Register(0), Const(0), Global(2))),
+        mov(g2)
+    );
+    assert_eq!(
+        reduced(Inst::sub(Register(0), Register(1), Const(0))),
+        mov(r1)
+    );
+    assert_eq!(
+        reduced(Inst::mul(Register(0), Register(1), Const(1))),
+        mov(r1)
+    );
+    assert_eq!(
+        reduced(Inst::mul(Register(0), Const(1), Global(2))),
+        mov(g2)
+    );
+    assert_eq!(
+        reduced(Inst::mul(Register(0), Register(1), Const(0))),
+        mov(Source::Const(Const(0)))
+    );
+    assert_eq!(
+        reduced(Inst::mul(Register(0), Const(0), Register(1))),
+        mov(Source::Const(Const(0)))
+    );
+}
+
+#[test]
+fn strength_reduce_keeps_others() {
+    let insts = [
+        Inst::add(Register(0), Register(1), Const(1)),
+        Inst::sub(Register(0), Const(0), Register(1)),
+        Inst::sub(Register(0), Register(1), Const(1)),
+        Inst::mul(Register(0), Register(1), Const(2)),
+        Inst::mul(Register(0), Register(1), Register(0)),
+        Inst::mul(Register(0), Register(1), Global(0)),
+        Inst::mul(Register(0), Source::StackTop, Const(0)),
+        Inst::div(Register(0), Register(1), Const(1)),
+        Inst::ret(Register(0)),
+    ];
+    assert_eq!(strength_reduce(&insts), insts);
+}
+
+#[test]
+fn strength_reduce_preserves_results() {
+    let insts = [
+        Inst::add(Register(1), Register(1), Const(7)),
+        Inst::add(Register(2), Const(0), Register(1)),
+        Inst::mul(Register(2), Register(2), Const(1)),
+        Inst::sub(Register(3), Register(2), Const(0)),
+        Inst::mul(Register(1), Register(1), Const(0)),
+        Inst::add(Register(0), Register(3), Register(1)),
+        Inst::ret(Register(0)),
+    ];
+    let reduced = strength_reduce(&insts);
+    assert_eq!(
+        run(&reduced, &mut Context::default()),
+        run(&insts, &mut Context::default())
+    );
+    assert_eq!(run(&reduced, &mut Context::default()), Ok(7));
+}
+
+#[test]
+fn move_sources() {
+    let insts = [
+        Inst::mov(Register(1), Const(42)),
+        Inst::mov(Global(0), Register(1)),
+        Inst::mov(Register(0), Global(0)),
+        Inst::ret(Register(0)),
+    ];
+    let mut context = Context::default();
+    assert_eq!(run(&insts, &mut context), Ok(42));
+    assert_eq!(context.get_global(Global(0)), 42);
+}
+
+#[test]
+fn eliminate_overwritten_store() {
+    let insts = [
+        // The store into r0 is overwritten before it is read.
+        Inst::mov(Register(0), Const(1)),
+        Inst::mov(Register(0), Const(3)),
+        // Loop header.
+        Inst::sub(Register(0), Register(0), Const(1)),
+        Inst::branch_nez(2, Register(0)),
+        Inst::ret(Register(0)),
+    ];
+    let optimized = eliminate_dead_stores(&insts);
+    assert_eq!(optimized.len(), 4);
+    assert!(matches!(
+        optimized[0],
+        Inst::Move(MoveInst {
+            src: Source::Const(Const(3)),
+            ..
+        })
+    ));
+    assert!(matches!(
+        optimized[2],
+        Inst::BranchNez(BranchNezInst { target: 1, .. })
+    ));
+    assert_eq!(
+        run(&optimized, &mut Context::default()),
+        run(&insts, &mut Context::default())
+    );
+}
+
+#[test]
+fn eliminate_with_out_of_bounds_target() {
+    let insts = [
+        Inst::mov(Register(0), Const(1)),
+        Inst::mov(Register(0), Const(2)),
+        // Never taken since r1 is zero.
+        Inst::branch_nez(10, Register(1)),
+        Inst::ret(Register(0)),
+    ];
+    let optimized = eliminate_dead_stores(&insts);
+    assert_eq!(optimized.len(), 3);
+    assert!(matches!(
+        optimized[1],
+        Inst::BranchNez(BranchNezInst { target: 10, .. })
+    ));
+    assert_eq!(run(&optimized, &mut Context::default()), Ok(2));
+}
+
+#[test]
+fn keep_store_before_branch_target() {
+    let insts = [
+        Inst::mov(Register(2), Const(2)),
+        // Kept since the overwriting instruction starts another basic block.
+        Inst::mov(Register(1), Const(5)),
+        // Loop header.
+        Inst::mov(Register(1), Const(6)),
+        Inst::add(Register(0), Register(0), Register(1)),
+        Inst::sub(Register(2), Register(2), Const(1)),
+        Inst::branch_nez(2, Register(2)),
+        Inst::ret(Register(0)),
+    ];
+    let optimized = eliminate_dead_stores(&insts);
+    assert_eq!(optimized.len(), insts.len());
+    assert_eq!(run(&optimized, &mut Context::default()), Ok(12));
+}
+
+#[test]
+fn arithmetic_32() {
+    let insts = [
+        // Overflow wraps at `u32::MAX`.
+        Inst::add32(Register(1), Const(0xFFFF_FFFF), Const(2)),
+        // High bits of the inputs are ignored and cleared in the result.
+        Inst::mul32(
+            Register(2),
+            Const(0xFFFF_0000_0000_0003),
+            Const(0x5555_5556),
+        ),
+        Inst::sub32(Register(3), Register(1), Const(0xDEAD_0000_0000_0002)),
+        Inst::shr_s32(Register(4), Const(0xFFFF_FFFF_8000_0000), Const(4)),
+        Inst::lt_s32(Register(5), Const(0xFFFF_FFFF), Const(0)),
+        Inst::ret(Register(0)),
+    ];
+    let mut context = Context::default();
+    assert_eq!(run(&insts, &mut context), Ok(0));
+    assert_eq!(context.get_reg(Register(1)), 1);
+    assert_eq!(context.get_reg(Register(2)), 2);
+    assert_eq!(context.get_reg(Register(3)), 0xFFFF_FFFF);
+    assert_eq!(context.get_reg(Register(4)), 0xF800_0000);
+    assert_eq!(context.get_reg(Register(5)), 1);
+}
+
+#[test]
+fn checked_arithmetic() {
+    let insts = [
+        Inst::sub_checked(Register(0), Const(Bits::MAX), Const(1)),
+        Inst::add_checked(Register(0), Register(0), Const(1)),
+        Inst::mul_checked(Register(1), Register(0), Const(2)),
+        Inst::ret(Register(1)),
+    ];
+    let mut context = Context::default();
+    assert_eq!(run(&insts, &mut context), Err(TrapCode::IntegerOverflow));
+    assert_eq!(context.get_reg(Register(0)), Bits::MAX);
+    assert_eq!(context.pc, 2);
+    assert_eq!(
+        run(&insts[..2], &mut Context::default()),
+        Err(TrapCode::OutOfBoundsPc)
+    );
+    let insts = [
+        Inst::add_checked(Register(0), Const(Bits::MAX - 1), Const(1)),
+        Inst::ret(Register(0)),
+    ];
+    assert_eq!(run(&insts, &mut Context::default()), Ok(Bits::MAX));
+}
+
+#[test]
+fn host_call() {
+    let mut context = Context::default();
+    let double = context.register_host(|arg| arg * 2);
+    let insts = [
+        Inst::add(Register(1), Const(20), Const(1)),
+        Inst::host_call(double, Register(1), Register(0)),
+        Inst::ret(Register(0)),
+    ];
+    assert_eq!(insts[1].to_string(), "host_call r0, 0, r1");
+    assert_eq!(run(&insts, &mut context), Ok(42));
+    context.reset();
+    assert_eq!(run(&insts, &mut context), Ok(42));
+    let insts = [
+        Inst::host_call(double + 1, Const(1), Register(0)),
+        Inst::ret(Register(0)),
+    ];
+    context.reset();
+    assert_eq!(
+        run(&insts, &mut context),
+        Err(TrapCode::UnknownHostFunction)
+    );
+    assert_eq!(context.pc, 0);
+}
+
+#[test]
+fn mul_wide() {
+    let (lhs, rhs) = (Bits::MAX - 1, Bits::MAX - 2);
+    let expected = u128::from(lhs) * u128::from(rhs);
+    let insts = [
+        Inst::mul_wide(Global(0), Register(1), Const(lhs), Register(0)),
+        Inst::ret(Register(1)),
+    ];
+    let mut context = Context::default();
+    context.set_reg(Register(0), rhs);
+    assert_eq!(run(&insts, &mut context), Ok(expected as Bits));
+    assert_eq!(context.get_global(Global(0)), (expected >> 64) as Bits);
+    let lowered = lower(&[switch::Inst::MulWide {
+        hi: crate::Register(0),
+        lo: crate::Register(1),
+        lhs: crate::Register(0),
+        rhs: crate::Register(0),
+    }])
+    .unwrap();
+    assert_eq!(lowered[0].to_string(), "mul_wide r0, r1, r0, r0");
+}
+
+#[test]
+fn allocate_spills() {
+    let insts = [
+        // r20 = (r0 + 5) * 3
+        Inst::add(Register(20), Register(0), Const(5)),
+        Inst::mul(Register(20), Register(20), Const(3)),
+        // Skip the increment if r20 is zero.
+        Inst::branch_eqz(4, Register(20)),
+        Inst::add(Register(20), Register(20), Global(0)),
+        Inst::ret(Register(20)),
+    ];
+    let allocated = allocate(&insts, 16).unwrap();
+    assert!(matches!(
+        allocated[0],
+        Inst::Add(AddInst {
+            result: Sink::Global(Global(5)),
+            lhs: Source::Register(Register(0)),
+            ..
+        })
+    ));
+    assert!(matches!(
+        allocated[2],
+        Inst::BranchEqz(BranchEqzInst {
+            target: 4,
+            condition: Source::Global(Global(5)),
+        })
+    ));
+    let mut context = Context::with_registers_and_globals(32, 16);
+    context.set_reg(Register(0), 2);
+    context.set_global(Global(0), 100);
+    let expected = run(&insts, &mut context);
+    assert_eq!(expected, Ok(121));
+    let mut context = Context::with_registers_and_globals(16, 16);
+    context.set_reg(Register(0), 2);
+    context.set_global(Global(0), 100);
+    assert_eq!(run(&allocated, &mut context), expected);
+}
+
+#[test]
+fn allocate_rejects_spilled_base() {
+    let insts = [
+        Inst::add(Register(20), Register(0), Const(8)),
+        Inst::load64(Register(0), Register(20), 0),
+        Inst::ret(Register(0)),
+    ];
+    assert_eq!(
+        allocate(&insts, 16),
+        Err(AllocError::SpilledBase { index: 1 })
+    );
+    assert!(allocate(&insts, 21).is_ok());
+}
+
+#[test]
+fn allocate_rejects_calls_when_spilling() {
+    let insts = [
+        Inst::call(3, 1),
+        Inst::ret(Register(0)),
+        Inst::Nop,
+        Inst::add(Register(20), Register(0), Const(1)),
+        Inst::ret(Register(20)),
+    ];
+    assert_eq!(allocate(&insts, 16), Err(AllocError::Call { index: 0 }));
+    assert_eq!(allocate(&insts, 21).as_deref(), Ok(&insts[..]));
+}
+
+#[test]
+fn bit_counts() {
+    let insts = [
+        Inst::popcnt(Register(0), Const(0b1011_0000)),
+        Inst::clz(Register(1), Global(0)),
+        Inst::ctz(Register(2), Global(0)),
+        Inst::ctz(Register(3), Const(1 << 63)),
+        Inst::ret(Register(0)),
+    ];
+    let mut context = Context::default();
+    assert_eq!(run(&insts, &mut context), Ok(3));
+    assert_eq!(context.get_reg(Register(1)), 64);
+    assert_eq!(context.get_reg(Register(2)), 64);
+    assert_eq!(context.get_reg(Register(3)), 63);
+}
+
+#[test]
+fn min_max() {
+    let minus_one = Bits::MAX;
+    let insts = [
+        Inst::min_s(Register(1), Register(0), Const(5)),
+        Inst::min_u(Register(2), Register(0), Const(5)),
+        Inst::max_s(Register(3), Register(0), Const(5)),
+        Inst::max_u(Register(4), Register(0), Const(5)),
+        Inst::ret(Register(1)),
+    ];
+    let mut context = Context::default();
+    context.set_reg(Register(0), minus_one);
+    assert_eq!(run(&insts, &mut context), Ok(minus_one));
+    assert_eq!(context.get_reg(Register(2)), 5);
+    assert_eq!(context.get_reg(Register(3)), 5);
+    assert_eq!(context.get_reg(Register(4)), minus_one);
+}
+
+#[test]
+fn signed_immediates() {
+    // Arithmetic wraps so that signed immediates behave like their two's complement.
+    let insts = [
+        Inst::sub(Register(1), Register(0), Const::from_i64(-5)),
+        Inst::add(Register(2), Register(0), -5_i64),
+        Inst::sub(Register(3), Register(0), 5_u64),
+        Inst::ret(Register(1)),
+    ];
+    let mut context = Context::default();
+    context.set_reg(Register(0), 3);
+    assert_eq!(run(&insts, &mut context), Ok(8));
+    assert_eq!(context.get_reg(Register(2)), (-2_i64) as Bits);
+    assert_eq!(context.get_reg(Register(3)), Bits::MAX - 1);
+    assert_eq!(Const::from_i64(-5).into_i64(), -5);
+    assert_eq!(Const::from_i64(-1).into_bits(), Bits::MAX);
+    assert_eq!(Const::from_u64(Bits::MAX).into_i64(), -1);
+}
+
+#[test]
+fn operand_stack() {
+    let insts = [
+        Inst::push(Const(2)),
+        Inst::push(Register(0)),
+        Inst::add(Register(1), Source::StackTop, Source::StackTop),
+        Inst::push(Const(10)),
+        Inst::push(Const(3)),
+        Inst::sub(Register(2), Source::StackTop, Source::StackTop),
+        Inst::ret(Register(1)),
+    ];
+    let mut context = Context::default();
+    context.set_reg(Register(0), 5);
+    assert_eq!(run(&insts, &mut context), Ok(7));
+    // Operands are popped from left to right.
+    assert_eq!(context.get_reg(Register(2)), (-7_i64) as Bits);
+    assert_eq!(const_fold(&insts), insts);
+    assert_eq!(eliminate_dead_stores(&insts), insts);
+}
+
+#[test]
+fn operand_stack_underflow() {
+    let insts = [
+        Inst::push(Const(1)),
+        Inst::add(Register(0), Source::StackTop, Source::StackTop),
+        Inst::ret(Register(0)),
     ];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::StackUnderflow)
+    );
+    let insts = [Inst::pop(Register(0)), Inst::ret(Register(0))];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::StackUnderflow)
+    );
+}
+
+#[test]
+fn nop_padding() {
+    let mut insts = counter_loop_insts(1_000);
+    let expected = run(&insts, &mut Context::default());
+    let len = insts.len();
+    pad_to(&mut insts, 64);
+    assert_eq!(insts.len(), 64);
+    assert!(insts[len..].iter().all(|inst| matches!(inst, Inst::Nop)));
+    assert_eq!(run(&insts, &mut Context::default()), expected);
+    let insts = [Inst::Nop, Inst::ret(Register(0))];
     let mut context = Context::default();
-    benchmark(|| execute(&insts, &mut context));
+    context.set_reg(Register(0), 11);
+    context.set_reg(Register(1), 22);
+    assert_eq!(run(&insts, &mut context), Ok(11));
+    assert_eq!(context.get_reg(Register(1)), 22);
+}
+
+#[test]
+fn display() {
+    let rendered = counter_loop_insts(100)
+        .iter()
+        .map(Inst::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rendered,
+        [
+            "add r0, r0, #100",
+            "branch_eqz @4, r0",
+            "sub r0, r0, #1",
+            "branch @1",
+            "ret r0",
+        ]
+    );
+    let insts = [
+        Inst::mov(Global(2), Register(1)),
+        Inst::load32(Register(1), Register(2), 8),
+        Inst::store8(Register(3), 1, Global(0)),
+        Inst::fadd(FRegister(0), FRegister(1), FConst(1.5)),
+    ];
+    assert_eq!(
+        format!("{insts:?}"),
+        "[mov g2, r1, load32 r1, r2, 8, store8 r3, 1, g0, fadd f0, f1, #1.5]"
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn intern_insts() {
+    let insts = [
+        Inst::add(Register(0), Register(0), Const(1)),
+        Inst::branch_eqz(3, Register(0)),
+        Inst::add(Register(0), Register(0), Const(1)),
+        Inst::add(Register(0), Register(0), Const(2)),
+        Inst::fadd(FRegister(0), FRegister(0), FConst(f64::NAN)),
+        Inst::fadd(FRegister(0), FRegister(0), FConst(f64::NAN)),
+        Inst::ret(Register(0)),
+    ];
+    let (stream, table) = intern(&insts);
+    assert_eq!(stream, [0, 1, 0, 2, 3, 3, 4]);
+    assert_eq!(table.len(), 5);
+    let reconstructed = stream
+        .iter()
+        .map(|&index| table[index as usize])
+        .collect::<Vec<_>>();
+    assert_eq!(reconstructed, insts);
 }
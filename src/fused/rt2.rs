@@ -3,12 +3,13 @@
 #[cfg(test)]
 use crate::benchmark;
 
-use super::{Bits, Const, Context, Outcome, Register, Target};
+use super::{Bits, Const, Context, Global, Outcome, Register, Target, TrapCode};
 
 #[derive(Copy, Clone)]
 pub enum Source {
     Const(Const),
     Register(Register),
+    Global(Global),
 }
 
 impl From<Const> for Source {
@@ -23,11 +24,18 @@ impl From<Register> for Source {
     }
 }
 
+impl From<Global> for Source {
+    fn from(global: Global) -> Self {
+        Self::Global(global)
+    }
+}
+
 impl Source {
     pub fn load(&self, context: &Context) -> Bits {
         match self {
             Source::Const(constant) => constant.into_bits(),
             Source::Register(register) => context.get_reg(*register),
+            Source::Global(global) => context.get_global(*global),
         }
     }
 }
@@ -43,6 +51,14 @@ pub enum Inst {
     Mul(MulInst),
     Eq(EqInst),
     Ne(NeInst),
+    LtU(LtUInst),
+    LeU(LeUInst),
+    GtU(GtUInst),
+    GeU(GeUInst),
+    LtS(LtSInst),
+    LeS(LeSInst),
+    GtS(GtSInst),
+    GeS(GeSInst),
     Branch(BranchInst),
     BranchEqz(BranchEqzInst),
     Return(ReturnInst),
@@ -85,6 +101,126 @@ impl Inst {
         })
     }
 
+    pub fn eq<P0, P1>(result: Register, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::Eq(EqInst {
+            result,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn ne<P0, P1>(result: Register, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::Ne(NeInst {
+            result,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn lt_u<P0, P1>(result: Register, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::LtU(LtUInst {
+            result,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn le_u<P0, P1>(result: Register, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::LeU(LeUInst {
+            result,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn gt_u<P0, P1>(result: Register, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::GtU(GtUInst {
+            result,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn ge_u<P0, P1>(result: Register, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::GeU(GeUInst {
+            result,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn lt_s<P0, P1>(result: Register, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::LtS(LtSInst {
+            result,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn le_s<P0, P1>(result: Register, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::LeS(LeSInst {
+            result,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn gt_s<P0, P1>(result: Register, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::GtS(GtSInst {
+            result,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
+    pub fn ge_s<P0, P1>(result: Register, lhs: P0, rhs: P1) -> Self
+    where
+        P0: Into<Source>,
+        P1: Into<Source>,
+    {
+        Self::GeS(GeSInst {
+            result,
+            lhs: lhs.into(),
+            rhs: rhs.into(),
+        })
+    }
+
     pub fn branch(target: Target) -> Self {
         Self::Branch(BranchInst { target })
     }
@@ -117,6 +253,14 @@ impl Execute for Inst {
             Inst::Mul(inst) => inst.execute(context),
             Inst::Eq(inst) => inst.execute(context),
             Inst::Ne(inst) => inst.execute(context),
+            Inst::LtU(inst) => inst.execute(context),
+            Inst::LeU(inst) => inst.execute(context),
+            Inst::GtU(inst) => inst.execute(context),
+            Inst::GeU(inst) => inst.execute(context),
+            Inst::LtS(inst) => inst.execute(context),
+            Inst::LeS(inst) => inst.execute(context),
+            Inst::GtS(inst) => inst.execute(context),
+            Inst::GeS(inst) => inst.execute(context),
             Inst::Branch(inst) => inst.execute(context),
             Inst::BranchEqz(inst) => inst.execute(context),
             Inst::Return(inst) => inst.execute(context),
@@ -125,7 +269,7 @@ impl Execute for Inst {
 }
 
 macro_rules! impl_cmp_insts {
-    ( $( $inst_name:ident($op_name:ident) ),* $(,)? ) => {
+    ( $( $inst_name:ident($op_name:ident $(as $ty:ty)?) ),* $(,)? ) => {
         $(
             #[derive(Copy, Clone)]
             pub struct $inst_name {
@@ -136,8 +280,8 @@ macro_rules! impl_cmp_insts {
 
             impl Execute for $inst_name {
                 fn execute(&self, context: &mut Context) -> Outcome {
-                    let lhs = self.lhs.load(context);
-                    let rhs = self.rhs.load(context);
+                    let lhs = self.lhs.load(context) $(as $ty)?;
+                    let rhs = self.rhs.load(context) $(as $ty)?;
                     context.set_reg(self.result, lhs.$op_name(&rhs) as u64);
                     context.next_inst()
                 }
@@ -148,6 +292,14 @@ macro_rules! impl_cmp_insts {
 impl_cmp_insts! {
     EqInst(eq),
     NeInst(ne),
+    LtUInst(lt),
+    LeUInst(le),
+    GtUInst(gt),
+    GeUInst(ge),
+    LtSInst(lt as i64),
+    LeSInst(le as i64),
+    GtSInst(gt as i64),
+    GeSInst(ge as i64),
 }
 
 #[derive(Copy, Clone)]
@@ -239,14 +391,31 @@ impl Execute for ReturnInst {
     }
 }
 
-/// Executes the list of instruction using the given [`Context`].
-fn execute(insts: &[Inst], context: &mut Context) {
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Bits {
     loop {
         let pc = context.pc;
         let inst = &insts[pc];
         match inst.execute(context) {
-            Outcome::Continue => continue,
-            Outcome::Return => return,
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return context.get_reg(Register(0)),
+        }
+    }
+}
+
+/// Runs the list of instruction using the given [`Context`] and returns the result.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn run(insts: &[Inst], context: &mut Context) -> Result<Bits, TrapCode> {
+    loop {
+        let pc = context.pc;
+        let inst = insts.get(pc).ok_or(TrapCode::OutOfBoundsPc)?;
+        match inst.execute(context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return => return Ok(context.get_reg(Register(0))),
+            Outcome::Trap(trap) => return Err(trap),
         }
     }
 }
@@ -268,5 +437,45 @@ fn counter_loop() {
         Inst::ret(Register(0)),
     ];
     let mut context = Context::default();
-    benchmark(|| execute(&insts, &mut context));
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn ordering_comparisons() {
+    let minus_one = -1_i64 as Bits;
+    let insts = [
+        Inst::lt_u(Register(1), Register(0), Const(1)),
+        Inst::le_u(Register(2), Register(0), Const(1)),
+        Inst::gt_u(Register(3), Register(0), Const(1)),
+        Inst::ge_u(Register(4), Register(0), Const(1)),
+        Inst::lt_s(Register(5), Register(0), Const(1)),
+        Inst::le_s(Register(6), Register(0), Const(1)),
+        Inst::gt_s(Register(7), Register(0), Const(1)),
+        Inst::ge_s(Register(8), Const(minus_one), Register(0)),
+        Inst::ret(Register(1)),
+    ];
+    let mut context = Context::default();
+    context.set_reg(Register(0), minus_one);
+    assert_eq!(run(&insts, &mut context), Ok(0));
+    assert_eq!(context.get_reg(Register(2)), 0);
+    assert_eq!(context.get_reg(Register(3)), 1);
+    assert_eq!(context.get_reg(Register(4)), 1);
+    assert_eq!(context.get_reg(Register(5)), 1);
+    assert_eq!(context.get_reg(Register(6)), 1);
+    assert_eq!(context.get_reg(Register(7)), 0);
+    assert_eq!(context.get_reg(Register(8)), 1);
+}
+
+#[test]
+fn global_operand() {
+    let insts = [
+        // Add g0 and `1` and store the result into r0.
+        Inst::add(Register(0), Global(0), Const(1)),
+        // Return value and end function execution.
+        Inst::ret(Register(0)),
+    ];
+    let mut context = Context::default();
+    context.set_global(Global(0), 41);
+    assert_eq!(run(&insts, &mut context), Ok(42));
 }
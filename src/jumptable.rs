@@ -0,0 +1,121 @@
+#![allow(dead_code)]
+
+use alloc::vec::Vec;
+
+#[cfg(test)]
+use crate::{benchmark, Register};
+
+use super::{
+    fetch,
+    switch::{self, Inst},
+    Bits, Context, Outcome,
+};
+
+/// Executes a single instruction of known opcode.
+pub type Handler = fn(&mut Context, &Inst) -> Outcome;
+
+/// Executes `inst` whose opcode is known to be `OPCODE`.
+///
+/// Each instance inlines [`Inst::execute`] for an instruction of known opcode so that the compiler
+/// reduces its `match` to the single arm of `OPCODE`.
+fn handle<const OPCODE: u8>(context: &mut Context, inst: &Inst) -> Outcome {
+    if inst.opcode() != OPCODE {
+        #[cfg(feature = "safe_dispatch")]
+        unreachable!(
+            "opcode {:#04X} dispatched to handler of {OPCODE:#04X}",
+            inst.opcode()
+        );
+        #[cfg(not(feature = "safe_dispatch"))]
+        unsafe {
+            core::hint::unreachable_unchecked()
+        }
+    }
+    inst.execute(context)
+}
+
+/// Builds the [`TABLE`] from the list of all opcodes.
+macro_rules! jump_table {
+    ( $( $opcode:literal ),* $(,)? ) => {
+        [ $( handle::<$opcode> as Handler ),* ]
+    };
+}
+
+/// The handlers of all instructions indexed by their [`Inst::opcode`].
+static TABLE: [Handler; switch::N_OPCODES] = jump_table![
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F,
+    0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1A, 0x1B, 0x1C, 0x1D, 0x1E, 0x1F,
+    0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2A, 0x2B, 0x2C, 0x2D, 0x2E, 0x2F,
+    0x30, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3A, 0x3B, 0x3C, 0x3D, 0x3E, 0x3F,
+    0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4A, 0x4B, 0x4C, 0x4D,
+];
+
+/// A [`switch::Inst`] program with the handler of every instruction resolved ahead of time.
+pub struct Compiled {
+    /// The handler of the instruction at the same index of `insts`.
+    handlers: Vec<Handler>,
+    /// The instructions passed to their handler.
+    insts: Vec<Inst>,
+}
+
+/// Resolves the handler of every instruction of `insts` via the opcode indexed [`TABLE`].
+pub fn compile(insts: &[Inst]) -> Compiled {
+    Compiled {
+        handlers: insts
+            .iter()
+            .map(|inst| TABLE[usize::from(inst.opcode())])
+            .collect(),
+        insts: insts.to_vec(),
+    }
+}
+
+/// Executes the compiled instructions using the given [`Context`] and returns the result in register 0.
+///
+/// Dispatch calls the handler stored in parallel to the instruction without any `match`.
+pub fn execute(compiled: &Compiled, context: &mut Context) -> Bits {
+    loop {
+        let pc = context.pc;
+        let (Ok(handler), Ok(inst)) = (fetch(&compiled.handlers, pc), fetch(&compiled.insts, pc))
+        else {
+            return context.get_reg_abs(0);
+        };
+        match handler(context, inst) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return context.get_reg_abs(0),
+        }
+    }
+}
+
+#[test]
+fn counter_loop() {
+    let insts = compile(&switch::counter_loop_insts(100_000_000));
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
+    let program = switch::more_comps_insts(1_000);
+    assert_eq!(
+        Ok(execute(&compile(&program), &mut Context::default())),
+        switch::execute(&program, &mut Context::default()),
+    );
+}
+
+#[test]
+fn table_matches_opcodes() {
+    let mut rng = crate::bytecode::Rng(0x2545_F491_4F6C_DD1D);
+    for _ in 0..1_000 {
+        let inst = rng.inst();
+        if let Inst::Call { .. } = inst {
+            continue;
+        }
+        let mut expected = Context::default();
+        let mut context = Context::default();
+        for reg in 0..crate::NUM_REGISTERS {
+            expected.set_reg(Register(reg), reg as Bits * 3);
+            context.set_reg(Register(reg), reg as Bits * 3);
+            expected.set_freg(Register(reg), reg as f64 + 0.5);
+            context.set_freg(Register(reg), reg as f64 + 0.5);
+        }
+        inst.execute(&mut expected);
+        TABLE[usize::from(inst.opcode())](&mut context, &inst);
+        assert_eq!(context.snapshot(), expected.snapshot(), "{inst:?}");
+    }
+}
@@ -1,3 +1,18 @@
+#![no_std]
+#![cfg_attr(feature = "nightly", feature(explicit_tail_calls))]
+#![cfg_attr(feature = "nightly", allow(incomplete_features))]
+
+#[macro_use]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+mod arith;
+mod asm;
+mod become_tail;
+mod block_dispatch;
+pub mod bytecode;
+mod cfg;
 mod closure_block;
 mod closure_loop;
 mod closure_tail;
@@ -6,18 +21,88 @@ mod closure_tree;
 // mod closure_tree;
 mod enum_tree;
 mod enum_tree_2;
+pub mod fixed;
 mod fused;
-mod switch;
+mod jumptable;
+mod peephole;
+pub mod program;
+mod regalloc;
+mod replicated;
+pub mod runner;
+mod soa;
+pub mod structured;
+mod subroutine_threaded;
+pub mod switch;
 mod switch_2;
+mod switch_cached2;
+mod switch_hot;
 mod switch_tail;
 mod switch_tail_2;
+mod threaded;
+pub mod validate;
 
-pub type Register = usize;
 pub type Bits = u64;
-pub type Target = usize;
 
-use std::time::{Duration, Instant};
+/// The index of a register relative to the register window of the executing function.
+///
+/// Float registers are indexed by [`Register`] as well.
+///
+/// Unlike a bare `usize` a [`Register`] cannot be passed where a [`Target`] is expected.
+///
+/// ```compile_fail
+/// use interpreter_dispatch_research::{Register, Target};
+/// let target: Target = Register(0);
+/// ```
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Register(pub usize);
+
+/// The index of an instruction that execution may continue at.
+///
+/// Unlike a bare `usize` a [`Target`] cannot be passed where a [`Register`] is expected.
+///
+/// ```compile_fail
+/// use interpreter_dispatch_research::{Register, Target};
+/// let register: Register = Target(0);
+/// ```
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Target(pub usize);
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The number of registers of a default constructed [`Context`].
+pub const NUM_REGISTERS: usize = 16;
+
+/// The number of bytes of memory of a default constructed [`Context`].
+pub const MEMORY_SIZE: usize = 0x1_0000;
+
+/// The maximum number of nested calls before execution traps.
+pub const MAX_CALL_DEPTH: usize = 1024;
+
+/// The seed of the random number generator of a default constructed [`Context`].
+pub const DEFAULT_SEED: u64 = 0x2545_F491_4F6C_DD1D;
 
+use alloc::{rc::Rc, vec::Vec};
+use core::{fmt, ops::Range};
+#[cfg(any(test, feature = "std"))]
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+#[cfg(feature = "std")]
 pub fn benchmark<F, R>(f: F) -> (Duration, R)
 where
     F: FnOnce() -> R,
@@ -25,10 +110,19 @@ where
     let before = Instant::now();
     let result = f();
     let duration = before.elapsed();
-    println!("duration = {:?}", duration);
+    std::println!("duration = {:?}", duration);
     (duration, result)
 }
 
+/// Runs `f` like [`benchmark`] for the tests of a `no_std` build which cannot measure time.
+#[cfg(all(test, not(feature = "std")))]
+pub(crate) fn benchmark<F, R>(f: F) -> (Duration, R)
+where
+    F: FnOnce() -> R,
+{
+    (Duration::ZERO, f())
+}
+
 /// The outcome of an instruction execution.
 #[derive(Copy, Clone)]
 pub enum Outcome {
@@ -36,26 +130,272 @@ pub enum Outcome {
     Continue,
     /// Return function execution.
     Return,
+    /// Suspend execution with the `pc` pointing to the next instruction so that it can be resumed.
+    Yield,
+    /// Abort function execution due to a runtime fault.
+    Trap(TrapCode),
+}
+
+/// The reason for a trapping instruction execution.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TrapCode {
+    /// An integer division or remainder had a divisor of zero.
+    DivisionByZero,
+    /// An unreachable instruction was executed.
+    UnreachableExecuted,
+    /// The `pc` pointed outside of the executed instructions.
+    OutOfBoundsPc,
+    /// A load or store accessed bytes outside of the memory.
+    OutOfBoundsMemory,
+    /// A call exceeded the maximum call depth.
+    CallStackOverflow,
+    /// The execution ran out of fuel before returning.
+    OutOfFuel,
+    /// A checked integer operation overflowed.
+    IntegerOverflow,
+    /// A pop found the operand stack empty.
+    StackUnderflow,
+    /// A host call referred to a host function that is not registered.
+    UnknownHostFunction,
+}
+
+/// The overflow semantics of the `add`, `sub` and `mul` handlers and their immediate forms.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ArithMode {
+    /// Overflowing results wrap around at the boundary of [`Bits`].
+    #[default]
+    Wrapping,
+    /// Overflowing results are clamped to `0` or [`Bits::MAX`].
+    Saturating,
+    /// Overflowing results trap with [`TrapCode::IntegerOverflow`].
+    Checked,
+}
+
+impl ArithMode {
+    /// Returns `lhs + rhs` or `None` if it overflows in [`ArithMode::Checked`].
+    pub fn add(self, lhs: Bits, rhs: Bits) -> Option<Bits> {
+        match self {
+            Self::Wrapping => Some(lhs.wrapping_add(rhs)),
+            Self::Saturating => Some(lhs.saturating_add(rhs)),
+            Self::Checked => lhs.checked_add(rhs),
+        }
+    }
+
+    /// Returns `lhs - rhs` or `None` if it overflows in [`ArithMode::Checked`].
+    pub fn sub(self, lhs: Bits, rhs: Bits) -> Option<Bits> {
+        match self {
+            Self::Wrapping => Some(lhs.wrapping_sub(rhs)),
+            Self::Saturating => Some(lhs.saturating_sub(rhs)),
+            Self::Checked => lhs.checked_sub(rhs),
+        }
+    }
+
+    /// Returns `lhs * rhs` or `None` if it overflows in [`ArithMode::Checked`].
+    pub fn mul(self, lhs: Bits, rhs: Bits) -> Option<Bits> {
+        match self {
+            Self::Wrapping => Some(lhs.wrapping_mul(rhs)),
+            Self::Saturating => Some(lhs.saturating_mul(rhs)),
+            Self::Checked => lhs.checked_mul(rhs),
+        }
+    }
+}
+
+/// The execution state accessed by the generic handler functions.
+///
+/// All handlers that only access registers and the `pc` are generic over [`ExecState`]
+/// so that instrumented or otherwise customized contexts can reuse them. Handlers that
+/// access float registers, memory or call frames still require a [`Context`].
+pub trait ExecState {
+    /// Returns the current value of `reg`.
+    fn get_reg(&self, reg: Register) -> Bits;
+
+    /// Sets the register `reg` to the `new_value`.
+    fn set_reg(&mut self, reg: Register, new_value: Bits);
+
+    /// Sets the `pc` to point to the `new_pc`.
+    fn branch_to(&mut self, new_pc: usize) -> Outcome;
+
+    /// Advance the `pc` to the next instruction.
+    fn next_inst(&mut self) -> Outcome;
+
+    /// Returns the overflow semantics of `add`, `sub` and `mul`.
+    fn arith_mode(&self) -> ArithMode {
+        ArithMode::Wrapping
+    }
+}
+
+impl ExecState for Context {
+    fn get_reg(&self, reg: Register) -> Bits {
+        Context::get_reg(self, reg)
+    }
+
+    fn set_reg(&mut self, reg: Register, new_value: Bits) {
+        Context::set_reg(self, reg, new_value)
+    }
+
+    fn branch_to(&mut self, new_pc: usize) -> Outcome {
+        Context::branch_to(self, new_pc)
+    }
+
+    fn next_inst(&mut self) -> Outcome {
+        Context::next_inst(self)
+    }
+
+    fn arith_mode(&self) -> ArithMode {
+        self.arith_mode
+    }
 }
 
-/// A simple execution context with a program counter and some registers.
+/// The call frame of a function that has been called but not yet returned.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// The `pc` at which the caller resumes execution.
+    pub(crate) return_pc: usize,
+    /// The index of the first register of the caller's register window.
+    pub(crate) base: usize,
+}
+
+/// A copy of the `pc`, registers, globals, call frames and operand stack of a [`Context`].
+///
+/// The memory is not part of a [`Snapshot`].
+/// Cloning a [`Snapshot`] only clones the reference counted copies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub(crate) pc: usize,
+    pub(crate) regs: Rc<[Bits]>,
+    pub(crate) fregs: Rc<[f64]>,
+    pub(crate) globals: Rc<[Bits]>,
+    pub(crate) frames: Rc<[Frame]>,
+    pub(crate) base: usize,
+    /// The operand stack of a [`fused::Context`] which is always empty for a [`Context`].
+    pub(crate) stack: Rc<[Bits]>,
+}
+
+/// A simple execution context with a program counter, some registers, globals and memory.
+///
+/// Every called function operates on its own window of `frame_size` registers
+/// starting at `base`. The registers of the window are accessed relative to `base`.
+/// The float registers `fregs` are not windowed and shared by all functions.
+#[derive(Clone)]
 pub struct Context {
     pc: usize,
     regs: Vec<Bits>,
+    fregs: Vec<f64>,
+    globals: Vec<Bits>,
+    mem: Vec<u8>,
+    /// The end of the prefix of `mem` that stores may have written to since the last reset.
+    mem_dirty: usize,
+    frames: Vec<Frame>,
+    base: usize,
+    frame_size: usize,
+    seed: u64,
+    rng: u64,
+    arith_mode: ArithMode,
 }
 
 impl Default for Context {
     fn default() -> Self {
+        Self::with_registers(NUM_REGISTERS)
+    }
+}
+
+impl Context {
+    /// Creates a new [`Context`] with `n` zero initialized registers.
+    pub fn with_registers(n: usize) -> Self {
         Self {
             pc: 0,
-            regs: vec![0x00; 16],
+            regs: vec![0x00; n],
+            fregs: vec![0.0; n],
+            globals: vec![0x00; 16],
+            mem: vec![0x00; MEMORY_SIZE],
+            mem_dirty: 0,
+            frames: Vec::new(),
+            base: 0,
+            frame_size: n,
+            seed: DEFAULT_SEED,
+            rng: DEFAULT_SEED,
+            arith_mode: ArithMode::Wrapping,
         }
     }
-}
 
-impl Context {
+    /// Creates a new default [`Context`] whose random number generator starts at `seed`.
+    ///
+    /// A zero `seed` is replaced by [`DEFAULT_SEED`] since xorshift64 never leaves zero.
+    pub fn with_seed(seed: u64) -> Self {
+        let seed = if seed == 0 { DEFAULT_SEED } else { seed };
+        Self {
+            seed,
+            rng: seed,
+            ..Self::default()
+        }
+    }
+
+    /// Creates a new default [`Context`] executing `add`, `sub` and `mul` under `arith_mode`.
+    pub fn with_arith_mode(arith_mode: ArithMode) -> Self {
+        Self {
+            arith_mode,
+            ..Self::default()
+        }
+    }
+
+    /// Advances the xorshift64 random number generator and returns its next value.
+    pub fn next_random(&mut self) -> Bits {
+        let mut x = self.rng;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng = x;
+        x
+    }
+
+    /// Resets the [`Context`] to its initial state without reallocating.
+    ///
+    /// Zeroes the `pc`, all registers, globals and memory, drops all call frames
+    /// and reseeds the random number generator so that runs can be replayed.
+    /// Only the prefix of memory up to the furthest store since the last reset is zeroed.
+    pub fn reset(&mut self) {
+        self.pc = 0;
+        self.regs.truncate(self.frame_size);
+        self.regs.fill(0x00);
+        self.fregs.fill(0.0);
+        self.globals.fill(0x00);
+        self.mem[..self.mem_dirty].fill(0x00);
+        self.mem_dirty = 0;
+        self.frames.clear();
+        self.base = 0;
+        self.rng = self.seed;
+    }
+
+    /// Returns a [`Snapshot`] of the `pc`, all registers, globals and call frames.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            pc: self.pc,
+            regs: self.regs.as_slice().into(),
+            fregs: self.fregs.as_slice().into(),
+            globals: self.globals.as_slice().into(),
+            frames: self.frames.as_slice().into(),
+            base: self.base,
+            stack: Rc::from([]),
+        }
+    }
+
+    /// Restores the `pc`, all registers, globals and call frames from the `snapshot`.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.pc = snapshot.pc;
+        self.regs.clear();
+        self.regs.extend_from_slice(&snapshot.regs);
+        self.fregs.clear();
+        self.fregs.extend_from_slice(&snapshot.fregs);
+        self.globals.clear();
+        self.globals.extend_from_slice(&snapshot.globals);
+        self.frames.clear();
+        self.frames.extend_from_slice(&snapshot.frames);
+        self.base = snapshot.base;
+    }
+
     /// Sets the register `reg` to the `new_value`.
     pub fn set_reg(&mut self, reg: Register, new_value: Bits) {
+        let reg = self.base + reg.0;
         debug_assert!(reg < self.regs.len());
         unsafe {
             *self.regs.get_unchecked_mut(reg) = new_value;
@@ -64,10 +404,164 @@ impl Context {
 
     /// Returns the current value of `reg`.
     pub fn get_reg(&self, reg: Register) -> Bits {
+        let reg = self.base + reg.0;
+        debug_assert!(reg < self.regs.len());
+        unsafe { *self.regs.get_unchecked(reg) }
+    }
+
+    /// Returns the current value of the register at the absolute index `reg`.
+    ///
+    /// Unlike [`Context::get_reg`] this ignores the register window, e.g. to read
+    /// the result of the outermost function in `r0` no matter where execution stopped.
+    pub fn get_reg_abs(&self, reg: usize) -> Bits {
         debug_assert!(reg < self.regs.len());
         unsafe { *self.regs.get_unchecked(reg) }
     }
 
+    /// Returns the index of the first register of the current register window.
+    pub fn get_base(&self) -> usize {
+        self.base
+    }
+
+    /// Moves the current register window to start at the absolute register index `base`.
+    ///
+    /// Grows the registers with zeroes if the window would not fit.
+    pub fn set_base(&mut self, base: usize) {
+        let end = base + self.frame_size;
+        if self.regs.len() < end {
+            self.regs.resize(end, 0x00);
+        }
+        self.base = base;
+    }
+
+    /// Sets the float register `reg` to the `new_value`.
+    pub fn set_freg(&mut self, reg: Register, new_value: f64) {
+        debug_assert!(reg.0 < self.fregs.len());
+        unsafe {
+            *self.fregs.get_unchecked_mut(reg.0) = new_value;
+        }
+    }
+
+    /// Returns the current value of the float register `reg`.
+    pub fn get_freg(&self, reg: Register) -> f64 {
+        debug_assert!(reg.0 < self.fregs.len());
+        unsafe { *self.fregs.get_unchecked(reg.0) }
+    }
+
+    /// Sets the global `global` to the `new_value`.
+    pub fn set_global(&mut self, global: usize, new_value: Bits) {
+        debug_assert!(global < self.globals.len());
+        unsafe {
+            *self.globals.get_unchecked_mut(global) = new_value;
+        }
+    }
+
+    /// Returns the current value of `global`.
+    pub fn get_global(&self, global: usize) -> Bits {
+        debug_assert!(global < self.globals.len());
+        unsafe { *self.globals.get_unchecked(global) }
+    }
+
+    /// Returns the registers of the current register window.
+    ///
+    /// The slice is indexed like [`Context::get_reg`].
+    pub fn regs(&self) -> &[Bits] {
+        &self.regs[self.base..self.base + self.frame_size]
+    }
+
+    /// Returns the registers of the current register window for bulk initialization.
+    ///
+    /// The slice is indexed like [`Context::set_reg`].
+    pub fn regs_mut(&mut self) -> &mut [Bits] {
+        &mut self.regs[self.base..self.base + self.frame_size]
+    }
+
+    /// Returns all globals.
+    pub fn globals(&self) -> &[Bits] {
+        &self.globals
+    }
+
+    /// Returns all globals for bulk initialization.
+    pub fn globals_mut(&mut self) -> &mut [Bits] {
+        &mut self.globals
+    }
+
+    /// Returns the range of `len` bytes of memory at `address` plus `offset`.
+    fn memory_range(
+        &self,
+        address: Bits,
+        offset: Bits,
+        len: usize,
+    ) -> Result<Range<usize>, TrapCode> {
+        address
+            .checked_add(offset)
+            .and_then(|start| usize::try_from(start).ok())
+            .and_then(|start| Some(start..start.checked_add(len)?))
+            .filter(|range| range.end <= self.mem.len())
+            .ok_or(TrapCode::OutOfBoundsMemory)
+    }
+
+    /// Returns the `N` bytes of memory at `address` plus `offset`.
+    fn load_bytes<const N: usize>(&self, address: Bits, offset: Bits) -> Result<[u8; N], TrapCode> {
+        let range = self.memory_range(address, offset, N)?;
+        let mut bytes = [0x00; N];
+        bytes.copy_from_slice(&self.mem[range]);
+        Ok(bytes)
+    }
+
+    /// Overwrites the `N` bytes of memory at `address` plus `offset` with `bytes`.
+    fn store_bytes<const N: usize>(
+        &mut self,
+        address: Bits,
+        offset: Bits,
+        bytes: [u8; N],
+    ) -> Result<(), TrapCode> {
+        let range = self.memory_range(address, offset, N)?;
+        self.mem_dirty = self.mem_dirty.max(range.end);
+        self.mem[range].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    /// Loads the little-endian `u8` at `address` plus `offset` from memory.
+    pub fn load8(&self, address: Bits, offset: Bits) -> Result<u8, TrapCode> {
+        self.load_bytes(address, offset).map(u8::from_le_bytes)
+    }
+
+    /// Loads the little-endian `u16` at `address` plus `offset` from memory.
+    pub fn load16(&self, address: Bits, offset: Bits) -> Result<u16, TrapCode> {
+        self.load_bytes(address, offset).map(u16::from_le_bytes)
+    }
+
+    /// Loads the little-endian `u32` at `address` plus `offset` from memory.
+    pub fn load32(&self, address: Bits, offset: Bits) -> Result<u32, TrapCode> {
+        self.load_bytes(address, offset).map(u32::from_le_bytes)
+    }
+
+    /// Loads the little-endian `u64` at `address` plus `offset` from memory.
+    pub fn load64(&self, address: Bits, offset: Bits) -> Result<u64, TrapCode> {
+        self.load_bytes(address, offset).map(u64::from_le_bytes)
+    }
+
+    /// Stores `value` as little-endian `u8` at `address` plus `offset` into memory.
+    pub fn store8(&mut self, address: Bits, offset: Bits, value: u8) -> Result<(), TrapCode> {
+        self.store_bytes(address, offset, value.to_le_bytes())
+    }
+
+    /// Stores `value` as little-endian `u16` at `address` plus `offset` into memory.
+    pub fn store16(&mut self, address: Bits, offset: Bits, value: u16) -> Result<(), TrapCode> {
+        self.store_bytes(address, offset, value.to_le_bytes())
+    }
+
+    /// Stores `value` as little-endian `u32` at `address` plus `offset` into memory.
+    pub fn store32(&mut self, address: Bits, offset: Bits, value: u32) -> Result<(), TrapCode> {
+        self.store_bytes(address, offset, value.to_le_bytes())
+    }
+
+    /// Stores `value` as little-endian `u64` at `address` plus `offset` into memory.
+    pub fn store64(&mut self, address: Bits, offset: Bits, value: u64) -> Result<(), TrapCode> {
+        self.store_bytes(address, offset, value.to_le_bytes())
+    }
+
     /// Sets the `pc` to point to the `new_pc`.
     pub fn branch_to(&mut self, new_pc: usize) -> Outcome {
         self.pc = new_pc;
@@ -79,69 +573,1211 @@ impl Context {
         self.pc += 1;
         Outcome::Continue
     }
+
+    /// Calls the function starting at `target` passing the first `num_args` registers as arguments.
+    ///
+    /// The arguments are copied into the low registers of the callee's fresh register window.
+    pub fn call(&mut self, target: Target, num_args: usize) -> Outcome {
+        debug_assert!(num_args <= self.frame_size);
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Outcome::Trap(TrapCode::CallStackOverflow);
+        }
+        self.frames.push(Frame {
+            return_pc: self.pc + 1,
+            base: self.base,
+        });
+        let base = self.base + self.frame_size;
+        let end = base + self.frame_size;
+        if self.regs.len() < end {
+            self.regs.resize(end, 0x00);
+        }
+        self.regs.copy_within(self.base..self.base + num_args, base);
+        self.regs[base + num_args..end].fill(0x00);
+        self.base = base;
+        self.branch_to(target.0)
+    }
+
+    /// Returns `result` to the caller in its `r0` or ends execution if there is no caller.
+    pub fn return_with(&mut self, result: Bits) -> Outcome {
+        match self.frames.pop() {
+            Some(frame) => {
+                self.base = frame.base;
+                self.set_reg(Register(0), result);
+                self.branch_to(frame.return_pc)
+            }
+            None => {
+                self.set_reg(Register(0), result);
+                Outcome::Return
+            }
+        }
+    }
+}
+
+/// Returns the instruction at `pc` of `insts` as fetched by the dispatch loops.
+///
+/// Without the `safe_dispatch` feature the `pc` is not bounds checked for performance
+/// which is undefined behavior if `pc` is out of bounds.
+///
+/// # Errors
+///
+/// With the `safe_dispatch` feature if `pc` is out of bounds.
+#[inline(always)]
+pub(crate) fn fetch<T>(insts: &[T], pc: usize) -> Result<&T, TrapCode> {
+    #[cfg(feature = "safe_dispatch")]
+    {
+        insts.get(pc).ok_or(TrapCode::OutOfBoundsPc)
+    }
+    #[cfg(not(feature = "safe_dispatch"))]
+    {
+        Ok(unsafe { insts.get_unchecked(pc) })
+    }
+}
+
+/// Defines the handler functions forcing their inlining into every dispatch site.
+///
+/// With the `no_inline_handlers` feature enabled the handlers are never inlined instead
+/// so that the only difference between both builds is the inlining attribute.
+macro_rules! handlers {
+    (
+        $(
+            $(#[$meta:meta])*
+            $vis:vis fn $name:ident $(<$($generic:ident: $bound:path),*>)? ($($params:tt)*) -> $ret:ty $body:block
+        )*
+    ) => {
+        $(
+            $(#[$meta])*
+            #[cfg_attr(feature = "no_inline_handlers", inline(never))]
+            #[cfg_attr(not(feature = "no_inline_handlers"), inline(always))]
+            $vis fn $name $(<$($generic: $bound),*>)? ($($params)*) -> $ret $body
+        )*
+    };
 }
 
 mod handler {
-    use super::{Bits, Context, Outcome, Register};
+    use super::{
+        arith::{cmp_s, cmp_u},
+        Bits, Context, ExecState, Outcome, Register, Target, TrapCode,
+    };
+
+    handlers! {
+        /// Stores `value` into `result` or traps if the arithmetic operation overflowed.
+        fn arith<C: ExecState>(context: &mut C, result: Register, value: Option<Bits>) -> Outcome {
+            match value {
+                Some(value) => {
+                    context.set_reg(result, value);
+                    context.next_inst()
+                }
+                None => Outcome::Trap(TrapCode::IntegerOverflow),
+            }
+        }
+
+        pub fn add<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            let value = context.arith_mode().add(lhs, rhs);
+            arith(context, result, value)
+        }
+
+        pub fn add_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            let value = context.arith_mode().add(lhs, rhs);
+            arith(context, result, value)
+        }
+
+        pub fn sub<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            let value = context.arith_mode().sub(lhs, rhs);
+            arith(context, result, value)
+        }
+
+        pub fn sub_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            let value = context.arith_mode().sub(lhs, rhs);
+            arith(context, result, value)
+        }
+
+        pub fn mul<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            let value = context.arith_mode().mul(lhs, rhs);
+            arith(context, result, value)
+        }
+
+        pub fn mul_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            let value = context.arith_mode().mul(lhs, rhs);
+            arith(context, result, value)
+        }
+
+        pub fn div<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            match lhs.checked_div(rhs) {
+                Some(quotient) => {
+                    context.set_reg(result, quotient);
+                    context.next_inst()
+                }
+                None => Outcome::Trap(TrapCode::DivisionByZero),
+            }
+        }
+
+        pub fn div_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            match lhs.checked_div(rhs) {
+                Some(quotient) => {
+                    context.set_reg(result, quotient);
+                    context.next_inst()
+                }
+                None => Outcome::Trap(TrapCode::DivisionByZero),
+            }
+        }
+
+        pub fn rem<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            match lhs.checked_rem(rhs) {
+                Some(remainder) => {
+                    context.set_reg(result, remainder);
+                    context.next_inst()
+                }
+                None => Outcome::Trap(TrapCode::DivisionByZero),
+            }
+        }
+
+        pub fn rem_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            match lhs.checked_rem(rhs) {
+                Some(remainder) => {
+                    context.set_reg(result, remainder);
+                    context.next_inst()
+                }
+                None => Outcome::Trap(TrapCode::DivisionByZero),
+            }
+        }
+
+        /// Masks the shift amount `rhs` to the `0..64` range of valid `u64` shifts.
+        fn shift_amount(rhs: Bits) -> u32 {
+            (rhs & 0x3F) as u32
+        }
+
+        pub fn shl<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            context.set_reg(result, lhs << shift_amount(rhs));
+            context.next_inst()
+        }
+
+        pub fn shl_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            context.set_reg(result, lhs << shift_amount(rhs));
+            context.next_inst()
+        }
+
+        pub fn shr_u<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            context.set_reg(result, lhs >> shift_amount(rhs));
+            context.next_inst()
+        }
+
+        pub fn shr_u_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            context.set_reg(result, lhs >> shift_amount(rhs));
+            context.next_inst()
+        }
+
+        pub fn shr_s<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            context.set_reg(result, ((lhs as i64) >> shift_amount(rhs)) as Bits);
+            context.next_inst()
+        }
+
+        pub fn shr_s_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            context.set_reg(result, ((lhs as i64) >> shift_amount(rhs)) as Bits);
+            context.next_inst()
+        }
+
+        pub fn rotl<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            context.set_reg(result, lhs.rotate_left(shift_amount(rhs)));
+            context.next_inst()
+        }
+
+        pub fn rotl_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            context.set_reg(result, lhs.rotate_left(shift_amount(rhs)));
+            context.next_inst()
+        }
+
+        pub fn rotr<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            context.set_reg(result, lhs.rotate_right(shift_amount(rhs)));
+            context.next_inst()
+        }
+
+        pub fn rotr_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            context.set_reg(result, lhs.rotate_right(shift_amount(rhs)));
+            context.next_inst()
+        }
+
+        pub fn eq<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            context.set_reg(result, Bits::from(lhs == rhs));
+            context.next_inst()
+        }
+
+        pub fn eq_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            context.set_reg(result, Bits::from(lhs == rhs));
+            context.next_inst()
+        }
+
+        pub fn ne<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            context.set_reg(result, Bits::from(lhs != rhs));
+            context.next_inst()
+        }
+
+        pub fn ne_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            context.set_reg(result, Bits::from(lhs != rhs));
+            context.next_inst()
+        }
+
+        pub fn lt_u<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            context.set_reg(result, Bits::from(cmp_u(lhs, rhs).is_lt()));
+            context.next_inst()
+        }
+
+        pub fn lt_u_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            context.set_reg(result, Bits::from(cmp_u(lhs, rhs).is_lt()));
+            context.next_inst()
+        }
+
+        pub fn lt_s<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            context.set_reg(result, Bits::from(cmp_s(lhs, rhs).is_lt()));
+            context.next_inst()
+        }
+
+        pub fn lt_s_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            context.set_reg(result, Bits::from(cmp_s(lhs, rhs).is_lt()));
+            context.next_inst()
+        }
+
+        pub fn mov<C: ExecState>(context: &mut C, result: Register, src: Register) -> Outcome {
+            let value = context.get_reg(src);
+            context.set_reg(result, value);
+            context.next_inst()
+        }
+
+        pub fn add32<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs) as u32;
+            let rhs = context.get_reg(rhs) as u32;
+            context.set_reg(result, Bits::from(lhs.wrapping_add(rhs)));
+            context.next_inst()
+        }
+
+        pub fn sub32<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs) as u32;
+            let rhs = context.get_reg(rhs) as u32;
+            context.set_reg(result, Bits::from(lhs.wrapping_sub(rhs)));
+            context.next_inst()
+        }
+
+        pub fn mul32<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs) as u32;
+            let rhs = context.get_reg(rhs) as u32;
+            context.set_reg(result, Bits::from(lhs.wrapping_mul(rhs)));
+            context.next_inst()
+        }
+
+        pub fn lt_s32<C: ExecState>(
+            context: &mut C,
+            result: Register,
+            lhs: Register,
+            rhs: Register,
+        ) -> Outcome {
+            let lhs = context.get_reg(lhs) as i32;
+            let rhs = context.get_reg(rhs) as i32;
+            context.set_reg(result, Bits::from(lhs < rhs));
+            context.next_inst()
+        }
+
+        pub fn shl32<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs) as u32;
+            let rhs = context.get_reg(rhs) as u32;
+            context.set_reg(result, Bits::from(lhs << (rhs & 0x1F)));
+            context.next_inst()
+        }
+
+        pub fn shr_u32<C: ExecState>(
+            context: &mut C,
+            result: Register,
+            lhs: Register,
+            rhs: Register,
+        ) -> Outcome {
+            let lhs = context.get_reg(lhs) as u32;
+            let rhs = context.get_reg(rhs) as u32;
+            context.set_reg(result, Bits::from(lhs >> (rhs & 0x1F)));
+            context.next_inst()
+        }
+
+        pub fn shr_s32<C: ExecState>(
+            context: &mut C,
+            result: Register,
+            lhs: Register,
+            rhs: Register,
+        ) -> Outcome {
+            let lhs = context.get_reg(lhs) as i32;
+            let rhs = context.get_reg(rhs) as i32;
+            context.set_reg(result, Bits::from((lhs >> (rhs & 0x1F)) as u32));
+            context.next_inst()
+        }
+
+        pub fn add_checked<C: ExecState>(
+            context: &mut C,
+            result: Register,
+            lhs: Register,
+            rhs: Register,
+        ) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            match lhs.checked_add(rhs) {
+                Some(value) => {
+                    context.set_reg(result, value);
+                    context.next_inst()
+                }
+                None => Outcome::Trap(TrapCode::IntegerOverflow),
+            }
+        }
+
+        pub fn sub_checked<C: ExecState>(
+            context: &mut C,
+            result: Register,
+            lhs: Register,
+            rhs: Register,
+        ) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            match lhs.checked_sub(rhs) {
+                Some(value) => {
+                    context.set_reg(result, value);
+                    context.next_inst()
+                }
+                None => Outcome::Trap(TrapCode::IntegerOverflow),
+            }
+        }
+
+        pub fn mul_checked<C: ExecState>(
+            context: &mut C,
+            result: Register,
+            lhs: Register,
+            rhs: Register,
+        ) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            match lhs.checked_mul(rhs) {
+                Some(value) => {
+                    context.set_reg(result, value);
+                    context.next_inst()
+                }
+                None => Outcome::Trap(TrapCode::IntegerOverflow),
+            }
+        }
+
+        pub fn mul_wide<C: ExecState>(
+            context: &mut C,
+            hi: Register,
+            lo: Register,
+            lhs: Register,
+            rhs: Register,
+        ) -> Outcome {
+            let lhs = u128::from(context.get_reg(lhs));
+            let rhs = u128::from(context.get_reg(rhs));
+            let product = lhs * rhs;
+            context.set_reg(lo, product as Bits);
+            context.set_reg(hi, (product >> 64) as Bits);
+            context.next_inst()
+        }
+
+        pub fn popcnt<C: ExecState>(context: &mut C, result: Register, src: Register) -> Outcome {
+            let value = context.get_reg(src);
+            context.set_reg(result, Bits::from(value.count_ones()));
+            context.next_inst()
+        }
+
+        pub fn clz<C: ExecState>(context: &mut C, result: Register, src: Register) -> Outcome {
+            let value = context.get_reg(src);
+            context.set_reg(result, Bits::from(value.leading_zeros()));
+            context.next_inst()
+        }
+
+        pub fn ctz<C: ExecState>(context: &mut C, result: Register, src: Register) -> Outcome {
+            let value = context.get_reg(src);
+            context.set_reg(result, Bits::from(value.trailing_zeros()));
+            context.next_inst()
+        }
+
+        pub fn min_u<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            context.set_reg(result, lhs.min(rhs));
+            context.next_inst()
+        }
+
+        pub fn min_u_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            context.set_reg(result, lhs.min(rhs));
+            context.next_inst()
+        }
+
+        pub fn max_u<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            context.set_reg(result, lhs.max(rhs));
+            context.next_inst()
+        }
+
+        pub fn max_u_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            context.set_reg(result, lhs.max(rhs));
+            context.next_inst()
+        }
+
+        pub fn min_s<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            context.set_reg(result, (lhs as i64).min(rhs as i64) as Bits);
+            context.next_inst()
+        }
+
+        pub fn min_s_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            context.set_reg(result, (lhs as i64).min(rhs as i64) as Bits);
+            context.next_inst()
+        }
+
+        pub fn max_s<C: ExecState>(context: &mut C, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_reg(lhs);
+            let rhs = context.get_reg(rhs);
+            context.set_reg(result, (lhs as i64).max(rhs as i64) as Bits);
+            context.next_inst()
+        }
+
+        pub fn max_s_imm<C: ExecState>(context: &mut C, result: Register, src: Register, imm: Bits) -> Outcome {
+            let lhs = context.get_reg(src);
+            let rhs = imm;
+            context.set_reg(result, (lhs as i64).max(rhs as i64) as Bits);
+            context.next_inst()
+        }
+
+        pub fn select<C: ExecState>(
+            context: &mut C,
+            result: Register,
+            cond: Register,
+            if_nonzero: Register,
+            if_zero: Register,
+        ) -> Outcome {
+            let cond = context.get_reg(cond);
+            let if_nonzero = context.get_reg(if_nonzero);
+            let if_zero = context.get_reg(if_zero);
+            context.set_reg(result, if cond != 0 { if_nonzero } else { if_zero });
+            context.next_inst()
+        }
 
-    pub fn add(context: &mut Context, result: Register, lhs: Register, rhs: Register) -> Outcome {
-        let lhs = context.get_reg(lhs);
-        let rhs = context.get_reg(rhs);
-        context.set_reg(result, lhs.wrapping_add(rhs));
-        context.next_inst()
+        pub fn fadd(context: &mut Context, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_freg(lhs);
+            let rhs = context.get_freg(rhs);
+            context.set_freg(result, lhs + rhs);
+            context.next_inst()
+        }
+
+        pub fn fsub(context: &mut Context, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_freg(lhs);
+            let rhs = context.get_freg(rhs);
+            context.set_freg(result, lhs - rhs);
+            context.next_inst()
+        }
+
+        pub fn fmul(context: &mut Context, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_freg(lhs);
+            let rhs = context.get_freg(rhs);
+            context.set_freg(result, lhs * rhs);
+            context.next_inst()
+        }
+
+        pub fn fdiv(context: &mut Context, result: Register, lhs: Register, rhs: Register) -> Outcome {
+            let lhs = context.get_freg(lhs);
+            let rhs = context.get_freg(rhs);
+            context.set_freg(result, lhs / rhs);
+            context.next_inst()
+        }
+
+        pub fn load8(context: &mut Context, result: Register, base: Register, offset: Bits) -> Outcome {
+            let address = context.get_reg(base);
+            match context.load8(address, offset) {
+                Ok(value) => {
+                    context.set_reg(result, Bits::from(value));
+                    context.next_inst()
+                }
+                Err(trap) => Outcome::Trap(trap),
+            }
+        }
+
+        pub fn load16(
+            context: &mut Context,
+            result: Register,
+            base: Register,
+            offset: Bits,
+        ) -> Outcome {
+            let address = context.get_reg(base);
+            match context.load16(address, offset) {
+                Ok(value) => {
+                    context.set_reg(result, Bits::from(value));
+                    context.next_inst()
+                }
+                Err(trap) => Outcome::Trap(trap),
+            }
+        }
+
+        pub fn load32(
+            context: &mut Context,
+            result: Register,
+            base: Register,
+            offset: Bits,
+        ) -> Outcome {
+            let address = context.get_reg(base);
+            match context.load32(address, offset) {
+                Ok(value) => {
+                    context.set_reg(result, Bits::from(value));
+                    context.next_inst()
+                }
+                Err(trap) => Outcome::Trap(trap),
+            }
+        }
+
+        pub fn load64(
+            context: &mut Context,
+            result: Register,
+            base: Register,
+            offset: Bits,
+        ) -> Outcome {
+            let address = context.get_reg(base);
+            match context.load64(address, offset) {
+                Ok(value) => {
+                    context.set_reg(result, Bits::from(value));
+                    context.next_inst()
+                }
+                Err(trap) => Outcome::Trap(trap),
+            }
+        }
+
+        pub fn store8(context: &mut Context, base: Register, offset: Bits, value: Register) -> Outcome {
+            let address = context.get_reg(base);
+            let value = context.get_reg(value) as u8;
+            match context.store8(address, offset, value) {
+                Ok(()) => context.next_inst(),
+                Err(trap) => Outcome::Trap(trap),
+            }
+        }
+
+        pub fn store16(
+            context: &mut Context,
+            base: Register,
+            offset: Bits,
+            value: Register,
+        ) -> Outcome {
+            let address = context.get_reg(base);
+            let value = context.get_reg(value) as u16;
+            match context.store16(address, offset, value) {
+                Ok(()) => context.next_inst(),
+                Err(trap) => Outcome::Trap(trap),
+            }
+        }
+
+        pub fn store32(
+            context: &mut Context,
+            base: Register,
+            offset: Bits,
+            value: Register,
+        ) -> Outcome {
+            let address = context.get_reg(base);
+            let value = context.get_reg(value) as u32;
+            match context.store32(address, offset, value) {
+                Ok(()) => context.next_inst(),
+                Err(trap) => Outcome::Trap(trap),
+            }
+        }
+
+        pub fn store64(
+            context: &mut Context,
+            base: Register,
+            offset: Bits,
+            value: Register,
+        ) -> Outcome {
+            let address = context.get_reg(base);
+            let value = context.get_reg(value);
+            match context.store64(address, offset, value) {
+                Ok(()) => context.next_inst(),
+                Err(trap) => Outcome::Trap(trap),
+            }
+        }
+
+        pub fn branch<C: ExecState>(context: &mut C, target: Target) -> Outcome {
+            context.branch_to(target.0)
+        }
+
+        pub fn branch_eqz<C: ExecState>(context: &mut C, target: Target, condition: Register) -> Outcome {
+            let condition = context.get_reg(condition);
+            if condition == 0 {
+                context.branch_to(target.0)
+            } else {
+                context.next_inst()
+            }
+        }
+
+        pub fn branch_nez<C: ExecState>(context: &mut C, target: Target, condition: Register) -> Outcome {
+            let condition = context.get_reg(condition);
+            if condition != 0 {
+                context.branch_to(target.0)
+            } else {
+                context.next_inst()
+            }
+        }
+
+        pub fn branch_eq<C: ExecState>(context: &mut C, target: Target, lhs: Register, rhs: Register) -> Outcome {
+            if context.get_reg(lhs) == context.get_reg(rhs) {
+                context.branch_to(target.0)
+            } else {
+                context.next_inst()
+            }
+        }
+
+        pub fn branch_ne<C: ExecState>(context: &mut C, target: Target, lhs: Register, rhs: Register) -> Outcome {
+            if context.get_reg(lhs) != context.get_reg(rhs) {
+                context.branch_to(target.0)
+            } else {
+                context.next_inst()
+            }
+        }
+
+        pub fn branch_lt_u<C: ExecState>(context: &mut C, target: Target, lhs: Register, rhs: Register) -> Outcome {
+            if cmp_u(context.get_reg(lhs), context.get_reg(rhs)).is_lt() {
+                context.branch_to(target.0)
+            } else {
+                context.next_inst()
+            }
+        }
+
+        pub fn branch_lt_s<C: ExecState>(context: &mut C, target: Target, lhs: Register, rhs: Register) -> Outcome {
+            if cmp_s(context.get_reg(lhs), context.get_reg(rhs)).is_lt() {
+                context.branch_to(target.0)
+            } else {
+                context.next_inst()
+            }
+        }
+
+        pub fn branch_table<C: ExecState>(
+            context: &mut C,
+            index: Register,
+            targets: &[Target],
+            default: Target,
+        ) -> Outcome {
+            let index = context.get_reg(index);
+            let target = usize::try_from(index)
+                .ok()
+                .and_then(|index| targets.get(index))
+                .copied()
+                .unwrap_or(default);
+            context.branch_to(target.0)
+        }
+
+        pub fn nop<C: ExecState>(context: &mut C) -> Outcome {
+            context.next_inst()
+        }
+
+        pub fn yield_<C: ExecState>(context: &mut C) -> Outcome {
+            context.next_inst();
+            Outcome::Yield
+        }
+
+        pub fn unreachable() -> Outcome {
+            Outcome::Trap(TrapCode::UnreachableExecuted)
+        }
+
+        pub fn call(context: &mut Context, target: Target, num_args: usize) -> Outcome {
+            context.call(target, num_args)
+        }
+
+        pub fn rand_next(context: &mut Context, result: Register) -> Outcome {
+            let value = context.next_random();
+            context.set_reg(result, value);
+            context.next_inst()
+        }
+
+        pub fn ret(context: &mut Context, result: Register) -> Outcome {
+            let result = context.get_reg(result);
+            context.return_with(result)
+        }
     }
+}
+
+/// The operands of an instruction passed to the handlers of the [`HANDLERS`] table.
+///
+/// The slots hold the operands in the order of the instruction fields, registers and
+/// targets are converted to [`Bits`]. Unused slots are zero.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct InstArgs {
+    pub a: Bits,
+    pub b: Bits,
+    pub c: Bits,
+}
+
+/// Decodes a handler operand from an [`InstArgs`] slot.
+trait FromArg {
+    fn from_arg(arg: Bits) -> Self;
+}
 
-    pub fn add_imm(context: &mut Context, result: Register, src: Register, imm: Bits) -> Outcome {
-        let lhs = context.get_reg(src);
-        let rhs = imm;
-        context.set_reg(result, lhs.wrapping_add(rhs));
-        context.next_inst()
+impl FromArg for Bits {
+    fn from_arg(arg: Bits) -> Self {
+        arg
     }
+}
 
-    pub fn sub(context: &mut Context, result: Register, lhs: Register, rhs: Register) -> Outcome {
-        let lhs = context.get_reg(lhs);
-        let rhs = context.get_reg(rhs);
-        context.set_reg(result, lhs.wrapping_sub(rhs));
-        context.next_inst()
+impl FromArg for usize {
+    fn from_arg(arg: Bits) -> Self {
+        arg as usize
     }
+}
 
-    pub fn sub_imm(context: &mut Context, result: Register, src: Register, imm: Bits) -> Outcome {
-        let lhs = context.get_reg(src);
-        let rhs = imm;
-        context.set_reg(result, lhs.wrapping_sub(rhs));
-        context.next_inst()
+impl FromArg for Register {
+    fn from_arg(arg: Bits) -> Self {
+        Register(arg as usize)
     }
+}
 
-    pub fn mul(context: &mut Context, result: Register, lhs: Register, rhs: Register) -> Outcome {
-        let lhs = context.get_reg(lhs);
-        let rhs = context.get_reg(rhs);
-        context.set_reg(result, lhs.wrapping_mul(rhs));
-        context.next_inst()
+impl FromArg for Target {
+    fn from_arg(arg: Bits) -> Self {
+        Target(arg as usize)
     }
+}
+
+/// A handler of the [`HANDLERS`] table.
+pub type Handler = fn(&mut Context, InstArgs) -> Outcome;
 
-    pub fn mul_imm(context: &mut Context, result: Register, src: Register, imm: Bits) -> Outcome {
-        let lhs = context.get_reg(src);
-        let rhs = imm;
-        context.set_reg(result, lhs.wrapping_mul(rhs));
-        context.next_inst()
+/// Builds the handler table from the opcodes and their handler with the types of its operand slots.
+macro_rules! handler_table {
+    ( $( $opcode:ident => $handler:ident ( $( $slot:ident: $ty:ty ),* ) ),* $(,)? ) => {{
+        let mut table: [Handler; switch::N_OPCODES] =
+            [|_, _| Outcome::Trap(TrapCode::UnreachableExecuted); switch::N_OPCODES];
+        $(
+            table[bytecode::opcode::$opcode as usize] =
+                |context, args| handler::$handler(context, $( <$ty as FromArg>::from_arg(args.$slot) ),*);
+        )*
+        table[bytecode::opcode::NOP as usize] = |context, _| handler::nop(context);
+        table[bytecode::opcode::UNREACHABLE as usize] = |_, _| handler::unreachable();
+        table[bytecode::opcode::YIELD as usize] = |context, _| handler::yield_(context);
+        table
+    }};
+}
+
+/// The handlers of all instructions indexed by their [`switch::Inst::opcode`].
+///
+/// Instructions whose operands do not fit into the [`InstArgs`] slots, namely `select`
+/// and `branch_table`, trap with [`TrapCode::UnreachableExecuted`] instead.
+pub static HANDLERS: [Handler; switch::N_OPCODES] = handler_table! {
+    ADD => add(a: Register, b: Register, c: Register),
+    ADD_IMM => add_imm(a: Register, b: Register, c: Bits),
+    SUB => sub(a: Register, b: Register, c: Register),
+    SUB_IMM => sub_imm(a: Register, b: Register, c: Bits),
+    MUL => mul(a: Register, b: Register, c: Register),
+    MUL_IMM => mul_imm(a: Register, b: Register, c: Bits),
+    DIV => div(a: Register, b: Register, c: Register),
+    DIV_IMM => div_imm(a: Register, b: Register, c: Bits),
+    REM => rem(a: Register, b: Register, c: Register),
+    REM_IMM => rem_imm(a: Register, b: Register, c: Bits),
+    SHL => shl(a: Register, b: Register, c: Register),
+    SHL_IMM => shl_imm(a: Register, b: Register, c: Bits),
+    SHR_U => shr_u(a: Register, b: Register, c: Register),
+    SHR_U_IMM => shr_u_imm(a: Register, b: Register, c: Bits),
+    SHR_S => shr_s(a: Register, b: Register, c: Register),
+    SHR_S_IMM => shr_s_imm(a: Register, b: Register, c: Bits),
+    ROTL => rotl(a: Register, b: Register, c: Register),
+    ROTL_IMM => rotl_imm(a: Register, b: Register, c: Bits),
+    ROTR => rotr(a: Register, b: Register, c: Register),
+    ROTR_IMM => rotr_imm(a: Register, b: Register, c: Bits),
+    EQ => eq(a: Register, b: Register, c: Register),
+    EQ_IMM => eq_imm(a: Register, b: Register, c: Bits),
+    NE => ne(a: Register, b: Register, c: Register),
+    NE_IMM => ne_imm(a: Register, b: Register, c: Bits),
+    LT_U => lt_u(a: Register, b: Register, c: Register),
+    LT_U_IMM => lt_u_imm(a: Register, b: Register, c: Bits),
+    LT_S => lt_s(a: Register, b: Register, c: Register),
+    LT_S_IMM => lt_s_imm(a: Register, b: Register, c: Bits),
+    MOVE => mov(a: Register, b: Register),
+    ADD32 => add32(a: Register, b: Register, c: Register),
+    SUB32 => sub32(a: Register, b: Register, c: Register),
+    MUL32 => mul32(a: Register, b: Register, c: Register),
+    LT_S32 => lt_s32(a: Register, b: Register, c: Register),
+    SHL32 => shl32(a: Register, b: Register, c: Register),
+    SHR_U32 => shr_u32(a: Register, b: Register, c: Register),
+    SHR_S32 => shr_s32(a: Register, b: Register, c: Register),
+    ADD_CHECKED => add_checked(a: Register, b: Register, c: Register),
+    SUB_CHECKED => sub_checked(a: Register, b: Register, c: Register),
+    MUL_CHECKED => mul_checked(a: Register, b: Register, c: Register),
+    POPCNT => popcnt(a: Register, b: Register),
+    CLZ => clz(a: Register, b: Register),
+    CTZ => ctz(a: Register, b: Register),
+    RAND_NEXT => rand_next(a: Register),
+    MIN_U => min_u(a: Register, b: Register, c: Register),
+    MIN_U_IMM => min_u_imm(a: Register, b: Register, c: Bits),
+    MAX_U => max_u(a: Register, b: Register, c: Register),
+    MAX_U_IMM => max_u_imm(a: Register, b: Register, c: Bits),
+    MIN_S => min_s(a: Register, b: Register, c: Register),
+    MIN_S_IMM => min_s_imm(a: Register, b: Register, c: Bits),
+    MAX_S => max_s(a: Register, b: Register, c: Register),
+    MAX_S_IMM => max_s_imm(a: Register, b: Register, c: Bits),
+    FADD => fadd(a: Register, b: Register, c: Register),
+    FSUB => fsub(a: Register, b: Register, c: Register),
+    FMUL => fmul(a: Register, b: Register, c: Register),
+    FDIV => fdiv(a: Register, b: Register, c: Register),
+    LOAD8 => load8(a: Register, b: Register, c: Bits),
+    LOAD16 => load16(a: Register, b: Register, c: Bits),
+    LOAD32 => load32(a: Register, b: Register, c: Bits),
+    LOAD64 => load64(a: Register, b: Register, c: Bits),
+    STORE8 => store8(a: Register, b: Bits, c: Register),
+    STORE16 => store16(a: Register, b: Bits, c: Register),
+    STORE32 => store32(a: Register, b: Bits, c: Register),
+    STORE64 => store64(a: Register, b: Bits, c: Register),
+    BRANCH => branch(a: Target),
+    BRANCH_EQZ => branch_eqz(a: Target, b: Register),
+    BRANCH_NEZ => branch_nez(a: Target, b: Register),
+    BRANCH_EQ => branch_eq(a: Target, b: Register, c: Register),
+    BRANCH_NE => branch_ne(a: Target, b: Register, c: Register),
+    BRANCH_LT_U => branch_lt_u(a: Target, b: Register, c: Register),
+    BRANCH_LT_S => branch_lt_s(a: Target, b: Register, c: Register),
+    CALL => call(a: Target, b: usize),
+    RETURN => ret(a: Register),
+};
+
+#[test]
+fn handlers_match_execute() {
+    let mut rng = bytecode::Rng(0x2545_F491_4F6C_DD1D);
+    for _ in 0..1_000 {
+        let inst = rng.inst();
+        let Some(args) = inst.args() else {
+            continue;
+        };
+        if let switch::Inst::Call { .. } = inst {
+            continue;
+        }
+        let mut expected = Context::default();
+        let mut context = Context::default();
+        for reg in 0..NUM_REGISTERS {
+            expected.set_reg(Register(reg), reg as Bits * 3);
+            context.set_reg(Register(reg), reg as Bits * 3);
+            expected.set_freg(Register(reg), reg as f64 + 0.5);
+            context.set_freg(Register(reg), reg as f64 + 0.5);
+        }
+        inst.execute(&mut expected);
+        HANDLERS[usize::from(inst.opcode())](&mut context, args);
+        assert_eq!(context.snapshot(), expected.snapshot(), "{inst:?}");
     }
+}
 
-    pub fn branch(context: &mut Context, target: Register) -> Outcome {
-        context.branch_to(target as usize)
+#[test]
+fn generic_handlers() {
+    /// An [`ExecState`] recording all register writes.
+    #[derive(Default)]
+    struct Recorder {
+        pc: usize,
+        regs: [Bits; 4],
+        writes: Vec<(Register, Bits)>,
     }
 
-    pub fn branch_eqz(context: &mut Context, target: Register, condition: Register) -> Outcome {
-        let condition = context.get_reg(condition);
-        if condition == 0 {
-            context.branch_to(target as usize)
-        } else {
-            context.next_inst()
+    impl ExecState for Recorder {
+        fn get_reg(&self, reg: Register) -> Bits {
+            self.regs[reg.0]
+        }
+
+        fn set_reg(&mut self, reg: Register, new_value: Bits) {
+            self.writes.push((reg, new_value));
+            self.regs[reg.0] = new_value;
+        }
+
+        fn branch_to(&mut self, new_pc: usize) -> Outcome {
+            self.pc = new_pc;
+            Outcome::Continue
+        }
+
+        fn next_inst(&mut self) -> Outcome {
+            self.pc += 1;
+            Outcome::Continue
         }
     }
 
-    pub fn ret(context: &mut Context, result: Register) -> Outcome {
-        let result = context.get_reg(result);
-        context.set_reg(0, result);
-        Outcome::Return
+    let mut recorder = Recorder {
+        regs: [2, 3, 0, 0],
+        ..Recorder::default()
+    };
+    handler::add(&mut recorder, Register(2), Register(0), Register(1));
+    handler::mul_imm(&mut recorder, Register(3), Register(2), 10);
+    handler::branch_nez(&mut recorder, Target(7), Register(3));
+    assert_eq!(recorder.writes, [(Register(2), 5), (Register(3), 50)]);
+    assert_eq!(recorder.regs, [2, 3, 5, 50]);
+    assert_eq!(recorder.pc, 7);
+}
+
+#[test]
+fn branch_handlers() {
+    let mut context = Context::default();
+    context.set_reg(Register(3), 1);
+    handler::branch(&mut context, Target(5));
+    assert_eq!(context.pc, 5);
+    handler::branch_eqz(&mut context, Target(9), Register(3));
+    assert_eq!(context.pc, 6);
+    handler::branch_nez(&mut context, Target(2), Register(3));
+    assert_eq!(context.pc, 2);
+    // The table decodes the first operand of `branch` as a target, not as a register.
+    let args = InstArgs { a: 12, b: 0, c: 0 };
+    HANDLERS[bytecode::opcode::BRANCH as usize](&mut context, args);
+    assert_eq!(context.pc, 12);
+}
+
+#[test]
+fn arith_modes() {
+    let insts = [
+        switch::Inst::Add {
+            result: Register(2),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        switch::Inst::Return {
+            result: Register(2),
+        },
+    ];
+    let run = |arith_mode| {
+        let mut context = Context::with_arith_mode(arith_mode);
+        context.set_reg(Register(0), Bits::MAX);
+        context.set_reg(Register(1), 2);
+        switch::run(&insts, &mut context)
+    };
+    assert_eq!(run(ArithMode::Wrapping), Ok(1));
+    assert_eq!(run(ArithMode::Saturating), Ok(Bits::MAX));
+    assert_eq!(run(ArithMode::Checked), Err(TrapCode::IntegerOverflow));
+    assert_eq!(ArithMode::Saturating.sub(2, 3), Some(0));
+    assert_eq!(ArithMode::Checked.mul(Bits::MAX, 2), None);
+    assert_eq!(ArithMode::Checked.mul(3, 2), Some(6));
+}
+
+#[test]
+fn context_with_registers() {
+    let mut context = Context::with_registers(64);
+    context.set_reg(Register(63), 42);
+    assert_eq!(context.get_reg(Register(63)), 42);
+    assert_eq!(Context::default().regs.len(), NUM_REGISTERS);
+    assert_eq!(NUM_REGISTERS, 16);
+}
+
+#[test]
+fn context_register_slices() {
+    let mut context = Context::default();
+    for (reg, value) in context.regs_mut().iter_mut().enumerate() {
+        *value = reg as Bits * 10;
     }
+    context.globals_mut().fill(7);
+    for reg in 0..NUM_REGISTERS {
+        assert_eq!(context.get_reg(Register(reg)), reg as Bits * 10);
+    }
+    assert_eq!(context.get_global(15), 7);
+    context.set_base(4);
+    context.regs_mut()[0] = 1;
+    assert_eq!(context.regs().len(), NUM_REGISTERS);
+    assert_eq!(context.get_reg(Register(0)), 1);
+    assert_eq!(context.get_reg_abs(4), 1);
+    assert_eq!(context.regs()[1], 50);
+    assert!(context.globals().iter().all(|&global| global == 7));
+}
+
+#[test]
+fn context_register_window() {
+    let mut context = Context::default();
+    context.set_reg(Register(0), 1);
+    context.set_base(4);
+    assert_eq!(context.get_base(), 4);
+    assert_eq!(context.get_reg(Register(0)), 0);
+    context.set_reg(Register(0), 2);
+    context.set_reg(Register(15), 3);
+    assert_eq!(context.regs.len(), 4 + NUM_REGISTERS);
+    assert_eq!(context.get_reg_abs(0), 1);
+    assert_eq!(context.get_reg_abs(4), 2);
+    assert_eq!(context.get_reg_abs(19), 3);
+    context.set_base(0);
+    assert_eq!(context.get_reg(Register(0)), 1);
+    assert_eq!(context.get_reg(Register(4)), 2);
+    // A trap within a callee leaves the `r0` of the outermost function intact.
+    let insts = [
+        switch::Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 5,
+        },
+        switch::Inst::Call {
+            target: Target(3),
+            num_args: 0,
+        },
+        switch::Inst::Return {
+            result: Register(0),
+        },
+        switch::Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 7,
+        },
+        switch::Inst::Unreachable,
+    ];
+    let mut context = Context::default();
+    assert_eq!(
+        switch::execute(&insts, &mut context),
+        Err(TrapCode::UnreachableExecuted)
+    );
+    assert_eq!(context.get_reg_abs(0), 5);
+    assert_eq!(context.get_reg(Register(0)), 7);
+}
+
+#[test]
+fn context_reset() {
+    let mut context = Context::default();
+    let insts = [
+        switch::Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 7,
+        },
+        switch::Inst::Call {
+            target: Target(3),
+            num_args: 2,
+        },
+        switch::Inst::Return {
+            result: Register(0),
+        },
+        switch::Inst::Store8 {
+            base: Register(1),
+            offset: 0,
+            value: Register(1),
+        },
+        switch::Inst::Return {
+            result: Register(1),
+        },
+    ];
+    assert_eq!(switch::run(&insts, &mut context), Ok(7));
+    context.set_global(3, 11);
+    context.set_freg(Register(2), 1.5);
+    context.call(Target(0), 0);
+    let capacities = (context.regs.capacity(), context.globals.capacity());
+    context.reset();
+    assert_eq!(context.pc, 0);
+    assert_eq!(context.base, 0);
+    assert!(context.frames.is_empty());
+    assert!(context.regs.iter().all(|&reg| reg == 0));
+    assert!(context.fregs.iter().all(|&reg| reg == 0.0));
+    assert!(context.globals.iter().all(|&global| global == 0));
+    assert!(context.mem.iter().all(|&byte| byte == 0));
+    assert_eq!(context.regs.len(), NUM_REGISTERS);
+    assert_eq!(context.regs.capacity(), capacities.0);
+    assert_eq!(context.globals.capacity(), capacities.1);
+}
+
+#[test]
+fn context_reset_zeroes_dirty_memory_only() {
+    let mut context = Context::default();
+    context.reset();
+    assert_eq!(context.mem_dirty, 0);
+    context.store64(8, 0, 1).unwrap();
+    context.store8(2, 0, 1).unwrap();
+    assert_eq!(context.mem_dirty, 16);
+    assert_eq!(context.store8(MEMORY_SIZE as Bits, 0, 1), Err(TrapCode::OutOfBoundsMemory));
+    assert_eq!(context.mem_dirty, 16);
+    context.reset();
+    assert_eq!(context.mem_dirty, 0);
+    assert!(context.mem.iter().all(|&byte| byte == 0));
+    context.store8(MEMORY_SIZE as Bits - 1, 0, 1).unwrap();
+    assert_eq!(context.mem_dirty, MEMORY_SIZE);
+    context.reset();
+    assert!(context.mem.iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn context_snapshot_restore() {
+    let mut context = Context::default();
+    context.set_reg(Register(0), 3);
+    context.set_global(2, 5);
+    let snapshot = context.snapshot();
+    let insts = [
+        switch::Inst::Call {
+            target: Target(2),
+            num_args: 1,
+        },
+        switch::Inst::Return {
+            result: Register(0),
+        },
+        switch::Inst::MulImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 7,
+        },
+        switch::Inst::AddImm {
+            result: Register(4),
+            src: Register(0),
+            imm: 1,
+        },
+        switch::Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_eq!(switch::run(&insts, &mut context), Ok(21));
+    context.set_global(2, 11);
+    context.set_freg(Register(1), 2.5);
+    context.pc = 2;
+    context.call(Target(3), 0);
+    assert_ne!(context.snapshot(), snapshot);
+    context.restore(&snapshot);
+    assert_eq!(context.snapshot(), snapshot);
+    assert_eq!(context.pc, 0);
+    assert_eq!(context.get_reg(Register(0)), 3);
+    assert_eq!(context.get_global(2), 5);
+    assert_eq!(context.regs.len(), NUM_REGISTERS);
+    assert!(context.frames.is_empty());
+    assert!(Rc::ptr_eq(&snapshot.regs, &snapshot.clone().regs));
 }
@@ -0,0 +1,411 @@
+#![allow(dead_code)]
+
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use super::{switch::Inst, validate, Register, Target};
+
+/// Removes every `branch` to its immediately following instruction from `insts`.
+///
+/// All remaining branch, branch table and call targets are renumbered to account for the
+/// removed instructions. A target pointing at a removed branch continues with the instruction
+/// that follows it, which is exactly where the removed branch would have jumped to.
+pub fn simplify_branches(insts: &[Inst]) -> Vec<Inst> {
+    let is_redundant = insts
+        .iter()
+        .enumerate()
+        .map(|(pc, inst)| matches!(*inst, Inst::Branch { target } if target == Target(pc + 1)))
+        .collect::<Vec<_>>();
+    remove(insts, &is_redundant)
+}
+
+/// Fuses comparisons immediately followed by a `branch_eqz` or `branch_nez` on their result
+/// into a single compare-and-branch instruction such as [`Inst::BranchEq`].
+///
+/// Since the fused branch neither writes the result of the comparison nor can be entered in
+/// between, a pair is only fused if no other instruction uses the result register and the
+/// conditional branch is not a branch or call target. Calls count as using their argument
+/// registers. `lt_u` and `lt_s` followed by `branch_eqz` have no fused form and are kept.
+pub fn fuse_compare_branches(insts: &[Inst]) -> Vec<Inst> {
+    let mut is_target = vec![false; insts.len()];
+    let mut uses = BTreeMap::<Register, usize>::new();
+    for inst in insts {
+        for target in validate::targets(inst) {
+            if let Some(is_target) = is_target.get_mut(target.0) {
+                *is_target = true;
+            }
+        }
+        let mut registers = match *inst {
+            Inst::Call { num_args, .. } => (0..num_args).map(Register).collect(),
+            _ => validate::registers(inst),
+        };
+        registers.sort_unstable();
+        registers.dedup();
+        for register in registers {
+            *uses.entry(register).or_default() += 1;
+        }
+    }
+    let mut fused = insts.to_vec();
+    let mut is_fused = vec![false; insts.len()];
+    let mut pc = 0;
+    while pc + 1 < insts.len() {
+        let branch = (!is_target[pc + 1])
+            .then(|| fuse_compare_branch(&insts[pc], &insts[pc + 1]))
+            .flatten();
+        match branch {
+            Some((result, branch)) if uses[&result] == 2 => {
+                fused[pc] = branch;
+                is_fused[pc + 1] = true;
+                pc += 2;
+            }
+            _ => pc += 1,
+        }
+    }
+    remove(&fused, &is_fused)
+}
+
+/// Returns the result register of the comparison `cmp` together with the compare-and-branch
+/// instruction equivalent to `cmp` followed by `branch` if `branch` tests that result.
+fn fuse_compare_branch(cmp: &Inst, branch: &Inst) -> Option<(Register, Inst)> {
+    let (branch_if_zero, target, condition) = match *branch {
+        Inst::BranchEqz { target, condition } => (true, target, condition),
+        Inst::BranchNez { target, condition } => (false, target, condition),
+        _ => return None,
+    };
+    let (result, fused) = match (cmp, branch_if_zero) {
+        (&Inst::Eq { result, lhs, rhs }, false) | (&Inst::Ne { result, lhs, rhs }, true) => {
+            (result, Inst::BranchEq { target, lhs, rhs })
+        }
+        (&Inst::Ne { result, lhs, rhs }, false) | (&Inst::Eq { result, lhs, rhs }, true) => {
+            (result, Inst::BranchNe { target, lhs, rhs })
+        }
+        (&Inst::LtU { result, lhs, rhs }, false) => (result, Inst::BranchLtU { target, lhs, rhs }),
+        (&Inst::LtS { result, lhs, rhs }, false) => (result, Inst::BranchLtS { target, lhs, rhs }),
+        _ => return None,
+    };
+    (result == condition).then_some((result, fused))
+}
+
+/// Removes the instructions of `insts` flagged in `removed`.
+///
+/// All remaining branch, branch table and call targets are renumbered to account for the
+/// removed instructions. A target pointing at a removed instruction continues with the
+/// instruction that follows it.
+fn remove(insts: &[Inst], removed: &[bool]) -> Vec<Inst> {
+    // The number of removed instructions in front of every index including one past the end.
+    let mut removed_before = Vec::with_capacity(insts.len() + 1);
+    let mut count = 0;
+    for &removed in removed {
+        removed_before.push(count);
+        count += usize::from(removed);
+    }
+    removed_before.push(count);
+    let remap =
+        |target: Target| Target(target.0 - removed_before.get(target.0).copied().unwrap_or(count));
+    insts
+        .iter()
+        .zip(removed)
+        .filter(|(_, &removed)| !removed)
+        .map(|(inst, _)| retarget(inst.clone(), remap))
+        .collect()
+}
+
+/// Returns `inst` with all of its branch, branch table and call targets replaced via `remap`.
+pub(crate) fn retarget(inst: Inst, mut remap: impl FnMut(Target) -> Target) -> Inst {
+    match inst {
+        Inst::Branch { target } => Inst::Branch {
+            target: remap(target),
+        },
+        Inst::BranchEqz { target, condition } => Inst::BranchEqz {
+            target: remap(target),
+            condition,
+        },
+        Inst::BranchNez { target, condition } => Inst::BranchNez {
+            target: remap(target),
+            condition,
+        },
+        Inst::BranchEq { target, lhs, rhs } => Inst::BranchEq {
+            target: remap(target),
+            lhs,
+            rhs,
+        },
+        Inst::BranchNe { target, lhs, rhs } => Inst::BranchNe {
+            target: remap(target),
+            lhs,
+            rhs,
+        },
+        Inst::BranchLtU { target, lhs, rhs } => Inst::BranchLtU {
+            target: remap(target),
+            lhs,
+            rhs,
+        },
+        Inst::BranchLtS { target, lhs, rhs } => Inst::BranchLtS {
+            target: remap(target),
+            lhs,
+            rhs,
+        },
+        Inst::BranchTable {
+            index,
+            targets,
+            default,
+        } => Inst::BranchTable {
+            index,
+            targets: targets.iter().map(|&target| remap(target)).collect(),
+            default: remap(default),
+        },
+        Inst::Call { target, num_args } => Inst::Call {
+            target: remap(target),
+            num_args,
+        },
+        inst => inst,
+    }
+}
+
+#[cfg(test)]
+use super::{switch, Context};
+
+#[test]
+fn redundant_forward_branch() {
+    let insts = [
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 3,
+        },
+        // Redundant since it jumps to the next instruction.
+        Inst::Branch { target: Target(2) },
+        // Loop header.
+        Inst::BranchEqz {
+            target: Target(6),
+            condition: Register(0),
+        },
+        Inst::SubImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 2,
+        },
+        Inst::Branch { target: Target(2) },
+        Inst::Return {
+            result: Register(1),
+        },
+    ];
+    let simplified = simplify_branches(&insts);
+    assert_eq!(
+        simplified,
+        [
+            Inst::AddImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 3,
+            },
+            Inst::BranchEqz {
+                target: Target(5),
+                condition: Register(0),
+            },
+            Inst::SubImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 1,
+            },
+            Inst::AddImm {
+                result: Register(1),
+                src: Register(1),
+                imm: 2,
+            },
+            Inst::Branch { target: Target(1) },
+            Inst::Return {
+                result: Register(1)
+            },
+        ]
+    );
+    assert_eq!(
+        switch::run(&simplified, &mut Context::default()),
+        switch::run(&insts, &mut Context::default()),
+    );
+}
+
+#[test]
+fn target_at_removed_branch() {
+    let insts = [
+        Inst::BranchNez {
+            target: Target(2),
+            condition: Register(0),
+        },
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 1,
+        },
+        // Redundant and itself the target of the first branch.
+        Inst::Branch { target: Target(3) },
+        Inst::Branch { target: Target(4) },
+        Inst::Return {
+            result: Register(1),
+        },
+    ];
+    let simplified = simplify_branches(&insts);
+    assert_eq!(
+        simplified,
+        [
+            Inst::BranchNez {
+                target: Target(2),
+                condition: Register(0),
+            },
+            Inst::AddImm {
+                result: Register(1),
+                src: Register(1),
+                imm: 1,
+            },
+            Inst::Return {
+                result: Register(1)
+            },
+        ]
+    );
+    for condition in [0, 1] {
+        let mut expected = Context::default();
+        let mut context = Context::default();
+        expected.set_reg(Register(0), condition);
+        context.set_reg(Register(0), condition);
+        assert_eq!(
+            switch::run(&simplified, &mut context),
+            switch::run(&insts, &mut expected),
+        );
+    }
+}
+
+/// Returns a loop searching the smallest `i < 100` with `i * i == square` in r0.
+///
+/// Yields `1_100` if there is no such `i`.
+#[cfg(test)]
+fn square_search(square: crate::Bits) -> Vec<Inst> {
+    vec![
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: square,
+        },
+        Inst::AddImm {
+            result: Register(4),
+            src: Register(4),
+            imm: 100,
+        },
+        // Loop header.
+        Inst::Mul {
+            result: Register(2),
+            lhs: Register(0),
+            rhs: Register(0),
+        },
+        Inst::Eq {
+            result: Register(3),
+            lhs: Register(2),
+            rhs: Register(1),
+        },
+        Inst::BranchNez {
+            target: Target(9),
+            condition: Register(3),
+        },
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        Inst::LtU {
+            result: Register(5),
+            lhs: Register(0),
+            rhs: Register(4),
+        },
+        Inst::BranchNez {
+            target: Target(2),
+            condition: Register(5),
+        },
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1_000,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ]
+}
+
+#[test]
+fn fuse_search_loop() {
+    let insts = square_search(49);
+    let fused = fuse_compare_branches(&insts);
+    assert_eq!(
+        fused[2..6],
+        [
+            Inst::Mul {
+                result: Register(2),
+                lhs: Register(0),
+                rhs: Register(0),
+            },
+            Inst::BranchEq {
+                target: Target(7),
+                lhs: Register(2),
+                rhs: Register(1),
+            },
+            Inst::AddImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 1,
+            },
+            Inst::BranchLtU {
+                target: Target(2),
+                lhs: Register(0),
+                rhs: Register(4),
+            },
+        ]
+    );
+    assert_eq!(fused.len(), insts.len() - 2);
+    for square in [0, 49, 50, 81] {
+        let insts = square_search(square);
+        let fused = fuse_compare_branches(&insts);
+        assert_eq!(
+            switch::run(&fused, &mut Context::default()),
+            switch::run(&insts, &mut Context::default()),
+        );
+    }
+    assert_eq!(switch::run(&fused, &mut Context::default()), Ok(7));
+}
+
+#[test]
+fn fuse_keeps_used_results() {
+    let mut insts = square_search(49);
+    // Reading the comparison result after the loop prevents fusing `eq`.
+    insts[8] = Inst::Add {
+        result: Register(0),
+        lhs: Register(0),
+        rhs: Register(3),
+    };
+    // A `lt_u` followed by `branch_eqz` has no fused form.
+    insts[7] = Inst::BranchEqz {
+        target: Target(9),
+        condition: Register(5),
+    };
+    insts.insert(8, Inst::Branch { target: Target(2) });
+    assert_eq!(fuse_compare_branches(&insts), insts);
+    assert_eq!(switch::run(&insts, &mut Context::default()), Ok(8));
+
+    // A conditional branch that is a branch target is kept.
+    let insts = [
+        Inst::Eq {
+            result: Register(1),
+            lhs: Register(0),
+            rhs: Register(0),
+        },
+        Inst::BranchNez {
+            target: Target(1),
+            condition: Register(1),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_eq!(fuse_compare_branches(&insts), insts);
+}
@@ -0,0 +1,106 @@
+//! Programs bundling their instructions with the metadata required to execute them.
+
+use alloc::vec::Vec;
+
+#[cfg(test)]
+use super::Register;
+use super::{
+    switch::{self, Inst},
+    validate::{validate_with_registers, ValidationError},
+    Bits, Context, Target, TrapCode,
+};
+
+/// A validated [`switch`] program together with its entry point and register count.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    /// The instructions of all functions of the program.
+    insts: Vec<Inst>,
+    /// The index of the instruction that starts execution.
+    entry: Target,
+    /// The number of registers of the register window of every function.
+    num_registers: usize,
+}
+
+impl Program {
+    /// Creates a new [`Program`] starting at `entry` that requires `num_registers` registers.
+    ///
+    /// # Errors
+    ///
+    /// If `insts` fail to validate for `num_registers` registers or `entry` is out of bounds.
+    pub fn new(
+        insts: Vec<Inst>,
+        entry: Target,
+        num_registers: usize,
+    ) -> Result<Self, ValidationError> {
+        validate_with_registers(&insts, num_registers)?;
+        if entry >= Target(insts.len()) {
+            return Err(ValidationError::EntryOutOfBounds { entry });
+        }
+        Ok(Self {
+            insts,
+            entry,
+            num_registers,
+        })
+    }
+
+    /// Returns the instructions of the [`Program`].
+    pub fn insts(&self) -> &[Inst] {
+        &self.insts
+    }
+
+    /// Returns the index of the instruction that starts execution.
+    pub fn entry(&self) -> Target {
+        self.entry
+    }
+
+    /// Returns the number of registers required by the [`Program`].
+    pub fn num_registers(&self) -> usize {
+        self.num_registers
+    }
+
+    /// Runs the [`Program`] on a fresh [`Context`] with the required number of registers.
+    ///
+    /// # Errors
+    ///
+    /// If the execution traps.
+    pub fn run(&self) -> Result<Bits, TrapCode> {
+        let mut context = Context::with_registers(self.num_registers);
+        context.pc = self.entry.0;
+        switch::run(&self.insts, &mut context)
+    }
+}
+
+#[test]
+fn high_register() {
+    let insts = vec![
+        // The entry skips the unreachable instruction.
+        Inst::Unreachable,
+        Inst::AddImm {
+            result: Register(31),
+            src: Register(31),
+            imm: 7,
+        },
+        Inst::MulImm {
+            result: Register(0),
+            src: Register(31),
+            imm: 6,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let program = Program::new(insts.clone(), Target(1), 32).unwrap();
+    assert_eq!(program.num_registers(), 32);
+    assert_eq!(program.run(), Ok(42));
+    assert_eq!(
+        Program::new(insts.clone(), Target(1), 16),
+        Err(ValidationError::RegisterOutOfBounds {
+            index: 1,
+            register: Register(31)
+        })
+    );
+    assert_eq!(
+        Program::new(insts, Target(4), 32),
+        Err(ValidationError::EntryOutOfBounds { entry: Target(4) })
+    );
+}
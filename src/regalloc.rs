@@ -0,0 +1,770 @@
+#![allow(dead_code)]
+
+use alloc::vec::Vec;
+
+use smallvec::SmallVec;
+
+use super::{cfg, peephole, switch::Inst, Bits, Register, Target, MEMORY_SIZE};
+#[cfg(test)]
+use super::{switch, Context};
+
+/// An error that prevents allocating the registers of a program.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AllocError {
+    /// The instruction at `index` is a call whose arguments and result use fixed registers.
+    Call { index: usize },
+    /// Spilling requires more than the `num_physical` available registers.
+    TooFewRegisters { num_physical: usize },
+    /// Spilling is required but the instruction at `index` accesses the memory holding the spill slots.
+    Memory { index: usize },
+}
+
+/// The number of physical registers reserved for the spilled operands of a single instruction.
+const NUM_SCRATCH: usize = 3;
+
+/// The place of a virtual register after allocation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Location {
+    /// The virtual register is kept in the physical register.
+    Register(Register),
+    /// The virtual register is kept in the 8 byte spill slot at the end of the memory.
+    Spilled { slot: usize },
+}
+
+/// The instructions from `start` up to and including `end` at which `register` is live or written.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Interval {
+    register: usize,
+    start: usize,
+    end: usize,
+}
+
+/// Remaps the virtual registers of `insts` to the physical registers `r0` up to `num_physical`
+/// via linear scan allocation over the live ranges found by [`cfg::liveness`].
+///
+/// Virtual registers whose live ranges do not overlap share a physical register.
+/// If they do not fit, the registers that stay live the longest are spilled to 8 byte slots
+/// at the end of the memory instead since [`Inst`] cannot access globals.
+///
+/// Spilling reserves the last 4 physical registers `r{num_physical - 4}` up to `r{num_physical - 1}`:
+///
+/// - The first 3 reload and store the spilled operands around every instruction using them.
+/// - The last one is never written and thus zero as the base address of all spill slots.
+///
+/// Registers that are read before being written are assumed to be zero just like in a
+/// fresh [`Context`](crate::Context).
+///
+/// # Errors
+///
+/// - If `insts` contains a call since calls pass arguments and results in fixed registers.
+/// - If spilling is required but `num_physical` cannot hold the 4 reserved registers.
+/// - If spilling is required but `insts` loads or stores memory which might overlap the spill slots.
+///
+/// # Panics
+///
+/// If a branch target of `insts` is out of bounds.
+pub fn allocate_registers(insts: &[Inst], num_physical: usize) -> Result<Vec<Inst>, AllocError> {
+    if let Some(index) = insts
+        .iter()
+        .position(|inst| matches!(inst, Inst::Call { .. }))
+    {
+        return Err(AllocError::Call { index });
+    }
+    let intervals = intervals(insts);
+    let locations = linear_scan(&intervals, num_physical);
+    let spills = locations
+        .iter()
+        .any(|location| matches!(location, Some(Location::Spilled { .. })));
+    if !spills {
+        return Ok(rewrite(insts, &locations, num_physical));
+    }
+    let available = num_physical
+        .checked_sub(NUM_SCRATCH + 1)
+        .ok_or(AllocError::TooFewRegisters { num_physical })?;
+    if let Some(index) = insts.iter().position(|inst| {
+        matches!(
+            inst,
+            Inst::Load8 { .. }
+                | Inst::Load16 { .. }
+                | Inst::Load32 { .. }
+                | Inst::Load64 { .. }
+                | Inst::Store8 { .. }
+                | Inst::Store16 { .. }
+                | Inst::Store32 { .. }
+                | Inst::Store64 { .. }
+        )
+    }) {
+        return Err(AllocError::Memory { index });
+    }
+    let locations = linear_scan(&intervals, available);
+    Ok(rewrite(insts, &locations, available))
+}
+
+/// Returns the live [`Interval`] of every register used by `insts` ordered by their start.
+fn intervals(insts: &[Inst]) -> Vec<Interval> {
+    let live = cfg::liveness(insts);
+    let mut ranges = Vec::<Option<(usize, usize)>>::new();
+    let mut extend = |register: usize, pc: usize| {
+        if ranges.len() <= register {
+            ranges.resize(register + 1, None);
+        }
+        let (start, end) = ranges[register].get_or_insert((pc, pc));
+        *start = (*start).min(pc);
+        *end = (*end).max(pc);
+    };
+    for (pc, inst) in insts.iter().enumerate() {
+        for register in live[pc].iter() {
+            extend(register, pc);
+        }
+        for register in inst.operands().1 {
+            extend(register.0, pc);
+        }
+    }
+    let mut intervals = ranges
+        .into_iter()
+        .enumerate()
+        .filter_map(|(register, range)| {
+            range.map(|(start, end)| Interval {
+                register,
+                start,
+                end,
+            })
+        })
+        .collect::<Vec<_>>();
+    intervals.sort_by_key(|interval| interval.start);
+    intervals
+}
+
+/// Assigns one of `num_registers` physical registers or a spill slot to every interval.
+///
+/// Returns the [`Location`] of every virtual register indexed by its index.
+/// Once all physical registers are taken the interval that ends last is spilled.
+fn linear_scan(intervals: &[Interval], num_registers: usize) -> Vec<Option<Location>> {
+    let num_virtual = intervals
+        .iter()
+        .map(|interval| interval.register + 1)
+        .max()
+        .unwrap_or(0);
+    let mut locations = vec![None; num_virtual];
+    let mut free = (0..num_registers).rev().map(Register).collect::<Vec<_>>();
+    let mut active = Vec::<(Interval, Register)>::new();
+    let mut num_slots = 0;
+    let mut spill = |locations: &mut [Option<Location>], register: usize| {
+        locations[register] = Some(Location::Spilled { slot: num_slots });
+        num_slots += 1;
+    };
+    for &interval in intervals {
+        active.retain(|&(active, physical)| {
+            let expired = active.end < interval.start;
+            if expired {
+                free.push(physical);
+            }
+            !expired
+        });
+        if let Some(physical) = free.pop() {
+            locations[interval.register] = Some(Location::Register(physical));
+            active.push((interval, physical));
+            continue;
+        }
+        let longest = active
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, (active, _))| active.end)
+            .map(|(index, _)| index);
+        match longest {
+            Some(index) if active[index].0.end > interval.end => {
+                let (longest, physical) = active.swap_remove(index);
+                spill(&mut locations, longest.register);
+                locations[interval.register] = Some(Location::Register(physical));
+                active.push((interval, physical));
+            }
+            _ => spill(&mut locations, interval.register),
+        }
+    }
+    locations
+}
+
+/// Rewrites the registers of `insts` to their `locations`.
+///
+/// Spilled registers are reloaded into the scratch registers starting at `scratch` before
+/// and stored back after every instruction using them. All targets are renumbered to
+/// account for the inserted instructions.
+fn rewrite(insts: &[Inst], locations: &[Option<Location>], scratch: usize) -> Vec<Inst> {
+    let base = Register(scratch + NUM_SCRATCH);
+    let offset = |slot: usize| (MEMORY_SIZE - 8 * (slot + 1)) as Bits;
+    let location =
+        |register: Register| locations[register.0].expect("every used register has an interval");
+    let mut starts = Vec::with_capacity(insts.len() + 1);
+    let mut rewritten = Vec::with_capacity(insts.len());
+    for inst in insts {
+        starts.push(Target(rewritten.len()));
+        let mut reloads = SmallVec::<[(usize, Register); NUM_SCRATCH]>::new();
+        let mut stores = SmallVec::<[(usize, Register); NUM_SCRATCH]>::new();
+        let assign = |spilled: &mut SmallVec<[(usize, Register); NUM_SCRATCH]>,
+                      register: Register| match location(register) {
+            Location::Register(physical) => physical,
+            Location::Spilled { slot } => {
+                let index = match spilled.iter().position(|&(other, _)| other == slot) {
+                    Some(index) => index,
+                    None => {
+                        spilled.push((slot, Register(scratch + spilled.len())));
+                        spilled.len() - 1
+                    }
+                };
+                spilled[index].1
+            }
+        };
+        let inst = rewrite_registers(
+            inst,
+            |register| assign(&mut reloads, register),
+            |register| assign(&mut stores, register),
+        );
+        rewritten.extend(reloads.iter().map(|&(slot, result)| Inst::Load64 {
+            result,
+            base,
+            offset: offset(slot),
+        }));
+        rewritten.push(inst);
+        rewritten.extend(stores.iter().map(|&(slot, value)| Inst::Store64 {
+            base,
+            offset: offset(slot),
+            value,
+        }));
+    }
+    starts.push(Target(rewritten.len()));
+    rewritten
+        .into_iter()
+        .map(|inst| peephole::retarget(inst, |target| starts[target.0]))
+        .collect()
+}
+
+/// Returns `inst` with every read register replaced via `read` and every written one via `write`.
+///
+/// All reads are replaced before the writes. Float registers are kept as they are.
+fn rewrite_registers(
+    inst: &Inst,
+    mut read: impl FnMut(Register) -> Register,
+    mut write: impl FnMut(Register) -> Register,
+) -> Inst {
+    match *inst {
+        Inst::Add { result, lhs, rhs } => Inst::Add {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::Sub { result, lhs, rhs } => Inst::Sub {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::Mul { result, lhs, rhs } => Inst::Mul {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::Div { result, lhs, rhs } => Inst::Div {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::Rem { result, lhs, rhs } => Inst::Rem {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::Shl { result, lhs, rhs } => Inst::Shl {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::ShrU { result, lhs, rhs } => Inst::ShrU {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::ShrS { result, lhs, rhs } => Inst::ShrS {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::Rotl { result, lhs, rhs } => Inst::Rotl {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::Rotr { result, lhs, rhs } => Inst::Rotr {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::Eq { result, lhs, rhs } => Inst::Eq {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::Ne { result, lhs, rhs } => Inst::Ne {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::LtU { result, lhs, rhs } => Inst::LtU {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::LtS { result, lhs, rhs } => Inst::LtS {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::Add32 { result, lhs, rhs } => Inst::Add32 {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::Sub32 { result, lhs, rhs } => Inst::Sub32 {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::Mul32 { result, lhs, rhs } => Inst::Mul32 {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::LtS32 { result, lhs, rhs } => Inst::LtS32 {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::Shl32 { result, lhs, rhs } => Inst::Shl32 {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::ShrU32 { result, lhs, rhs } => Inst::ShrU32 {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::ShrS32 { result, lhs, rhs } => Inst::ShrS32 {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::AddChecked { result, lhs, rhs } => Inst::AddChecked {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::SubChecked { result, lhs, rhs } => Inst::SubChecked {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::MulChecked { result, lhs, rhs } => Inst::MulChecked {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::MinU { result, lhs, rhs } => Inst::MinU {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::MaxU { result, lhs, rhs } => Inst::MaxU {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::MinS { result, lhs, rhs } => Inst::MinS {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::MaxS { result, lhs, rhs } => Inst::MaxS {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            result: write(result),
+        },
+        Inst::AddImm { result, src, imm } => Inst::AddImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::SubImm { result, src, imm } => Inst::SubImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::MulImm { result, src, imm } => Inst::MulImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::DivImm { result, src, imm } => Inst::DivImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::RemImm { result, src, imm } => Inst::RemImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::ShlImm { result, src, imm } => Inst::ShlImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::ShrUImm { result, src, imm } => Inst::ShrUImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::ShrSImm { result, src, imm } => Inst::ShrSImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::RotlImm { result, src, imm } => Inst::RotlImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::RotrImm { result, src, imm } => Inst::RotrImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::EqImm { result, src, imm } => Inst::EqImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::NeImm { result, src, imm } => Inst::NeImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::LtUImm { result, src, imm } => Inst::LtUImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::LtSImm { result, src, imm } => Inst::LtSImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::MinUImm { result, src, imm } => Inst::MinUImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::MaxUImm { result, src, imm } => Inst::MaxUImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::MinSImm { result, src, imm } => Inst::MinSImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::MaxSImm { result, src, imm } => Inst::MaxSImm {
+            src: read(src),
+            result: write(result),
+            imm,
+        },
+        Inst::Move { result, src } => Inst::Move {
+            src: read(src),
+            result: write(result),
+        },
+        Inst::Popcnt { result, src } => Inst::Popcnt {
+            src: read(src),
+            result: write(result),
+        },
+        Inst::Clz { result, src } => Inst::Clz {
+            src: read(src),
+            result: write(result),
+        },
+        Inst::Ctz { result, src } => Inst::Ctz {
+            src: read(src),
+            result: write(result),
+        },
+        Inst::Select {
+            result,
+            cond,
+            if_nonzero,
+            if_zero,
+        } => Inst::Select {
+            cond: read(cond),
+            if_nonzero: read(if_nonzero),
+            if_zero: read(if_zero),
+            result: write(result),
+        },
+        Inst::MulWide { hi, lo, lhs, rhs } => Inst::MulWide {
+            lhs: read(lhs),
+            rhs: read(rhs),
+            hi: write(hi),
+            lo: write(lo),
+        },
+        Inst::Load8 {
+            result,
+            base,
+            offset,
+        } => Inst::Load8 {
+            base: read(base),
+            result: write(result),
+            offset,
+        },
+        Inst::Load16 {
+            result,
+            base,
+            offset,
+        } => Inst::Load16 {
+            base: read(base),
+            result: write(result),
+            offset,
+        },
+        Inst::Load32 {
+            result,
+            base,
+            offset,
+        } => Inst::Load32 {
+            base: read(base),
+            result: write(result),
+            offset,
+        },
+        Inst::Load64 {
+            result,
+            base,
+            offset,
+        } => Inst::Load64 {
+            base: read(base),
+            result: write(result),
+            offset,
+        },
+        Inst::Store8 {
+            base,
+            offset,
+            value,
+        } => Inst::Store8 {
+            base: read(base),
+            value: read(value),
+            offset,
+        },
+        Inst::Store16 {
+            base,
+            offset,
+            value,
+        } => Inst::Store16 {
+            base: read(base),
+            value: read(value),
+            offset,
+        },
+        Inst::Store32 {
+            base,
+            offset,
+            value,
+        } => Inst::Store32 {
+            base: read(base),
+            value: read(value),
+            offset,
+        },
+        Inst::Store64 {
+            base,
+            offset,
+            value,
+        } => Inst::Store64 {
+            base: read(base),
+            value: read(value),
+            offset,
+        },
+        Inst::BranchEq { target, lhs, rhs } => Inst::BranchEq {
+            target,
+            lhs: read(lhs),
+            rhs: read(rhs),
+        },
+        Inst::BranchNe { target, lhs, rhs } => Inst::BranchNe {
+            target,
+            lhs: read(lhs),
+            rhs: read(rhs),
+        },
+        Inst::BranchLtU { target, lhs, rhs } => Inst::BranchLtU {
+            target,
+            lhs: read(lhs),
+            rhs: read(rhs),
+        },
+        Inst::BranchLtS { target, lhs, rhs } => Inst::BranchLtS {
+            target,
+            lhs: read(lhs),
+            rhs: read(rhs),
+        },
+        Inst::BranchEqz { target, condition } => Inst::BranchEqz {
+            target,
+            condition: read(condition),
+        },
+        Inst::BranchNez { target, condition } => Inst::BranchNez {
+            target,
+            condition: read(condition),
+        },
+        Inst::BranchTable {
+            index,
+            ref targets,
+            default,
+        } => Inst::BranchTable {
+            index: read(index),
+            targets: targets.clone(),
+            default,
+        },
+        Inst::RandNext { result } => Inst::RandNext {
+            result: write(result),
+        },
+        Inst::Return { result } => Inst::Return {
+            result: read(result),
+        },
+        Inst::FAdd { .. }
+        | Inst::FSub { .. }
+        | Inst::FMul { .. }
+        | Inst::FDiv { .. }
+        | Inst::Branch { .. }
+        | Inst::Call { .. }
+        | Inst::Nop
+        | Inst::Yield
+        | Inst::Unreachable => inst.clone(),
+    }
+}
+
+/// Returns whether all registers used by `insts` are below `num_physical`.
+#[cfg(test)]
+fn uses_only(insts: &[Inst], num_physical: usize) -> bool {
+    insts.iter().all(|inst| {
+        let (reads, writes) = inst.operands();
+        reads
+            .iter()
+            .chain(writes.iter())
+            .all(|register| register.0 < num_physical)
+    })
+}
+
+#[test]
+fn reuses_registers() {
+    // r[i] = r[i - 1] + i for 30 registers that are never live at the same time.
+    let mut insts = vec![Inst::AddImm {
+        result: Register(0),
+        src: Register(0),
+        imm: 1,
+    }];
+    insts.extend((1..30).map(|i| Inst::AddImm {
+        result: Register(i),
+        src: Register(i - 1),
+        imm: i as Bits,
+    }));
+    insts.push(Inst::Return {
+        result: Register(29),
+    });
+    let allocated = allocate_registers(&insts, 8).unwrap();
+    assert_eq!(allocated.len(), insts.len());
+    assert!(uses_only(&allocated, 2));
+    assert_eq!(
+        switch::run(&allocated, &mut Context::with_registers(8)),
+        switch::run(&insts, &mut Context::with_registers(30)),
+    );
+}
+
+#[test]
+fn spills_registers() {
+    let mut insts = Vec::new();
+    // r[i] = i + 1 for all 30 registers which stay live until the end of the loop.
+    insts.extend((0..30).map(|i| Inst::AddImm {
+        result: Register(i),
+        src: Register(i),
+        imm: i as Bits + 1,
+    }));
+    // while r29 != 0 { r0 += r1 + .. + r28; r29 -= 1; }
+    insts.push(Inst::BranchEqz {
+        target: Target(30 + 1 + 28 + 2),
+        condition: Register(29),
+    });
+    insts.extend((1..29).map(|i| Inst::Add {
+        result: Register(0),
+        lhs: Register(0),
+        rhs: Register(i),
+    }));
+    insts.push(Inst::SubImm {
+        result: Register(29),
+        src: Register(29),
+        imm: 1,
+    });
+    insts.push(Inst::Branch { target: Target(30) });
+    insts.push(Inst::Return {
+        result: Register(0),
+    });
+    let allocated = allocate_registers(&insts, 8).unwrap();
+    assert!(uses_only(&allocated, 8));
+    assert!(allocated
+        .iter()
+        .any(|inst| matches!(inst, Inst::Store64 { .. })));
+    let expected = 1 + 30 * (2..30).sum::<Bits>();
+    assert_eq!(
+        switch::run(&insts, &mut Context::with_registers(30)),
+        Ok(expected)
+    );
+    assert_eq!(
+        switch::run(&allocated, &mut Context::with_registers(8)),
+        Ok(expected)
+    );
+    assert_eq!(
+        allocate_registers(&insts, NUM_SCRATCH),
+        Err(AllocError::TooFewRegisters {
+            num_physical: NUM_SCRATCH
+        })
+    );
+    // Storing r0 might overwrite the spill slots.
+    insts.insert(
+        30,
+        Inst::Store64 {
+            base: Register(0),
+            offset: 0,
+            value: Register(1),
+        },
+    );
+    assert_eq!(
+        allocate_registers(&insts, 8),
+        Err(AllocError::Memory { index: 30 })
+    );
+    assert!(allocate_registers(&insts, 30).is_ok());
+}
+
+#[test]
+fn more_comps() {
+    let insts = switch::more_comps_insts(1_000);
+    let allocated = allocate_registers(&insts, 2).unwrap();
+    assert_eq!(allocated, insts);
+    assert_eq!(
+        switch::run(&allocated, &mut Context::default()),
+        switch::run(&insts, &mut Context::default()),
+    );
+}
+
+#[test]
+fn rejects_calls() {
+    let insts = [
+        Inst::Call {
+            target: Target(2),
+            num_args: 0,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_eq!(
+        allocate_registers(&insts, 8),
+        Err(AllocError::Call { index: 0 })
+    );
+}
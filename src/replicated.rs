@@ -0,0 +1,85 @@
+#![allow(dead_code)]
+
+#[cfg(test)]
+use crate::{benchmark, switch};
+
+use super::{fetch, handler, switch::Inst, Bits, Context, Outcome};
+
+/// The hot opcode of the previously executed instruction.
+///
+/// Selects the replica of the dispatch that executes the next instruction.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Predecessor {
+    AddImm,
+    SubImm,
+    BranchEqz,
+    Branch,
+    Other,
+}
+
+/// Executes a single instruction and returns its [`Predecessor`] class for the next dispatch.
+///
+/// The hot opcodes call their handlers directly so that every replica owns a copy of their bodies.
+#[inline(always)]
+fn dispatch(inst: &Inst, context: &mut Context) -> (Outcome, Predecessor) {
+    match inst {
+        Inst::AddImm { result, src, imm } => (
+            handler::add_imm(context, *result, *src, *imm),
+            Predecessor::AddImm,
+        ),
+        Inst::SubImm { result, src, imm } => (
+            handler::sub_imm(context, *result, *src, *imm),
+            Predecessor::SubImm,
+        ),
+        Inst::BranchEqz { target, condition } => (
+            handler::branch_eqz(context, *target, *condition),
+            Predecessor::BranchEqz,
+        ),
+        Inst::Branch { target } => (handler::branch(context, *target), Predecessor::Branch),
+        _ => (inst.execute(context), Predecessor::Other),
+    }
+}
+
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+///
+/// Approximates handler replication by inlining a separate [`dispatch`] for every
+/// [`Predecessor`] so that each of them gets its own indirect jump and branch predictor entry.
+/// Note that the compiler is free to merge the identical replicas again.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Bits {
+    let mut predecessor = Predecessor::Other;
+    loop {
+        let pc = context.pc;
+        let Ok(inst) = fetch(insts, pc) else {
+            return context.get_reg_abs(0);
+        };
+        let outcome;
+        (outcome, predecessor) = match predecessor {
+            Predecessor::AddImm => dispatch(inst, context),
+            Predecessor::SubImm => dispatch(inst, context),
+            Predecessor::BranchEqz => dispatch(inst, context),
+            Predecessor::Branch => dispatch(inst, context),
+            Predecessor::Other => dispatch(inst, context),
+        };
+        match outcome {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return context.get_reg_abs(0),
+        }
+    }
+}
+
+#[test]
+fn counter_loop() {
+    let insts = switch::counter_loop_insts(100_000_000);
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn more_comps() {
+    let insts = switch::more_comps_insts(1_000);
+    assert_eq!(
+        Ok(execute(&insts, &mut Context::default())),
+        switch::execute(&insts, &mut Context::default()),
+    );
+}
@@ -0,0 +1,445 @@
+//! Runners exposing the otherwise private dispatch strategies to the benchmarks.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::cell::RefCell;
+
+#[cfg(test)]
+use super::Register;
+
+use super::{
+    block_dispatch, closure_loop, closure_tail, enum_tree, fused, jumptable, replicated, soa,
+    subroutine_threaded, switch, switch_2, switch_cached2, switch_hot, switch_tail, switch_tail_2,
+    threaded, ArithMode, Bits, Context,
+};
+
+/// A dispatch strategy prepared to execute a workload.
+pub trait Runner {
+    /// Returns the name of the dispatch strategy.
+    fn name(&self) -> &'static str;
+
+    /// Executes the prepared workload on a freshly reset context and returns the contents of `r0`.
+    fn run(&self) -> Bits;
+}
+
+/// An execution context that can be reused across runs.
+trait Reset: Default {
+    /// Resets the context to its initial state without reallocating.
+    fn reset(&mut self);
+}
+
+impl Reset for Context {
+    fn reset(&mut self) {
+        Context::reset(self)
+    }
+}
+
+impl Reset for fused::Context {
+    fn reset(&mut self) {
+        fused::Context::reset(self)
+    }
+}
+
+/// A [`Runner`] executing a program of type `P` on a context of type `C`.
+///
+/// The program usually is a slice of instructions.
+struct Strategy<P: ?Sized, C> {
+    /// The name of the dispatch strategy.
+    name: &'static str,
+    /// The prepared program of the workload.
+    program: Box<P>,
+    /// The benchmark loop of the dispatch strategy.
+    execute: fn(&P, &mut C) -> Bits,
+    /// The context reused by all runs.
+    context: RefCell<C>,
+}
+
+impl<P: ?Sized, C> Strategy<P, C>
+where
+    C: Reset,
+{
+    /// Creates a new [`Strategy`] executing `program` via `execute`.
+    fn new(
+        name: &'static str,
+        program: impl Into<Box<P>>,
+        execute: fn(&P, &mut C) -> Bits,
+    ) -> Self {
+        Self::with_context(name, program, execute, C::default())
+    }
+
+    /// Creates a new [`Strategy`] executing `program` via `execute` on `context`.
+    fn with_context(
+        name: &'static str,
+        program: impl Into<Box<P>>,
+        execute: fn(&P, &mut C) -> Bits,
+        context: C,
+    ) -> Self {
+        Self {
+            name,
+            program: program.into(),
+            execute,
+            context: RefCell::new(context),
+        }
+    }
+}
+
+impl<P: ?Sized, C> Runner for Strategy<P, C>
+where
+    C: Reset,
+{
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn run(&self) -> Bits {
+        let mut context = self.context.borrow_mut();
+        context.reset();
+        (self.execute)(&self.program, &mut context)
+    }
+}
+
+/// Executes `insts` via [`switch::execute`] and returns `r0` also if the execution traps
+/// just like the dispatch strategies that do not report traps.
+fn switch_execute(insts: &[switch::Inst], context: &mut Context) -> Bits {
+    switch::execute(insts, context).unwrap_or_else(|_| context.get_reg_abs(0))
+}
+
+/// Executes `insts` via [`closure_loop::execute`] and returns `r0` also if the execution traps.
+fn closure_loop_execute(insts: &[closure_loop::Inst], context: &mut Context) -> Bits {
+    closure_loop::execute(insts, context).unwrap_or_else(|_| context.get_reg_abs(0))
+}
+
+/// Executes `insts` via [`fused::rt::execute`] and returns `r0` also if the execution traps.
+fn fused_rt_execute(insts: &[fused::rt::Inst], context: &mut fused::Context) -> Bits {
+    fused::rt::execute(insts, context).unwrap_or_else(|_| context.get_reg_abs(0))
+}
+
+/// An instruction of a strategy neutral program that a dispatch strategy cannot express.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsupported {
+    /// The name of the dispatch strategy.
+    pub strategy: &'static str,
+    /// The first instruction the dispatch strategy cannot express.
+    pub inst: switch::Inst,
+}
+
+/// Prepares a [`Runner`] from the lowered `program` or reports the unsupported instruction.
+fn prepare<P, C>(
+    name: &'static str,
+    program: Result<impl Into<Box<P>>, switch::Inst>,
+    execute: fn(&P, &mut C) -> Bits,
+) -> Result<Box<dyn Runner>, Unsupported>
+where
+    P: ?Sized + 'static,
+    C: Reset + 'static,
+{
+    prepare_with_context(name, program, execute, C::default())
+}
+
+/// Prepares a [`Runner`] executing the lowered `program` on `context` or reports the unsupported instruction.
+fn prepare_with_context<P, C>(
+    name: &'static str,
+    program: Result<impl Into<Box<P>>, switch::Inst>,
+    execute: fn(&P, &mut C) -> Bits,
+    context: C,
+) -> Result<Box<dyn Runner>, Unsupported>
+where
+    P: ?Sized + 'static,
+    C: Reset + 'static,
+{
+    match program {
+        Ok(program) => Ok(Box::new(Strategy::with_context(
+            name, program, execute, context,
+        ))),
+        Err(inst) => Err(Unsupported {
+            strategy: name,
+            inst,
+        }),
+    }
+}
+
+/// Lowers the strategy neutral `program` to every dispatch strategy operating on a flat list of instructions.
+///
+/// Dispatch strategies lacking an instruction used by `program` yield [`Unsupported`].
+pub fn lower(program: &[switch::Inst]) -> Vec<Result<Box<dyn Runner>, Unsupported>> {
+    let mut runners = lower_with_arith_mode(program, ArithMode::default());
+    runners.push(prepare(
+        "enum_tree",
+        enum_tree::lower(program),
+        enum_tree::execute,
+    ));
+    runners.push(prepare(
+        "fused::rt",
+        fused::rt::lower(program),
+        fused_rt_execute,
+    ));
+    runners.push(prepare(
+        "fused::ct",
+        fused::ct::lower(program),
+        fused::ct::execute,
+    ));
+    runners
+}
+
+/// Lowers `program` like [`lower`] to every dispatch strategy executing `add`, `sub` and `mul` under `arith_mode`.
+///
+/// Leaves out `enum_tree` and the `fused` strategies since their arithmetic always wraps around on overflow.
+pub fn lower_with_arith_mode(
+    program: &[switch::Inst],
+    arith_mode: ArithMode,
+) -> Vec<Result<Box<dyn Runner>, Unsupported>> {
+    let context = || Context::with_arith_mode(arith_mode);
+    vec![
+        prepare_with_context("switch", Ok(program.to_vec()), switch_execute, context()),
+        prepare_with_context(
+            "jumptable",
+            Ok(jumptable::compile(program)),
+            jumptable::execute,
+            context(),
+        ),
+        prepare_with_context(
+            "replicated",
+            Ok(program.to_vec()),
+            replicated::execute,
+            context(),
+        ),
+        prepare_with_context("soa", soa::lower(program), soa::execute, context()),
+        prepare_with_context(
+            "switch_2",
+            switch_2::lower(program),
+            switch_2::execute,
+            context(),
+        ),
+        prepare_with_context(
+            "switch_cached2",
+            switch_cached2::lower(program),
+            switch_cached2::execute,
+            context(),
+        ),
+        prepare_with_context(
+            "switch_tail",
+            Ok(program.to_vec()),
+            switch_tail::execute,
+            context(),
+        ),
+        prepare_with_context(
+            "switch_tail_2",
+            switch_tail_2::lower(program),
+            switch_tail_2::execute,
+            context(),
+        ),
+        prepare_with_context(
+            "subroutine_threaded",
+            Ok(subroutine_threaded::compile(program)),
+            subroutine_threaded::execute,
+            context(),
+        ),
+        prepare_with_context(
+            "threaded",
+            threaded::lower(program),
+            threaded::execute,
+            context(),
+        ),
+        prepare_with_context(
+            "closure_loop",
+            closure_loop::lower(program),
+            closure_loop_execute,
+            context(),
+        ),
+        prepare_with_context(
+            "closure_tail",
+            closure_tail::lower(program),
+            closure_tail::execute,
+            context(),
+        ),
+        prepare_with_context(
+            "block_dispatch",
+            block_dispatch::compile(program),
+            block_dispatch::execute,
+            context(),
+        ),
+    ]
+}
+
+/// Returns the `counter_loop` workload counting down `repetitions` for every dispatch strategy.
+pub fn counter_loop(repetitions: Bits) -> Vec<Box<dyn Runner>> {
+    vec![
+        Box::new(Strategy::new(
+            "switch",
+            switch::counter_loop_insts(repetitions),
+            switch_execute,
+        )),
+        Box::new(Strategy::new(
+            "jumptable",
+            jumptable::compile(&switch::counter_loop_insts(repetitions)),
+            jumptable::execute,
+        )),
+        Box::new(Strategy::new(
+            "replicated",
+            switch::counter_loop_insts(repetitions),
+            replicated::execute,
+        )),
+        Box::new(Strategy::new(
+            "soa",
+            soa::lower(&switch::counter_loop_insts(repetitions))
+                .expect("`counter_loop` only uses operands fitting `InstArgs`"),
+            soa::execute,
+        )),
+        Box::new(Strategy::new(
+            "switch_tail",
+            switch::counter_loop_insts(repetitions),
+            switch_tail::execute,
+        )),
+        Box::new(Strategy::new(
+            "closure_loop",
+            closure_loop::counter_loop_insts(repetitions),
+            closure_loop_execute,
+        )),
+        Box::new(Strategy::new(
+            "closure_tail",
+            closure_tail::counter_loop_insts(repetitions),
+            closure_tail::execute,
+        )),
+        Box::new(Strategy::new(
+            "fused::rt",
+            fused::rt::counter_loop_insts(repetitions),
+            fused_rt_execute,
+        )),
+        Box::new(Strategy::new(
+            "fused::ct",
+            fused::ct::counter_loop_insts(repetitions),
+            fused::ct::execute,
+        )),
+        Box::new(Strategy::new(
+            "fused::ct_imm",
+            fused::ct::counter_loop_imm_insts(repetitions),
+            fused::ct::execute,
+        )),
+        Box::new(Strategy::new(
+            "enum_tree",
+            enum_tree::counter_loop_insts(repetitions),
+            enum_tree::execute,
+        )),
+    ]
+}
+
+/// Returns the `counter_loop` workload for the default and the frequency ordered dispatch of [`switch`].
+///
+/// `hot_first` dispatches the opcodes in the order returned by `switch_hot::reorder_by_frequency`,
+/// `cold_first` dispatches opcodes that the workload never executes first.
+pub fn counter_loop_ordering(repetitions: Bits) -> Vec<Box<dyn Runner>> {
+    use super::bytecode::opcode::{BRANCH, BRANCH_EQZ, CALL, DIV, REM, SUB_IMM};
+    vec![
+        Box::new(Strategy::new(
+            "default",
+            switch::counter_loop_insts(repetitions),
+            switch_execute,
+        )),
+        Box::new(Strategy::new(
+            "hot_first",
+            switch::counter_loop_insts(repetitions),
+            switch_hot::execute::<BRANCH_EQZ, SUB_IMM, BRANCH>,
+        )),
+        Box::new(Strategy::new(
+            "cold_first",
+            switch::counter_loop_insts(repetitions),
+            switch_hot::execute::<CALL, DIV, REM>,
+        )),
+    ]
+}
+
+/// Returns the `more_comps` workload for every dispatch strategy supporting it.
+///
+/// The workload keeps its loop counter in `r0` and its accumulator in `r1`.
+pub fn more_comps(repetitions: Bits) -> Vec<Box<dyn Runner>> {
+    lower(&switch::more_comps_insts(repetitions))
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect()
+}
+
+/// Returns the `random_branch` workload for `switch`, `closure_loop` and `fused::rt`.
+///
+/// The workload counts the odd values among `repetitions` pseudo-random values in `r0`.
+pub fn random_branch(repetitions: Bits) -> Vec<Box<dyn Runner>> {
+    let program = switch::random_branch_insts(repetitions);
+    [
+        prepare("switch", Ok(program.clone()), switch_execute),
+        prepare(
+            "closure_loop",
+            closure_loop::lower(&program),
+            closure_loop_execute,
+        ),
+        prepare("fused::rt", fused::rt::lower(&program), fused_rt_execute),
+    ]
+    .into_iter()
+    .collect::<Result<_, _>>()
+    .expect("all strategies support the `random_branch` workload")
+}
+
+/// Returns a straight-line workload of `n` copies of `inst` for `switch`, `closure_loop` and `fused::rt`.
+///
+/// Dispatch strategies lacking `inst` are left out.
+pub fn single_opcode(inst: switch::Inst, n: usize) -> Vec<Box<dyn Runner>> {
+    let program = switch::repeat_inst(inst, n);
+    [
+        prepare("switch", Ok(program.clone()), switch_execute),
+        prepare(
+            "closure_loop",
+            closure_loop::lower(&program),
+            closure_loop_execute,
+        ),
+        prepare("fused::rt", fused::rt::lower(&program), fused_rt_execute),
+    ]
+    .into_iter()
+    .filter_map(Result::ok)
+    .collect()
+}
+
+#[test]
+fn single_opcode_results() {
+    let inst = switch::Inst::AddImm {
+        result: Register(0),
+        src: Register(0),
+        imm: 3,
+    };
+    let runners = single_opcode(inst, 1_000);
+    assert_eq!(runners.len(), 3);
+    for runner in runners {
+        assert_eq!(runner.run(), 3_000, "{}", runner.name());
+    }
+    let runners = single_opcode(
+        switch::Inst::Popcnt {
+            result: Register(1),
+            src: Register(0),
+        },
+        1_000,
+    );
+    let names = runners
+        .iter()
+        .map(|runner| runner.name())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["switch", "fused::rt"]);
+}
+
+#[test]
+fn random_branch_results() {
+    let runners = random_branch(1_000);
+    assert_eq!(runners.len(), 3);
+    let expected = runners[0].run();
+    assert_ne!(expected, 0);
+    for runner in &runners {
+        // Every run reseeds the random number generator and thus yields the same result.
+        assert_eq!(runner.run(), expected, "{}", runner.name());
+        assert_eq!(runner.run(), expected, "{}", runner.name());
+    }
+}
+
+#[test]
+fn counter_loop_results() {
+    for runner in counter_loop(1_000)
+        .into_iter()
+        .chain(counter_loop_ordering(1_000))
+    {
+        assert_eq!(runner.run(), 0, "{}", runner.name());
+    }
+}
@@ -0,0 +1,196 @@
+#![allow(dead_code)]
+
+use alloc::vec::Vec;
+
+#[cfg(test)]
+use crate::{benchmark, switch};
+
+use super::{
+    bytecode::opcode, fetch, handler, switch::Inst, Bits, Context, FromArg, Outcome, Register,
+    Target,
+};
+
+/// The [`InstArgs`](crate::InstArgs) slots of all instructions of a program stored as a struct-of-arrays.
+///
+/// Every array holds one slot per instruction in parallel to [`Compiled::opcodes`].
+#[derive(Debug, Default, Clone)]
+pub struct Operands {
+    a: Vec<Bits>,
+    b: Vec<Bits>,
+    c: Vec<Bits>,
+}
+
+/// A [`switch::Inst`] program split into a stream of opcodes and their operands.
+#[derive(Debug, Default, Clone)]
+pub struct Compiled {
+    /// The [`Inst::opcode`] of every instruction.
+    opcodes: Vec<u8>,
+    /// The operands of the instruction at the same index of `opcodes`.
+    operands: Operands,
+}
+
+/// Splits `insts` into their opcodes and operands.
+///
+/// # Errors
+///
+/// If `insts` contains an instruction whose operands do not fit [`InstArgs`](crate::InstArgs).
+pub fn lower(insts: &[Inst]) -> Result<Compiled, Inst> {
+    let mut compiled = Compiled::default();
+    for inst in insts {
+        let args = inst.args().ok_or_else(|| inst.clone())?;
+        compiled.opcodes.push(inst.opcode());
+        compiled.operands.a.push(args.a);
+        compiled.operands.b.push(args.b);
+        compiled.operands.c.push(args.c);
+    }
+    Ok(compiled)
+}
+
+/// Returns the slot of the instruction at `pc`.
+///
+/// The `pc` has already been used to fetch the opcode and all arrays are as long as the opcodes.
+#[inline(always)]
+fn slot(slots: &[Bits], pc: usize) -> Bits {
+    debug_assert!(pc < slots.len());
+    unsafe { *slots.get_unchecked(pc) }
+}
+
+/// Dispatches the opcode to its handler which reads its operand slots from the arrays.
+macro_rules! dispatch {
+    (
+        $op:expr, $context:ident, $operands:ident, $pc:ident;
+        $( $opcode:ident => $handler:ident ( $( $slot:ident: $ty:ty ),* ) ),* $(,)?
+    ) => {
+        match $op {
+            $(
+                opcode::$opcode => handler::$handler(
+                    $context,
+                    $( <$ty as FromArg>::from_arg(slot(&$operands.$slot, $pc)) ),*
+                ),
+            )*
+            opcode::NOP => handler::nop($context),
+            opcode::YIELD => handler::yield_($context),
+            _ => handler::unreachable(),
+        }
+    };
+}
+
+/// Executes the compiled instructions using the given [`Context`] and returns the result in register 0.
+///
+/// Dispatch matches on the opcode stream and only loads the operand slots used by the opcode.
+pub fn execute(compiled: &Compiled, context: &mut Context) -> Bits {
+    let operands = &compiled.operands;
+    loop {
+        let pc = context.pc;
+        let Ok(&op) = fetch(&compiled.opcodes, pc) else {
+            return context.get_reg_abs(0);
+        };
+        let outcome = dispatch! {
+            op, context, operands, pc;
+            ADD => add(a: Register, b: Register, c: Register),
+            ADD_IMM => add_imm(a: Register, b: Register, c: Bits),
+            SUB => sub(a: Register, b: Register, c: Register),
+            SUB_IMM => sub_imm(a: Register, b: Register, c: Bits),
+            MUL => mul(a: Register, b: Register, c: Register),
+            MUL_IMM => mul_imm(a: Register, b: Register, c: Bits),
+            DIV => div(a: Register, b: Register, c: Register),
+            DIV_IMM => div_imm(a: Register, b: Register, c: Bits),
+            REM => rem(a: Register, b: Register, c: Register),
+            REM_IMM => rem_imm(a: Register, b: Register, c: Bits),
+            SHL => shl(a: Register, b: Register, c: Register),
+            SHL_IMM => shl_imm(a: Register, b: Register, c: Bits),
+            SHR_U => shr_u(a: Register, b: Register, c: Register),
+            SHR_U_IMM => shr_u_imm(a: Register, b: Register, c: Bits),
+            SHR_S => shr_s(a: Register, b: Register, c: Register),
+            SHR_S_IMM => shr_s_imm(a: Register, b: Register, c: Bits),
+            ROTL => rotl(a: Register, b: Register, c: Register),
+            ROTL_IMM => rotl_imm(a: Register, b: Register, c: Bits),
+            ROTR => rotr(a: Register, b: Register, c: Register),
+            ROTR_IMM => rotr_imm(a: Register, b: Register, c: Bits),
+            EQ => eq(a: Register, b: Register, c: Register),
+            EQ_IMM => eq_imm(a: Register, b: Register, c: Bits),
+            NE => ne(a: Register, b: Register, c: Register),
+            NE_IMM => ne_imm(a: Register, b: Register, c: Bits),
+            LT_U => lt_u(a: Register, b: Register, c: Register),
+            LT_U_IMM => lt_u_imm(a: Register, b: Register, c: Bits),
+            LT_S => lt_s(a: Register, b: Register, c: Register),
+            LT_S_IMM => lt_s_imm(a: Register, b: Register, c: Bits),
+            MOVE => mov(a: Register, b: Register),
+            ADD32 => add32(a: Register, b: Register, c: Register),
+            SUB32 => sub32(a: Register, b: Register, c: Register),
+            MUL32 => mul32(a: Register, b: Register, c: Register),
+            LT_S32 => lt_s32(a: Register, b: Register, c: Register),
+            SHL32 => shl32(a: Register, b: Register, c: Register),
+            SHR_U32 => shr_u32(a: Register, b: Register, c: Register),
+            SHR_S32 => shr_s32(a: Register, b: Register, c: Register),
+            ADD_CHECKED => add_checked(a: Register, b: Register, c: Register),
+            SUB_CHECKED => sub_checked(a: Register, b: Register, c: Register),
+            MUL_CHECKED => mul_checked(a: Register, b: Register, c: Register),
+            POPCNT => popcnt(a: Register, b: Register),
+            CLZ => clz(a: Register, b: Register),
+            CTZ => ctz(a: Register, b: Register),
+            RAND_NEXT => rand_next(a: Register),
+            MIN_U => min_u(a: Register, b: Register, c: Register),
+            MIN_U_IMM => min_u_imm(a: Register, b: Register, c: Bits),
+            MAX_U => max_u(a: Register, b: Register, c: Register),
+            MAX_U_IMM => max_u_imm(a: Register, b: Register, c: Bits),
+            MIN_S => min_s(a: Register, b: Register, c: Register),
+            MIN_S_IMM => min_s_imm(a: Register, b: Register, c: Bits),
+            MAX_S => max_s(a: Register, b: Register, c: Register),
+            MAX_S_IMM => max_s_imm(a: Register, b: Register, c: Bits),
+            FADD => fadd(a: Register, b: Register, c: Register),
+            FSUB => fsub(a: Register, b: Register, c: Register),
+            FMUL => fmul(a: Register, b: Register, c: Register),
+            FDIV => fdiv(a: Register, b: Register, c: Register),
+            LOAD8 => load8(a: Register, b: Register, c: Bits),
+            LOAD16 => load16(a: Register, b: Register, c: Bits),
+            LOAD32 => load32(a: Register, b: Register, c: Bits),
+            LOAD64 => load64(a: Register, b: Register, c: Bits),
+            STORE8 => store8(a: Register, b: Bits, c: Register),
+            STORE16 => store16(a: Register, b: Bits, c: Register),
+            STORE32 => store32(a: Register, b: Bits, c: Register),
+            STORE64 => store64(a: Register, b: Bits, c: Register),
+            BRANCH => branch(a: Target),
+            BRANCH_EQZ => branch_eqz(a: Target, b: Register),
+            BRANCH_NEZ => branch_nez(a: Target, b: Register),
+            BRANCH_EQ => branch_eq(a: Target, b: Register, c: Register),
+            BRANCH_NE => branch_ne(a: Target, b: Register, c: Register),
+            BRANCH_LT_U => branch_lt_u(a: Target, b: Register, c: Register),
+            BRANCH_LT_S => branch_lt_s(a: Target, b: Register, c: Register),
+            CALL => call(a: Target, b: usize),
+            RETURN => ret(a: Register),
+        };
+        match outcome {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return context.get_reg_abs(0),
+        }
+    }
+}
+
+#[test]
+fn counter_loop() {
+    let insts = lower(&switch::counter_loop_insts(100_000_000)).unwrap();
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn more_comps() {
+    let insts = switch::more_comps_insts(1_000);
+    assert_eq!(
+        Ok(execute(&lower(&insts).unwrap(), &mut Context::default())),
+        switch::execute(&insts, &mut Context::default()),
+    );
+}
+
+#[test]
+fn lower_rejects_select() {
+    let select = Inst::Select {
+        result: Register(0),
+        cond: Register(1),
+        if_nonzero: Register(2),
+        if_zero: Register(3),
+    };
+    assert_eq!(lower(core::slice::from_ref(&select)).unwrap_err(), select);
+}
@@ -0,0 +1,311 @@
+//! Structured control flow lowered to flat [`Inst`] programs.
+
+use alloc::vec::Vec;
+
+use super::{cfg, switch::Inst, Register, Target};
+#[cfg(test)]
+use super::{switch, Context};
+
+/// An error found while building a structured program.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StructureError {
+    /// An `end` without a matching `block` or `loop`.
+    UnmatchedEnd,
+    /// The branch at `index` has a relative `depth` exceeding the number of enclosing frames.
+    BranchDepthOutOfBounds { index: usize, depth: usize },
+    /// The program was finished while `open` frames were still missing their `end`.
+    UnclosedFrames { open: usize },
+    /// A branch instruction was pushed via [`Builder::inst`] instead of `br` or `br_if`.
+    RawBranch { index: usize },
+}
+
+/// The kind of a structured control frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum FrameKind {
+    /// Branches to a `block` continue after its `end`.
+    Block,
+    /// Branches to a `loop` continue at its start.
+    Loop,
+}
+
+/// A structured control frame that has not yet seen its `end`.
+#[derive(Debug)]
+struct Frame {
+    kind: FrameKind,
+    /// The index of the first instruction of the frame.
+    start: Target,
+    /// The index of every branch to this `block` whose target is known once it ends.
+    fixups: Vec<usize>,
+}
+
+/// Builds a flat [`Inst`] program from nested `block`, `loop`, `br` and `br_if`.
+///
+/// Like in Wasm branches refer to their enclosing frames by relative depth where depth 0
+/// is the innermost frame. Branching to a `block` continues after its `end` while branching
+/// to a `loop` continues at its start.
+///
+/// Errors are recorded as they occur and reported by [`Builder::finish`].
+#[derive(Debug, Default)]
+pub struct Builder {
+    insts: Vec<Inst>,
+    frames: Vec<Frame>,
+    error: Option<StructureError>,
+}
+
+impl Builder {
+    /// Creates a new empty [`Builder`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records `error` unless an earlier error has been recorded already.
+    fn fail(&mut self, error: StructureError) {
+        self.error.get_or_insert(error);
+    }
+
+    /// Pushes the non-branching instruction `inst`.
+    pub fn inst(&mut self, inst: Inst) -> &mut Self {
+        if cfg::branch_targets(core::slice::from_ref(&inst))
+            .next()
+            .is_some()
+        {
+            let index = self.insts.len();
+            self.fail(StructureError::RawBranch { index });
+        }
+        self.insts.push(inst);
+        self
+    }
+
+    /// Opens a `block` whose branches continue after its matching `end`.
+    pub fn block(&mut self) -> &mut Self {
+        self.open(FrameKind::Block)
+    }
+
+    /// Opens a `loop` whose branches continue at its start.
+    pub fn loop_(&mut self) -> &mut Self {
+        self.open(FrameKind::Loop)
+    }
+
+    fn open(&mut self, kind: FrameKind) -> &mut Self {
+        self.frames.push(Frame {
+            kind,
+            start: Target(self.insts.len()),
+            fixups: Vec::new(),
+        });
+        self
+    }
+
+    /// Closes the innermost `block` or `loop`.
+    ///
+    /// Resolves all pending branches out of a `block` to the instruction following its `end`.
+    pub fn end(&mut self) -> &mut Self {
+        let Some(frame) = self.frames.pop() else {
+            self.fail(StructureError::UnmatchedEnd);
+            return self;
+        };
+        let end = self.insts.len();
+        for index in frame.fixups {
+            match &mut self.insts[index] {
+                Inst::Branch { target } | Inst::BranchNez { target, .. } => *target = Target(end),
+                _ => unreachable!("only `br` and `br_if` are fixed up"),
+            }
+        }
+        self
+    }
+
+    /// Unconditionally branches to the frame at relative `depth`.
+    pub fn br(&mut self, depth: usize) -> &mut Self {
+        self.branch(depth, |target| Inst::Branch { target })
+    }
+
+    /// Branches to the frame at relative `depth` if the contents of `condition` are not zero.
+    pub fn br_if(&mut self, depth: usize, condition: Register) -> &mut Self {
+        self.branch(depth, |target| Inst::BranchNez { target, condition })
+    }
+
+    /// Pushes the branch created by `make` to the frame at relative `depth`.
+    fn branch(&mut self, depth: usize, make: impl FnOnce(Target) -> Inst) -> &mut Self {
+        let index = self.insts.len();
+        let Some(frame) = self
+            .frames
+            .len()
+            .checked_sub(depth + 1)
+            .map(|frame| &mut self.frames[frame])
+        else {
+            self.fail(StructureError::BranchDepthOutOfBounds { index, depth });
+            self.insts.push(make(Target(0)));
+            return self;
+        };
+        let target = match frame.kind {
+            FrameKind::Loop => frame.start,
+            FrameKind::Block => {
+                frame.fixups.push(index);
+                Target(0)
+            }
+        };
+        self.insts.push(make(target));
+        self
+    }
+
+    /// Returns the flat program with all branch targets resolved to absolute instruction indices.
+    ///
+    /// # Errors
+    ///
+    /// - If an `end` has no matching `block` or `loop`.
+    /// - If a branch depth exceeds the number of enclosing frames.
+    /// - If a `block` or `loop` is missing its `end`.
+    /// - If a branch was pushed via [`Builder::inst`].
+    pub fn finish(&mut self) -> Result<Vec<Inst>, StructureError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if !self.frames.is_empty() {
+            return Err(StructureError::UnclosedFrames {
+                open: self.frames.len(),
+            });
+        }
+        Ok(core::mem::take(&mut self.insts))
+    }
+}
+
+#[test]
+fn counting_loop() {
+    let repetitions = 1_000;
+    let program = Builder::new()
+        .inst(Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: repetitions,
+        })
+        .loop_()
+        .inst(Inst::SubImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        })
+        .inst(Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 2,
+        })
+        .br_if(0, Register(0))
+        .end()
+        .inst(Inst::Return {
+            result: Register(1),
+        })
+        .finish()
+        .unwrap();
+    let expected = [
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: repetitions,
+        },
+        Inst::SubImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 2,
+        },
+        Inst::BranchNez {
+            target: Target(1),
+            condition: Register(0),
+        },
+        Inst::Return {
+            result: Register(1),
+        },
+    ];
+    assert_eq!(program, expected);
+    assert_eq!(
+        switch::run(&program, &mut Context::default()),
+        switch::run(&expected, &mut Context::default()),
+    );
+    assert_eq!(switch::run(&program, &mut Context::default()), Ok(2_000));
+}
+
+#[test]
+fn nested_block_and_loop() {
+    // Counts r0 down to zero, skipping the increment of r1 whenever r0 is odd.
+    let program = Builder::new()
+        .inst(Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 10,
+        })
+        .block()
+        .loop_()
+        .inst(Inst::EqImm {
+            result: Register(2),
+            src: Register(0),
+            imm: 0,
+        })
+        .br_if(1, Register(2))
+        .inst(Inst::SubImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        })
+        .block()
+        .inst(Inst::RemImm {
+            result: Register(2),
+            src: Register(0),
+            imm: 2,
+        })
+        .br_if(0, Register(2))
+        .inst(Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 1,
+        })
+        .end()
+        .br(0)
+        .end()
+        .end()
+        .inst(Inst::Return {
+            result: Register(1),
+        })
+        .finish()
+        .unwrap();
+    assert!(matches!(
+        program[2],
+        Inst::BranchNez {
+            target: Target(8),
+            ..
+        }
+    ));
+    assert!(matches!(
+        program[5],
+        Inst::BranchNez {
+            target: Target(7),
+            ..
+        }
+    ));
+    assert!(matches!(program[7], Inst::Branch { target: Target(1) }));
+    assert_eq!(switch::run(&program, &mut Context::default()), Ok(5));
+}
+
+#[test]
+fn malformed() {
+    assert_eq!(
+        Builder::new().end().finish(),
+        Err(StructureError::UnmatchedEnd)
+    );
+    assert_eq!(
+        Builder::new().block().br(1).end().finish(),
+        Err(StructureError::BranchDepthOutOfBounds { index: 0, depth: 1 })
+    );
+    assert_eq!(
+        Builder::new().loop_().block().end().finish(),
+        Err(StructureError::UnclosedFrames { open: 1 })
+    );
+    assert_eq!(
+        Builder::new()
+            .inst(Inst::Branch { target: Target(0) })
+            .finish(),
+        Err(StructureError::RawBranch { index: 0 })
+    );
+}
@@ -0,0 +1,310 @@
+#![allow(dead_code)]
+
+use alloc::{boxed::Box, vec::Vec};
+
+#[cfg(test)]
+use crate::{benchmark, Register, Target};
+
+use super::{handler, switch::Inst, Bits, Context, Outcome};
+
+/// A compiled instruction.
+///
+/// Unlike the closures of the `closure_loop` technique a subroutine is a thin
+/// adaptor that only captures the `Copy` operands of its [`Inst`] and calls the
+/// matching function of the `handler` module.
+pub type Subroutine = Box<dyn Fn(&mut Context) -> Outcome>;
+
+/// Compiles the list of instructions into a list of [`Subroutine`].
+pub fn compile(insts: &[Inst]) -> Vec<Subroutine> {
+    insts.iter().cloned().map(compile_inst).collect()
+}
+
+/// Compiles a single [`Inst`] into its [`Subroutine`].
+pub fn compile_inst(inst: Inst) -> Subroutine {
+    match inst {
+        Inst::Add { result, lhs, rhs } => {
+            Box::new(move |context| handler::add(context, result, lhs, rhs))
+        }
+        Inst::AddImm { result, src, imm } => {
+            Box::new(move |context| handler::add_imm(context, result, src, imm))
+        }
+        Inst::Sub { result, lhs, rhs } => {
+            Box::new(move |context| handler::sub(context, result, lhs, rhs))
+        }
+        Inst::SubImm { result, src, imm } => {
+            Box::new(move |context| handler::sub_imm(context, result, src, imm))
+        }
+        Inst::Mul { result, lhs, rhs } => {
+            Box::new(move |context| handler::mul(context, result, lhs, rhs))
+        }
+        Inst::MulImm { result, src, imm } => {
+            Box::new(move |context| handler::mul_imm(context, result, src, imm))
+        }
+        Inst::Div { result, lhs, rhs } => {
+            Box::new(move |context| handler::div(context, result, lhs, rhs))
+        }
+        Inst::DivImm { result, src, imm } => {
+            Box::new(move |context| handler::div_imm(context, result, src, imm))
+        }
+        Inst::Rem { result, lhs, rhs } => {
+            Box::new(move |context| handler::rem(context, result, lhs, rhs))
+        }
+        Inst::RemImm { result, src, imm } => {
+            Box::new(move |context| handler::rem_imm(context, result, src, imm))
+        }
+        Inst::Shl { result, lhs, rhs } => {
+            Box::new(move |context| handler::shl(context, result, lhs, rhs))
+        }
+        Inst::ShlImm { result, src, imm } => {
+            Box::new(move |context| handler::shl_imm(context, result, src, imm))
+        }
+        Inst::ShrU { result, lhs, rhs } => {
+            Box::new(move |context| handler::shr_u(context, result, lhs, rhs))
+        }
+        Inst::ShrUImm { result, src, imm } => {
+            Box::new(move |context| handler::shr_u_imm(context, result, src, imm))
+        }
+        Inst::ShrS { result, lhs, rhs } => {
+            Box::new(move |context| handler::shr_s(context, result, lhs, rhs))
+        }
+        Inst::ShrSImm { result, src, imm } => {
+            Box::new(move |context| handler::shr_s_imm(context, result, src, imm))
+        }
+        Inst::Rotl { result, lhs, rhs } => {
+            Box::new(move |context| handler::rotl(context, result, lhs, rhs))
+        }
+        Inst::RotlImm { result, src, imm } => {
+            Box::new(move |context| handler::rotl_imm(context, result, src, imm))
+        }
+        Inst::Rotr { result, lhs, rhs } => {
+            Box::new(move |context| handler::rotr(context, result, lhs, rhs))
+        }
+        Inst::RotrImm { result, src, imm } => {
+            Box::new(move |context| handler::rotr_imm(context, result, src, imm))
+        }
+        Inst::Eq { result, lhs, rhs } => {
+            Box::new(move |context| handler::eq(context, result, lhs, rhs))
+        }
+        Inst::EqImm { result, src, imm } => {
+            Box::new(move |context| handler::eq_imm(context, result, src, imm))
+        }
+        Inst::Ne { result, lhs, rhs } => {
+            Box::new(move |context| handler::ne(context, result, lhs, rhs))
+        }
+        Inst::NeImm { result, src, imm } => {
+            Box::new(move |context| handler::ne_imm(context, result, src, imm))
+        }
+        Inst::LtU { result, lhs, rhs } => {
+            Box::new(move |context| handler::lt_u(context, result, lhs, rhs))
+        }
+        Inst::LtUImm { result, src, imm } => {
+            Box::new(move |context| handler::lt_u_imm(context, result, src, imm))
+        }
+        Inst::LtS { result, lhs, rhs } => {
+            Box::new(move |context| handler::lt_s(context, result, lhs, rhs))
+        }
+        Inst::LtSImm { result, src, imm } => {
+            Box::new(move |context| handler::lt_s_imm(context, result, src, imm))
+        }
+        Inst::Move { result, src } => Box::new(move |context| handler::mov(context, result, src)),
+        Inst::Add32 { result, lhs, rhs } => {
+            Box::new(move |context| handler::add32(context, result, lhs, rhs))
+        }
+        Inst::Sub32 { result, lhs, rhs } => {
+            Box::new(move |context| handler::sub32(context, result, lhs, rhs))
+        }
+        Inst::Mul32 { result, lhs, rhs } => {
+            Box::new(move |context| handler::mul32(context, result, lhs, rhs))
+        }
+        Inst::LtS32 { result, lhs, rhs } => {
+            Box::new(move |context| handler::lt_s32(context, result, lhs, rhs))
+        }
+        Inst::Shl32 { result, lhs, rhs } => {
+            Box::new(move |context| handler::shl32(context, result, lhs, rhs))
+        }
+        Inst::ShrU32 { result, lhs, rhs } => {
+            Box::new(move |context| handler::shr_u32(context, result, lhs, rhs))
+        }
+        Inst::ShrS32 { result, lhs, rhs } => {
+            Box::new(move |context| handler::shr_s32(context, result, lhs, rhs))
+        }
+        Inst::AddChecked { result, lhs, rhs } => {
+            Box::new(move |context| handler::add_checked(context, result, lhs, rhs))
+        }
+        Inst::SubChecked { result, lhs, rhs } => {
+            Box::new(move |context| handler::sub_checked(context, result, lhs, rhs))
+        }
+        Inst::MulChecked { result, lhs, rhs } => {
+            Box::new(move |context| handler::mul_checked(context, result, lhs, rhs))
+        }
+        Inst::MulWide { hi, lo, lhs, rhs } => {
+            Box::new(move |context| handler::mul_wide(context, hi, lo, lhs, rhs))
+        }
+        Inst::Popcnt { result, src } => {
+            Box::new(move |context| handler::popcnt(context, result, src))
+        }
+        Inst::Clz { result, src } => Box::new(move |context| handler::clz(context, result, src)),
+        Inst::Ctz { result, src } => Box::new(move |context| handler::ctz(context, result, src)),
+        Inst::RandNext { result } => Box::new(move |context| handler::rand_next(context, result)),
+        Inst::MinU { result, lhs, rhs } => {
+            Box::new(move |context| handler::min_u(context, result, lhs, rhs))
+        }
+        Inst::MinUImm { result, src, imm } => {
+            Box::new(move |context| handler::min_u_imm(context, result, src, imm))
+        }
+        Inst::MaxU { result, lhs, rhs } => {
+            Box::new(move |context| handler::max_u(context, result, lhs, rhs))
+        }
+        Inst::MaxUImm { result, src, imm } => {
+            Box::new(move |context| handler::max_u_imm(context, result, src, imm))
+        }
+        Inst::MinS { result, lhs, rhs } => {
+            Box::new(move |context| handler::min_s(context, result, lhs, rhs))
+        }
+        Inst::MinSImm { result, src, imm } => {
+            Box::new(move |context| handler::min_s_imm(context, result, src, imm))
+        }
+        Inst::MaxS { result, lhs, rhs } => {
+            Box::new(move |context| handler::max_s(context, result, lhs, rhs))
+        }
+        Inst::MaxSImm { result, src, imm } => {
+            Box::new(move |context| handler::max_s_imm(context, result, src, imm))
+        }
+        Inst::Select {
+            result,
+            cond,
+            if_nonzero,
+            if_zero,
+        } => Box::new(move |context| handler::select(context, result, cond, if_nonzero, if_zero)),
+        Inst::FAdd { result, lhs, rhs } => {
+            Box::new(move |context| handler::fadd(context, result, lhs, rhs))
+        }
+        Inst::FSub { result, lhs, rhs } => {
+            Box::new(move |context| handler::fsub(context, result, lhs, rhs))
+        }
+        Inst::FMul { result, lhs, rhs } => {
+            Box::new(move |context| handler::fmul(context, result, lhs, rhs))
+        }
+        Inst::FDiv { result, lhs, rhs } => {
+            Box::new(move |context| handler::fdiv(context, result, lhs, rhs))
+        }
+        Inst::Load8 {
+            result,
+            base,
+            offset,
+        } => Box::new(move |context| handler::load8(context, result, base, offset)),
+        Inst::Load16 {
+            result,
+            base,
+            offset,
+        } => Box::new(move |context| handler::load16(context, result, base, offset)),
+        Inst::Load32 {
+            result,
+            base,
+            offset,
+        } => Box::new(move |context| handler::load32(context, result, base, offset)),
+        Inst::Load64 {
+            result,
+            base,
+            offset,
+        } => Box::new(move |context| handler::load64(context, result, base, offset)),
+        Inst::Store8 {
+            base,
+            offset,
+            value,
+        } => Box::new(move |context| handler::store8(context, base, offset, value)),
+        Inst::Store16 {
+            base,
+            offset,
+            value,
+        } => Box::new(move |context| handler::store16(context, base, offset, value)),
+        Inst::Store32 {
+            base,
+            offset,
+            value,
+        } => Box::new(move |context| handler::store32(context, base, offset, value)),
+        Inst::Store64 {
+            base,
+            offset,
+            value,
+        } => Box::new(move |context| handler::store64(context, base, offset, value)),
+        Inst::Branch { target } => Box::new(move |context| handler::branch(context, target)),
+        Inst::BranchEqz { target, condition } => {
+            Box::new(move |context| handler::branch_eqz(context, target, condition))
+        }
+        Inst::BranchNez { target, condition } => {
+            Box::new(move |context| handler::branch_nez(context, target, condition))
+        }
+        Inst::BranchEq { target, lhs, rhs } => {
+            Box::new(move |context| handler::branch_eq(context, target, lhs, rhs))
+        }
+        Inst::BranchNe { target, lhs, rhs } => {
+            Box::new(move |context| handler::branch_ne(context, target, lhs, rhs))
+        }
+        Inst::BranchLtU { target, lhs, rhs } => {
+            Box::new(move |context| handler::branch_lt_u(context, target, lhs, rhs))
+        }
+        Inst::BranchLtS { target, lhs, rhs } => {
+            Box::new(move |context| handler::branch_lt_s(context, target, lhs, rhs))
+        }
+        Inst::BranchTable {
+            index,
+            targets,
+            default,
+        } => Box::new(move |context| handler::branch_table(context, index, &targets, default)),
+        Inst::Call { target, num_args } => {
+            Box::new(move |context| handler::call(context, target, num_args))
+        }
+        Inst::Nop => Box::new(handler::nop),
+        Inst::Yield => Box::new(handler::yield_),
+        Inst::Unreachable => Box::new(|_| handler::unreachable()),
+        Inst::Return { result } => Box::new(move |context| handler::ret(context, result)),
+    }
+}
+
+/// Executes the list of subroutines using the given [`Context`] and returns the result in register 0.
+pub fn execute(subroutines: &[Subroutine], context: &mut Context) -> Bits {
+    loop {
+        let pc = context.pc;
+        let subroutine = &subroutines[pc];
+        match subroutine(context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return context.get_reg_abs(0),
+        }
+    }
+}
+
+#[test]
+fn counter_loop() {
+    let repetitions = 100_000_000;
+    let insts = [
+        // Store `repetitions` into r0.
+        // Note: r0 is our loop counter register.
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: repetitions,
+        },
+        // Branch to the end if r0 is zero.
+        Inst::BranchEqz {
+            target: Target(4),
+            condition: Register(0),
+        },
+        // Decrease r0 by 1.
+        Inst::SubImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        // Jump back to the loop header.
+        Inst::Branch { target: Target(1) },
+        // Return value and end function execution.
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let subroutines = compile(&insts);
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&subroutines, &mut context));
+    assert_eq!(result, 0);
+}
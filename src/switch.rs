@@ -1,11 +1,19 @@
 #![allow(dead_code)]
 
+use alloc::{boxed::Box, collections::BTreeMap, vec::Vec};
+use smallvec::{smallvec, SmallVec};
+#[cfg(feature = "std")]
+use std::time::Instant;
+
 #[cfg(test)]
 use crate::benchmark;
 
-use super::{handler, Bits, Context, Outcome, Register, Target};
+use super::{
+    bytecode::opcode, fetch, handler, Bits, Context, InstArgs, Outcome, Register, Target, TrapCode,
+};
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Inst {
     /// Adds the contents of `lhs` and `rhs` and stores the result into `result`.
     Add {
@@ -43,10 +51,437 @@ pub enum Inst {
         src: Register,
         imm: Bits,
     },
+    /// Divides the contents of `lhs` by `rhs` and stores the result into `result`.
+    ///
+    /// Traps if the contents of `rhs` are zero.
+    Div {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Divides the contents of `src` by the constant `imm` and stores the result into `result`.
+    ///
+    /// Traps if `imm` is zero.
+    DivImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Computes the remainder of the contents of `lhs` divided by `rhs` and stores the result into `result`.
+    ///
+    /// Traps if the contents of `rhs` are zero.
+    Rem {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Computes the remainder of the contents of `src` divided by the constant `imm` and stores the result into `result`.
+    ///
+    /// Traps if `imm` is zero.
+    RemImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Shifts the contents of `lhs` left by the contents of `rhs` and stores the result into `result`.
+    Shl {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Shifts the contents of `src` left by the constant `imm` and stores the result into `result`.
+    ShlImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Logically shifts the contents of `lhs` right by the contents of `rhs` and stores the result into `result`.
+    ShrU {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Logically shifts the contents of `src` right by the constant `imm` and stores the result into `result`.
+    ShrUImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Arithmetically shifts the contents of `lhs` right by the contents of `rhs` and stores the result into `result`.
+    ShrS {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Arithmetically shifts the contents of `src` right by the constant `imm` and stores the result into `result`.
+    ShrSImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Rotates the contents of `lhs` left by the contents of `rhs` and stores the result into `result`.
+    Rotl {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Rotates the contents of `src` left by the constant `imm` and stores the result into `result`.
+    RotlImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Rotates the contents of `lhs` right by the contents of `rhs` and stores the result into `result`.
+    Rotr {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Rotates the contents of `src` right by the constant `imm` and stores the result into `result`.
+    RotrImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Stores `1` into `result` if the contents of `lhs` and `rhs` are equal and `0` otherwise.
+    Eq {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Stores `1` into `result` if the contents of `src` equals the constant `imm` and `0` otherwise.
+    EqImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Stores `1` into `result` if the contents of `lhs` and `rhs` are not equal and `0` otherwise.
+    Ne {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Stores `1` into `result` if the contents of `src` does not equal the constant `imm` and `0` otherwise.
+    NeImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Stores `1` into `result` if the contents of `lhs` is less than the contents of `rhs` when interpreted as unsigned integers and `0` otherwise.
+    LtU {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Stores `1` into `result` if the contents of `src` is less than the constant `imm` when interpreted as unsigned integers and `0` otherwise.
+    LtUImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Stores `1` into `result` if the contents of `lhs` is less than the contents of `rhs` when interpreted as signed integers and `0` otherwise.
+    LtS {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Stores `1` into `result` if the contents of `src` is less than the constant `imm` when interpreted as signed integers and `0` otherwise.
+    LtSImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Copies the contents of `src` into `result`.
+    Move { result: Register, src: Register },
+    /// Adds the low 32 bits of `lhs` and `rhs` with wrap around and stores the zero extended result into `result`.
+    Add32 {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Subtracts the low 32 bits of `rhs` from the low 32 bits of `lhs` with wrap around and stores the zero extended result into `result`.
+    Sub32 {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Multiplies the low 32 bits of `lhs` and `rhs` with wrap around and stores the zero extended result into `result`.
+    Mul32 {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Stores `1` into `result` if the low 32 bits of `lhs` are less than the low 32 bits of `rhs` when interpreted as signed integers and `0` otherwise.
+    LtS32 {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Shifts the low 32 bits of `lhs` left by the contents of `rhs` modulo 32 and stores the zero extended result into `result`.
+    Shl32 {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Logically shifts the low 32 bits of `lhs` right by the contents of `rhs` modulo 32 and stores the zero extended result into `result`.
+    ShrU32 {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Arithmetically shifts the low 32 bits of `lhs` right by the contents of `rhs` modulo 32 and stores the zero extended result into `result`.
+    ShrS32 {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Adds the contents of `lhs` and `rhs` and stores the result into `result`.
+    ///
+    /// Traps if the addition overflows.
+    AddChecked {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Subtracts the contents of `rhs` from `lhs` and stores the result into `result`.
+    ///
+    /// Traps if the subtraction overflows.
+    SubChecked {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Multiplies the contents of `lhs` and `rhs` and stores the result into `result`.
+    ///
+    /// Traps if the multiplication overflows.
+    MulChecked {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Multiplies the contents of `lhs` and `rhs` into the full 128-bit product and stores
+    /// its high 64 bits into `hi` and its low 64 bits into `lo`.
+    ///
+    /// If `hi` and `lo` are the same register it ends up holding the high bits.
+    MulWide {
+        hi: Register,
+        lo: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Stores the number of set bits of the contents of `src` into `result`.
+    Popcnt { result: Register, src: Register },
+    /// Stores the number of leading zero bits of the contents of `src` into `result`.
+    ///
+    /// Stores `64` if the contents of `src` are zero.
+    Clz { result: Register, src: Register },
+    /// Stores the number of trailing zero bits of the contents of `src` into `result`.
+    ///
+    /// Stores `64` if the contents of `src` are zero.
+    Ctz { result: Register, src: Register },
+    /// Advances the random number generator of the context and stores its next value into `result`.
+    ///
+    /// The sequence of values only depends on the seed of the context, see [`Context::with_seed`].
+    RandNext { result: Register },
+    /// Stores the unsigned minimum of the contents of `lhs` and `rhs` into `result`.
+    MinU {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Stores the unsigned minimum of the contents of `src` and the constant `imm` into `result`.
+    MinUImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Stores the unsigned maximum of the contents of `lhs` and `rhs` into `result`.
+    MaxU {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Stores the unsigned maximum of the contents of `src` and the constant `imm` into `result`.
+    MaxUImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Stores the signed minimum of the contents of `lhs` and `rhs` into `result`.
+    MinS {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Stores the signed minimum of the contents of `src` and the constant `imm` into `result`.
+    MinSImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Stores the signed maximum of the contents of `lhs` and `rhs` into `result`.
+    MaxS {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Stores the signed maximum of the contents of `src` and the constant `imm` into `result`.
+    MaxSImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Stores the contents of `if_nonzero` into `result` if the contents of `cond` are not zero
+    /// and the contents of `if_zero` otherwise.
+    ///
+    /// Both values are read before selecting so that no branch is taken, similar to a `cmov`.
+    Select {
+        result: Register,
+        cond: Register,
+        if_nonzero: Register,
+        if_zero: Register,
+    },
+    /// Adds the contents of the float registers `lhs` and `rhs` and stores the result into `result`.
+    FAdd {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Subtracts the contents of the float register `rhs` from `lhs` and stores the result into `result`.
+    FSub {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Multiplies the contents of the float registers `lhs` and `rhs` and stores the result into `result`.
+    FMul {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Divides the contents of the float register `lhs` by `rhs` and stores the result into `result`.
+    FDiv {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Loads the `u8` at the address in `base` plus `offset` from memory and stores it zero extended into `result`.
+    ///
+    /// Traps if the accessed bytes are out of bounds.
+    Load8 {
+        result: Register,
+        base: Register,
+        offset: Bits,
+    },
+    /// Loads the `u16` at the address in `base` plus `offset` from memory and stores it zero extended into `result`.
+    ///
+    /// Traps if the accessed bytes are out of bounds.
+    Load16 {
+        result: Register,
+        base: Register,
+        offset: Bits,
+    },
+    /// Loads the `u32` at the address in `base` plus `offset` from memory and stores it zero extended into `result`.
+    ///
+    /// Traps if the accessed bytes are out of bounds.
+    Load32 {
+        result: Register,
+        base: Register,
+        offset: Bits,
+    },
+    /// Loads the `u64` at the address in `base` plus `offset` from memory and stores it zero extended into `result`.
+    ///
+    /// Traps if the accessed bytes are out of bounds.
+    Load64 {
+        result: Register,
+        base: Register,
+        offset: Bits,
+    },
+    /// Stores the low `u8` of the contents of `value` at the address in `base` plus `offset` into memory.
+    ///
+    /// Traps if the accessed bytes are out of bounds.
+    Store8 {
+        base: Register,
+        offset: Bits,
+        value: Register,
+    },
+    /// Stores the low `u16` of the contents of `value` at the address in `base` plus `offset` into memory.
+    ///
+    /// Traps if the accessed bytes are out of bounds.
+    Store16 {
+        base: Register,
+        offset: Bits,
+        value: Register,
+    },
+    /// Stores the low `u32` of the contents of `value` at the address in `base` plus `offset` into memory.
+    ///
+    /// Traps if the accessed bytes are out of bounds.
+    Store32 {
+        base: Register,
+        offset: Bits,
+        value: Register,
+    },
+    /// Stores the contents of `value` at the address in `base` plus `offset` into memory.
+    ///
+    /// Traps if the accessed bytes are out of bounds.
+    Store64 {
+        base: Register,
+        offset: Bits,
+        value: Register,
+    },
     /// Branches to the instruction indexed by `target`.
     Branch { target: Target },
     /// Branches to the instruction indexed by `target` if the contents of `condition` are zero.
     BranchEqz { target: Target, condition: Register },
+    /// Branches to the instruction indexed by `target` if the contents of `condition` are not zero.
+    BranchNez { target: Target, condition: Register },
+    /// Branches to the instruction indexed by `target` if the contents of `lhs` and `rhs` are equal.
+    BranchEq {
+        target: Target,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Branches to the instruction indexed by `target` if the contents of `lhs` and `rhs` are not equal.
+    BranchNe {
+        target: Target,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Branches to the instruction indexed by `target` if the contents of `lhs` are less than the contents of `rhs`,
+    /// interpreted as unsigned integers.
+    BranchLtU {
+        target: Target,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Branches to the instruction indexed by `target` if the contents of `lhs` are less than the contents of `rhs`,
+    /// interpreted as signed integers.
+    BranchLtS {
+        target: Target,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Branches to the instruction indexed by `targets[i]` where `i` are the contents of `index`.
+    ///
+    /// Branches to the instruction indexed by `default` if `i` is out of bounds for `targets`.
+    BranchTable {
+        index: Register,
+        targets: Box<[Target]>,
+        default: Target,
+    },
+    /// Calls the function starting at the instruction indexed by `target`.
+    ///
+    /// The first `num_args` registers are passed as arguments and the result is returned in `r0`.
+    /// Traps if the maximum call depth is exceeded.
+    Call { target: Target, num_args: usize },
+    /// Does nothing and continues with the next instruction.
+    Nop,
+    /// Suspends execution of the program so that [`resume`] returns to its caller.
+    ///
+    /// A later [`resume`] continues with the next instruction.
+    Yield,
+    /// Traps execution of the function unconditionally.
+    Unreachable,
     /// Returns execution of the function and returns the result in `result`.
     Return { result: Register },
 }
@@ -60,105 +495,2318 @@ impl Inst {
             Inst::SubImm { result, src, imm } => handler::sub_imm(context, *result, *src, *imm),
             Inst::Mul { result, lhs, rhs } => handler::mul(context, *result, *lhs, *rhs),
             Inst::MulImm { result, src, imm } => handler::mul_imm(context, *result, *src, *imm),
+            Inst::Div { result, lhs, rhs } => handler::div(context, *result, *lhs, *rhs),
+            Inst::DivImm { result, src, imm } => handler::div_imm(context, *result, *src, *imm),
+            Inst::Rem { result, lhs, rhs } => handler::rem(context, *result, *lhs, *rhs),
+            Inst::RemImm { result, src, imm } => handler::rem_imm(context, *result, *src, *imm),
+            Inst::Shl { result, lhs, rhs } => handler::shl(context, *result, *lhs, *rhs),
+            Inst::ShlImm { result, src, imm } => handler::shl_imm(context, *result, *src, *imm),
+            Inst::ShrU { result, lhs, rhs } => handler::shr_u(context, *result, *lhs, *rhs),
+            Inst::ShrUImm { result, src, imm } => handler::shr_u_imm(context, *result, *src, *imm),
+            Inst::ShrS { result, lhs, rhs } => handler::shr_s(context, *result, *lhs, *rhs),
+            Inst::ShrSImm { result, src, imm } => handler::shr_s_imm(context, *result, *src, *imm),
+            Inst::Rotl { result, lhs, rhs } => handler::rotl(context, *result, *lhs, *rhs),
+            Inst::RotlImm { result, src, imm } => handler::rotl_imm(context, *result, *src, *imm),
+            Inst::Rotr { result, lhs, rhs } => handler::rotr(context, *result, *lhs, *rhs),
+            Inst::RotrImm { result, src, imm } => handler::rotr_imm(context, *result, *src, *imm),
+            Inst::Eq { result, lhs, rhs } => handler::eq(context, *result, *lhs, *rhs),
+            Inst::EqImm { result, src, imm } => handler::eq_imm(context, *result, *src, *imm),
+            Inst::Ne { result, lhs, rhs } => handler::ne(context, *result, *lhs, *rhs),
+            Inst::NeImm { result, src, imm } => handler::ne_imm(context, *result, *src, *imm),
+            Inst::LtU { result, lhs, rhs } => handler::lt_u(context, *result, *lhs, *rhs),
+            Inst::LtUImm { result, src, imm } => handler::lt_u_imm(context, *result, *src, *imm),
+            Inst::LtS { result, lhs, rhs } => handler::lt_s(context, *result, *lhs, *rhs),
+            Inst::LtSImm { result, src, imm } => handler::lt_s_imm(context, *result, *src, *imm),
+            Inst::Move { result, src } => handler::mov(context, *result, *src),
+            Inst::Add32 { result, lhs, rhs } => handler::add32(context, *result, *lhs, *rhs),
+            Inst::Sub32 { result, lhs, rhs } => handler::sub32(context, *result, *lhs, *rhs),
+            Inst::Mul32 { result, lhs, rhs } => handler::mul32(context, *result, *lhs, *rhs),
+            Inst::LtS32 { result, lhs, rhs } => handler::lt_s32(context, *result, *lhs, *rhs),
+            Inst::Shl32 { result, lhs, rhs } => handler::shl32(context, *result, *lhs, *rhs),
+            Inst::ShrU32 { result, lhs, rhs } => handler::shr_u32(context, *result, *lhs, *rhs),
+            Inst::ShrS32 { result, lhs, rhs } => handler::shr_s32(context, *result, *lhs, *rhs),
+            Inst::AddChecked { result, lhs, rhs } => {
+                handler::add_checked(context, *result, *lhs, *rhs)
+            }
+            Inst::SubChecked { result, lhs, rhs } => {
+                handler::sub_checked(context, *result, *lhs, *rhs)
+            }
+            Inst::MulChecked { result, lhs, rhs } => {
+                handler::mul_checked(context, *result, *lhs, *rhs)
+            }
+            Inst::MulWide { hi, lo, lhs, rhs } => handler::mul_wide(context, *hi, *lo, *lhs, *rhs),
+            Inst::Popcnt { result, src } => handler::popcnt(context, *result, *src),
+            Inst::Clz { result, src } => handler::clz(context, *result, *src),
+            Inst::Ctz { result, src } => handler::ctz(context, *result, *src),
+            Inst::RandNext { result } => handler::rand_next(context, *result),
+            Inst::MinU { result, lhs, rhs } => handler::min_u(context, *result, *lhs, *rhs),
+            Inst::MinUImm { result, src, imm } => handler::min_u_imm(context, *result, *src, *imm),
+            Inst::MaxU { result, lhs, rhs } => handler::max_u(context, *result, *lhs, *rhs),
+            Inst::MaxUImm { result, src, imm } => handler::max_u_imm(context, *result, *src, *imm),
+            Inst::MinS { result, lhs, rhs } => handler::min_s(context, *result, *lhs, *rhs),
+            Inst::MinSImm { result, src, imm } => handler::min_s_imm(context, *result, *src, *imm),
+            Inst::MaxS { result, lhs, rhs } => handler::max_s(context, *result, *lhs, *rhs),
+            Inst::MaxSImm { result, src, imm } => handler::max_s_imm(context, *result, *src, *imm),
+            Inst::Select {
+                result,
+                cond,
+                if_nonzero,
+                if_zero,
+            } => handler::select(context, *result, *cond, *if_nonzero, *if_zero),
+            Inst::FAdd { result, lhs, rhs } => handler::fadd(context, *result, *lhs, *rhs),
+            Inst::FSub { result, lhs, rhs } => handler::fsub(context, *result, *lhs, *rhs),
+            Inst::FMul { result, lhs, rhs } => handler::fmul(context, *result, *lhs, *rhs),
+            Inst::FDiv { result, lhs, rhs } => handler::fdiv(context, *result, *lhs, *rhs),
+            Inst::Load8 {
+                result,
+                base,
+                offset,
+            } => handler::load8(context, *result, *base, *offset),
+            Inst::Load16 {
+                result,
+                base,
+                offset,
+            } => handler::load16(context, *result, *base, *offset),
+            Inst::Load32 {
+                result,
+                base,
+                offset,
+            } => handler::load32(context, *result, *base, *offset),
+            Inst::Load64 {
+                result,
+                base,
+                offset,
+            } => handler::load64(context, *result, *base, *offset),
+            Inst::Store8 {
+                base,
+                offset,
+                value,
+            } => handler::store8(context, *base, *offset, *value),
+            Inst::Store16 {
+                base,
+                offset,
+                value,
+            } => handler::store16(context, *base, *offset, *value),
+            Inst::Store32 {
+                base,
+                offset,
+                value,
+            } => handler::store32(context, *base, *offset, *value),
+            Inst::Store64 {
+                base,
+                offset,
+                value,
+            } => handler::store64(context, *base, *offset, *value),
             Inst::Branch { target } => handler::branch(context, *target),
             Inst::BranchEqz { target, condition } => {
                 handler::branch_eqz(context, *target, *condition)
             }
+            Inst::BranchNez { target, condition } => {
+                handler::branch_nez(context, *target, *condition)
+            }
+            Inst::BranchEq { target, lhs, rhs } => handler::branch_eq(context, *target, *lhs, *rhs),
+            Inst::BranchNe { target, lhs, rhs } => handler::branch_ne(context, *target, *lhs, *rhs),
+            Inst::BranchLtU { target, lhs, rhs } => {
+                handler::branch_lt_u(context, *target, *lhs, *rhs)
+            }
+            Inst::BranchLtS { target, lhs, rhs } => {
+                handler::branch_lt_s(context, *target, *lhs, *rhs)
+            }
+            Inst::BranchTable {
+                index,
+                targets,
+                default,
+            } => handler::branch_table(context, *index, targets, *default),
+            Inst::Call { target, num_args } => handler::call(context, *target, *num_args),
+            Inst::Nop => handler::nop(context),
+            Inst::Yield => handler::yield_(context),
+            Inst::Unreachable => handler::unreachable(),
             Inst::Return { result } => handler::ret(context, *result),
         }
     }
+
+    /// Returns the opcode of the instruction as used by the byte-code encoding.
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Inst::Add { .. } => opcode::ADD,
+            Inst::AddImm { .. } => opcode::ADD_IMM,
+            Inst::Sub { .. } => opcode::SUB,
+            Inst::SubImm { .. } => opcode::SUB_IMM,
+            Inst::Mul { .. } => opcode::MUL,
+            Inst::MulImm { .. } => opcode::MUL_IMM,
+            Inst::Div { .. } => opcode::DIV,
+            Inst::DivImm { .. } => opcode::DIV_IMM,
+            Inst::Rem { .. } => opcode::REM,
+            Inst::RemImm { .. } => opcode::REM_IMM,
+            Inst::Shl { .. } => opcode::SHL,
+            Inst::ShlImm { .. } => opcode::SHL_IMM,
+            Inst::ShrU { .. } => opcode::SHR_U,
+            Inst::ShrUImm { .. } => opcode::SHR_U_IMM,
+            Inst::ShrS { .. } => opcode::SHR_S,
+            Inst::ShrSImm { .. } => opcode::SHR_S_IMM,
+            Inst::Rotl { .. } => opcode::ROTL,
+            Inst::RotlImm { .. } => opcode::ROTL_IMM,
+            Inst::Rotr { .. } => opcode::ROTR,
+            Inst::RotrImm { .. } => opcode::ROTR_IMM,
+            Inst::Eq { .. } => opcode::EQ,
+            Inst::EqImm { .. } => opcode::EQ_IMM,
+            Inst::Ne { .. } => opcode::NE,
+            Inst::NeImm { .. } => opcode::NE_IMM,
+            Inst::LtU { .. } => opcode::LT_U,
+            Inst::LtUImm { .. } => opcode::LT_U_IMM,
+            Inst::LtS { .. } => opcode::LT_S,
+            Inst::LtSImm { .. } => opcode::LT_S_IMM,
+            Inst::Move { .. } => opcode::MOVE,
+            Inst::Add32 { .. } => opcode::ADD32,
+            Inst::Sub32 { .. } => opcode::SUB32,
+            Inst::Mul32 { .. } => opcode::MUL32,
+            Inst::LtS32 { .. } => opcode::LT_S32,
+            Inst::Shl32 { .. } => opcode::SHL32,
+            Inst::ShrU32 { .. } => opcode::SHR_U32,
+            Inst::ShrS32 { .. } => opcode::SHR_S32,
+            Inst::AddChecked { .. } => opcode::ADD_CHECKED,
+            Inst::SubChecked { .. } => opcode::SUB_CHECKED,
+            Inst::MulChecked { .. } => opcode::MUL_CHECKED,
+            Inst::MulWide { .. } => opcode::MUL_WIDE,
+            Inst::Popcnt { .. } => opcode::POPCNT,
+            Inst::Clz { .. } => opcode::CLZ,
+            Inst::Ctz { .. } => opcode::CTZ,
+            Inst::RandNext { .. } => opcode::RAND_NEXT,
+            Inst::MinU { .. } => opcode::MIN_U,
+            Inst::MinUImm { .. } => opcode::MIN_U_IMM,
+            Inst::MaxU { .. } => opcode::MAX_U,
+            Inst::MaxUImm { .. } => opcode::MAX_U_IMM,
+            Inst::MinS { .. } => opcode::MIN_S,
+            Inst::MinSImm { .. } => opcode::MIN_S_IMM,
+            Inst::MaxS { .. } => opcode::MAX_S,
+            Inst::MaxSImm { .. } => opcode::MAX_S_IMM,
+            Inst::Select { .. } => opcode::SELECT,
+            Inst::FAdd { .. } => opcode::FADD,
+            Inst::FSub { .. } => opcode::FSUB,
+            Inst::FMul { .. } => opcode::FMUL,
+            Inst::FDiv { .. } => opcode::FDIV,
+            Inst::Load8 { .. } => opcode::LOAD8,
+            Inst::Load16 { .. } => opcode::LOAD16,
+            Inst::Load32 { .. } => opcode::LOAD32,
+            Inst::Load64 { .. } => opcode::LOAD64,
+            Inst::Store8 { .. } => opcode::STORE8,
+            Inst::Store16 { .. } => opcode::STORE16,
+            Inst::Store32 { .. } => opcode::STORE32,
+            Inst::Store64 { .. } => opcode::STORE64,
+            Inst::Branch { .. } => opcode::BRANCH,
+            Inst::BranchEqz { .. } => opcode::BRANCH_EQZ,
+            Inst::BranchNez { .. } => opcode::BRANCH_NEZ,
+            Inst::BranchTable { .. } => opcode::BRANCH_TABLE,
+            Inst::Call { .. } => opcode::CALL,
+            Inst::Nop => opcode::NOP,
+            Inst::BranchEq { .. } => opcode::BRANCH_EQ,
+            Inst::BranchNe { .. } => opcode::BRANCH_NE,
+            Inst::BranchLtU { .. } => opcode::BRANCH_LT_U,
+            Inst::BranchLtS { .. } => opcode::BRANCH_LT_S,
+            Inst::Yield => opcode::YIELD,
+            Inst::Unreachable => opcode::UNREACHABLE,
+            Inst::Return { .. } => opcode::RETURN,
+        }
+    }
+
+    /// Returns the operands of the instruction as passed to its handler in [`crate::HANDLERS`].
+    ///
+    /// Returns `None` for `select`, `mul_wide` and `branch_table` whose operands do not fit [`InstArgs`].
+    pub fn args(&self) -> Option<InstArgs> {
+        let [a, b, c] = match *self {
+            Inst::Add { result, lhs, rhs }
+            | Inst::Sub { result, lhs, rhs }
+            | Inst::Mul { result, lhs, rhs }
+            | Inst::Div { result, lhs, rhs }
+            | Inst::Rem { result, lhs, rhs }
+            | Inst::Shl { result, lhs, rhs }
+            | Inst::ShrU { result, lhs, rhs }
+            | Inst::ShrS { result, lhs, rhs }
+            | Inst::Rotl { result, lhs, rhs }
+            | Inst::Rotr { result, lhs, rhs }
+            | Inst::Eq { result, lhs, rhs }
+            | Inst::Ne { result, lhs, rhs }
+            | Inst::LtU { result, lhs, rhs }
+            | Inst::LtS { result, lhs, rhs }
+            | Inst::Add32 { result, lhs, rhs }
+            | Inst::Sub32 { result, lhs, rhs }
+            | Inst::Mul32 { result, lhs, rhs }
+            | Inst::LtS32 { result, lhs, rhs }
+            | Inst::Shl32 { result, lhs, rhs }
+            | Inst::ShrU32 { result, lhs, rhs }
+            | Inst::ShrS32 { result, lhs, rhs }
+            | Inst::AddChecked { result, lhs, rhs }
+            | Inst::SubChecked { result, lhs, rhs }
+            | Inst::MulChecked { result, lhs, rhs }
+            | Inst::MinU { result, lhs, rhs }
+            | Inst::MaxU { result, lhs, rhs }
+            | Inst::MinS { result, lhs, rhs }
+            | Inst::MaxS { result, lhs, rhs }
+            | Inst::FAdd { result, lhs, rhs }
+            | Inst::FSub { result, lhs, rhs }
+            | Inst::FMul { result, lhs, rhs }
+            | Inst::FDiv { result, lhs, rhs } => [result.0 as Bits, lhs.0 as Bits, rhs.0 as Bits],
+            Inst::AddImm { result, src, imm }
+            | Inst::SubImm { result, src, imm }
+            | Inst::MulImm { result, src, imm }
+            | Inst::DivImm { result, src, imm }
+            | Inst::RemImm { result, src, imm }
+            | Inst::ShlImm { result, src, imm }
+            | Inst::ShrUImm { result, src, imm }
+            | Inst::ShrSImm { result, src, imm }
+            | Inst::RotlImm { result, src, imm }
+            | Inst::RotrImm { result, src, imm }
+            | Inst::EqImm { result, src, imm }
+            | Inst::NeImm { result, src, imm }
+            | Inst::LtUImm { result, src, imm }
+            | Inst::LtSImm { result, src, imm }
+            | Inst::MinUImm { result, src, imm }
+            | Inst::MaxUImm { result, src, imm }
+            | Inst::MinSImm { result, src, imm }
+            | Inst::MaxSImm { result, src, imm } => [result.0 as Bits, src.0 as Bits, imm],
+            Inst::Move { result, src }
+            | Inst::Popcnt { result, src }
+            | Inst::Clz { result, src }
+            | Inst::Ctz { result, src } => [result.0 as Bits, src.0 as Bits, 0],
+            Inst::Load8 {
+                result,
+                base,
+                offset,
+            }
+            | Inst::Load16 {
+                result,
+                base,
+                offset,
+            }
+            | Inst::Load32 {
+                result,
+                base,
+                offset,
+            }
+            | Inst::Load64 {
+                result,
+                base,
+                offset,
+            } => [result.0 as Bits, base.0 as Bits, offset],
+            Inst::Store8 {
+                base,
+                offset,
+                value,
+            }
+            | Inst::Store16 {
+                base,
+                offset,
+                value,
+            }
+            | Inst::Store32 {
+                base,
+                offset,
+                value,
+            }
+            | Inst::Store64 {
+                base,
+                offset,
+                value,
+            } => [base.0 as Bits, offset, value.0 as Bits],
+            Inst::Branch { target } => [target.0 as Bits, 0, 0],
+            Inst::BranchEqz { target, condition } | Inst::BranchNez { target, condition } => {
+                [target.0 as Bits, condition.0 as Bits, 0]
+            }
+            Inst::BranchEq { target, lhs, rhs }
+            | Inst::BranchNe { target, lhs, rhs }
+            | Inst::BranchLtU { target, lhs, rhs }
+            | Inst::BranchLtS { target, lhs, rhs } => {
+                [target.0 as Bits, lhs.0 as Bits, rhs.0 as Bits]
+            }
+            Inst::Call { target, num_args } => [target.0 as Bits, num_args as Bits, 0],
+            Inst::RandNext { result } | Inst::Return { result } => [result.0 as Bits, 0, 0],
+            Inst::Nop | Inst::Yield | Inst::Unreachable => [0, 0, 0],
+            Inst::Select { .. } | Inst::MulWide { .. } | Inst::BranchTable { .. } => return None,
+        };
+        Some(InstArgs { a, b, c })
+    }
+
+    /// Returns the registers read and written by the instruction in this order.
+    ///
+    /// Immediates are no operands and the float registers of the float instructions are not
+    /// reported since they belong to a separate register file.
+    /// A `call` reads its `num_args` argument registers and writes the returned `r0`.
+    /// Only `mul_wide` writes two registers.
+    pub fn operands(&self) -> (SmallVec<[Register; 3]>, SmallVec<[Register; 2]>) {
+        match *self {
+            Inst::Add { result, lhs, rhs }
+            | Inst::Sub { result, lhs, rhs }
+            | Inst::Mul { result, lhs, rhs }
+            | Inst::Div { result, lhs, rhs }
+            | Inst::Rem { result, lhs, rhs }
+            | Inst::Shl { result, lhs, rhs }
+            | Inst::ShrU { result, lhs, rhs }
+            | Inst::ShrS { result, lhs, rhs }
+            | Inst::Rotl { result, lhs, rhs }
+            | Inst::Rotr { result, lhs, rhs }
+            | Inst::Eq { result, lhs, rhs }
+            | Inst::Ne { result, lhs, rhs }
+            | Inst::LtU { result, lhs, rhs }
+            | Inst::LtS { result, lhs, rhs }
+            | Inst::Add32 { result, lhs, rhs }
+            | Inst::Sub32 { result, lhs, rhs }
+            | Inst::Mul32 { result, lhs, rhs }
+            | Inst::LtS32 { result, lhs, rhs }
+            | Inst::Shl32 { result, lhs, rhs }
+            | Inst::ShrU32 { result, lhs, rhs }
+            | Inst::ShrS32 { result, lhs, rhs }
+            | Inst::AddChecked { result, lhs, rhs }
+            | Inst::SubChecked { result, lhs, rhs }
+            | Inst::MulChecked { result, lhs, rhs }
+            | Inst::MinU { result, lhs, rhs }
+            | Inst::MaxU { result, lhs, rhs }
+            | Inst::MinS { result, lhs, rhs }
+            | Inst::MaxS { result, lhs, rhs } => (smallvec![lhs, rhs], smallvec![result]),
+            Inst::AddImm { result, src, .. }
+            | Inst::SubImm { result, src, .. }
+            | Inst::MulImm { result, src, .. }
+            | Inst::DivImm { result, src, .. }
+            | Inst::RemImm { result, src, .. }
+            | Inst::ShlImm { result, src, .. }
+            | Inst::ShrUImm { result, src, .. }
+            | Inst::ShrSImm { result, src, .. }
+            | Inst::RotlImm { result, src, .. }
+            | Inst::RotrImm { result, src, .. }
+            | Inst::EqImm { result, src, .. }
+            | Inst::NeImm { result, src, .. }
+            | Inst::LtUImm { result, src, .. }
+            | Inst::LtSImm { result, src, .. }
+            | Inst::MinUImm { result, src, .. }
+            | Inst::MaxUImm { result, src, .. }
+            | Inst::MinSImm { result, src, .. }
+            | Inst::MaxSImm { result, src, .. }
+            | Inst::Move { result, src }
+            | Inst::Popcnt { result, src }
+            | Inst::Clz { result, src }
+            | Inst::Ctz { result, src } => (smallvec![src], smallvec![result]),
+            Inst::Select {
+                result,
+                cond,
+                if_nonzero,
+                if_zero,
+            } => (smallvec![cond, if_nonzero, if_zero], smallvec![result]),
+            Inst::MulWide { hi, lo, lhs, rhs } => (smallvec![lhs, rhs], smallvec![hi, lo]),
+            Inst::Load8 { result, base, .. }
+            | Inst::Load16 { result, base, .. }
+            | Inst::Load32 { result, base, .. }
+            | Inst::Load64 { result, base, .. } => (smallvec![base], smallvec![result]),
+            Inst::Store8 { base, value, .. }
+            | Inst::Store16 { base, value, .. }
+            | Inst::Store32 { base, value, .. }
+            | Inst::Store64 { base, value, .. } => (smallvec![base, value], SmallVec::new()),
+            Inst::BranchEq { lhs, rhs, .. }
+            | Inst::BranchNe { lhs, rhs, .. }
+            | Inst::BranchLtU { lhs, rhs, .. }
+            | Inst::BranchLtS { lhs, rhs, .. } => (smallvec![lhs, rhs], SmallVec::new()),
+            Inst::BranchEqz { condition, .. }
+            | Inst::BranchNez { condition, .. }
+            | Inst::BranchTable {
+                index: condition, ..
+            } => (smallvec![condition], SmallVec::new()),
+            Inst::RandNext { result } => (SmallVec::new(), smallvec![result]),
+            Inst::Return { result } => (smallvec![result], SmallVec::new()),
+            Inst::Call { num_args, .. } => (
+                (0..num_args).map(Register).collect(),
+                smallvec![Register(0)],
+            ),
+            Inst::FAdd { .. }
+            | Inst::FSub { .. }
+            | Inst::FMul { .. }
+            | Inst::FDiv { .. }
+            | Inst::Branch { .. }
+            | Inst::Nop
+            | Inst::Yield
+            | Inst::Unreachable => (SmallVec::new(), SmallVec::new()),
+        }
+    }
+
+    /// Returns the estimated latency of the instruction in cycles for scheduling experiments.
+    ///
+    /// The estimates roughly follow the latencies of a modern `x86_64` CPU.
+    /// Conditional branches additionally pay a quarter of the [`MISPREDICTION_PENALTY`]
+    /// and `branch_table` half of it since indirect jumps are predicted worse.
+    pub fn cost(&self) -> u64 {
+        match self {
+            Inst::Nop => 0,
+            Inst::Add { .. }
+            | Inst::AddImm { .. }
+            | Inst::Sub { .. }
+            | Inst::SubImm { .. }
+            | Inst::Shl { .. }
+            | Inst::ShlImm { .. }
+            | Inst::ShrU { .. }
+            | Inst::ShrUImm { .. }
+            | Inst::ShrS { .. }
+            | Inst::ShrSImm { .. }
+            | Inst::Rotl { .. }
+            | Inst::RotlImm { .. }
+            | Inst::Rotr { .. }
+            | Inst::RotrImm { .. }
+            | Inst::Eq { .. }
+            | Inst::EqImm { .. }
+            | Inst::Ne { .. }
+            | Inst::NeImm { .. }
+            | Inst::LtU { .. }
+            | Inst::LtUImm { .. }
+            | Inst::LtS { .. }
+            | Inst::LtSImm { .. }
+            | Inst::Move { .. }
+            | Inst::Add32 { .. }
+            | Inst::Sub32 { .. }
+            | Inst::LtS32 { .. }
+            | Inst::Shl32 { .. }
+            | Inst::ShrU32 { .. }
+            | Inst::ShrS32 { .. }
+            | Inst::Popcnt { .. }
+            | Inst::Clz { .. }
+            | Inst::Ctz { .. }
+            | Inst::MinU { .. }
+            | Inst::MinUImm { .. }
+            | Inst::MaxU { .. }
+            | Inst::MaxUImm { .. }
+            | Inst::MinS { .. }
+            | Inst::MinSImm { .. }
+            | Inst::MaxS { .. }
+            | Inst::MaxSImm { .. }
+            | Inst::Select { .. }
+            | Inst::Store8 { .. }
+            | Inst::Store16 { .. }
+            | Inst::Store32 { .. }
+            | Inst::Store64 { .. }
+            | Inst::Branch { .. }
+            | Inst::Yield
+            | Inst::Unreachable => 1,
+            Inst::AddChecked { .. } | Inst::SubChecked { .. } | Inst::Return { .. } => 2,
+            Inst::Mul { .. }
+            | Inst::MulImm { .. }
+            | Inst::Mul32 { .. }
+            | Inst::MulChecked { .. }
+            | Inst::RandNext { .. } => 3,
+            Inst::MulWide { .. } => 4,
+            Inst::FAdd { .. }
+            | Inst::FSub { .. }
+            | Inst::FMul { .. }
+            | Inst::Load8 { .. }
+            | Inst::Load16 { .. }
+            | Inst::Load32 { .. }
+            | Inst::Load64 { .. } => 4,
+            Inst::Call { .. } => 5,
+            Inst::FDiv { .. } => 14,
+            Inst::Div { .. } | Inst::DivImm { .. } | Inst::Rem { .. } | Inst::RemImm { .. } => 40,
+            Inst::BranchEqz { .. }
+            | Inst::BranchNez { .. }
+            | Inst::BranchEq { .. }
+            | Inst::BranchNe { .. }
+            | Inst::BranchLtU { .. }
+            | Inst::BranchLtS { .. } => 1 + MISPREDICTION_PENALTY / 4,
+            Inst::BranchTable { .. } => 1 + MISPREDICTION_PENALTY / 2,
+        }
+    }
+}
+
+/// The estimated number of cycles lost to a mispredicted branch used by [`Inst::cost`].
+pub const MISPREDICTION_PENALTY: u64 = 16;
+
+/// Returns the summed [`Inst::cost`] of all instructions of `insts`.
+///
+/// With `frequencies` every cost is weighted by the execution count of its instruction
+/// indexed by its `pc`, e.g. as counted from the trace of [`run_trace`].
+/// Instructions missing from `frequencies` are considered to never execute.
+pub fn program_cost(insts: &[Inst], frequencies: Option<&BTreeMap<usize, u64>>) -> u64 {
+    insts
+        .iter()
+        .enumerate()
+        .map(|(pc, inst)| {
+            let frequency =
+                frequencies.map_or(1, |frequencies| frequencies.get(&pc).copied().unwrap_or(0));
+            inst.cost() * frequency
+        })
+        .sum()
+}
+
+/// The number of distinct [`Inst::opcode`]s.
+pub const N_OPCODES: usize = 0x4E;
+
+/// Execution statistics gathered by [`profiled_execute`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    /// The number of executed instructions indexed by their [`Inst::opcode`].
+    pub counts: [u64; N_OPCODES],
+    /// The number of executed instructions that did not continue with the next instruction.
+    pub branches: u64,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            counts: [0; N_OPCODES],
+            branches: 0,
+        }
+    }
+}
+
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+///
+/// Bounds checks the `pc` just like [`run`] which it delegates to.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Result<Bits, TrapCode> {
+    run(insts, context)
 }
 
-/// Executes the list of instruction using the given [`Context`].
-fn execute(insts: &[Inst], context: &mut Context) {
+/// Executes the list of instruction like [`execute`] and additionally returns a [`Profile`] of the execution.
+pub fn profiled_execute(insts: &[Inst], context: &mut Context) -> (Bits, Profile) {
+    let mut profile = Profile::default();
     loop {
         let pc = context.pc;
-        // let inst = &insts[pc];
-        let inst = unsafe { insts.get_unchecked(pc) };
+        let Ok(inst) = fetch(insts, pc) else {
+            return (context.get_reg_abs(0), profile);
+        };
+        profile.counts[usize::from(inst.opcode())] += 1;
         match inst.execute(context) {
-            Outcome::Continue => continue,
-            Outcome::Return => return,
+            Outcome::Continue | Outcome::Yield => {
+                if context.pc != pc + 1 {
+                    profile.branches += 1;
+                }
+            }
+            Outcome::Return | Outcome::Trap(_) => return (context.get_reg_abs(0), profile),
         }
     }
 }
 
-#[test]
-fn counter_loop() {
-    let repetitions = 100_000_000;
-    let insts = vec![
-        // Store `repetitions` into r0.
+/// Reads the time stamp counter of the CPU.
+///
+/// The counter is not guaranteed to be synchronized across cores.
+#[cfg(all(feature = "std", feature = "rdtsc", target_arch = "x86_64"))]
+fn read_counter(_start: Instant) -> u64 {
+    // Safety: every `x86_64` CPU supports `rdtsc`.
+    unsafe { core::arch::x86_64::_rdtsc() }
+}
+
+/// Reads the nanoseconds passed since `start`.
+#[cfg(all(feature = "std", not(all(feature = "rdtsc", target_arch = "x86_64"))))]
+fn read_counter(start: Instant) -> u64 {
+    start.elapsed().as_nanos() as u64
+}
+
+/// Executes the list of instruction like [`execute`] and additionally samples a monotonic
+/// counter after every `sample_every` dispatched instructions.
+///
+/// Returns the deltas of the counter between consecutive samples, the first one relative to
+/// the start of the execution. The counter measures nanoseconds by default and CPU cycles
+/// via `rdtsc` on `x86_64` with the `rdtsc` feature.
+///
+/// # Panics
+///
+/// If `sample_every` is zero.
+#[cfg(feature = "std")]
+pub fn sampled_execute(
+    insts: &[Inst],
+    context: &mut Context,
+    sample_every: u32,
+) -> (Bits, Vec<u64>) {
+    assert_ne!(
+        sample_every, 0,
+        "must sample after at least one instruction"
+    );
+    let mut samples = Vec::new();
+    let start = Instant::now();
+    let mut last = read_counter(start);
+    let mut until_sample = sample_every;
+    loop {
+        let pc = context.pc;
+        let Ok(inst) = fetch(insts, pc) else {
+            return (context.get_reg_abs(0), samples);
+        };
+        let outcome = inst.execute(context);
+        until_sample -= 1;
+        if until_sample == 0 {
+            let now = read_counter(start);
+            samples.push(now.saturating_sub(last));
+            last = now;
+            until_sample = sample_every;
+        }
+        match outcome {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return (context.get_reg_abs(0), samples),
+        }
+    }
+}
+
+/// Executes the list of instruction like [`execute`] and additionally returns the `pc` of every dispatched instruction.
+pub fn run_trace(insts: &[Inst], context: &mut Context) -> (Bits, Vec<usize>) {
+    let mut trace = Vec::new();
+    loop {
+        let pc = context.pc;
+        trace.push(pc);
+        let Ok(inst) = fetch(insts, pc) else {
+            return (context.get_reg_abs(0), trace);
+        };
+        match inst.execute(context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return (context.get_reg_abs(0), trace),
+        }
+    }
+}
+
+/// Runs the list of instruction like [`run`] but dispatches at most `fuel` instructions.
+///
+/// # Errors
+///
+/// If the execution traps, the `pc` leaves the bounds of `insts` or `fuel` is exhausted
+/// before the execution returns.
+pub fn execute_with_fuel(
+    insts: &[Inst],
+    context: &mut Context,
+    mut fuel: u64,
+) -> Result<Bits, TrapCode> {
+    loop {
+        fuel = fuel.checked_sub(1).ok_or(TrapCode::OutOfFuel)?;
+        let pc = context.pc;
+        let inst = insts.get(pc).ok_or(TrapCode::OutOfBoundsPc)?;
+        match inst.execute(context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return => return Ok(context.get_reg_abs(0)),
+            Outcome::Trap(trap) => return Err(trap),
+        }
+    }
+}
+
+/// Runs the list of instruction using the given [`Context`] and returns the result.
+///
+/// Executed [`Inst::Yield`] are resumed immediately.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn run(insts: &[Inst], context: &mut Context) -> Result<Bits, TrapCode> {
+    loop {
+        let pc = context.pc;
+        let inst = insts.get(pc).ok_or(TrapCode::OutOfBoundsPc)?;
+        match inst.execute(context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return => return Ok(context.get_reg_abs(0)),
+            Outcome::Trap(trap) => return Err(trap),
+        }
+    }
+}
+
+/// Runs the list of instruction on a fresh [`Context`] and returns the [`Context`]
+/// together with the outcome so that callers can inspect the final registers.
+///
+/// Unlike [`run`] the top-level [`Inst::Return`] is not executed so that it does not
+/// overwrite `r0` with the result. The result is found in the register it returns instead.
+pub fn run_to_completion(insts: &[Inst]) -> (Context, Result<(), TrapCode>) {
+    let mut context = Context::default();
+    let outcome = loop {
+        let Some(inst) = insts.get(context.pc) else {
+            break Err(TrapCode::OutOfBoundsPc);
+        };
+        if matches!(inst, Inst::Return { .. }) && context.frames.is_empty() {
+            break Ok(());
+        }
+        match inst.execute(&mut context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return => break Ok(()),
+            Outcome::Trap(trap) => break Err(trap),
+        }
+    };
+    (context, outcome)
+}
+
+/// The reason for [`resume`] returning control to its caller.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Status {
+    /// The program executed an [`Inst::Yield`] and continues after it when resumed.
+    Yielded,
+    /// The program returned with the result.
+    Returned(Bits),
+}
+
+/// Resumes the list of instruction at the `pc` of `context` until it yields or returns.
+///
+/// Starting with a fresh [`Context`] runs the program from its first instruction.
+/// After [`Status::Yielded`] the `pc` points to the instruction following the
+/// [`Inst::Yield`] so that the next call to [`resume`] continues there.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn resume(insts: &[Inst], context: &mut Context) -> Result<Status, TrapCode> {
+    loop {
+        let pc = context.pc;
+        let inst = insts.get(pc).ok_or(TrapCode::OutOfBoundsPc)?;
+        match inst.execute(context) {
+            Outcome::Continue => continue,
+            Outcome::Yield => return Ok(Status::Yielded),
+            Outcome::Return => return Ok(Status::Returned(context.get_reg_abs(0))),
+            Outcome::Trap(trap) => return Err(trap),
+        }
+    }
+}
+
+/// Runs every program of `programs` on the context at the same index of `contexts`.
+///
+/// Each program is [`resume`]d until it yields or returns before the next unfinished
+/// program takes its turn, cycling until all programs returned or trapped.
+/// Returns the result of every program in the order of `programs`.
+///
+/// # Panics
+///
+/// If `programs` and `contexts` differ in length.
+pub fn run_round_robin(
+    programs: &[&[Inst]],
+    contexts: &mut [Context],
+) -> Vec<Result<Bits, TrapCode>> {
+    assert_eq!(
+        programs.len(),
+        contexts.len(),
+        "every program requires its own context"
+    );
+    let mut results = vec![None; programs.len()];
+    let mut unfinished = programs.len();
+    while unfinished != 0 {
+        for (index, (insts, context)) in programs.iter().zip(contexts.iter_mut()).enumerate() {
+            if results[index].is_some() {
+                continue;
+            }
+            let result = match resume(insts, context) {
+                Ok(Status::Yielded) => continue,
+                Ok(Status::Returned(result)) => Ok(result),
+                Err(trap) => Err(trap),
+            };
+            results[index] = Some(result);
+            unfinished -= 1;
+        }
+    }
+    results.into_iter().flatten().collect()
+}
+
+/// Saves the program `insts` as JSON into `writer`.
+///
+/// # Errors
+///
+/// If serializing or writing the program fails.
+#[cfg(feature = "serde")]
+pub fn save_program<W: std::io::Write>(insts: &[Inst], writer: W) -> serde_json::Result<()> {
+    serde_json::to_writer(writer, insts)
+}
+
+/// Loads a program saved by [`save_program`] as JSON from `reader`.
+///
+/// # Errors
+///
+/// If reading fails or the JSON does not describe a program.
+#[cfg(feature = "serde")]
+pub fn load_program<R: std::io::Read>(reader: R) -> serde_json::Result<Vec<Inst>> {
+    serde_json::from_reader(reader)
+}
+
+/// Appends [`Inst::Nop`] to `insts` until it holds `len` instructions.
+///
+/// Since the padding is appended at the end all branch targets of `insts` stay intact.
+/// Does nothing if `insts` already holds at least `len` instructions.
+pub fn pad_to(insts: &mut Vec<Inst>, len: usize) {
+    if insts.len() < len {
+        insts.resize(len, Inst::Nop);
+    }
+}
+
+/// Returns a straight-line program of `n` copies of `inst` followed by a `return` of `r0`.
+///
+/// Useful to isolate the dispatch and execution cost of a single opcode.
+pub fn repeat_inst(inst: Inst, n: usize) -> Vec<Inst> {
+    let mut insts = vec![inst; n];
+    insts.push(Inst::Return {
+        result: Register(0),
+    });
+    insts
+}
+
+/// Returns the `counter_loop` benchmark program counting `repetitions` down to zero.
+pub(crate) fn counter_loop_insts(repetitions: Bits) -> Vec<Inst> {
+    vec![
+        // Store `repetitions` into r0.
         // Note: r0 is our loop counter register.
         Inst::AddImm {
-            result: 0,
-            src: 0,
+            result: Register(0),
+            src: Register(0),
             imm: repetitions,
         },
         // Branch to the end if r0 is zero.
         Inst::BranchEqz {
-            target: 4,
-            condition: 0,
+            target: Target(4),
+            condition: Register(0),
         },
         // Decrease r0 by 1.
         Inst::SubImm {
-            result: 0,
-            src: 0,
+            result: Register(0),
+            src: Register(0),
             imm: 1,
         },
         // Jump back to the loop header.
-        Inst::Branch { target: 1 },
+        Inst::Branch { target: Target(1) },
         // Return value and end function execution.
-        Inst::Return { result: 0 },
+        Inst::Return {
+            result: Register(0),
+        },
+    ]
+}
+
+#[test]
+fn counter_loop() {
+    let insts = counter_loop_insts(100_000_000);
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, Ok(0));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn save_load_program() {
+    let insts = counter_loop_insts(1_000);
+    let mut json = Vec::new();
+    save_program(&insts, &mut json).unwrap();
+    assert_eq!(load_program(&json[..]).unwrap(), insts);
+    assert!(load_program(&b"[{\"Jump\":{}}]"[..]).is_err());
+}
+
+#[test]
+fn handler_table() {
+    let insts = counter_loop_insts(1_000);
+    let mut context = Context::default();
+    let result = loop {
+        let inst = &insts[context.pc];
+        let args = inst.args().unwrap();
+        match crate::HANDLERS[usize::from(inst.opcode())](&mut context, args) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => break context.get_reg_abs(0),
+        }
+    };
+    assert_eq!(Ok(result), execute(&insts, &mut Context::default()));
+    assert_eq!(result, 0);
+}
+
+#[test]
+#[cfg(feature = "safe_dispatch")]
+fn out_of_bounds_branch_traps() {
+    let insts = [
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        Inst::Branch {
+            target: Target(100),
+        },
     ];
+    assert_eq!(
+        execute(&insts, &mut Context::default()),
+        Err(TrapCode::OutOfBoundsPc)
+    );
+    assert_eq!(
+        run_trace(&insts, &mut Context::default()),
+        (1, vec![0, 1, 100])
+    );
+    let insts = crate::threaded::lower(&insts).unwrap();
+    assert_eq!(crate::threaded::execute(&insts, &mut Context::default()), 1);
+}
+
+#[test]
+fn operands() {
+    let add = Inst::Add {
+        result: Register(1),
+        lhs: Register(2),
+        rhs: Register(3),
+    };
+    let (reads, writes) = add.operands();
+    assert_eq!(reads[..], [Register(2), Register(3)]);
+    assert_eq!(writes[..], [Register(1)]);
+    let add_imm = Inst::AddImm {
+        result: Register(1),
+        src: Register(2),
+        imm: 3,
+    };
+    let (reads, writes) = add_imm.operands();
+    assert_eq!(reads[..], [Register(2)]);
+    assert_eq!(writes[..], [Register(1)]);
+    let (reads, writes) = Inst::Branch { target: Target(7) }.operands();
+    assert!(reads.is_empty());
+    assert!(writes.is_empty());
+    let call = Inst::Call {
+        target: Target(7),
+        num_args: 2,
+    };
+    let (reads, writes) = call.operands();
+    assert_eq!(reads[..], [Register(0), Register(1)]);
+    assert_eq!(writes[..], [Register(0)]);
+}
+
+#[test]
+fn cost_model() {
+    let add = Inst::Add {
+        result: Register(0),
+        lhs: Register(1),
+        rhs: Register(2),
+    };
+    let mul = Inst::Mul {
+        result: Register(0),
+        lhs: Register(1),
+        rhs: Register(2),
+    };
+    assert!(mul.cost() > add.cost());
+    let insts = counter_loop_insts(10);
+    let total = insts.iter().map(Inst::cost).sum::<u64>();
+    assert_eq!(program_cost(&insts, None), total);
+    let (_, trace) = run_trace(&insts, &mut Context::default());
+    let mut frequencies = BTreeMap::new();
+    for pc in trace {
+        *frequencies.entry(pc).or_insert(0) += 1;
+    }
+    assert_eq!(frequencies[&2], 10);
+    let weighted = insts
+        .iter()
+        .enumerate()
+        .map(|(pc, inst)| inst.cost() * frequencies[&pc])
+        .sum::<u64>();
+    assert_eq!(program_cost(&insts, Some(&frequencies)), weighted);
+}
+
+#[test]
+fn out_of_bounds_branch_returns_trap() {
+    let insts = [
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        Inst::Branch {
+            target: Target(999),
+        },
+    ];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::OutOfBoundsPc)
+    );
+    assert_eq!(
+        execute(&insts, &mut Context::default()),
+        Err(TrapCode::OutOfBoundsPc)
+    );
+    let closure_loop = crate::closure_loop::lower(&insts).unwrap();
+    assert_eq!(
+        crate::closure_loop::run(&closure_loop, &mut Context::default()),
+        Err(TrapCode::OutOfBoundsPc)
+    );
+    assert_eq!(
+        crate::closure_loop::execute(&closure_loop, &mut Context::default()),
+        Err(TrapCode::OutOfBoundsPc)
+    );
+    let fused = crate::fused::rt::lower(&insts).unwrap();
+    assert_eq!(
+        crate::fused::rt::run(&fused, &mut crate::fused::Context::default()),
+        Err(TrapCode::OutOfBoundsPc)
+    );
+    assert_eq!(
+        crate::fused::rt::execute(&fused, &mut crate::fused::Context::default()),
+        Err(TrapCode::OutOfBoundsPc)
+    );
+}
+
+#[test]
+fn profiled_counter_loop() {
+    let repetitions = 1_000;
+    let insts = counter_loop_insts(repetitions);
     let mut context = Context::default();
-    benchmark(|| execute(&insts, &mut context));
+    let (result, profile) = profiled_execute(&insts, &mut context);
+    assert_eq!(result, 0);
+    let counts = profile.counts;
+    assert_eq!(counts[usize::from(opcode::SUB_IMM)], repetitions);
+    assert_eq!(counts[usize::from(opcode::BRANCH_EQZ)], repetitions + 1);
+    assert_eq!(counts[usize::from(opcode::RETURN)], 1);
+    assert_eq!(counts.iter().sum::<u64>(), 3 * repetitions + 3);
+    // Every `branch` plus the final taken `branch_eqz`.
+    assert_eq!(profile.branches, repetitions + 1);
 }
 
 #[test]
-fn more_comps() {
+fn run_to_completion_registers() {
+    let insts = more_comps_insts(10);
+    let expected = run(&insts, &mut Context::default()).unwrap();
+    let (context, outcome) = run_to_completion(&insts);
+    assert_eq!(outcome, Ok(()));
+    // The accumulator in `r1` holds the result and the counter in `r0` counted down to zero.
+    assert_eq!(context.get_reg(Register(1)), expected);
+    assert_eq!(context.get_reg(Register(0)), 0);
+    let (context, outcome) = run_to_completion(&random_branch_insts(10));
+    assert_eq!(outcome, Ok(()));
+    assert_eq!(context.get_reg(Register(1)), 0);
+    assert!(context.get_reg(Register(0)) <= 10);
+    let (_, outcome) = run_to_completion(&[Inst::Unreachable]);
+    assert_eq!(outcome, Err(TrapCode::UnreachableExecuted));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn sampled_counter_loop() {
+    let repetitions = 1_000;
+    let insts = counter_loop_insts(repetitions);
+    let (result, samples) = sampled_execute(&insts, &mut Context::default(), 10);
+    assert_eq!(result, 0);
+    let total = 3 * repetitions + 3;
+    assert_eq!(samples.len() as u64, total / 10);
+    let (_, samples) = sampled_execute(&insts, &mut Context::default(), 1);
+    assert_eq!(samples.len() as u64, total);
+}
+
+#[test]
+fn counter_loop_nez() {
     let repetitions = 100_000_000;
     let insts = vec![
         // Store `repetitions` into r0.
         // Note: r0 is our loop counter register.
         Inst::AddImm {
-            result: 0,
-            src: 0,
+            result: Register(0),
+            src: Register(0),
+            imm: repetitions,
+        },
+        // Decrease r0 by 1.
+        Inst::SubImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        // Jump back to the loop header if r0 is not zero.
+        Inst::BranchNez {
+            target: Target(1),
+            condition: Register(0),
+        },
+        // Return value and end function execution.
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, Ok(0));
+}
+
+/// Returns the `more_comps` benchmark program computing a factorial-like product of `repetitions` in `r1`.
+pub(crate) fn more_comps_insts(repetitions: Bits) -> Vec<Inst> {
+    vec![
+        // Store `repetitions` into r0.
+        // Note: r0 is our loop counter register.
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
             imm: repetitions,
         },
         // Store `1` into r1.
         // Note: r1 is our accumulator register.
         Inst::AddImm {
-            result: 1,
-            src: 1,
+            result: Register(1),
+            src: Register(1),
             imm: 1,
         },
         // Branch to the end if r0 is zero.
         Inst::BranchEqz {
-            target: 7,
-            condition: 0,
+            target: Target(7),
+            condition: Register(0),
         },
         // Multiply r1 with r0.
         Inst::Mul {
-            result: 1,
-            lhs: 1,
-            rhs: 0,
+            result: Register(1),
+            lhs: Register(1),
+            rhs: Register(0),
         },
         // Subtract r0 from r1.
         Inst::Sub {
-            result: 1,
-            lhs: 1,
-            rhs: 0,
+            result: Register(1),
+            lhs: Register(1),
+            rhs: Register(0),
         },
         // Decrease r0 by 1.
         Inst::SubImm {
-            result: 0,
-            src: 0,
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        // Jump back to the loop header.
+        Inst::Branch { target: Target(2) },
+        // Return value and end function execution.
+        Inst::Return {
+            result: Register(1),
+        },
+    ]
+}
+
+/// Returns the `random_branch` benchmark program counting the odd values among `repetitions`
+/// pseudo-random values in `r0`.
+///
+/// Unlike the loop exit of `counter_loop` the parity branch cannot be predicted.
+pub(crate) fn random_branch_insts(repetitions: Bits) -> Vec<Inst> {
+    vec![
+        // Store `repetitions` into r1.
+        // Note: r1 is our loop counter register.
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: repetitions,
+        },
+        // Branch to the end if r1 is zero.
+        Inst::BranchEqz {
+            target: Target(8),
+            condition: Register(1),
+        },
+        // Store the next pseudo-random value into r2.
+        Inst::RandNext {
+            result: Register(2),
+        },
+        // Store the parity of r2 into r2.
+        Inst::RemImm {
+            result: Register(2),
+            src: Register(2),
+            imm: 2,
+        },
+        // Skip the increment if r2 is even.
+        Inst::BranchEqz {
+            target: Target(6),
+            condition: Register(2),
+        },
+        // Increase r0 by 1.
+        // Note: r0 is our accumulator register.
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        // Decrease r1 by 1.
+        Inst::SubImm {
+            result: Register(1),
+            src: Register(1),
             imm: 1,
         },
         // Jump back to the loop header.
-        Inst::Branch { target: 2 },
+        Inst::Branch { target: Target(1) },
+        // Return value and end function execution.
+        Inst::Return {
+            result: Register(0),
+        },
+    ]
+}
+
+#[test]
+fn more_comps() {
+    let insts = more_comps_insts(100_000_000);
+    let mut context = Context::default();
+    benchmark(|| execute(&insts, &mut context).unwrap());
+}
+
+#[test]
+fn random_branch() {
+    let repetitions = 10_000;
+    let insts = random_branch_insts(repetitions);
+    let mut reference = Context::default();
+    let odd = (0..repetitions)
+        .filter(|_| reference.next_random() % 2 == 1)
+        .count();
+    assert_eq!(run(&insts, &mut Context::default()), Ok(odd as Bits));
+    // The parity is close to a coin flip which defeats the branch predictor.
+    assert!((4_000..6_000).contains(&odd));
+}
+
+#[test]
+fn trace_counter_loop() {
+    let repetitions = 3;
+    let insts = counter_loop_insts(repetitions);
+    let (result, trace) = run_trace(&insts, &mut Context::default());
+    assert_eq!(result, 0);
+    assert_eq!(trace, [0, 1, 2, 3, 1, 2, 3, 1, 2, 3, 1, 4]);
+    assert_eq!(trace.len(), 3 * repetitions as usize + 3);
+}
+
+#[test]
+fn div_rem() {
+    let insts = vec![
+        // Store `17` into r0 and `5` into r1.
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 17,
+        },
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 5,
+        },
+        // Divide and take the remainder of r0 by r1.
+        Inst::Div {
+            result: Register(2),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::Rem {
+            result: Register(3),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        // Divide and take the remainder of r0 by `4`.
+        Inst::DivImm {
+            result: Register(4),
+            src: Register(0),
+            imm: 4,
+        },
+        Inst::RemImm {
+            result: Register(5),
+            src: Register(0),
+            imm: 4,
+        },
         // Return value and end function execution.
-        Inst::Return { result: 1 },
+        Inst::Return {
+            result: Register(2),
+        },
     ];
     let mut context = Context::default();
-    benchmark(|| execute(&insts, &mut context));
+    execute(&insts, &mut context).unwrap();
+    assert_eq!(context.get_reg(Register(0)), 17 / 5);
+    assert_eq!(context.get_reg(Register(3)), 17 % 5);
+    assert_eq!(context.get_reg(Register(4)), 17 / 4);
+    assert_eq!(context.get_reg(Register(5)), 17 % 4);
+}
+
+#[test]
+fn div_rem_by_zero() {
+    let mut context = Context::default();
+    context.set_reg(Register(0), 17);
+    for inst in [
+        Inst::Div {
+            result: Register(2),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::DivImm {
+            result: Register(2),
+            src: Register(0),
+            imm: 0,
+        },
+        Inst::Rem {
+            result: Register(2),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::RemImm {
+            result: Register(2),
+            src: Register(0),
+            imm: 0,
+        },
+    ] {
+        assert!(matches!(
+            inst.execute(&mut context),
+            Outcome::Trap(TrapCode::DivisionByZero)
+        ));
+    }
+}
+
+#[test]
+fn run_traps() {
+    let insts = [
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 42,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_eq!(run(&insts, &mut Context::default()), Ok(42));
+    let insts = [
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 42,
+        },
+        Inst::DivImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 0,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::DivisionByZero)
+    );
+    let insts = [
+        Inst::Branch { target: Target(2) },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::OutOfBoundsPc)
+    );
+}
+
+#[cfg(test)]
+pub(crate) const SHIFT_INPUTS: [(Bits, Bits); 8] = [
+    (0x8000_0000_0000_0001, 0),
+    (0x8000_0000_0000_0001, 1),
+    (0x8000_0000_0000_0001, 63),
+    (0x8000_0000_0000_0001, 64),
+    (0x8000_0000_0000_0001, 65),
+    (0xF0F0_F0F0_0F0F_0F0F, 127),
+    (0xF0F0_F0F0_0F0F_0F0F, 200),
+    (0xF0F0_F0F0_0F0F_0F0F, Bits::MAX),
+];
+
+#[test]
+fn shifts() {
+    for (value, amount) in SHIFT_INPUTS {
+        let insts = [
+            Inst::Shl {
+                result: Register(2),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+            Inst::ShrUImm {
+                result: Register(3),
+                src: Register(0),
+                imm: amount,
+            },
+            Inst::ShrS {
+                result: Register(4),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+            Inst::RotlImm {
+                result: Register(5),
+                src: Register(0),
+                imm: amount,
+            },
+            Inst::Rotr {
+                result: Register(6),
+                lhs: Register(0),
+                rhs: Register(1),
+            },
+            Inst::Return {
+                result: Register(2),
+            },
+        ];
+        let mut context = Context::default();
+        context.set_reg(Register(0), value);
+        context.set_reg(Register(1), amount);
+        assert_eq!(
+            run(&insts, &mut context),
+            Ok(value.wrapping_shl(amount as u32))
+        );
+        assert_eq!(
+            context.get_reg(Register(3)),
+            value.wrapping_shr(amount as u32)
+        );
+        assert_eq!(
+            context.get_reg(Register(4)),
+            (value as i64).wrapping_shr(amount as u32) as Bits
+        );
+        assert_eq!(
+            context.get_reg(Register(5)),
+            value.rotate_left(amount as u32)
+        );
+        assert_eq!(
+            context.get_reg(Register(6)),
+            value.rotate_right(amount as u32)
+        );
+    }
+}
+
+#[test]
+fn comparisons() {
+    let minus_one = -1_i64 as Bits;
+    let insts = vec![
+        // Store `-1` into r0 and `1` into r1.
+        Inst::SubImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 1,
+        },
+        // Compare r0 with r1.
+        Inst::Eq {
+            result: Register(2),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::Ne {
+            result: Register(3),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::LtU {
+            result: Register(4),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::LtS {
+            result: Register(5),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        // Compare r0 with constants.
+        Inst::EqImm {
+            result: Register(6),
+            src: Register(0),
+            imm: minus_one,
+        },
+        Inst::NeImm {
+            result: Register(7),
+            src: Register(0),
+            imm: minus_one,
+        },
+        Inst::LtUImm {
+            result: Register(8),
+            src: Register(1),
+            imm: minus_one,
+        },
+        Inst::LtSImm {
+            result: Register(9),
+            src: Register(1),
+            imm: minus_one,
+        },
+        // Chain the comparison results: r10 = r4 + r5 == 1
+        Inst::Add {
+            result: Register(10),
+            lhs: Register(4),
+            rhs: Register(5),
+        },
+        Inst::EqImm {
+            result: Register(10),
+            src: Register(10),
+            imm: 1,
+        },
+        // Return value and end function execution.
+        Inst::Return {
+            result: Register(10),
+        },
+    ];
+    let mut context = Context::default();
+    assert_eq!(run(&insts, &mut context), Ok(1));
+    assert_eq!(context.get_reg(Register(2)), 0);
+    assert_eq!(context.get_reg(Register(3)), 1);
+    assert_eq!(context.get_reg(Register(4)), 0);
+    assert_eq!(context.get_reg(Register(5)), 1);
+    assert_eq!(context.get_reg(Register(6)), 1);
+    assert_eq!(context.get_reg(Register(7)), 0);
+    assert_eq!(context.get_reg(Register(8)), 1);
+    assert_eq!(context.get_reg(Register(9)), 0);
+}
+
+#[test]
+fn select_max() {
+    let insts = [
+        // r2 = (r0 - r1) >> 63 is not zero if r0 < r1.
+        Inst::Sub {
+            result: Register(2),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::ShrUImm {
+            result: Register(2),
+            src: Register(2),
+            imm: 63,
+        },
+        Inst::Select {
+            result: Register(0),
+            cond: Register(2),
+            if_nonzero: Register(1),
+            if_zero: Register(0),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    for (a, b) in [(0, 0), (3, 7), (7, 3), (42, 42), (1 << 40, 5)] {
+        let mut context = Context::default();
+        context.set_reg(Register(0), a);
+        context.set_reg(Register(1), b);
+        assert_eq!(run(&insts, &mut context), Ok(a.max(b)));
+    }
+}
+
+#[test]
+fn fuel() {
+    let insts = [Inst::Branch { target: Target(0) }];
+    assert_eq!(
+        execute_with_fuel(&insts, &mut Context::default(), 1_000),
+        Err(TrapCode::OutOfFuel)
+    );
+    // The `counter_loop` dispatches 3 instructions per iteration plus 3 more.
+    let insts = counter_loop_insts(100);
+    assert_eq!(
+        execute_with_fuel(&insts, &mut Context::default(), 303),
+        Ok(0)
+    );
+    assert_eq!(
+        execute_with_fuel(&insts, &mut Context::default(), 302),
+        Err(TrapCode::OutOfFuel)
+    );
+}
+
+#[test]
+fn move_register() {
+    let insts = [
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 42,
+        },
+        Inst::Move {
+            result: Register(0),
+            src: Register(1),
+        },
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 1,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let mut context = Context::default();
+    assert_eq!(run(&insts, &mut context), Ok(42));
+    assert_eq!(context.get_reg(Register(1)), 43);
+}
+
+#[test]
+fn arithmetic_32() {
+    let insts = [
+        Inst::Add32 {
+            result: Register(2),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::Sub32 {
+            result: Register(3),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::Mul32 {
+            result: Register(4),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::LtS32 {
+            result: Register(5),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::Shl32 {
+            result: Register(6),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::ShrU32 {
+            result: Register(7),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::ShrS32 {
+            result: Register(8),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::Return {
+            result: Register(2),
+        },
+    ];
+    // Overflow wraps at `u32::MAX`.
+    let mut context = Context::default();
+    context.set_reg(Register(0), 0xFFFF_FFFF);
+    context.set_reg(Register(1), 1);
+    assert_eq!(run(&insts, &mut context), Ok(0));
+    assert_eq!(context.get_reg(Register(3)), 0xFFFF_FFFE);
+    assert_eq!(context.get_reg(Register(4)), 0xFFFF_FFFF);
+    assert_eq!(context.get_reg(Register(5)), 1);
+    assert_eq!(context.get_reg(Register(6)), 0xFFFF_FFFE);
+    assert_eq!(context.get_reg(Register(7)), 0x7FFF_FFFF);
+    assert_eq!(context.get_reg(Register(8)), 0xFFFF_FFFF);
+    // High bits of the inputs are ignored and the high bits of the results are zero.
+    let mut context = Context::default();
+    context.set_reg(Register(0), 0xDEAD_BEEF_0000_0002);
+    context.set_reg(Register(1), 0xFFFF_FFFF_0000_0003);
+    assert_eq!(run(&insts, &mut context), Ok(5));
+    assert_eq!(context.get_reg(Register(3)), 0xFFFF_FFFF);
+    assert_eq!(context.get_reg(Register(4)), 6);
+    assert_eq!(context.get_reg(Register(5)), 1);
+    assert_eq!(context.get_reg(Register(6)), 16);
+    assert_eq!(context.get_reg(Register(7)), 0);
+    assert_eq!(context.get_reg(Register(8)), 0);
+}
+
+#[test]
+fn checked_arithmetic() {
+    let add = [
+        Inst::AddChecked {
+            result: Register(0),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let sub = [
+        Inst::SubChecked {
+            result: Register(0),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let mul = [
+        Inst::MulChecked {
+            result: Register(0),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let cases = [
+        (&add, Bits::MAX, 1, Err(TrapCode::IntegerOverflow)),
+        (&add, Bits::MAX - 1, 1, Ok(Bits::MAX)),
+        (&sub, Bits::MAX, 1, Ok(Bits::MAX - 1)),
+        (&sub, 0, 1, Err(TrapCode::IntegerOverflow)),
+        (&mul, 1 << 32, 1 << 31, Ok(1 << 63)),
+        (&mul, 1 << 32, 1 << 32, Err(TrapCode::IntegerOverflow)),
+    ];
+    for (insts, lhs, rhs, expected) in cases {
+        let mut context = Context::default();
+        context.set_reg(Register(0), lhs);
+        context.set_reg(Register(1), rhs);
+        assert_eq!(run(insts, &mut context), expected);
+    }
+}
+
+#[test]
+fn mul_wide() {
+    let insts = [
+        Inst::MulWide {
+            hi: Register(2),
+            lo: Register(3),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::Return {
+            result: Register(2),
+        },
+    ];
+    let cases = [
+        (Bits::MAX, Bits::MAX),
+        (Bits::MAX - 1, Bits::MAX - 2),
+        (Bits::MAX, 2),
+        (1 << 32, 1 << 32),
+        (3, 5),
+    ];
+    for (lhs, rhs) in cases {
+        let expected = u128::from(lhs) * u128::from(rhs);
+        let mut context = Context::default();
+        context.set_reg(Register(0), lhs);
+        context.set_reg(Register(1), rhs);
+        assert_eq!(run(&insts, &mut context), Ok((expected >> 64) as Bits));
+        assert_eq!(context.get_reg(Register(2)), (expected >> 64) as Bits);
+        assert_eq!(context.get_reg(Register(3)), expected as Bits);
+    }
+}
+
+#[test]
+fn bit_counts() {
+    let popcnt = [
+        Inst::Popcnt {
+            result: Register(0),
+            src: Register(0),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let clz = [
+        Inst::Clz {
+            result: Register(0),
+            src: Register(0),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let ctz = [
+        Inst::Ctz {
+            result: Register(0),
+            src: Register(0),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let cases = [
+        (&popcnt, 0, 0),
+        (&popcnt, Bits::MAX, 64),
+        (&popcnt, 0b1011_0000, 3),
+        (&clz, 0, 64),
+        (&clz, 1, 63),
+        (&clz, 1 << 63, 0),
+        (&ctz, 0, 64),
+        (&ctz, 1, 0),
+        (&ctz, 0b1011_0000, 4),
+    ];
+    for (insts, input, expected) in cases {
+        let mut context = Context::default();
+        context.set_reg(Register(0), input);
+        assert_eq!(run(insts, &mut context), Ok(expected));
+    }
+}
+
+#[test]
+fn min_max() {
+    let minus_one = Bits::MAX;
+    let insts = [
+        Inst::MinS {
+            result: Register(2),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::MinU {
+            result: Register(3),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::MaxS {
+            result: Register(4),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::MaxU {
+            result: Register(5),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::MinSImm {
+            result: Register(6),
+            src: Register(1),
+            imm: minus_one,
+        },
+        Inst::MaxUImm {
+            result: Register(7),
+            src: Register(1),
+            imm: minus_one,
+        },
+        Inst::Return {
+            result: Register(2),
+        },
+    ];
+    let mut context = Context::default();
+    context.set_reg(Register(0), minus_one);
+    context.set_reg(Register(1), 5);
+    assert_eq!(run(&insts, &mut context), Ok(minus_one));
+    assert_eq!(context.get_reg(Register(3)), 5);
+    assert_eq!(context.get_reg(Register(4)), 5);
+    assert_eq!(context.get_reg(Register(5)), minus_one);
+    assert_eq!(context.get_reg(Register(6)), minus_one);
+    assert_eq!(context.get_reg(Register(7)), minus_one);
+}
+
+#[test]
+fn rand_next() {
+    let insts = [
+        Inst::RandNext {
+            result: Register(0),
+        },
+        Inst::RandNext {
+            result: Register(1),
+        },
+        Inst::RandNext {
+            result: Register(2),
+        },
+        Inst::Return {
+            result: Register(2),
+        },
+    ];
+    let sequence = |context: &mut Context| {
+        run(&insts, context).unwrap();
+        [
+            context.get_reg(Register(0)),
+            context.get_reg(Register(1)),
+            context.get_reg(Register(2)),
+        ]
+    };
+    let mut context = Context::with_seed(42);
+    let first = sequence(&mut context);
+    assert_ne!(first[0], first[1]);
+    assert_eq!(sequence(&mut Context::with_seed(42)), first);
+    assert_ne!(sequence(&mut Context::with_seed(43)), first);
+    // Resetting reseeds the generator so that the run is replayed.
+    context.reset();
+    assert_eq!(sequence(&mut context), first);
+    assert_eq!(
+        sequence(&mut Context::with_seed(0)),
+        sequence(&mut Context::default())
+    );
+}
+
+#[test]
+fn yield_resume() {
+    let insts = [
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 3,
+        },
+        Inst::Yield,
+        Inst::MulImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 5,
+        },
+        Inst::Yield,
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 1,
+        },
+        Inst::Return {
+            result: Register(1),
+        },
+    ];
+    let mut context = Context::default();
+    assert_eq!(resume(&insts, &mut context), Ok(Status::Yielded));
+    assert_eq!(context.pc, 2);
+    assert_eq!(context.get_reg(Register(1)), 3);
+    assert_eq!(resume(&insts, &mut context), Ok(Status::Yielded));
+    assert_eq!(context.get_reg(Register(1)), 15);
+    assert_eq!(resume(&insts, &mut context), Ok(Status::Returned(16)));
+    // Drivers without a scheduler resume yields immediately.
+    assert_eq!(run(&insts, &mut Context::default()), Ok(16));
+    assert_eq!(execute(&insts, &mut Context::default()), Ok(16));
+}
+
+#[test]
+fn round_robin() {
+    /// Returns a loop adding `step` to r1 for `repetitions` iterations yielding after each.
+    fn yielding_loop(repetitions: Bits, step: Bits) -> Vec<Inst> {
+        vec![
+            Inst::AddImm {
+                result: Register(0),
+                src: Register(0),
+                imm: repetitions,
+            },
+            Inst::BranchEqz {
+                target: Target(6),
+                condition: Register(0),
+            },
+            Inst::SubImm {
+                result: Register(0),
+                src: Register(0),
+                imm: 1,
+            },
+            Inst::AddImm {
+                result: Register(1),
+                src: Register(1),
+                imm: step,
+            },
+            Inst::Yield,
+            Inst::Branch { target: Target(1) },
+            Inst::Return {
+                result: Register(1),
+            },
+        ]
+    }
+    let short = yielding_loop(3, 10);
+    let long = yielding_loop(100, 1);
+    let mut contexts = [Context::default(), Context::default()];
+    assert_eq!(
+        run_round_robin(&[&short, &long], &mut contexts),
+        [Ok(30), Ok(100)]
+    );
+
+    // A clone taken at a yield continues independently of the original.
+    let mut context = Context::default();
+    assert_eq!(resume(&long, &mut context), Ok(Status::Yielded));
+    let mut contexts = [context.clone(), context];
+    contexts[1].set_reg(Register(1), 1_000);
+    assert_eq!(
+        run_round_robin(&[&long, &long], &mut contexts),
+        [Ok(100), Ok(1_099)]
+    );
+    let unreachable: &[Inst] = &[Inst::Yield, Inst::Unreachable];
+    assert_eq!(
+        run_round_robin(
+            &[unreachable, &short],
+            &mut [Context::default(), Context::default()]
+        ),
+        [Err(TrapCode::UnreachableExecuted), Ok(30)]
+    );
+}
+
+#[test]
+fn nop() {
+    let insts = [
+        Inst::Nop,
+        Inst::Nop,
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let mut context = Context::default();
+    context.set_reg(Register(0), 11);
+    context.set_reg(Register(1), 22);
+    let expected = context.snapshot();
+    assert_eq!(run(&insts, &mut context), Ok(11));
+    assert_eq!(context.snapshot().regs, expected.regs);
+}
+
+#[test]
+fn pad_counter_loop() {
+    let insts = counter_loop_insts(1_000);
+    let mut padded = insts.clone();
+    pad_to(&mut padded, 64);
+    assert_eq!(padded.len(), 64);
+    assert_eq!(padded[..insts.len()], insts[..]);
+    assert_eq!(
+        run(&padded, &mut Context::default()),
+        run(&insts, &mut Context::default()),
+    );
+    pad_to(&mut padded, 8);
+    assert_eq!(padded.len(), 64);
+}
+
+#[test]
+fn branch_table() {
+    let set_result = |value| Inst::AddImm {
+        result: Register(0),
+        src: Register(1),
+        imm: value,
+    };
+    let insts = [
+        Inst::BranchTable {
+            index: Register(0),
+            targets: Box::new([Target(1), Target(3), Target(5), Target(7)]),
+            default: Target(9),
+        },
+        set_result(10),
+        Inst::Return {
+            result: Register(0),
+        },
+        set_result(20),
+        Inst::Return {
+            result: Register(0),
+        },
+        set_result(30),
+        Inst::Return {
+            result: Register(0),
+        },
+        set_result(40),
+        Inst::Return {
+            result: Register(0),
+        },
+        set_result(99),
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let cases = [(0, 10), (1, 20), (2, 30), (3, 40), (4, 99), (Bits::MAX, 99)];
+    for (index, expected) in cases {
+        let mut context = Context::default();
+        context.set_reg(Register(0), index);
+        assert_eq!(run(&insts, &mut context), Ok(expected));
+    }
+}
+
+#[test]
+fn unreachable() {
+    let insts = [
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 42,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+        Inst::Unreachable,
+    ];
+    assert_eq!(run(&insts, &mut Context::default()), Ok(42));
+    let insts = [
+        Inst::Branch { target: Target(2) },
+        Inst::Return {
+            result: Register(0),
+        },
+        Inst::Unreachable,
+    ];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::UnreachableExecuted)
+    );
+}
+
+#[test]
+fn memory() {
+    let insts = [
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 0x1122_3344_5566_7788,
+        },
+        Inst::AddImm {
+            result: Register(2),
+            src: Register(2),
+            imm: 100,
+        },
+        Inst::Store64 {
+            base: Register(2),
+            offset: 8,
+            value: Register(1),
+        },
+        Inst::Load8 {
+            result: Register(3),
+            base: Register(2),
+            offset: 8,
+        },
+        Inst::Load16 {
+            result: Register(4),
+            base: Register(2),
+            offset: 8,
+        },
+        Inst::Load32 {
+            result: Register(5),
+            base: Register(2),
+            offset: 8,
+        },
+        Inst::Load64 {
+            result: Register(6),
+            base: Register(2),
+            offset: 8,
+        },
+        Inst::Load8 {
+            result: Register(7),
+            base: Register(2),
+            offset: 15,
+        },
+        Inst::Store16 {
+            base: Register(2),
+            offset: 8,
+            value: Register(2),
+        },
+        Inst::Load64 {
+            result: Register(0),
+            base: Register(2),
+            offset: 8,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let mut context = Context::default();
+    assert_eq!(run(&insts, &mut context), Ok(0x1122_3344_5566_0064));
+    assert_eq!(context.get_reg(Register(3)), 0x88);
+    assert_eq!(context.get_reg(Register(4)), 0x7788);
+    assert_eq!(context.get_reg(Register(5)), 0x5566_7788);
+    assert_eq!(context.get_reg(Register(6)), 0x1122_3344_5566_7788);
+    assert_eq!(context.get_reg(Register(7)), 0x11);
+}
+
+#[test]
+fn memory_out_of_bounds() {
+    let out_of_bounds = [
+        (crate::MEMORY_SIZE as Bits - 4, 0),
+        (crate::MEMORY_SIZE as Bits, 0),
+        (1, Bits::MAX),
+    ];
+    for (address, offset) in out_of_bounds {
+        let load = [
+            Inst::AddImm {
+                result: Register(1),
+                src: Register(1),
+                imm: address,
+            },
+            Inst::Load64 {
+                result: Register(0),
+                base: Register(1),
+                offset,
+            },
+            Inst::Return {
+                result: Register(0),
+            },
+        ];
+        assert_eq!(
+            run(&load, &mut Context::default()),
+            Err(TrapCode::OutOfBoundsMemory)
+        );
+        let store = [
+            Inst::AddImm {
+                result: Register(1),
+                src: Register(1),
+                imm: address,
+            },
+            Inst::Store64 {
+                base: Register(1),
+                offset,
+                value: Register(0),
+            },
+            Inst::Return {
+                result: Register(0),
+            },
+        ];
+        assert_eq!(
+            run(&store, &mut Context::default()),
+            Err(TrapCode::OutOfBoundsMemory)
+        );
+    }
+}
+
+#[test]
+fn factorial() {
+    let insts = [
+        // Compute the factorial of 20 and return it.
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 20,
+        },
+        Inst::Call {
+            target: Target(3),
+            num_args: 1,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+        // fn factorial(r0) -> r0
+        //
+        // Return 1 if r0 is zero.
+        Inst::BranchNez {
+            target: Target(6),
+            condition: Register(0),
+        },
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+        // Otherwise return r0 * factorial(r0 - 1).
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(0),
+            imm: 0,
+        },
+        Inst::SubImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        Inst::Call {
+            target: Target(3),
+            num_args: 1,
+        },
+        Inst::Mul {
+            result: Register(0),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Ok(2_432_902_008_176_640_000)
+    );
+}
+
+#[test]
+fn call_stack_overflow() {
+    let insts = [
+        Inst::Call {
+            target: Target(0),
+            num_args: 0,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_eq!(
+        run(&insts, &mut Context::default()),
+        Err(TrapCode::CallStackOverflow)
+    );
+}
+
+#[test]
+fn float_nan() {
+    let insts = [
+        Inst::FDiv {
+            result: Register(2),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::FAdd {
+            result: Register(3),
+            lhs: Register(2),
+            rhs: Register(0),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let mut context = Context::default();
+    assert_eq!(run(&insts, &mut context), Ok(0));
+    assert!(context.get_freg(Register(2)).is_nan());
+    assert!(context.get_freg(Register(3)).is_nan());
+}
+
+#[test]
+fn float_dot_product() {
+    let lhs = [1.5, 2.0, -3.0, 0.25];
+    let rhs = [4.0, 0.5, 2.0, 8.0];
+    let mut context = Context::default();
+    for (i, (lhs, rhs)) in lhs.into_iter().zip(rhs).enumerate() {
+        context.set_freg(Register(2 + i), lhs);
+        context.set_freg(Register(6 + i), rhs);
+    }
+    let mut insts = Vec::new();
+    for i in 0..lhs.len() {
+        // f1 = lhs[i] * rhs[i]
+        insts.push(Inst::FMul {
+            result: Register(1),
+            lhs: Register(2 + i),
+            rhs: Register(6 + i),
+        });
+        // f0 = f0 + f1
+        insts.push(Inst::FAdd {
+            result: Register(0),
+            lhs: Register(0),
+            rhs: Register(1),
+        });
+    }
+    insts.push(Inst::Return {
+        result: Register(0),
+    });
+    assert_eq!(run(&insts, &mut context), Ok(0));
+    assert_eq!(context.get_freg(Register(0)), 3.0);
 }
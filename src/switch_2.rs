@@ -1,9 +1,11 @@
 #![allow(dead_code)]
 
+use alloc::vec::Vec;
+
 #[cfg(test)]
 use crate::benchmark;
 
-use super::{handler, Bits, Context, Outcome, Register, Target};
+use super::{fetch, handler, switch, Bits, Context, Outcome, Register, Target, TrapCode};
 
 #[derive(Copy, Clone)]
 pub enum Inst {
@@ -83,7 +85,7 @@ impl Inst {
             }
             Inst::BranchEqz0 { target } => {
                 if *reg0 == 0 {
-                    context.branch_to(*target)
+                    context.branch_to(target.0)
                 } else {
                     context.next_inst()
                 }
@@ -93,16 +95,61 @@ impl Inst {
     }
 }
 
-/// Executes the list of instruction using the given [`Context`].
-fn execute(insts: &[Inst], context: &mut Context) {
+/// Lowers the [`switch::Inst`] program to instructions without `reg0` specializations.
+///
+/// # Errors
+///
+/// Returns the first instruction that has no counterpart.
+pub fn lower(insts: &[switch::Inst]) -> Result<Vec<Inst>, switch::Inst> {
+    insts.iter().cloned().map(lower_inst).collect()
+}
+
+/// Lowers a single [`switch::Inst`].
+fn lower_inst(inst: switch::Inst) -> Result<Inst, switch::Inst> {
+    let lowered = match inst {
+        switch::Inst::Add { result, lhs, rhs } => Inst::Add { result, lhs, rhs },
+        switch::Inst::AddImm { result, src, imm } => Inst::AddImm { result, src, imm },
+        switch::Inst::Sub { result, lhs, rhs } => Inst::Sub { result, lhs, rhs },
+        switch::Inst::SubImm { result, src, imm } => Inst::SubImm { result, src, imm },
+        switch::Inst::Mul { result, lhs, rhs } => Inst::Mul { result, lhs, rhs },
+        switch::Inst::MulImm { result, src, imm } => Inst::MulImm { result, src, imm },
+        switch::Inst::Branch { target } => Inst::Branch { target },
+        switch::Inst::BranchEqz { target, condition } => Inst::BranchEqz { target, condition },
+        switch::Inst::Return { result } => Inst::Return { result },
+        unsupported => return Err(unsupported),
+    };
+    Ok(lowered)
+}
+
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Bits {
+    let mut reg0 = 0;
+    loop {
+        let pc = context.pc;
+        let Ok(inst) = fetch(insts, pc) else {
+            return context.get_reg_abs(0);
+        };
+        match inst.execute(context, &mut reg0) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return context.get_reg_abs(0),
+        }
+    }
+}
+
+/// Runs the list of instruction using the given [`Context`] and returns the result.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn run(insts: &[Inst], context: &mut Context) -> Result<Bits, TrapCode> {
     let mut reg0 = 0;
     loop {
         let pc = context.pc;
-        // let inst = &insts[pc];
-        let inst = unsafe { insts.get_unchecked(pc) };
+        let inst = insts.get(pc).ok_or(TrapCode::OutOfBoundsPc)?;
         match inst.execute(context, &mut reg0) {
-            Outcome::Continue => continue,
-            Outcome::Return => return,
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return => return Ok(context.get_reg_abs(0)),
+            Outcome::Trap(trap) => return Err(trap),
         }
     }
 }
@@ -118,17 +165,18 @@ fn counter_loop() {
         },
         // Branch to the end if r0 is zero.
         Inst::BranchEqz0 {
-            target: 4,
+            target: Target(4),
         },
         // Decrease r0 by 1.
         Inst::SubImm0 {
             imm: 1,
         },
         // Jump back to the loop header.
-        Inst::Branch { target: 1 },
+        Inst::Branch { target: Target(1) },
         // Return value and end function execution.
-        Inst::Return { result: 0 },
+        Inst::Return { result: Register(0) },
     ];
     let mut context = Context::default();
-    benchmark(|| execute(&insts, &mut context));
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
 }
@@ -0,0 +1,392 @@
+#![allow(dead_code)]
+
+use alloc::vec::Vec;
+
+#[cfg(test)]
+use crate::benchmark;
+
+use super::{fetch, handler, switch, Bits, Context, Outcome, Register, Target, TrapCode};
+
+/// Instructions of the `switch` dispatch caching the contents of `r0` and `r1` in locals.
+///
+/// The `*0` and `*1` variants operate on the cached `r0` and `r1` respectively.
+/// All other instructions operate on the [`Context`] and must not access `r0` or `r1`
+/// unless they write back the cache first.
+#[derive(Copy, Clone)]
+pub enum Inst {
+    /// Adds the contents of `lhs` and `rhs` and stores the result into `result`.
+    Add {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Adds the contents of `r1` to `r0`.
+    Add0,
+    /// Adds the contents of `r0` to `r1`.
+    Add1,
+    /// Adds the constant `imm` and the contents of `src` and stores the result into `result`.
+    AddImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Adds the constant `imm` to `r0`.
+    AddImm0 { imm: Bits },
+    /// Adds the constant `imm` to `r1`.
+    AddImm1 { imm: Bits },
+    /// Subtracts the contents of `rhs` from `lhs` and stores the result into `result`.
+    Sub {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Subtracts the contents of `r1` from `r0`.
+    Sub0,
+    /// Subtracts the contents of `r0` from `r1`.
+    Sub1,
+    /// Subtracts the constant `imm` from the contents of `src` and stores the result into `result`.
+    SubImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Subtracts the constant `imm` from `r0`.
+    SubImm0 { imm: Bits },
+    /// Subtracts the constant `imm` from `r1`.
+    SubImm1 { imm: Bits },
+    /// Multiplies the contents of `lhs` and `rhs` and stores the result into `result`.
+    Mul {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Multiplies `r0` with the contents of `r1`.
+    Mul0,
+    /// Multiplies `r1` with the contents of `r0`.
+    Mul1,
+    /// Multiplies the constant `imm` and the contents of `src` and stores the result into `result`.
+    MulImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    /// Multiplies `r0` with the constant `imm`.
+    MulImm0 { imm: Bits },
+    /// Multiplies `r1` with the constant `imm`.
+    MulImm1 { imm: Bits },
+    /// Branches to the instruction indexed by `target`.
+    Branch { target: Target },
+    /// Branches to the instruction indexed by `target` if the contents of `condition` are zero.
+    BranchEqz { target: Target, condition: Register },
+    /// Branches to the instruction indexed by `target` if the contents of `r0` are zero.
+    BranchEqz0 { target: Target },
+    /// Branches to the instruction indexed by `target` if the contents of `r1` are zero.
+    BranchEqz1 { target: Target },
+    /// Returns execution of the function and returns the result in `result`.
+    Return { result: Register },
+}
+
+/// Writes the cached contents of `r0` and `r1` back into the `context`.
+fn write_back(context: &mut Context, reg0: Bits, reg1: Bits) {
+    context.set_reg(Register(0), reg0);
+    context.set_reg(Register(1), reg1);
+}
+
+impl Inst {
+    pub fn execute(&self, context: &mut Context, reg0: &mut Bits, reg1: &mut Bits) -> Outcome {
+        match self {
+            Inst::Add { result, lhs, rhs } => handler::add(context, *result, *lhs, *rhs),
+            Inst::Add0 => {
+                *reg0 = reg0.wrapping_add(*reg1);
+                context.next_inst()
+            }
+            Inst::Add1 => {
+                *reg1 = reg1.wrapping_add(*reg0);
+                context.next_inst()
+            }
+            Inst::AddImm { result, src, imm } => handler::add_imm(context, *result, *src, *imm),
+            Inst::AddImm0 { imm } => {
+                *reg0 = reg0.wrapping_add(*imm);
+                context.next_inst()
+            }
+            Inst::AddImm1 { imm } => {
+                *reg1 = reg1.wrapping_add(*imm);
+                context.next_inst()
+            }
+            Inst::Sub { result, lhs, rhs } => handler::sub(context, *result, *lhs, *rhs),
+            Inst::Sub0 => {
+                *reg0 = reg0.wrapping_sub(*reg1);
+                context.next_inst()
+            }
+            Inst::Sub1 => {
+                *reg1 = reg1.wrapping_sub(*reg0);
+                context.next_inst()
+            }
+            Inst::SubImm { result, src, imm } => handler::sub_imm(context, *result, *src, *imm),
+            Inst::SubImm0 { imm } => {
+                *reg0 = reg0.wrapping_sub(*imm);
+                context.next_inst()
+            }
+            Inst::SubImm1 { imm } => {
+                *reg1 = reg1.wrapping_sub(*imm);
+                context.next_inst()
+            }
+            Inst::Mul { result, lhs, rhs } => handler::mul(context, *result, *lhs, *rhs),
+            Inst::Mul0 => {
+                *reg0 = reg0.wrapping_mul(*reg1);
+                context.next_inst()
+            }
+            Inst::Mul1 => {
+                *reg1 = reg1.wrapping_mul(*reg0);
+                context.next_inst()
+            }
+            Inst::MulImm { result, src, imm } => handler::mul_imm(context, *result, *src, *imm),
+            Inst::MulImm0 { imm } => {
+                *reg0 = reg0.wrapping_mul(*imm);
+                context.next_inst()
+            }
+            Inst::MulImm1 { imm } => {
+                *reg1 = reg1.wrapping_mul(*imm);
+                context.next_inst()
+            }
+            Inst::Branch { target } => {
+                write_back(context, *reg0, *reg1);
+                handler::branch(context, *target)
+            }
+            Inst::BranchEqz { target, condition } => {
+                write_back(context, *reg0, *reg1);
+                handler::branch_eqz(context, *target, *condition)
+            }
+            Inst::BranchEqz0 { target } => {
+                write_back(context, *reg0, *reg1);
+                if *reg0 == 0 {
+                    context.branch_to(target.0)
+                } else {
+                    context.next_inst()
+                }
+            }
+            Inst::BranchEqz1 { target } => {
+                write_back(context, *reg0, *reg1);
+                if *reg1 == 0 {
+                    context.branch_to(target.0)
+                } else {
+                    context.next_inst()
+                }
+            }
+            Inst::Return { result } => {
+                write_back(context, *reg0, *reg1);
+                handler::ret(context, *result)
+            }
+        }
+    }
+}
+
+/// Lowers the [`switch::Inst`] program to instructions operating on the cached `r0` and `r1` where possible.
+///
+/// # Errors
+///
+/// Returns the first instruction that has no counterpart or that accesses `r0` or `r1` in a way
+/// that has no cached specialization.
+pub fn lower(insts: &[switch::Inst]) -> Result<Vec<Inst>, switch::Inst> {
+    insts.iter().cloned().map(lower_inst).collect()
+}
+
+/// Returns `true` if `reg` is cached in a local.
+fn is_cached(reg: Register) -> bool {
+    reg < Register(2)
+}
+
+/// Lowers a single [`switch::Inst`].
+fn lower_inst(inst: switch::Inst) -> Result<Inst, switch::Inst> {
+    use switch::Inst as I;
+    let lowered = match inst {
+        I::Add {
+            result: Register(0),
+            lhs: Register(0),
+            rhs: Register(1),
+        }
+        | I::Add {
+            result: Register(0),
+            lhs: Register(1),
+            rhs: Register(0),
+        } => Inst::Add0,
+        I::Add {
+            result: Register(1),
+            lhs: Register(1),
+            rhs: Register(0),
+        }
+        | I::Add {
+            result: Register(1),
+            lhs: Register(0),
+            rhs: Register(1),
+        } => Inst::Add1,
+        I::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm,
+        } => Inst::AddImm0 { imm },
+        I::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm,
+        } => Inst::AddImm1 { imm },
+        I::Sub {
+            result: Register(0),
+            lhs: Register(0),
+            rhs: Register(1),
+        } => Inst::Sub0,
+        I::Sub {
+            result: Register(1),
+            lhs: Register(1),
+            rhs: Register(0),
+        } => Inst::Sub1,
+        I::SubImm {
+            result: Register(0),
+            src: Register(0),
+            imm,
+        } => Inst::SubImm0 { imm },
+        I::SubImm {
+            result: Register(1),
+            src: Register(1),
+            imm,
+        } => Inst::SubImm1 { imm },
+        I::Mul {
+            result: Register(0),
+            lhs: Register(0),
+            rhs: Register(1),
+        }
+        | I::Mul {
+            result: Register(0),
+            lhs: Register(1),
+            rhs: Register(0),
+        } => Inst::Mul0,
+        I::Mul {
+            result: Register(1),
+            lhs: Register(1),
+            rhs: Register(0),
+        }
+        | I::Mul {
+            result: Register(1),
+            lhs: Register(0),
+            rhs: Register(1),
+        } => Inst::Mul1,
+        I::MulImm {
+            result: Register(0),
+            src: Register(0),
+            imm,
+        } => Inst::MulImm0 { imm },
+        I::MulImm {
+            result: Register(1),
+            src: Register(1),
+            imm,
+        } => Inst::MulImm1 { imm },
+        I::Add { result, lhs, rhs } if ![result, lhs, rhs].into_iter().any(is_cached) => {
+            Inst::Add { result, lhs, rhs }
+        }
+        I::AddImm { result, src, imm } if ![result, src].into_iter().any(is_cached) => {
+            Inst::AddImm { result, src, imm }
+        }
+        I::Sub { result, lhs, rhs } if ![result, lhs, rhs].into_iter().any(is_cached) => {
+            Inst::Sub { result, lhs, rhs }
+        }
+        I::SubImm { result, src, imm } if ![result, src].into_iter().any(is_cached) => {
+            Inst::SubImm { result, src, imm }
+        }
+        I::Mul { result, lhs, rhs } if ![result, lhs, rhs].into_iter().any(is_cached) => {
+            Inst::Mul { result, lhs, rhs }
+        }
+        I::MulImm { result, src, imm } if ![result, src].into_iter().any(is_cached) => {
+            Inst::MulImm { result, src, imm }
+        }
+        I::Branch { target } => Inst::Branch { target },
+        I::BranchEqz {
+            target,
+            condition: Register(0),
+        } => Inst::BranchEqz0 { target },
+        I::BranchEqz {
+            target,
+            condition: Register(1),
+        } => Inst::BranchEqz1 { target },
+        I::BranchEqz { target, condition } => Inst::BranchEqz { target, condition },
+        I::Return { result } => Inst::Return { result },
+        unsupported => return Err(unsupported),
+    };
+    Ok(lowered)
+}
+
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Bits {
+    let mut reg0 = context.get_reg(Register(0));
+    let mut reg1 = context.get_reg(Register(1));
+    loop {
+        let pc = context.pc;
+        let Ok(inst) = fetch(insts, pc) else {
+            return context.get_reg_abs(0);
+        };
+        match inst.execute(context, &mut reg0, &mut reg1) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return context.get_reg_abs(0),
+        }
+    }
+}
+
+/// Runs the list of instruction using the given [`Context`] and returns the result.
+///
+/// # Errors
+///
+/// If the execution traps or the `pc` leaves the bounds of `insts`.
+pub fn run(insts: &[Inst], context: &mut Context) -> Result<Bits, TrapCode> {
+    let mut reg0 = context.get_reg(Register(0));
+    let mut reg1 = context.get_reg(Register(1));
+    loop {
+        let pc = context.pc;
+        let inst = insts.get(pc).ok_or(TrapCode::OutOfBoundsPc)?;
+        match inst.execute(context, &mut reg0, &mut reg1) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return => return Ok(context.get_reg_abs(0)),
+            Outcome::Trap(trap) => return Err(trap),
+        }
+    }
+}
+
+#[test]
+fn counter_loop() {
+    let insts = lower(&switch::counter_loop_insts(100_000_000)).unwrap();
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn more_comps() {
+    let program = switch::more_comps_insts(100_000_000);
+    let insts = lower(&program).unwrap();
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    let expected = switch::run(&program, &mut Context::default());
+    assert_eq!(Ok(result), expected);
+}
+
+#[test]
+fn lower_rejects_uncached_access() {
+    let add = switch::Inst::Add {
+        result: Register(2),
+        lhs: Register(0),
+        rhs: Register(3),
+    };
+    assert!(lower(&[add]).is_err());
+    let insts = lower(&[switch::Inst::Add {
+        result: Register(2),
+        lhs: Register(3),
+        rhs: Register(4),
+    }])
+    .unwrap();
+    assert!(matches!(
+        insts[..],
+        [Inst::Add {
+            result: Register(2),
+            lhs: Register(3),
+            rhs: Register(4)
+        }]
+    ));
+}
@@ -0,0 +1,159 @@
+#![allow(dead_code)]
+
+use alloc::vec::Vec;
+
+#[cfg(test)]
+use crate::benchmark;
+
+use super::{
+    bytecode::opcode,
+    fetch, handler,
+    switch::{self, Inst, Profile},
+    Bits, Context, Outcome,
+};
+
+/// Returns the opcodes executed in `profile` ordered from the most to the least frequently executed.
+///
+/// The `profile` is usually obtained from [`switch::profiled_execute`].
+/// Opcodes with equal frequencies are ordered by their value.
+pub fn reorder_by_frequency(profile: &Profile) -> Vec<u8> {
+    let mut opcodes = (0..switch::N_OPCODES)
+        .filter(|&opcode| profile.counts[opcode] != 0)
+        .collect::<Vec<_>>();
+    opcodes.sort_by_key(|&opcode| core::cmp::Reverse(profile.counts[opcode]));
+    opcodes.into_iter().map(|opcode| opcode as u8).collect()
+}
+
+/// Executes `inst` of opcode `OPCODE` by calling the handler of `OPCODE` directly.
+///
+/// The `match` on the constant `OPCODE` is resolved at compile time to a single arm.
+/// Opcodes without an arm of their own fall back to the `match` of [`Inst::execute`].
+#[inline(always)]
+fn execute_hot<const OPCODE: u8>(inst: &Inst, context: &mut Context) -> Outcome {
+    match (OPCODE, inst) {
+        (opcode::ADD, Inst::Add { result, lhs, rhs }) => handler::add(context, *result, *lhs, *rhs),
+        (opcode::ADD_IMM, Inst::AddImm { result, src, imm }) => {
+            handler::add_imm(context, *result, *src, *imm)
+        }
+        (opcode::SUB, Inst::Sub { result, lhs, rhs }) => handler::sub(context, *result, *lhs, *rhs),
+        (opcode::SUB_IMM, Inst::SubImm { result, src, imm }) => {
+            handler::sub_imm(context, *result, *src, *imm)
+        }
+        (opcode::MUL, Inst::Mul { result, lhs, rhs }) => handler::mul(context, *result, *lhs, *rhs),
+        (opcode::MUL_IMM, Inst::MulImm { result, src, imm }) => {
+            handler::mul_imm(context, *result, *src, *imm)
+        }
+        (opcode::DIV, Inst::Div { result, lhs, rhs }) => handler::div(context, *result, *lhs, *rhs),
+        (opcode::DIV_IMM, Inst::DivImm { result, src, imm }) => {
+            handler::div_imm(context, *result, *src, *imm)
+        }
+        (opcode::REM, Inst::Rem { result, lhs, rhs }) => handler::rem(context, *result, *lhs, *rhs),
+        (opcode::REM_IMM, Inst::RemImm { result, src, imm }) => {
+            handler::rem_imm(context, *result, *src, *imm)
+        }
+        (opcode::MOVE, Inst::Move { result, src }) => handler::mov(context, *result, *src),
+        (opcode::BRANCH, Inst::Branch { target }) => handler::branch(context, *target),
+        (opcode::BRANCH_EQZ, Inst::BranchEqz { target, condition }) => {
+            handler::branch_eqz(context, *target, *condition)
+        }
+        (opcode::BRANCH_NEZ, Inst::BranchNez { target, condition }) => {
+            handler::branch_nez(context, *target, *condition)
+        }
+        (opcode::CALL, Inst::Call { target, num_args }) => {
+            handler::call(context, *target, *num_args)
+        }
+        (opcode::RETURN, Inst::Return { result }) => handler::ret(context, *result),
+        _ => inst.execute(context),
+    }
+}
+
+/// Executes a single instruction testing the opcodes `H0`, `H1` and `H2` in order before
+/// falling through to the `match` of [`Inst::execute`].
+///
+/// Each hot opcode calls its handler directly via [`execute_hot`].
+#[inline(always)]
+fn dispatch<const H0: u8, const H1: u8, const H2: u8>(
+    inst: &Inst,
+    context: &mut Context,
+) -> Outcome {
+    let opcode = inst.opcode();
+    if opcode == H0 {
+        execute_hot::<H0>(inst, context)
+    } else if opcode == H1 {
+        execute_hot::<H1>(inst, context)
+    } else if opcode == H2 {
+        execute_hot::<H2>(inst, context)
+    } else {
+        inst.execute(context)
+    }
+}
+
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+///
+/// Dispatches the opcodes `H0`, `H1` and `H2` ahead of all other opcodes,
+/// e.g. ordered as returned by [`reorder_by_frequency`].
+pub fn execute<const H0: u8, const H1: u8, const H2: u8>(
+    insts: &[Inst],
+    context: &mut Context,
+) -> Bits {
+    loop {
+        let pc = context.pc;
+        let Ok(inst) = fetch(insts, pc) else {
+            return context.get_reg_abs(0);
+        };
+        match dispatch::<H0, H1, H2>(inst, context) {
+            Outcome::Continue | Outcome::Yield => continue,
+            Outcome::Return | Outcome::Trap(_) => return context.get_reg_abs(0),
+        }
+    }
+}
+
+#[test]
+fn counter_loop_order() {
+    let insts = switch::counter_loop_insts(1_000);
+    let (_, profile) = switch::profiled_execute(&insts, &mut Context::default());
+    assert_eq!(
+        reorder_by_frequency(&profile),
+        [
+            opcode::BRANCH_EQZ,
+            opcode::SUB_IMM,
+            opcode::BRANCH,
+            opcode::ADD_IMM,
+            opcode::RETURN,
+        ]
+    );
+}
+
+#[test]
+fn counter_loop() {
+    let insts = switch::counter_loop_insts(100_000_000);
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| {
+        execute::<{ opcode::BRANCH_EQZ }, { opcode::SUB_IMM }, { opcode::BRANCH }>(
+            &insts,
+            &mut context,
+        )
+    });
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn more_comps() {
+    let insts = switch::more_comps_insts(1_000);
+    let (_, profile) = switch::profiled_execute(&insts, &mut Context::default());
+    let [h0, h1, h2, ..] = reorder_by_frequency(&profile)[..] else {
+        panic!("expected at least three executed opcodes");
+    };
+    assert_eq!(
+        [h0, h1, h2],
+        [opcode::BRANCH_EQZ, opcode::SUB, opcode::SUB_IMM]
+    );
+    assert_eq!(
+        Ok(execute::<
+            { opcode::BRANCH_EQZ },
+            { opcode::SUB },
+            { opcode::SUB_IMM },
+        >(&insts, &mut Context::default())),
+        switch::execute(&insts, &mut Context::default()),
+    );
+}
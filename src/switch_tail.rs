@@ -3,7 +3,9 @@
 #[cfg(test)]
 use crate::benchmark;
 
-use super::{handler, switch::Inst, Context, Outcome};
+use super::{fetch, handler, switch::Inst, Bits, Context, Outcome};
+#[cfg(test)]
+use super::{switch, Register, Target};
 
 pub struct ExecContext<'i, 'c> {
     insts: &'i [Inst],
@@ -12,8 +14,10 @@ pub struct ExecContext<'i, 'c> {
 
 impl<'i, 'c> ExecContext<'i, 'c> {
     pub fn tail_execute_next(&mut self) -> Outcome {
-        let inst = unsafe { self.insts.get_unchecked(self.context.pc) };
-        inst.tail_execute(self)
+        match fetch(self.insts, self.context.pc) {
+            Ok(inst) => inst.tail_execute(self),
+            Err(trap) => Outcome::Trap(trap),
+        }
     }
 }
 
@@ -21,29 +25,328 @@ impl Inst {
     pub fn tail_execute(&self, context: &mut ExecContext) -> Outcome {
         match self {
             Inst::Add { result, lhs, rhs } => {
-                handler::add(context.context, *result, *lhs, *rhs);
-                context.tail_execute_next()
+                match handler::add(context.context, *result, *lhs, *rhs) {
+                    Outcome::Continue => context.tail_execute_next(),
+                    outcome => outcome,
+                }
             }
             Inst::AddImm { result, src, imm } => {
-                handler::add_imm(context.context, *result, *src, *imm);
-                context.tail_execute_next()
+                match handler::add_imm(context.context, *result, *src, *imm) {
+                    Outcome::Continue => context.tail_execute_next(),
+                    outcome => outcome,
+                }
             }
             Inst::Sub { result, lhs, rhs } => {
-                handler::sub(context.context, *result, *lhs, *rhs);
-                context.tail_execute_next()
+                match handler::sub(context.context, *result, *lhs, *rhs) {
+                    Outcome::Continue => context.tail_execute_next(),
+                    outcome => outcome,
+                }
             }
             Inst::SubImm { result, src, imm } => {
-                handler::sub_imm(context.context, *result, *src, *imm);
-                context.tail_execute_next()
+                match handler::sub_imm(context.context, *result, *src, *imm) {
+                    Outcome::Continue => context.tail_execute_next(),
+                    outcome => outcome,
+                }
             }
             Inst::Mul { result, lhs, rhs } => {
-                handler::mul(context.context, *result, *lhs, *rhs);
-                context.tail_execute_next()
+                match handler::mul(context.context, *result, *lhs, *rhs) {
+                    Outcome::Continue => context.tail_execute_next(),
+                    outcome => outcome,
+                }
             }
             Inst::MulImm { result, src, imm } => {
-                handler::mul_imm(context.context, *result, *src, *imm);
+                match handler::mul_imm(context.context, *result, *src, *imm) {
+                    Outcome::Continue => context.tail_execute_next(),
+                    outcome => outcome,
+                }
+            }
+            Inst::Div { result, lhs, rhs } => {
+                match handler::div(context.context, *result, *lhs, *rhs) {
+                    Outcome::Continue => context.tail_execute_next(),
+                    outcome => outcome,
+                }
+            }
+            Inst::DivImm { result, src, imm } => {
+                match handler::div_imm(context.context, *result, *src, *imm) {
+                    Outcome::Continue => context.tail_execute_next(),
+                    outcome => outcome,
+                }
+            }
+            Inst::Rem { result, lhs, rhs } => {
+                match handler::rem(context.context, *result, *lhs, *rhs) {
+                    Outcome::Continue => context.tail_execute_next(),
+                    outcome => outcome,
+                }
+            }
+            Inst::RemImm { result, src, imm } => {
+                match handler::rem_imm(context.context, *result, *src, *imm) {
+                    Outcome::Continue => context.tail_execute_next(),
+                    outcome => outcome,
+                }
+            }
+            Inst::Shl { result, lhs, rhs } => {
+                handler::shl(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::ShlImm { result, src, imm } => {
+                handler::shl_imm(context.context, *result, *src, *imm);
+                context.tail_execute_next()
+            }
+            Inst::ShrU { result, lhs, rhs } => {
+                handler::shr_u(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::ShrUImm { result, src, imm } => {
+                handler::shr_u_imm(context.context, *result, *src, *imm);
+                context.tail_execute_next()
+            }
+            Inst::ShrS { result, lhs, rhs } => {
+                handler::shr_s(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::ShrSImm { result, src, imm } => {
+                handler::shr_s_imm(context.context, *result, *src, *imm);
+                context.tail_execute_next()
+            }
+            Inst::Rotl { result, lhs, rhs } => {
+                handler::rotl(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::RotlImm { result, src, imm } => {
+                handler::rotl_imm(context.context, *result, *src, *imm);
+                context.tail_execute_next()
+            }
+            Inst::Rotr { result, lhs, rhs } => {
+                handler::rotr(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::RotrImm { result, src, imm } => {
+                handler::rotr_imm(context.context, *result, *src, *imm);
+                context.tail_execute_next()
+            }
+            Inst::Eq { result, lhs, rhs } => {
+                handler::eq(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::EqImm { result, src, imm } => {
+                handler::eq_imm(context.context, *result, *src, *imm);
+                context.tail_execute_next()
+            }
+            Inst::Ne { result, lhs, rhs } => {
+                handler::ne(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::NeImm { result, src, imm } => {
+                handler::ne_imm(context.context, *result, *src, *imm);
+                context.tail_execute_next()
+            }
+            Inst::LtU { result, lhs, rhs } => {
+                handler::lt_u(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::LtUImm { result, src, imm } => {
+                handler::lt_u_imm(context.context, *result, *src, *imm);
+                context.tail_execute_next()
+            }
+            Inst::LtS { result, lhs, rhs } => {
+                handler::lt_s(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::LtSImm { result, src, imm } => {
+                handler::lt_s_imm(context.context, *result, *src, *imm);
+                context.tail_execute_next()
+            }
+            Inst::Move { result, src } => {
+                handler::mov(context.context, *result, *src);
+                context.tail_execute_next()
+            }
+            Inst::Add32 { result, lhs, rhs } => {
+                handler::add32(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::Sub32 { result, lhs, rhs } => {
+                handler::sub32(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::Mul32 { result, lhs, rhs } => {
+                handler::mul32(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::LtS32 { result, lhs, rhs } => {
+                handler::lt_s32(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::Shl32 { result, lhs, rhs } => {
+                handler::shl32(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::ShrU32 { result, lhs, rhs } => {
+                handler::shr_u32(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::ShrS32 { result, lhs, rhs } => {
+                handler::shr_s32(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::AddChecked { result, lhs, rhs } => {
+                match handler::add_checked(context.context, *result, *lhs, *rhs) {
+                    Outcome::Continue => context.tail_execute_next(),
+                    outcome => outcome,
+                }
+            }
+            Inst::SubChecked { result, lhs, rhs } => {
+                match handler::sub_checked(context.context, *result, *lhs, *rhs) {
+                    Outcome::Continue => context.tail_execute_next(),
+                    outcome => outcome,
+                }
+            }
+            Inst::MulChecked { result, lhs, rhs } => {
+                match handler::mul_checked(context.context, *result, *lhs, *rhs) {
+                    Outcome::Continue => context.tail_execute_next(),
+                    outcome => outcome,
+                }
+            }
+            Inst::MulWide { hi, lo, lhs, rhs } => {
+                handler::mul_wide(context.context, *hi, *lo, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::Popcnt { result, src } => {
+                handler::popcnt(context.context, *result, *src);
+                context.tail_execute_next()
+            }
+            Inst::Clz { result, src } => {
+                handler::clz(context.context, *result, *src);
+                context.tail_execute_next()
+            }
+            Inst::Ctz { result, src } => {
+                handler::ctz(context.context, *result, *src);
                 context.tail_execute_next()
             }
+            Inst::RandNext { result } => {
+                handler::rand_next(context.context, *result);
+                context.tail_execute_next()
+            }
+            Inst::MinU { result, lhs, rhs } => {
+                handler::min_u(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::MinUImm { result, src, imm } => {
+                handler::min_u_imm(context.context, *result, *src, *imm);
+                context.tail_execute_next()
+            }
+            Inst::MaxU { result, lhs, rhs } => {
+                handler::max_u(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::MaxUImm { result, src, imm } => {
+                handler::max_u_imm(context.context, *result, *src, *imm);
+                context.tail_execute_next()
+            }
+            Inst::MinS { result, lhs, rhs } => {
+                handler::min_s(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::MinSImm { result, src, imm } => {
+                handler::min_s_imm(context.context, *result, *src, *imm);
+                context.tail_execute_next()
+            }
+            Inst::MaxS { result, lhs, rhs } => {
+                handler::max_s(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::MaxSImm { result, src, imm } => {
+                handler::max_s_imm(context.context, *result, *src, *imm);
+                context.tail_execute_next()
+            }
+            Inst::Select {
+                result,
+                cond,
+                if_nonzero,
+                if_zero,
+            } => {
+                handler::select(context.context, *result, *cond, *if_nonzero, *if_zero);
+                context.tail_execute_next()
+            }
+            Inst::FAdd { result, lhs, rhs } => {
+                handler::fadd(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::FSub { result, lhs, rhs } => {
+                handler::fsub(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::FMul { result, lhs, rhs } => {
+                handler::fmul(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::FDiv { result, lhs, rhs } => {
+                handler::fdiv(context.context, *result, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::Load8 {
+                result,
+                base,
+                offset,
+            } => match handler::load8(context.context, *result, *base, *offset) {
+                Outcome::Continue => context.tail_execute_next(),
+                outcome => outcome,
+            },
+            Inst::Load16 {
+                result,
+                base,
+                offset,
+            } => match handler::load16(context.context, *result, *base, *offset) {
+                Outcome::Continue => context.tail_execute_next(),
+                outcome => outcome,
+            },
+            Inst::Load32 {
+                result,
+                base,
+                offset,
+            } => match handler::load32(context.context, *result, *base, *offset) {
+                Outcome::Continue => context.tail_execute_next(),
+                outcome => outcome,
+            },
+            Inst::Load64 {
+                result,
+                base,
+                offset,
+            } => match handler::load64(context.context, *result, *base, *offset) {
+                Outcome::Continue => context.tail_execute_next(),
+                outcome => outcome,
+            },
+            Inst::Store8 {
+                base,
+                offset,
+                value,
+            } => match handler::store8(context.context, *base, *offset, *value) {
+                Outcome::Continue => context.tail_execute_next(),
+                outcome => outcome,
+            },
+            Inst::Store16 {
+                base,
+                offset,
+                value,
+            } => match handler::store16(context.context, *base, *offset, *value) {
+                Outcome::Continue => context.tail_execute_next(),
+                outcome => outcome,
+            },
+            Inst::Store32 {
+                base,
+                offset,
+                value,
+            } => match handler::store32(context.context, *base, *offset, *value) {
+                Outcome::Continue => context.tail_execute_next(),
+                outcome => outcome,
+            },
+            Inst::Store64 {
+                base,
+                offset,
+                value,
+            } => match handler::store64(context.context, *base, *offset, *value) {
+                Outcome::Continue => context.tail_execute_next(),
+                outcome => outcome,
+            },
             Inst::Branch { target } => {
                 handler::branch(context.context, *target);
                 context.tail_execute_next()
@@ -52,46 +355,68 @@ impl Inst {
                 handler::branch_eqz(context.context, *target, *condition);
                 context.tail_execute_next()
             }
+            Inst::BranchNez { target, condition } => {
+                handler::branch_nez(context.context, *target, *condition);
+                context.tail_execute_next()
+            }
+            Inst::BranchEq { target, lhs, rhs } => {
+                handler::branch_eq(context.context, *target, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::BranchNe { target, lhs, rhs } => {
+                handler::branch_ne(context.context, *target, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::BranchLtU { target, lhs, rhs } => {
+                handler::branch_lt_u(context.context, *target, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::BranchLtS { target, lhs, rhs } => {
+                handler::branch_lt_s(context.context, *target, *lhs, *rhs);
+                context.tail_execute_next()
+            }
+            Inst::BranchTable {
+                index,
+                targets,
+                default,
+            } => {
+                handler::branch_table(context.context, *index, targets, *default);
+                context.tail_execute_next()
+            }
+            Inst::Call { target, num_args } => {
+                match handler::call(context.context, *target, *num_args) {
+                    Outcome::Continue => context.tail_execute_next(),
+                    outcome => outcome,
+                }
+            }
+            Inst::Nop => {
+                handler::nop(context.context);
+                context.tail_execute_next()
+            }
+            // There is no dispatch loop to return to so yields are resumed immediately.
+            Inst::Yield => {
+                handler::yield_(context.context);
+                context.tail_execute_next()
+            }
+            Inst::Unreachable => handler::unreachable(),
             Inst::Return { result } => handler::ret(context.context, *result),
         }
     }
 }
 
-/// Executes the list of instruction using the given [`Context`].
-fn execute(insts: &[Inst], context: &mut Context) {
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Bits {
     let mut exec_context = ExecContext { insts, context };
     exec_context.tail_execute_next();
+    exec_context.context.get_reg_abs(0)
 }
 
 #[test]
 fn counter_loop() {
-    let repetitions = 100_000_000;
-    let insts = vec![
-        // Store `repetitions` into r0.
-        // Note: r0 is our loop counter register.
-        Inst::AddImm {
-            result: 0,
-            src: 0,
-            imm: repetitions,
-        },
-        // Branch to the end if r0 is zero.
-        Inst::BranchEqz {
-            target: 4,
-            condition: 0,
-        },
-        // Decrease r0 by 1.
-        Inst::SubImm {
-            result: 0,
-            src: 0,
-            imm: 1,
-        },
-        // Jump back to the loop header.
-        Inst::Branch { target: 1 },
-        // Return value and end function execution.
-        Inst::Return { result: 0 },
-    ];
+    let insts = switch::counter_loop_insts(100_000_000);
     let mut context = Context::default();
-    benchmark(|| execute(&insts, &mut context));
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
 }
 
 #[test]
@@ -101,45 +426,72 @@ fn more_comps() {
         // Store `repetitions` into r0.
         // Note: r0 is our loop counter register.
         Inst::AddImm {
-            result: 0,
-            src: 0,
+            result: Register(0),
+            src: Register(0),
             imm: repetitions,
         },
         // Store `1` into r1.
         // Note: r1 is our accumulator register.
         Inst::AddImm {
-            result: 1,
-            src: 1,
+            result: Register(1),
+            src: Register(1),
             imm: 1,
         },
         // Branch to the end if r0 is zero.
         Inst::BranchEqz {
-            target: 7,
-            condition: 0,
+            target: Target(7),
+            condition: Register(0),
         },
         // Multiply r1 with r0.
         Inst::Mul {
-            result: 1,
-            lhs: 1,
-            rhs: 0,
+            result: Register(1),
+            lhs: Register(1),
+            rhs: Register(0),
         },
         // Subtract r0 from r1.
         Inst::Sub {
-            result: 1,
-            lhs: 1,
-            rhs: 0,
+            result: Register(1),
+            lhs: Register(1),
+            rhs: Register(0),
         },
         // Decrease r0 by 1.
         Inst::SubImm {
-            result: 0,
-            src: 0,
+            result: Register(0),
+            src: Register(0),
             imm: 1,
         },
         // Jump back to the loop header.
-        Inst::Branch { target: 2 },
+        Inst::Branch { target: Target(2) },
         // Return value and end function execution.
-        Inst::Return { result: 1 },
+        Inst::Return {
+            result: Register(1),
+        },
     ];
     let mut context = Context::default();
     benchmark(|| execute(&insts, &mut context));
 }
+
+#[test]
+#[cfg(feature = "safe_dispatch")]
+fn out_of_bounds_branch_traps() {
+    let insts = [
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        Inst::Branch {
+            target: Target(100),
+        },
+    ];
+    let mut context = Context::default();
+    let mut exec_context = ExecContext {
+        insts: &insts,
+        context: &mut context,
+    };
+    assert!(matches!(
+        exec_context.tail_execute_next(),
+        Outcome::Trap(crate::TrapCode::OutOfBoundsPc)
+    ));
+    assert_eq!(execute(&insts, &mut Context::default()), 1);
+}
@@ -1,9 +1,11 @@
 #![allow(dead_code)]
 
+use alloc::vec::Vec;
+
 #[cfg(test)]
 use crate::benchmark;
 
-use super::{handler, Register, Target, Context, Outcome, Bits};
+use super::{fetch, handler, switch, Register, Target, Context, Outcome, Bits};
 
 #[derive(Copy, Clone)]
 pub enum Inst {
@@ -25,6 +27,21 @@ pub enum Inst {
     SubImm0 {
         imm: Bits,
     },
+    /// Multiplies the contents of `lhs` and `rhs` and stores the result into `result`.
+    Mul {
+        result: Register,
+        lhs: Register,
+        rhs: Register,
+    },
+    /// Multiplies the constant `imm` and the contents of `src` and stores the result into `result`.
+    MulImm {
+        result: Register,
+        src: Register,
+        imm: Bits,
+    },
+    MulImm0 {
+        imm: Bits,
+    },
     /// Branches to the instruction indexed by `target`.
     Branch { target: Target },
     /// Branches to the instruction indexed by `target` if the contents of `condition` are zero.
@@ -41,8 +58,10 @@ pub struct ExecContext<'i, 'c> {
 
 impl<'i, 'c> ExecContext<'i, 'c> {
     pub fn tail_execute_next_2(&mut self, reg0: Bits) -> Outcome {
-        let inst = unsafe { self.insts.get_unchecked(self.context.pc) };
-        inst.tail_execute_2(self, reg0)
+        match fetch(self.insts, self.context.pc) {
+            Ok(inst) => inst.tail_execute_2(self, reg0),
+            Err(trap) => Outcome::Trap(trap),
+        }
     }
 }
 
@@ -50,8 +69,10 @@ impl Inst {
     pub fn tail_execute_2(&self, context: &mut ExecContext, reg0: Bits) -> Outcome {
         match self {
             Inst::AddImm { result, src, imm } => {
-                handler::add_imm(context.context, *result, *src, *imm);
-                context.tail_execute_next_2(reg0)
+                match handler::add_imm(context.context, *result, *src, *imm) {
+                    Outcome::Continue => context.tail_execute_next_2(reg0),
+                    outcome => outcome,
+                }
             }
             Inst::AddImm0 { imm } => {
                 let result = reg0.wrapping_add(*imm);
@@ -59,14 +80,33 @@ impl Inst {
                 context.tail_execute_next_2(result)
             }
             Inst::SubImm { result, src, imm } => {
-                handler::sub_imm(context.context, *result, *src, *imm);
-                context.tail_execute_next_2(reg0)
+                match handler::sub_imm(context.context, *result, *src, *imm) {
+                    Outcome::Continue => context.tail_execute_next_2(reg0),
+                    outcome => outcome,
+                }
             }
             Inst::SubImm0 { imm } => {
                 let result = reg0.wrapping_sub(*imm);
                 context.context.pc += 1;
                 context.tail_execute_next_2(result)
             }
+            Inst::Mul { result, lhs, rhs } => {
+                match handler::mul(context.context, *result, *lhs, *rhs) {
+                    Outcome::Continue => context.tail_execute_next_2(reg0),
+                    outcome => outcome,
+                }
+            }
+            Inst::MulImm { result, src, imm } => {
+                match handler::mul_imm(context.context, *result, *src, *imm) {
+                    Outcome::Continue => context.tail_execute_next_2(reg0),
+                    outcome => outcome,
+                }
+            }
+            Inst::MulImm0 { imm } => {
+                let result = reg0.wrapping_mul(*imm);
+                context.context.pc += 1;
+                context.tail_execute_next_2(result)
+            }
             Inst::Branch { target } => {
                 handler::branch(context.context, *target);
                 context.tail_execute_next_2(reg0)
@@ -77,7 +117,7 @@ impl Inst {
             }
             Inst::BranchEqz0 { target } => {
                 if reg0 == 0 {
-                    context.context.pc = *target as usize;
+                    context.context.pc = target.0;
                 } else {
                     context.context.pc += 1;
                 }
@@ -88,10 +128,35 @@ impl Inst {
     }
 }
 
-/// Executes the list of instruction using the given [`Context`].
-fn execute(insts: &[Inst], context: &mut Context) {
+/// Lowers the [`switch::Inst`] program to instructions without `reg0` specializations.
+///
+/// # Errors
+///
+/// Returns the first instruction that has no counterpart.
+pub fn lower(insts: &[switch::Inst]) -> Result<Vec<Inst>, switch::Inst> {
+    insts.iter().cloned().map(lower_inst).collect()
+}
+
+/// Lowers a single [`switch::Inst`].
+fn lower_inst(inst: switch::Inst) -> Result<Inst, switch::Inst> {
+    let lowered = match inst {
+        switch::Inst::AddImm { result, src, imm } => Inst::AddImm { result, src, imm },
+        switch::Inst::SubImm { result, src, imm } => Inst::SubImm { result, src, imm },
+        switch::Inst::Mul { result, lhs, rhs } => Inst::Mul { result, lhs, rhs },
+        switch::Inst::MulImm { result, src, imm } => Inst::MulImm { result, src, imm },
+        switch::Inst::Branch { target } => Inst::Branch { target },
+        switch::Inst::BranchEqz { target, condition } => Inst::BranchEqz { target, condition },
+        switch::Inst::Return { result } => Inst::Return { result },
+        unsupported => return Err(unsupported),
+    };
+    Ok(lowered)
+}
+
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+pub fn execute(insts: &[Inst], context: &mut Context) -> Bits {
     let mut exec_context = ExecContext { insts, context };
     exec_context.tail_execute_next_2(0);
+    exec_context.context.get_reg_abs(0)
 }
 
 #[test]
@@ -105,17 +170,47 @@ fn counter_loop() {
         },
         // Branch to the end if r0 is zero.
         Inst::BranchEqz0 {
-            target: 4,
+            target: Target(4),
         },
         // Decrease r0 by 1.
         Inst::SubImm0 {
             imm: 1,
         },
         // Jump back to the loop header.
-        Inst::Branch { target: 1 },
+        Inst::Branch { target: Target(1) },
+        // Return value and end function execution.
+        Inst::Return { result: Register(0) },
+    ];
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(result, 0);
+}
+
+#[test]
+fn more_comps() {
+    let repetitions = 100_000_000;
+    let program = [
+        // Store `repetitions` into r0.
+        // Note: r0 is our loop counter register.
+        switch::Inst::AddImm { result: Register(0), src: Register(0), imm: repetitions },
+        // Store `1` into r1.
+        // Note: r1 is our accumulator register.
+        switch::Inst::AddImm { result: Register(1), src: Register(1), imm: 1 },
+        // Branch to the end if r0 is zero.
+        switch::Inst::BranchEqz { target: Target(7), condition: Register(0) },
+        // Multiply r1 with r0.
+        switch::Inst::Mul { result: Register(1), lhs: Register(1), rhs: Register(0) },
+        // Multiply r1 with 3.
+        switch::Inst::MulImm { result: Register(1), src: Register(1), imm: 3 },
+        // Decrease r0 by 1.
+        switch::Inst::SubImm { result: Register(0), src: Register(0), imm: 1 },
+        // Jump back to the loop header.
+        switch::Inst::Branch { target: Target(2) },
         // Return value and end function execution.
-        Inst::Return { result: 0 },
+        switch::Inst::Return { result: Register(1) },
     ];
+    let insts = lower(&program).unwrap();
     let mut context = Context::default();
-    benchmark(|| execute(&insts, &mut context));
+    let (_, result) = benchmark(|| execute(&insts, &mut context));
+    assert_eq!(Ok(result), switch::run(&program, &mut Context::default()));
 }
@@ -0,0 +1,251 @@
+#![allow(dead_code)]
+
+use alloc::vec::Vec;
+
+#[cfg(test)]
+use crate::benchmark;
+
+use super::{fetch, handler, switch, Bits, Context, Outcome, Register, Target};
+
+/// The handler of a threaded instruction.
+///
+/// Every handler executes its instruction and then directly calls the handler
+/// of the next instruction instead of returning to a central dispatch loop.
+pub type Handler = fn(&mut Context, &[Op]) -> Outcome;
+
+/// A threaded instruction: the handler that executes it and its operands.
+#[derive(Copy, Clone)]
+pub struct Op {
+    /// The handler executing this instruction.
+    handler: Handler,
+    /// The first operand, e.g. the `result` register or the branch `target`.
+    a: usize,
+    /// The second operand, e.g. the `lhs` register or the branch `condition`.
+    b: usize,
+    /// The third operand, e.g. the `rhs` register or an immediate value.
+    c: Bits,
+}
+
+impl Op {
+    /// Creates a new [`Op`] from the given handler and operands.
+    fn new(handler: Handler, a: usize, b: usize, c: Bits) -> Self {
+        Self { handler, a, b, c }
+    }
+
+    /// Adds the contents of `lhs` and `rhs` and stores the result into `result`.
+    pub fn add(result: Register, lhs: Register, rhs: Register) -> Self {
+        Self::new(exec_add, result.0, lhs.0, rhs.0 as Bits)
+    }
+
+    /// Adds the constant `imm` and the contents of `src` and stores the result into `result`.
+    pub fn add_imm(result: Register, src: Register, imm: Bits) -> Self {
+        Self::new(exec_add_imm, result.0, src.0, imm)
+    }
+
+    /// Subtracts the contents of `rhs` from `lhs` and stores the result into `result`.
+    pub fn sub(result: Register, lhs: Register, rhs: Register) -> Self {
+        Self::new(exec_sub, result.0, lhs.0, rhs.0 as Bits)
+    }
+
+    /// Subtracts the constant `imm` from the contents of `src` and stores the result into `result`.
+    pub fn sub_imm(result: Register, src: Register, imm: Bits) -> Self {
+        Self::new(exec_sub_imm, result.0, src.0, imm)
+    }
+
+    /// Multiplies the contents of `lhs` and `rhs` and stores the result into `result`.
+    pub fn mul(result: Register, lhs: Register, rhs: Register) -> Self {
+        Self::new(exec_mul, result.0, lhs.0, rhs.0 as Bits)
+    }
+
+    /// Multiplies the constant `imm` and the contents of `src` and stores the result into `result`.
+    pub fn mul_imm(result: Register, src: Register, imm: Bits) -> Self {
+        Self::new(exec_mul_imm, result.0, src.0, imm)
+    }
+
+    /// Branches to the instruction indexed by `target`.
+    pub fn branch(target: Target) -> Self {
+        Self::new(exec_branch, target.0, 0, 0)
+    }
+
+    /// Branches to the instruction indexed by `target` if the contents of `condition` are zero.
+    pub fn branch_eqz(target: Target, condition: Register) -> Self {
+        Self::new(exec_branch_eqz, target.0, condition.0, 0)
+    }
+
+    /// Branches to the instruction indexed by `target` if the contents of `condition` are not zero.
+    pub fn branch_nez(target: Target, condition: Register) -> Self {
+        Self::new(exec_branch_nez, target.0, condition.0, 0)
+    }
+
+    /// Traps execution of the function unconditionally.
+    pub fn unreachable() -> Self {
+        Self::new(exec_unreachable, 0, 0, 0)
+    }
+
+    /// Returns execution of the function and returns the result in `result`.
+    pub fn ret(result: Register) -> Self {
+        Self::new(exec_ret, result.0, 0, 0)
+    }
+}
+
+/// Returns the [`Op`] at the current `pc` which [`dispatch`] has already bounds checked.
+fn current<'a>(context: &Context, ops: &'a [Op]) -> &'a Op {
+    unsafe { ops.get_unchecked(context.pc) }
+}
+
+/// Calls the handler of the [`Op`] at the current `pc`.
+fn dispatch(context: &mut Context, ops: &[Op]) -> Outcome {
+    match fetch(ops, context.pc) {
+        Ok(op) => (op.handler)(context, ops),
+        Err(trap) => Outcome::Trap(trap),
+    }
+}
+
+fn exec_add(context: &mut Context, ops: &[Op]) -> Outcome {
+    let op = current(context, ops);
+    match handler::add(
+        context,
+        Register(op.a),
+        Register(op.b),
+        Register(op.c as usize),
+    ) {
+        Outcome::Continue => dispatch(context, ops),
+        outcome => outcome,
+    }
+}
+
+fn exec_add_imm(context: &mut Context, ops: &[Op]) -> Outcome {
+    let op = current(context, ops);
+    match handler::add_imm(context, Register(op.a), Register(op.b), op.c) {
+        Outcome::Continue => dispatch(context, ops),
+        outcome => outcome,
+    }
+}
+
+fn exec_sub(context: &mut Context, ops: &[Op]) -> Outcome {
+    let op = current(context, ops);
+    match handler::sub(
+        context,
+        Register(op.a),
+        Register(op.b),
+        Register(op.c as usize),
+    ) {
+        Outcome::Continue => dispatch(context, ops),
+        outcome => outcome,
+    }
+}
+
+fn exec_sub_imm(context: &mut Context, ops: &[Op]) -> Outcome {
+    let op = current(context, ops);
+    match handler::sub_imm(context, Register(op.a), Register(op.b), op.c) {
+        Outcome::Continue => dispatch(context, ops),
+        outcome => outcome,
+    }
+}
+
+fn exec_mul(context: &mut Context, ops: &[Op]) -> Outcome {
+    let op = current(context, ops);
+    match handler::mul(
+        context,
+        Register(op.a),
+        Register(op.b),
+        Register(op.c as usize),
+    ) {
+        Outcome::Continue => dispatch(context, ops),
+        outcome => outcome,
+    }
+}
+
+fn exec_mul_imm(context: &mut Context, ops: &[Op]) -> Outcome {
+    let op = current(context, ops);
+    match handler::mul_imm(context, Register(op.a), Register(op.b), op.c) {
+        Outcome::Continue => dispatch(context, ops),
+        outcome => outcome,
+    }
+}
+
+fn exec_branch(context: &mut Context, ops: &[Op]) -> Outcome {
+    let op = current(context, ops);
+    handler::branch(context, Target(op.a));
+    dispatch(context, ops)
+}
+
+fn exec_branch_eqz(context: &mut Context, ops: &[Op]) -> Outcome {
+    let op = current(context, ops);
+    handler::branch_eqz(context, Target(op.a), Register(op.b));
+    dispatch(context, ops)
+}
+
+fn exec_branch_nez(context: &mut Context, ops: &[Op]) -> Outcome {
+    let op = current(context, ops);
+    handler::branch_nez(context, Target(op.a), Register(op.b));
+    dispatch(context, ops)
+}
+
+fn exec_unreachable(_context: &mut Context, _ops: &[Op]) -> Outcome {
+    handler::unreachable()
+}
+
+fn exec_ret(context: &mut Context, ops: &[Op]) -> Outcome {
+    let op = current(context, ops);
+    handler::ret(context, Register(op.a))
+}
+
+/// Lowers the [`switch::Inst`] program to threaded instructions.
+///
+/// # Errors
+///
+/// Returns the first instruction that has no counterpart.
+pub fn lower(insts: &[switch::Inst]) -> Result<Vec<Op>, switch::Inst> {
+    insts.iter().cloned().map(lower_inst).collect()
+}
+
+/// Lowers a single [`switch::Inst`].
+fn lower_inst(inst: switch::Inst) -> Result<Op, switch::Inst> {
+    let lowered = match inst {
+        switch::Inst::Add { result, lhs, rhs } => Op::add(result, lhs, rhs),
+        switch::Inst::AddImm { result, src, imm } => Op::add_imm(result, src, imm),
+        switch::Inst::Sub { result, lhs, rhs } => Op::sub(result, lhs, rhs),
+        switch::Inst::SubImm { result, src, imm } => Op::sub_imm(result, src, imm),
+        switch::Inst::Mul { result, lhs, rhs } => Op::mul(result, lhs, rhs),
+        switch::Inst::MulImm { result, src, imm } => Op::mul_imm(result, src, imm),
+        switch::Inst::Branch { target } => Op::branch(target),
+        switch::Inst::BranchEqz { target, condition } => Op::branch_eqz(target, condition),
+        switch::Inst::BranchNez { target, condition } => Op::branch_nez(target, condition),
+        switch::Inst::Unreachable => Op::unreachable(),
+        switch::Inst::Return { result } => Op::ret(result),
+        unsupported => return Err(unsupported),
+    };
+    Ok(lowered)
+}
+
+/// Executes the list of instruction using the given [`Context`] and returns the result in register 0.
+pub fn execute(ops: &[Op], context: &mut Context) -> Bits {
+    dispatch(context, ops);
+    context.get_reg_abs(0)
+}
+
+// Measured with `cargo test --release` on the same machine:
+//
+// - `threaded::counter_loop`: ~530ms
+// - `switch::counter_loop`: ~980ms
+#[test]
+fn counter_loop() {
+    let repetitions = 100_000_000;
+    let ops = vec![
+        // Store `repetitions` into r0.
+        // Note: r0 is our loop counter register.
+        Op::add_imm(Register(0), Register(0), repetitions),
+        // Branch to the end if r0 is zero.
+        Op::branch_eqz(Target(4), Register(0)),
+        // Decrease r0 by 1.
+        Op::sub_imm(Register(0), Register(0), 1),
+        // Jump back to the loop header.
+        Op::branch(Target(1)),
+        // Return value and end function execution.
+        Op::ret(Register(0)),
+    ];
+    let mut context = Context::default();
+    let (_, result) = benchmark(|| execute(&ops, &mut context));
+    assert_eq!(result, 0);
+}
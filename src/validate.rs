@@ -0,0 +1,330 @@
+#![allow(dead_code)]
+
+use alloc::vec::Vec;
+
+use super::{switch::Inst, Register, Target, NUM_REGISTERS};
+
+/// An error found while validating a list of instructions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The program contains no instructions at all.
+    Empty,
+    /// The `entry` of the program lies outside of its instructions.
+    EntryOutOfBounds { entry: Target },
+    /// The instruction at `index` branches to a `target` outside of the program.
+    BranchOutOfBounds { index: usize, target: Target },
+    /// The instruction at `index` uses a `register` the context does not have.
+    RegisterOutOfBounds { index: usize, register: Register },
+    /// The instruction at `index` uses a float `register` the context does not have.
+    FloatRegisterOutOfBounds { index: usize, register: Register },
+    /// The call at `index` passes more arguments than a register window holds.
+    TooManyArguments { index: usize, num_args: usize },
+    /// Execution may continue past the last instruction at `index`.
+    FallsOffEnd { index: usize },
+}
+
+/// Validates that `insts` can be executed without the `pc` or registers going out of bounds.
+///
+/// # Errors
+///
+/// - If a branch target lies outside of `insts`.
+/// - If an instruction uses a register or float register index of at least [`NUM_REGISTERS`].
+/// - If a call passes more than [`NUM_REGISTERS`] arguments.
+/// - If execution can continue past the last instruction.
+pub fn validate(insts: &[Inst]) -> Result<(), ValidationError> {
+    validate_with_registers(insts, NUM_REGISTERS)
+}
+
+/// Validates `insts` like [`validate`] for a context with `num_registers` registers.
+///
+/// # Errors
+///
+/// Like [`validate`] with `num_registers` in place of [`NUM_REGISTERS`].
+pub fn validate_with_registers(
+    insts: &[Inst],
+    num_registers: usize,
+) -> Result<(), ValidationError> {
+    let last = insts.last().ok_or(ValidationError::Empty)?;
+    for (index, inst) in insts.iter().enumerate() {
+        for register in registers(inst) {
+            if register >= Register(num_registers) {
+                return Err(ValidationError::RegisterOutOfBounds { index, register });
+            }
+        }
+        for register in float_registers(inst) {
+            if register >= Register(num_registers) {
+                return Err(ValidationError::FloatRegisterOutOfBounds { index, register });
+            }
+        }
+        if let Inst::Call { num_args, .. } = *inst {
+            if num_args > num_registers {
+                return Err(ValidationError::TooManyArguments { index, num_args });
+            }
+        }
+        for target in targets(inst) {
+            if target >= Target(insts.len()) {
+                return Err(ValidationError::BranchOutOfBounds { index, target });
+            }
+        }
+    }
+    match last {
+        Inst::Branch { .. }
+        | Inst::BranchTable { .. }
+        | Inst::Unreachable
+        | Inst::Return { .. } => Ok(()),
+        _ => Err(ValidationError::FallsOffEnd {
+            index: insts.len() - 1,
+        }),
+    }
+}
+
+/// Returns all registers used by `inst`.
+pub(crate) fn registers(inst: &Inst) -> Vec<Register> {
+    match *inst {
+        Inst::Add { result, lhs, rhs }
+        | Inst::Sub { result, lhs, rhs }
+        | Inst::Mul { result, lhs, rhs }
+        | Inst::Div { result, lhs, rhs }
+        | Inst::Rem { result, lhs, rhs }
+        | Inst::Shl { result, lhs, rhs }
+        | Inst::ShrU { result, lhs, rhs }
+        | Inst::ShrS { result, lhs, rhs }
+        | Inst::Rotl { result, lhs, rhs }
+        | Inst::Rotr { result, lhs, rhs }
+        | Inst::Eq { result, lhs, rhs }
+        | Inst::Ne { result, lhs, rhs }
+        | Inst::LtU { result, lhs, rhs }
+        | Inst::LtS { result, lhs, rhs }
+        | Inst::Add32 { result, lhs, rhs }
+        | Inst::Sub32 { result, lhs, rhs }
+        | Inst::Mul32 { result, lhs, rhs }
+        | Inst::LtS32 { result, lhs, rhs }
+        | Inst::Shl32 { result, lhs, rhs }
+        | Inst::ShrU32 { result, lhs, rhs }
+        | Inst::ShrS32 { result, lhs, rhs }
+        | Inst::AddChecked { result, lhs, rhs }
+        | Inst::SubChecked { result, lhs, rhs }
+        | Inst::MulChecked { result, lhs, rhs }
+        | Inst::MinU { result, lhs, rhs }
+        | Inst::MaxU { result, lhs, rhs }
+        | Inst::MinS { result, lhs, rhs }
+        | Inst::MaxS { result, lhs, rhs } => vec![result, lhs, rhs],
+        Inst::AddImm { result, src, .. }
+        | Inst::SubImm { result, src, .. }
+        | Inst::MulImm { result, src, .. }
+        | Inst::DivImm { result, src, .. }
+        | Inst::RemImm { result, src, .. }
+        | Inst::ShlImm { result, src, .. }
+        | Inst::ShrUImm { result, src, .. }
+        | Inst::ShrSImm { result, src, .. }
+        | Inst::RotlImm { result, src, .. }
+        | Inst::RotrImm { result, src, .. }
+        | Inst::EqImm { result, src, .. }
+        | Inst::NeImm { result, src, .. }
+        | Inst::LtUImm { result, src, .. }
+        | Inst::LtSImm { result, src, .. }
+        | Inst::MinUImm { result, src, .. }
+        | Inst::MaxUImm { result, src, .. }
+        | Inst::MinSImm { result, src, .. }
+        | Inst::MaxSImm { result, src, .. }
+        | Inst::Move { result, src }
+        | Inst::Popcnt { result, src }
+        | Inst::Clz { result, src }
+        | Inst::Ctz { result, src } => vec![result, src],
+        Inst::Select {
+            result,
+            cond,
+            if_nonzero,
+            if_zero,
+        } => vec![result, cond, if_nonzero, if_zero],
+        Inst::MulWide { hi, lo, lhs, rhs } => vec![hi, lo, lhs, rhs],
+        Inst::Load8 { result, base, .. }
+        | Inst::Load16 { result, base, .. }
+        | Inst::Load32 { result, base, .. }
+        | Inst::Load64 { result, base, .. } => vec![result, base],
+        Inst::Store8 { base, value, .. }
+        | Inst::Store16 { base, value, .. }
+        | Inst::Store32 { base, value, .. }
+        | Inst::Store64 { base, value, .. } => vec![base, value],
+        Inst::BranchEq { lhs, rhs, .. }
+        | Inst::BranchNe { lhs, rhs, .. }
+        | Inst::BranchLtU { lhs, rhs, .. }
+        | Inst::BranchLtS { lhs, rhs, .. } => vec![lhs, rhs],
+        Inst::BranchEqz { condition, .. }
+        | Inst::BranchNez { condition, .. }
+        | Inst::BranchTable {
+            index: condition, ..
+        } => vec![condition],
+        Inst::RandNext { result } | Inst::Return { result } => vec![result],
+        Inst::FAdd { .. }
+        | Inst::FSub { .. }
+        | Inst::FMul { .. }
+        | Inst::FDiv { .. }
+        | Inst::Branch { .. }
+        | Inst::Call { .. }
+        | Inst::Nop
+        | Inst::Yield
+        | Inst::Unreachable => vec![],
+    }
+}
+
+/// Returns all float registers used by `inst`.
+fn float_registers(inst: &Inst) -> Vec<Register> {
+    match *inst {
+        Inst::FAdd { result, lhs, rhs }
+        | Inst::FSub { result, lhs, rhs }
+        | Inst::FMul { result, lhs, rhs }
+        | Inst::FDiv { result, lhs, rhs } => vec![result, lhs, rhs],
+        _ => vec![],
+    }
+}
+
+/// Returns the branch targets of `inst`.
+pub(crate) fn targets(inst: &Inst) -> Vec<Target> {
+    match *inst {
+        Inst::Branch { target }
+        | Inst::BranchEqz { target, .. }
+        | Inst::BranchNez { target, .. }
+        | Inst::BranchEq { target, .. }
+        | Inst::BranchNe { target, .. }
+        | Inst::BranchLtU { target, .. }
+        | Inst::BranchLtS { target, .. }
+        | Inst::Call { target, .. } => vec![target],
+        Inst::BranchTable {
+            ref targets,
+            default,
+            ..
+        } => targets.iter().copied().chain([default]).collect(),
+        _ => vec![],
+    }
+}
+
+#[test]
+fn valid() {
+    let insts = [
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 10,
+        },
+        Inst::BranchEqz {
+            target: Target(4),
+            condition: Register(0),
+        },
+        Inst::SubImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        Inst::Branch { target: Target(1) },
+        Inst::Return {
+            result: Register(15),
+        },
+    ];
+    assert_eq!(validate(&insts), Ok(()));
+}
+
+#[test]
+fn empty() {
+    assert_eq!(validate(&[]), Err(ValidationError::Empty));
+}
+
+#[test]
+fn branch_out_of_bounds() {
+    let insts = [
+        Inst::BranchNez {
+            target: Target(2),
+            condition: Register(0),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_eq!(
+        validate(&insts),
+        Err(ValidationError::BranchOutOfBounds {
+            index: 0,
+            target: Target(2)
+        })
+    );
+}
+
+#[test]
+fn register_out_of_bounds() {
+    let insts = [
+        Inst::Add {
+            result: Register(0),
+            lhs: Register(1),
+            rhs: Register(NUM_REGISTERS),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_eq!(
+        validate(&insts),
+        Err(ValidationError::RegisterOutOfBounds {
+            index: 0,
+            register: Register(NUM_REGISTERS)
+        })
+    );
+}
+
+#[test]
+fn falls_off_end() {
+    let insts = [
+        Inst::Return {
+            result: Register(0),
+        },
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+    ];
+    assert_eq!(
+        validate(&insts),
+        Err(ValidationError::FallsOffEnd { index: 1 })
+    );
+}
+
+#[test]
+fn too_many_arguments() {
+    let insts = [
+        Inst::Call {
+            target: Target(1),
+            num_args: NUM_REGISTERS + 1,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_eq!(
+        validate(&insts),
+        Err(ValidationError::TooManyArguments {
+            index: 0,
+            num_args: NUM_REGISTERS + 1
+        })
+    );
+}
+
+#[test]
+fn float_register_out_of_bounds() {
+    let insts = [
+        Inst::FMul {
+            result: Register(NUM_REGISTERS),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_eq!(
+        validate(&insts),
+        Err(ValidationError::FloatRegisterOutOfBounds {
+            index: 0,
+            register: Register(NUM_REGISTERS)
+        })
+    );
+}
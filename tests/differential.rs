@@ -0,0 +1,302 @@
+//! Runs the same programs on every dispatch strategy and asserts that they agree.
+
+use interpreter_dispatch_research::{runner, switch::Inst, ArithMode, Bits, Register, Target};
+
+/// Runs `program` on every dispatch strategy supporting it and asserts that all return `expected`.
+///
+/// Dispatch strategies lacking an instruction used by `program` are filtered out
+/// but at least two strategies must remain to compare.
+fn assert_agree(name: &str, program: &[Inst], expected: Bits) {
+    let runners = runner::lower(program)
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>();
+    assert!(
+        runners.len() > 1,
+        "`{name}` only lowers to {} strategies",
+        runners.len()
+    );
+    for runner in runners {
+        assert_eq!(
+            runner.run(),
+            expected,
+            "`{name}` diverges on `{}`",
+            runner.name()
+        );
+    }
+}
+
+#[test]
+fn counter_loop() {
+    let program = [
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1_000,
+        },
+        Inst::BranchEqz {
+            target: Target(4),
+            condition: Register(0),
+        },
+        Inst::SubImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        Inst::Branch { target: Target(1) },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_agree("counter_loop", &program, 0);
+}
+
+#[test]
+fn factorial() {
+    let program = [
+        // r0 = 1, r1 = 20
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 20,
+        },
+        // while r1 != 0 { r0 *= r1; r1 -= 1; }
+        Inst::BranchEqz {
+            target: Target(6),
+            condition: Register(1),
+        },
+        Inst::Mul {
+            result: Register(0),
+            lhs: Register(0),
+            rhs: Register(1),
+        },
+        Inst::SubImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 1,
+        },
+        Inst::Branch { target: Target(2) },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_agree("factorial", &program, 2_432_902_008_176_640_000);
+}
+
+#[test]
+fn branch_to_target() {
+    // r3 = 5 so that branching to the contents of r3 instead of the target returns 5.
+    let program = [
+        Inst::AddImm {
+            result: Register(3),
+            src: Register(3),
+            imm: 5,
+        },
+        Inst::Branch { target: Target(3) },
+        Inst::Return {
+            result: Register(3),
+        },
+        Inst::BranchNez {
+            target: Target(6),
+            condition: Register(3),
+        },
+        Inst::Return {
+            result: Register(3),
+        },
+        Inst::Return {
+            result: Register(3),
+        },
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 6,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_agree("branch_to_target", &program, 6);
+}
+
+#[test]
+fn nested_arithmetic() {
+    let program = [
+        // r1 = 7, r2 = 5
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 7,
+        },
+        Inst::AddImm {
+            result: Register(2),
+            src: Register(2),
+            imm: 5,
+        },
+        // r3 = (r1 + r2) * (r1 - r2) = 24
+        Inst::Add {
+            result: Register(3),
+            lhs: Register(1),
+            rhs: Register(2),
+        },
+        Inst::Sub {
+            result: Register(4),
+            lhs: Register(1),
+            rhs: Register(2),
+        },
+        Inst::Mul {
+            result: Register(3),
+            lhs: Register(3),
+            rhs: Register(4),
+        },
+        // r0 = r3 * 3 - 100 wrapping below zero
+        Inst::MulImm {
+            result: Register(3),
+            src: Register(3),
+            imm: 3,
+        },
+        Inst::SubImm {
+            result: Register(0),
+            src: Register(3),
+            imm: 100,
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    assert_agree("nested_arithmetic", &program, 72_u64.wrapping_sub(100));
+}
+
+#[test]
+fn div_rem_shifts() {
+    let program = [
+        Inst::AddImm {
+            result: Register(1),
+            src: Register(1),
+            imm: 1_000_003,
+        },
+        Inst::DivImm {
+            result: Register(2),
+            src: Register(1),
+            imm: 7,
+        },
+        Inst::RemImm {
+            result: Register(3),
+            src: Register(1),
+            imm: 7,
+        },
+        Inst::ShlImm {
+            result: Register(2),
+            src: Register(2),
+            imm: 68,
+        },
+        Inst::Rotr {
+            result: Register(2),
+            lhs: Register(2),
+            rhs: Register(3),
+        },
+        Inst::ShrS {
+            result: Register(0),
+            lhs: Register(2),
+            rhs: Register(3),
+        },
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let quotient: Bits = 1_000_003 / 7;
+    let remainder = 1_000_003 % 7;
+    let expected = (((quotient << 4).rotate_right(remainder as u32)) as i64 >> remainder) as Bits;
+    assert_agree("div_rem_shifts", &program, expected);
+}
+
+/// Checks the benchmark workloads against their expected results.
+///
+/// Run with and without the `no_inline_handlers` feature to check that the inlining
+/// of the handlers does not change the results of any dispatch strategy.
+#[test]
+fn benchmark_workloads() {
+    let repetitions: Bits = 1_000;
+    for runner in runner::counter_loop(repetitions) {
+        assert_eq!(
+            runner.run(),
+            0,
+            "`counter_loop` diverges on `{}`",
+            runner.name()
+        );
+    }
+    let expected = (1..=repetitions)
+        .rev()
+        .fold(1, |acc: Bits, n| acc.wrapping_mul(n).wrapping_sub(n));
+    for runner in runner::more_comps(repetitions) {
+        assert_eq!(
+            runner.run(),
+            expected,
+            "`more_comps` diverges on `{}`",
+            runner.name()
+        );
+    }
+}
+
+/// Checks that every dispatch strategy traps on overflowing `add`, `sub` and `mul` in [`ArithMode::Checked`].
+///
+/// A trapped instruction leaves its `result` register `r0` at zero while wrapping around does not.
+#[test]
+fn checked_overflow() {
+    let (max, zero) = (Register(1), Register(2));
+    let overflows = [
+        Inst::Add {
+            result: Register(0),
+            lhs: max,
+            rhs: max,
+        },
+        Inst::AddImm {
+            result: Register(0),
+            src: max,
+            imm: 1,
+        },
+        Inst::Sub {
+            result: Register(0),
+            lhs: zero,
+            rhs: max,
+        },
+        Inst::SubImm {
+            result: Register(0),
+            src: zero,
+            imm: 1,
+        },
+        Inst::Mul {
+            result: Register(0),
+            lhs: max,
+            rhs: max,
+        },
+        Inst::MulImm {
+            result: Register(0),
+            src: max,
+            imm: 2,
+        },
+    ];
+    for overflow in overflows {
+        let program = [
+            Inst::AddImm {
+                result: max,
+                src: max,
+                imm: Bits::MAX,
+            },
+            overflow.clone(),
+            Inst::Return {
+                result: Register(0),
+            },
+        ];
+        for runner in runner::lower_with_arith_mode(&program, ArithMode::Checked)
+            .into_iter()
+            .filter_map(Result::ok)
+        {
+            assert_eq!(runner.run(), 0, "{overflow:?} wraps on `{}`", runner.name());
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Executes a program from a `no_std` crate to check that the dispatch core only needs `alloc`.
+
+#![no_std]
+
+use interpreter_dispatch_research::{switch, switch::Inst, Context, Register, Target};
+
+#[test]
+fn counter_loop() {
+    let repetitions = 1_000;
+    let insts = [
+        // Store `repetitions` into r0.
+        Inst::AddImm {
+            result: Register(0),
+            src: Register(0),
+            imm: repetitions,
+        },
+        // Branch to the end if r0 is zero.
+        Inst::BranchEqz {
+            target: Target(4),
+            condition: Register(0),
+        },
+        // Decrease r0 by 1.
+        Inst::SubImm {
+            result: Register(0),
+            src: Register(0),
+            imm: 1,
+        },
+        // Jump back to the loop header.
+        Inst::Branch { target: Target(1) },
+        // Return value and end function execution.
+        Inst::Return {
+            result: Register(0),
+        },
+    ];
+    let mut context = Context::default();
+    assert_eq!(switch::execute(&insts, &mut context), Ok(0));
+    assert_eq!(switch::run(&insts, &mut Context::default()), Ok(0));
+}